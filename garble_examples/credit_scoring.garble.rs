@@ -1,4 +1,4 @@
-pub fn compute_score(scoring_algorithm: ScoringAlgorithm, user: User) -> Score {
+pub fn main(scoring_algorithm: ScoringAlgorithm, user: User) -> Score {
     let User {
         age,
         income,
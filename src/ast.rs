@@ -21,6 +21,10 @@ pub struct Program<T> {
     pub enum_defs: HashMap<String, EnumDef>,
     /// Top level function definitions.
     pub fn_defs: HashMap<String, FnDef<T>>,
+    /// Names and locations of top level functions that redefine an already defined function,
+    /// which are only kept by the parser so that the type checker can report them.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) duplicate_fn_defs: Vec<(String, MetaInfo)>,
 }
 
 /// A top level const definition.
@@ -63,6 +67,10 @@ pub enum ConstExprEnum {
     Max(Vec<ConstExpr>),
     /// The minimum of several constant expressions.
     Min(Vec<ConstExpr>),
+    /// A reference to another top-level constant.
+    Const(String),
+    /// An arithmetic (`+`, `-`, `*`, `/`, `%`) or bitwise (`&`, `|`, `^`) operation on literals.
+    Op(Op, Box<ConstExpr>, Box<ConstExpr>),
 }
 
 /// A top level struct type definition.
@@ -127,6 +135,12 @@ pub struct FnDef<T> {
     pub is_pub: bool,
     /// The name of the function.
     pub identifier: String,
+    /// The type parameter of a generic function, instantiated for each distinct type it is called
+    /// with (only before typechecking, typed functions are always instantiated).
+    pub type_param: Option<String>,
+    /// The parameter of the party that is the only recipient of the output and the location of
+    /// the `#[reveal(param)]` attribute (the output is revealed to all parties if `None`).
+    pub reveal_to: Option<(String, MetaInfo)>,
     /// The return type of the function.
     pub ty: Type,
     /// The parameters of the function.
@@ -179,6 +193,14 @@ pub enum Type {
     Unsigned(UnsignedNumType),
     /// Signed number types
     Signed(SignedNumType),
+    /// Signed fixed-point number type `fx<bits>_<frac>` with the specified total and fractional
+    /// bits, represented as a signed number of `bits` bits that is scaled by `2^frac`.
+    Fixed(u8, u8),
+    /// Unicode scalar value, represented as a 32-bit unsigned number.
+    Char,
+    /// UTF-8 string of at most the specified number of bytes, represented as its length (a
+    /// `usize`) followed by its bytes, padded with zeros to the maximum length.
+    String(usize),
     /// Function type with the specified parameters and the specified return type.
     Fn(Vec<Type>, Box<Type>),
     /// Array type of a fixed size, containing elements of the specified type.
@@ -201,6 +223,9 @@ impl std::fmt::Display for Type {
             Type::Bool => f.write_str("bool"),
             Type::Unsigned(n) => n.fmt(f),
             Type::Signed(n) => n.fmt(f),
+            Type::Fixed(bits, frac) => write!(f, "fx{bits}_{frac}"),
+            Type::Char => f.write_str("char"),
+            Type::String(max_len) => write!(f, "String<{max_len}>"),
             Type::Fn(params, ret_ty) => {
                 f.write_str("(")?;
                 let mut params = params.iter();
@@ -328,8 +353,17 @@ pub enum ExprEnum<T> {
     False,
     /// Unsigned number literal.
     NumUnsigned(u64, UnsignedNumType),
+    /// Unsigned number literal that does not fit into a `u64` (and thus only into a `u256`), as
+    /// big-endian bytes without leading zeros.
+    NumUnsignedBytes(Vec<u8>, UnsignedNumType),
     /// Signed number literal.
     NumSigned(i64, SignedNumType),
+    /// Fixed-point number literal (scaled by `2^frac`) with its total and fractional bits.
+    NumFixed(i64, u8, u8),
+    /// Char literal.
+    Char(char),
+    /// String literal, typed as an array of its UTF-8 bytes unless a `String` is expected.
+    StringLiteral(String),
     /// Identifier (either a variable or a function).
     Identifier(String),
     /// Array literal which explicitly specifies all of its elements.
@@ -340,6 +374,9 @@ pub enum ExprEnum<T> {
     ArrayRepeatLiteralConst(Box<Expr<T>>, String),
     /// Access of an array at the specified index, returning its element.
     ArrayAccess(Box<Expr<T>>, Box<Expr<T>>),
+    /// Contiguous part of an array, from the specified start (inclusive) to the specified end
+    /// (exclusive).
+    ArraySlice(Box<Expr<T>>, usize, usize),
     /// Tuple literal containing the specified fields.
     TupleLiteral(Vec<Expr<T>>),
     /// Access of a tuple at the specified position.
@@ -348,6 +385,8 @@ pub enum ExprEnum<T> {
     StructAccess(Box<Expr<T>>, String),
     /// Struct literal with the specified fields.
     StructLiteral(String, Vec<(String, Expr<T>)>),
+    /// Struct literal with the specified fields, copying all other fields from the base struct.
+    StructUpdate(String, Vec<(String, Expr<T>)>, Box<Expr<T>>),
     /// Enum literal of the specified variant, possibly with fields.
     EnumLiteral(String, String, VariantExprEnum<T>),
     /// Matching the specified expression with a list of clauses (pattern + expression).
@@ -360,6 +399,9 @@ pub enum ExprEnum<T> {
     Block(Vec<Stmt<T>>),
     /// Call of the specified function with a list of arguments.
     FnCall(String, Vec<Expr<T>>),
+    /// Call of the specified builtin function with a list of arguments (only used after
+    /// type-checking, which resolves calls that are not shadowed by a top level function).
+    BuiltinFnCall(String, Vec<Expr<T>>),
     /// If-else expression for the specified condition, if-expr and else-expr.
     If(Box<Expr<T>>, Box<Expr<T>>, Box<Expr<T>>),
     /// Explicit cast of an expression to the specified type.
@@ -403,6 +445,8 @@ impl Pattern<Type> {
 pub enum PatternEnum<T> {
     /// A variable, always matches.
     Identifier(String),
+    /// The wildcard `_`, always matches without binding a variable.
+    Wildcard,
     /// Matches `true`.
     True,
     /// Matches `false`.
@@ -431,6 +475,7 @@ impl<T> std::fmt::Display for Pattern<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.0 {
             PatternEnum::Identifier(name) => f.write_str(name),
+            PatternEnum::Wildcard => f.write_str("_"),
             PatternEnum::True => f.write_str("true"),
             PatternEnum::False => f.write_str("false"),
             PatternEnum::NumUnsigned(n, suffix) => f.write_fmt(format_args!("{n}{suffix}")),
@@ -524,6 +569,13 @@ pub enum Op {
     Sub,
     /// Multiplication (`*`).
     Mul,
+    /// Saturating addition (`+|`), clamping the result to the minimum or maximum of the type.
+    SaturatingAdd,
+    /// Saturating subtraction (`-|`), clamping the result to the minimum or maximum of the type.
+    SaturatingSub,
+    /// Saturating multiplication (`*|`), clamping the result to the minimum or maximum of the
+    /// type.
+    SaturatingMul,
     /// Division (`/`).
     Div,
     /// Modulo (`%`).
@@ -544,8 +596,12 @@ pub enum Op {
     NotEq,
     /// Bitwise shift-left (`<<`).
     ShiftLeft,
-    /// Bitwise shift-right (`>>`).
+    /// Bitwise shift-right (`>>`), logical for unsigned and arithmetic for signed numbers.
     ShiftRight,
+    /// Bitwise rotate-right (`>>>`).
+    RotateRight,
+    /// Bitwise rotate-left (`<<<`).
+    RotateLeft,
     /// Short-circuiting and (`&&`).
     ShortCircuitAnd,
     /// Short-circuiting or (`||`).
@@ -558,6 +614,9 @@ impl std::fmt::Display for Op {
             Op::Add => f.write_str("+"),
             Op::Sub => f.write_str("-"),
             Op::Mul => f.write_str("*"),
+            Op::SaturatingAdd => f.write_str("+|"),
+            Op::SaturatingSub => f.write_str("-|"),
+            Op::SaturatingMul => f.write_str("*|"),
             Op::Div => f.write_str("/"),
             Op::Mod => f.write_str("%"),
             Op::BitAnd => f.write_str("&"),
@@ -569,6 +628,8 @@ impl std::fmt::Display for Op {
             Op::NotEq => f.write_str("!="),
             Op::ShiftLeft => f.write_str("<<"),
             Op::ShiftRight => f.write_str(">>"),
+            Op::RotateRight => f.write_str(">>>"),
+            Op::RotateLeft => f.write_str("<<<"),
             Op::ShortCircuitAnd => f.write_str("&&"),
             Op::ShortCircuitOr => f.write_str("||"),
         }
@@ -1,7 +1,10 @@
 //! Type-checker, transforming an untyped [`crate::ast::Program`] into a typed
 //! [`crate::ast::Program`].
 
-use std::collections::{HashMap, HashSet};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
 
 use crate::{
     ast::{
@@ -9,14 +12,19 @@ use crate::{
         ParamDef, Pattern, PatternEnum, Stmt, StmtEnum, StructDef, Type, UnaryOp, Variant,
         VariantExprEnum,
     },
+    circuit::USIZE_BITS,
     env::Env,
     token::{MetaInfo, SignedNumType, UnsignedNumType},
     TypedExpr, TypedFnDef, TypedPattern, TypedProgram, TypedStmt, UntypedExpr, UntypedFnDef,
     UntypedPattern, UntypedProgram, UntypedStmt,
 };
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 /// An error found during type-checking, with its location in the source code.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TypeError(pub TypeErrorEnum, pub MetaInfo);
 
 impl PartialOrd for TypeError {
@@ -33,15 +41,38 @@ impl Ord for TypeError {
 
 /// The different kinds of errors found during type-checking.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TypeErrorEnum {
     /// The identifier is not a top level function.
     NoTopLevelFn(String),
+    /// Expected the name of a top level function.
+    ExpectedFnName,
     /// The specified function does not have any input parameters.
     PubFnWithoutParams(String),
     /// A top-level function is declared but never used.
     UnusedFn(String),
+    /// A variable bound by `let` or `let mut` is never used.
+    UnusedVariable(String),
     /// A top-level function calls itself recursively.
     RecursiveFnDef(String),
+    /// The constant is defined in terms of itself.
+    RecursiveConstDef(String),
+    /// The type parameter of a generic fn could not be inferred from the args of the call.
+    CannotInferTypeParam(String),
+    /// The type parameter of a generic fn is inferred as two different types in the same call.
+    ConflictingTypeParam(String, Box<Type>, Box<Type>),
+    /// The type parameter of a generic fn can only be instantiated with an integer type.
+    NonIntegerTypeParam(String, Type),
+    /// Top level pub functions cannot be generic.
+    PubFnWithTypeParam(String),
+    /// The party of a `#[reveal(param)]` attribute is not a parameter of the function.
+    UnknownRevealParty(String),
+    /// The program does not contain a `main` function.
+    MissingMain,
+    /// The `main` function is defined more than once.
+    DuplicateMain,
+    /// A function other than `main` is defined more than once.
+    DuplicateFnDef(String),
     /// No struct or enum declaration with the specified name exists.
     UnknownStructOrEnum(String),
     /// No struct declaration with the specified name exists.
@@ -68,10 +99,25 @@ pub enum TypeErrorEnum {
     ExpectedNumberType(Type),
     /// A signed number expression was expected.
     ExpectedSignedNumberType(Type),
+    /// An unsigned number expression was expected.
+    ExpectedUnsignedNumberType(Type),
     /// An array type was expected.
     ExpectedArrayType(Type),
+    /// The elements of an array literal do not all have the same type.
+    InconsistentArrayElements {
+        /// The element type of the array, as determined by the first elements.
+        expected: Type,
+        /// The type of the first element that does not match.
+        found: Type,
+        /// The index of the first element that does not match.
+        index: usize,
+    },
     /// A tuple type was expected.
     ExpectedTupleType(Type),
+    /// An array literal of number literals was expected as an index path.
+    ExpectedIndexPath,
+    /// The index of an index path cannot be used to access a value of the type.
+    InvalidIndexPath(Type, u64),
     /// A struct type was expected.
     ExpectedStructType(Type),
     /// An enum type was expected.
@@ -115,21 +161,113 @@ pub enum TypeErrorEnum {
     TypeDoesNotSupportPatternMatching(Type),
     /// The specified identifier is not a constant.
     ArraySizeNotConst(String),
+    /// The constant used as an array size is not of type usize.
+    ArraySizeNotUsize(String, Type),
     /// The specified expression is not a literal usize number.
     UsizeNotLiteral,
+    /// A tuple type with fields of the same type was expected.
+    ExpectedHomogeneousTupleType(Type),
+    /// A number literal was expected, because the value must be known at compile time.
+    ExpectedNumberLiteral,
+    /// The condition of an `if` is always true or always false (only reported in strict mode).
+    ConstantCondition(bool),
+    /// A number is compared with a literal outside of its range (only reported in strict mode).
+    ComparisonAlwaysFalse(Type),
+    /// The string literal is longer than the maximum length of the string type.
+    StringTooLong {
+        /// The maximum length (in bytes) of the string type.
+        max_len: usize,
+        /// The length (in bytes) of the string literal.
+        actual_len: usize,
+    },
+    /// The pattern is covered by previous patterns and can never match (only reported in strict
+    /// mode).
+    UnreachablePattern,
+    /// The array slice is empty or exceeds the bounds of the array.
+    InvalidArraySlice {
+        /// The start of the slice (inclusive).
+        from: usize,
+        /// The end of the slice (exclusive).
+        to: usize,
+        /// The type of the sliced array.
+        ty: Type,
+    },
+    /// The constant operation overflows, divides by zero or has an operand that is not a literal.
+    InvalidConstOp(Op),
+    /// The enum is cast to a number type that cannot hold the discriminants of all variants.
+    EnumCastOutOfRange {
+        /// The name of the enum.
+        enum_name: String,
+        /// The number of variants of the enum.
+        variants: usize,
+        /// The number type that the enum is cast to.
+        ty: Type,
+    },
+    /// The size of the type in bits does not fit into a usize.
+    TypeTooLarge(Type),
 }
 
 impl std::fmt::Display for TypeErrorEnum {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TypeErrorEnum::NoTopLevelFn(fn_name) => f.write_fmt(format_args!("'{fn_name}' is not a top level function")),
+            TypeErrorEnum::ExpectedFnName => f.write_str("Expected the name of a top level function"),
             TypeErrorEnum::PubFnWithoutParams(fn_name) => f.write_fmt(format_args!("The function '{fn_name}' is declared pub, but has no parameters")),
             TypeErrorEnum::UnusedFn(name) => f.write_fmt(format_args!(
                 "Function '{name}' is declared but never used"
             )),
+            TypeErrorEnum::UnusedVariable(name) => f.write_fmt(format_args!(
+                "Variable '{name}' is declared but never used (prefix it with '_' if this is intentional)"
+            )),
+            TypeErrorEnum::RecursiveConstDef(name) => f.write_fmt(format_args!(
+                "Const '{name}' is defined in terms of itself"
+            )),
             TypeErrorEnum::RecursiveFnDef(name) => f.write_fmt(format_args!(
                 "Function '{name}' is declared recursively, which is not supported"
             )),
+            TypeErrorEnum::CannotInferTypeParam(name) => f.write_fmt(format_args!(
+                "The type parameter '{name}' cannot be inferred from the arguments of the call"
+            )),
+            TypeErrorEnum::ConflictingTypeParam(name, a, b) => f.write_fmt(format_args!(
+                "The type parameter '{name}' is inferred as both '{a}' and '{b}'"
+            )),
+            TypeErrorEnum::NonIntegerTypeParam(name, ty) => f.write_fmt(format_args!(
+                "The type parameter '{name}' must be an integer type, but is inferred as '{ty}'"
+            )),
+            TypeErrorEnum::PubFnWithTypeParam(fn_name) => f.write_fmt(format_args!("The function '{fn_name}' is declared pub, but has a type parameter")),
+            TypeErrorEnum::UnknownRevealParty(party) => f.write_fmt(format_args!(
+                "The output cannot be revealed to '{party}', which is not a parameter of the function"
+            )),
+            TypeErrorEnum::MissingMain => f.write_str("The program does not define a 'main' function"),
+            TypeErrorEnum::DuplicateMain => f.write_str("The function 'main' is defined more than once"),
+            TypeErrorEnum::DuplicateFnDef(name) => f.write_fmt(format_args!("The function '{name}' is defined more than once")),
+            TypeErrorEnum::ExpectedNumberLiteral => {
+                f.write_str("Expected a number literal, because the value must be known at compile time")
+            }
+            TypeErrorEnum::ConstantCondition(b) => f.write_fmt(format_args!(
+                "The condition is always {b}, so one of the branches is never taken"
+            )),
+            TypeErrorEnum::ComparisonAlwaysFalse(ty) => f.write_fmt(format_args!(
+                "The comparison is always false, because the literal is outside the range of {ty}"
+            )),
+            TypeErrorEnum::StringTooLong {
+                max_len,
+                actual_len,
+            } => f.write_fmt(format_args!(
+                "The string literal has {actual_len} bytes, but at most {max_len} bytes are allowed"
+            )),
+            TypeErrorEnum::UnreachablePattern => f.write_str(
+                "The pattern is unreachable, because all of its cases are covered by previous patterns",
+            ),
+            TypeErrorEnum::InvalidConstOp(op) => f.write_fmt(format_args!(
+                "The constant operation '{op}' overflows, divides by zero or has an operand that is not a literal"
+            )),
+            TypeErrorEnum::EnumCastOutOfRange { enum_name, variants, ty } => f.write_fmt(format_args!(
+                "Cannot cast enum '{enum_name}' to {ty}, which is too small for the discriminants of its {variants} variants"
+            )),
+            TypeErrorEnum::InvalidArraySlice { from, to, ty } => f.write_fmt(format_args!(
+                "Cannot slice {ty} from {from} to {to}, slices must be non-empty and within the bounds of an array of literal size"
+            )),
             TypeErrorEnum::UnknownStructOrEnum(name) => {
                 f.write_fmt(format_args!("Unknown struct or enum '{name}'"))
             }
@@ -169,9 +307,25 @@ impl std::fmt::Display for TypeErrorEnum {
             TypeErrorEnum::ExpectedSignedNumberType(ty) => f.write_fmt(format_args!(
                 "Expected a signed number type, but found {ty}"
             )),
+            TypeErrorEnum::ExpectedUnsignedNumberType(ty) => f.write_fmt(format_args!(
+                "Expected an unsigned number type, but found {ty}"
+            )),
             TypeErrorEnum::ExpectedArrayType(ty) => {
                 f.write_fmt(format_args!("Expected an array type, but found {ty}"))
             }
+            TypeErrorEnum::InconsistentArrayElements {
+                expected,
+                found,
+                index,
+            } => f.write_fmt(format_args!(
+                "The elements of an array must have the same type, but element {index} has type {found} instead of {expected}"
+            )),
+            TypeErrorEnum::ExpectedIndexPath => f.write_str(
+                "Expected an index path, i.e. an array literal of number literals such as [0, 2, 1]",
+            ),
+            TypeErrorEnum::InvalidIndexPath(ty, i) => f.write_fmt(format_args!(
+                "The index {i} cannot be used to access a value of type {ty}"
+            )),
             TypeErrorEnum::ExpectedTupleType(ty) => {
                 f.write_fmt(format_args!("Expected a tuple type, but found {ty}"))
             }
@@ -230,9 +384,18 @@ impl std::fmt::Display for TypeErrorEnum {
             TypeErrorEnum::ArraySizeNotConst(identifier) => {
                 f.write_fmt(format_args!("Array sizes must be constants, but '{identifier}' is a variable"))
             }
+            TypeErrorEnum::ArraySizeNotUsize(identifier, ty) => f.write_fmt(format_args!(
+                "Array sizes must be usize constants, but '{identifier}' is of type {ty}"
+            )),
             TypeErrorEnum::UsizeNotLiteral => {
                 f.write_str("Expected a usize number literal")
             }
+            TypeErrorEnum::ExpectedHomogeneousTupleType(ty) => f.write_fmt(format_args!(
+                "Expected a non-empty tuple with fields of the same type, but found {ty}"
+            )),
+            TypeErrorEnum::TypeTooLarge(ty) => f.write_fmt(format_args!(
+                "The type {ty} is too large, its size in bits does not fit into a usize"
+            )),
         }
     }
 }
@@ -242,39 +405,86 @@ type TypeErrors = Vec<Option<TypeError>>;
 pub(crate) struct TopLevelTypes<'a> {
     pub(crate) struct_names: HashSet<&'a String>,
     pub(crate) enum_names: HashSet<&'a String>,
+    pub(crate) type_args: HashMap<String, Type>,
+    pub(crate) const_types: HashMap<&'a String, &'a Type>,
+    pub(crate) const_sizes: HashMap<&'a String, usize>,
+}
+
+/// Returns the values of the folded usize consts among the named consts, which can be used as
+/// array sizes.
+pub(crate) fn usize_const_sizes<'a>(
+    const_names: impl Iterator<Item = &'a String>,
+    const_defs: &HashMap<String, ConstDef>,
+) -> HashMap<&'a String, usize> {
+    const_names
+        .filter_map(|name| match const_defs.get(name)?.value {
+            ConstExpr(ConstExprEnum::NumUnsigned(n, UnsignedNumType::Usize), _) => {
+                Some((name, n as usize))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+impl TopLevelTypes<'_> {
+    fn resolve_const_size(
+        &self,
+        size: &String,
+        meta: MetaInfo,
+    ) -> Result<Option<usize>, TypeErrors> {
+        match self.const_types.get(size) {
+            Some(Type::Unsigned(UnsignedNumType::Usize)) => Ok(self.const_sizes.get(size).copied()),
+            Some(&ty) => {
+                let e = TypeErrorEnum::ArraySizeNotUsize(size.clone(), ty.clone());
+                Err(vec![Some(TypeError(e, meta))])
+            }
+            None => {
+                let e = TypeErrorEnum::UnknownIdentifier(size.clone());
+                Err(vec![Some(TypeError(e, meta))])
+            }
+        }
+    }
 }
 
 impl Type {
-    fn as_concrete_type(&self, types: &TopLevelTypes) -> Result<Type, TypeErrors> {
+    fn as_concrete_type(&self, types: &TopLevelTypes, meta: MetaInfo) -> Result<Type, TypeErrors> {
         let ty = match self {
             Type::Bool => Type::Bool,
+            Type::Char => Type::Char,
+            Type::String(max_len) => check_size_in_bits(Type::String(*max_len), meta)?,
             Type::Unsigned(n) => Type::Unsigned(*n),
             Type::Signed(n) => Type::Signed(*n),
+            Type::Fixed(bits, frac) => Type::Fixed(*bits, *frac),
             Type::Fn(args, ret) => {
                 let mut concrete_args = Vec::with_capacity(args.len());
                 for arg in args.iter() {
-                    concrete_args.push(arg.as_concrete_type(types)?);
+                    concrete_args.push(arg.as_concrete_type(types, meta)?);
                 }
-                let ret = ret.as_concrete_type(types)?;
+                let ret = ret.as_concrete_type(types, meta)?;
                 Type::Fn(concrete_args, Box::new(ret))
             }
             Type::Array(elem, size) => {
-                let elem = elem.as_concrete_type(types)?;
-                Type::Array(Box::new(elem), *size)
+                let elem = elem.as_concrete_type(types, meta)?;
+                check_size_in_bits(Type::Array(Box::new(elem), *size), meta)?
             }
             Type::ArrayConst(elem, size) => {
-                let elem = elem.as_concrete_type(types)?;
-                Type::ArrayConst(Box::new(elem), size.clone())
+                let elem = elem.as_concrete_type(types, meta)?;
+                match types.resolve_const_size(size, meta)? {
+                    Some(size) => check_size_in_bits(Type::Array(Box::new(elem), size), meta)?,
+                    None => Type::ArrayConst(Box::new(elem), size.clone()),
+                }
             }
             Type::Tuple(fields) => {
                 let mut concrete_fields = Vec::with_capacity(fields.len());
                 for field in fields.iter() {
-                    concrete_fields.push(field.as_concrete_type(types)?);
+                    concrete_fields.push(field.as_concrete_type(types, meta)?);
                 }
                 Type::Tuple(concrete_fields)
             }
             Type::UntypedTopLevelDefinition(name, meta) => {
-                if types.struct_names.contains(name) {
+                if let Some(ty) = types.type_args.get(name) {
+                    ty.clone()
+                } else if types.struct_names.contains(name) {
                     Type::Struct(name.clone())
                 } else if types.enum_names.contains(name) {
                     Type::Enum(name.clone())
@@ -296,6 +506,8 @@ pub struct Defs<'a> {
     structs: HashMap<&'a str, (Vec<&'a str>, HashMap<&'a str, Type>)>,
     enums: HashMap<&'a str, HashMap<&'a str, Option<Vec<Type>>>>,
     fns: HashMap<&'a str, &'a UntypedFnDef>,
+    strict: bool,
+    diagnostics: RefCell<Vec<TypeError>>,
 }
 
 impl<'a> Defs<'a> {
@@ -309,6 +521,8 @@ impl<'a> Defs<'a> {
             structs: HashMap::new(),
             enums: HashMap::new(),
             fns: HashMap::new(),
+            strict: false,
+            diagnostics: RefCell::new(vec![]),
         };
         for (const_name, ty) in const_defs.iter() {
             defs.consts.insert(const_name, ty);
@@ -331,6 +545,169 @@ impl<'a> Defs<'a> {
         }
         defs
     }
+
+    fn report_diagnostic(&self, e: TypeErrorEnum, meta: MetaInfo) {
+        self.diagnostics.borrow_mut().push(TypeError(e, meta));
+    }
+}
+
+// Folds const definitions into literals (or external values), resolving references to other
+// consts and constant operators, so that the compiler never needs to evaluate const exprs itself:
+struct ConstFolder<'a> {
+    const_defs: &'a HashMap<String, ConstDef>,
+    folded: HashMap<String, Option<ConstExpr>>,
+    being_folded: HashSet<String>,
+    const_deps: HashMap<String, HashMap<String, (Type, MetaInfo)>>,
+    errors: Vec<Option<TypeError>>,
+}
+
+impl ConstFolder<'_> {
+    fn fold_const_def(&mut self, const_name: &str, meta: MetaInfo) -> Option<ConstExpr> {
+        if let Some(folded) = self.folded.get(const_name) {
+            return folded.clone();
+        }
+        if !self.being_folded.insert(const_name.to_string()) {
+            let e = TypeErrorEnum::RecursiveConstDef(const_name.to_string());
+            self.errors.push(Some(TypeError(e, meta)));
+            return None;
+        }
+        let const_def = &self.const_defs[const_name];
+        let folded = self.fold_const_expr(&const_def.value, &const_def.ty);
+        self.being_folded.remove(const_name);
+        self.folded.insert(const_name.to_string(), folded.clone());
+        folded
+    }
+
+    fn fold_const_expr(&mut self, value: &ConstExpr, ty: &Type) -> Option<ConstExpr> {
+        let ConstExpr(value, meta) = value;
+        let meta = *meta;
+        let unexpected_type = |actual: Type| {
+            let e = TypeErrorEnum::UnexpectedType {
+                expected: ty.clone(),
+                actual,
+            };
+            Some(TypeError(e, meta))
+        };
+        let folded = match (value, ty) {
+            (ConstExprEnum::True | ConstExprEnum::False, Type::Bool) => value.clone(),
+            (ConstExprEnum::True | ConstExprEnum::False, _) => {
+                self.errors.push(unexpected_type(Type::Bool));
+                return None;
+            }
+            (ConstExprEnum::NumUnsigned(n, suffix), Type::Unsigned(ty_suffix))
+                if (suffix == ty_suffix || *suffix == UnsignedNumType::Unspecified)
+                    && (Some(*n) <= ty_suffix.max() || *ty_suffix == UnsignedNumType::U256) =>
+            {
+                ConstExprEnum::NumUnsigned(*n, *ty_suffix)
+            }
+            (
+                ConstExprEnum::NumUnsigned(n, UnsignedNumType::Unspecified),
+                Type::Signed(ty_suffix),
+            ) if Some(*n as i128) <= ty_suffix.max().map(|max| max as i128) => {
+                ConstExprEnum::NumSigned(*n as i64, *ty_suffix)
+            }
+            (ConstExprEnum::NumUnsigned(_, suffix), _) => {
+                self.errors.push(unexpected_type(Type::Unsigned(*suffix)));
+                return None;
+            }
+            (ConstExprEnum::NumSigned(n, suffix), Type::Signed(ty_suffix))
+                if (suffix == ty_suffix || *suffix == SignedNumType::Unspecified)
+                    && ty_suffix.min() <= Some(*n)
+                    && Some(*n) <= ty_suffix.max() =>
+            {
+                ConstExprEnum::NumSigned(*n, *ty_suffix)
+            }
+            (ConstExprEnum::NumSigned(_, suffix), _) => {
+                self.errors.push(unexpected_type(Type::Signed(*suffix)));
+                return None;
+            }
+            (ConstExprEnum::ExternalValue { party, identifier }, _) => {
+                self.const_deps
+                    .entry(party.clone())
+                    .or_default()
+                    .insert(identifier.clone(), (ty.clone(), meta));
+                value.clone()
+            }
+            (ConstExprEnum::Max(args) | ConstExprEnum::Min(args), _) => {
+                let mut folded_args = Vec::with_capacity(args.len());
+                for arg in args {
+                    folded_args.push(self.fold_const_expr(arg, ty));
+                }
+                let folded_args = folded_args.into_iter().collect::<Option<Vec<_>>>()?;
+                if let ConstExprEnum::Max(_) = value {
+                    ConstExprEnum::Max(folded_args)
+                } else {
+                    ConstExprEnum::Min(folded_args)
+                }
+            }
+            (ConstExprEnum::Const(const_name), _) => {
+                let Some(const_def) = self.const_defs.get(const_name) else {
+                    let e = TypeErrorEnum::UnknownIdentifier(const_name.clone());
+                    self.errors.push(Some(TypeError(e, meta)));
+                    return None;
+                };
+                if &const_def.ty != ty {
+                    self.errors.push(unexpected_type(const_def.ty.clone()));
+                    return None;
+                }
+                let ConstExpr(folded, _) = self.fold_const_def(const_name, meta)?;
+                folded
+            }
+            (ConstExprEnum::Op(op, x, y), Type::Unsigned(_) | Type::Signed(_)) => {
+                let x = self.fold_const_expr(x, ty);
+                let y = self.fold_const_expr(y, ty);
+                let as_i128 = |ConstExpr(value, _): ConstExpr| match value {
+                    ConstExprEnum::NumUnsigned(n, _) => Some(n as i128),
+                    ConstExprEnum::NumSigned(n, _) => Some(n as i128),
+                    _ => None,
+                };
+                let (x, y) = (as_i128(x?), as_i128(y?));
+                let result = match (x, y) {
+                    (Some(x), Some(y)) => match op {
+                        Op::Add => x.checked_add(y),
+                        Op::Sub => x.checked_sub(y),
+                        Op::Mul => x.checked_mul(y),
+                        Op::Div => x.checked_div(y),
+                        Op::Mod => x.checked_rem(y),
+                        Op::BitAnd => Some(x & y),
+                        Op::BitOr => Some(x | y),
+                        Op::BitXor => Some(x ^ y),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                match (result, ty) {
+                    (Some(n), Type::Unsigned(UnsignedNumType::U256))
+                        if n >= 0 && n <= u64::MAX as i128 =>
+                    {
+                        ConstExprEnum::NumUnsigned(n as u64, UnsignedNumType::U256)
+                    }
+                    (Some(n), Type::Unsigned(suffix))
+                        if n >= 0 && Some(n) <= suffix.max().map(|max| max as i128) =>
+                    {
+                        ConstExprEnum::NumUnsigned(n as u64, *suffix)
+                    }
+                    (Some(n), Type::Signed(suffix))
+                        if suffix.min().map(|min| min as i128) <= Some(n)
+                            && Some(n) <= suffix.max().map(|max| max as i128) =>
+                    {
+                        ConstExprEnum::NumSigned(n as i64, *suffix)
+                    }
+                    _ => {
+                        let e = TypeErrorEnum::InvalidConstOp(*op);
+                        self.errors.push(Some(TypeError(e, meta)));
+                        return None;
+                    }
+                }
+            }
+            (ConstExprEnum::Op(_, _, _), _) => {
+                let e = TypeErrorEnum::ExpectedNumberType(ty.clone());
+                self.errors.push(Some(TypeError(e, meta)));
+                return None;
+            }
+        };
+        Some(ConstExpr(folded, meta))
+    }
 }
 
 pub(crate) struct TypedFns {
@@ -347,85 +724,121 @@ impl TypedFns {
     }
 }
 
+/// Option-like enums (with the variants `None` and `Some(value)`) that are available in every
+/// program without being declared. Enums cannot be generic, so there is one enum for each primitive
+/// type. A struct or enum declared by the program with the same name takes precedence.
+const PRELUDE_OPTION_ENUMS: &[(&str, Type)] = &[
+    ("OptionBool", Type::Bool),
+    ("OptionU8", Type::Unsigned(UnsignedNumType::U8)),
+    ("OptionU16", Type::Unsigned(UnsignedNumType::U16)),
+    ("OptionU32", Type::Unsigned(UnsignedNumType::U32)),
+    ("OptionU64", Type::Unsigned(UnsignedNumType::U64)),
+    ("OptionUsize", Type::Unsigned(UnsignedNumType::Usize)),
+    ("OptionI8", Type::Signed(SignedNumType::I8)),
+    ("OptionI16", Type::Signed(SignedNumType::I16)),
+    ("OptionI32", Type::Signed(SignedNumType::I32)),
+    ("OptionI64", Type::Signed(SignedNumType::I64)),
+];
+
+fn prelude_option_enum(ty: &Type) -> EnumDef {
+    let variants = vec![
+        Variant::Unit("None".to_string()),
+        Variant::Tuple("Some".to_string(), vec![ty.clone()]),
+    ];
+    let meta = MetaInfo {
+        start: (0, 0),
+        end: (0, 0),
+    };
+    EnumDef { variants, meta }
+}
+
+/// Returns `true` if the enum is one of the enums that are available without being declared.
+pub(crate) fn is_prelude_enum(enum_name: &str, enum_def: &EnumDef) -> bool {
+    PRELUDE_OPTION_ENUMS
+        .iter()
+        .any(|(name, ty)| *name == enum_name && prelude_option_enum(ty) == *enum_def)
+}
+
 impl UntypedProgram {
+    fn prelude_enum_defs(&self) -> Vec<(String, EnumDef)> {
+        PRELUDE_OPTION_ENUMS
+            .iter()
+            .filter(|(name, _)| {
+                !self.enum_defs.contains_key(*name) && !self.struct_defs.contains_key(*name)
+            })
+            .map(|(name, ty)| (name.to_string(), prelude_option_enum(ty)))
+            .collect()
+    }
+
     /// Type-checks the parsed program, returning either a typed AST or type errors.
     pub fn type_check(&self) -> Result<TypedProgram, Vec<TypeError>> {
+        self.type_check_with_diagnostics(false).0
+    }
+
+    /// Type-checks the parsed program like [`UntypedProgram::type_check`], but additionally
+    /// reports likely logic bugs (such as `if` conditions that are always true or false) as
+    /// type errors.
+    pub fn type_check_strict(&self) -> Result<TypedProgram, Vec<TypeError>> {
+        match self.type_check_with_diagnostics(true) {
+            (Ok(program), diagnostics) if diagnostics.is_empty() => Ok(program),
+            (Ok(_), diagnostics) => Err(diagnostics),
+            (Err(mut errors), diagnostics) => {
+                errors.extend(diagnostics);
+                errors.sort();
+                errors.dedup();
+                Err(errors)
+            }
+        }
+    }
+
+    /// Type-checks the parsed program, returning the typed AST (or type errors) together with the
+    /// errors that are only reported in strict mode, which are only collected if `strict` is set.
+    pub(crate) fn type_check_with_diagnostics(
+        &self,
+        strict: bool,
+    ) -> (Result<TypedProgram, Vec<TypeError>>, Vec<TypeError>) {
         let mut errors = vec![];
+        let prelude_enum_defs = self.prelude_enum_defs();
         let mut struct_names = HashSet::with_capacity(self.struct_defs.len());
         let mut enum_names = HashSet::with_capacity(self.enum_defs.len());
         struct_names.extend(self.struct_defs.keys());
         enum_names.extend(self.enum_defs.keys());
-        let top_level_defs = TopLevelTypes {
-            struct_names,
-            enum_names,
+        enum_names.extend(prelude_enum_defs.iter().map(|(name, _)| name));
+        let mut consts = ConstFolder {
+            const_defs: &self.const_defs,
+            folded: HashMap::with_capacity(self.const_defs.len()),
+            being_folded: HashSet::new(),
+            const_deps: HashMap::new(),
+            errors: vec![],
         };
-        let mut const_deps: HashMap<String, HashMap<String, (Type, MetaInfo)>> = HashMap::new();
         let mut const_types = HashMap::with_capacity(self.const_defs.len());
         let mut const_defs = HashMap::with_capacity(self.const_defs.len());
-        {
-            for (const_name, const_def) in self.const_defs.iter() {
-                fn check_const_expr(
-                    value: &ConstExpr,
-                    const_def: &ConstDef,
-                    errors: &mut Vec<Option<TypeError>>,
-                    const_deps: &mut HashMap<String, HashMap<String, (Type, MetaInfo)>>,
-                ) {
-                    let ConstExpr(value, meta) = value;
-                    let meta = *meta;
-                    match value {
-                        ConstExprEnum::True | ConstExprEnum::False => {
-                            if const_def.ty != Type::Bool {
-                                let e = TypeErrorEnum::UnexpectedType {
-                                    expected: const_def.ty.clone(),
-                                    actual: Type::Bool,
-                                };
-                                errors.extend(vec![Some(TypeError(e, meta))]);
-                            }
-                        }
-                        ConstExprEnum::NumUnsigned(_, ty) => {
-                            let ty = Type::Unsigned(*ty);
-                            if const_def.ty != ty {
-                                let e = TypeErrorEnum::UnexpectedType {
-                                    expected: const_def.ty.clone(),
-                                    actual: ty,
-                                };
-                                errors.extend(vec![Some(TypeError(e, meta))]);
-                            }
-                        }
-                        ConstExprEnum::NumSigned(_, ty) => {
-                            let ty = Type::Signed(*ty);
-                            if const_def.ty != ty {
-                                let e = TypeErrorEnum::UnexpectedType {
-                                    expected: const_def.ty.clone(),
-                                    actual: ty,
-                                };
-                                errors.extend(vec![Some(TypeError(e, meta))]);
-                            }
-                        }
-                        ConstExprEnum::ExternalValue { party, identifier } => {
-                            const_deps
-                                .entry(party.clone())
-                                .or_default()
-                                .insert(identifier.clone(), (const_def.ty.clone(), meta));
-                        }
-                        ConstExprEnum::Max(args) | ConstExprEnum::Min(args) => {
-                            for arg in args {
-                                check_const_expr(arg, const_def, errors, const_deps);
-                            }
-                        }
-                    }
-                }
-                check_const_expr(&const_def.value, const_def, &mut errors, &mut const_deps);
-                const_defs.insert(const_name.clone(), const_def.clone());
-                const_types.insert(const_name.clone(), const_def.ty.clone());
+        // consts are folded in source order, so that a cycle is always reported at the same const:
+        let mut const_defs_in_order: Vec<_> = self.const_defs.iter().collect();
+        const_defs_in_order.sort_by_key(|(_, const_def)| const_def.meta);
+        for (const_name, const_def) in const_defs_in_order {
+            if let Some(value) = consts.fold_const_def(const_name, const_def.meta) {
+                let ty = const_def.ty.clone();
+                let meta = const_def.meta;
+                const_defs.insert(const_name.clone(), ConstDef { ty, value, meta });
             }
+            const_types.insert(const_name.clone(), const_def.ty.clone());
         }
+        let const_deps = consts.const_deps;
+        errors.extend(consts.errors);
+        let top_level_defs = TopLevelTypes {
+            struct_names,
+            enum_names,
+            type_args: HashMap::new(),
+            const_types: self.const_defs.iter().map(|(n, c)| (n, &c.ty)).collect(),
+            const_sizes: usize_const_sizes(self.const_defs.keys(), &const_defs),
+        };
         let mut struct_defs = HashMap::with_capacity(self.struct_defs.len());
         for (struct_name, struct_def) in self.struct_defs.iter() {
             let meta = struct_def.meta;
             let mut fields = Vec::with_capacity(struct_def.fields.len());
             for (name, ty) in struct_def.fields.iter() {
-                match ty.as_concrete_type(&top_level_defs) {
+                match ty.as_concrete_type(&top_level_defs, meta) {
                     Ok(ty) => fields.push((name.clone(), ty)),
                     Err(e) => errors.extend(e),
                 }
@@ -442,7 +855,7 @@ impl UntypedProgram {
                     Variant::Tuple(variant_name, variant_fields) => {
                         let mut fields = Vec::with_capacity(variant_fields.len());
                         for field in variant_fields.iter() {
-                            match field.as_concrete_type(&top_level_defs) {
+                            match field.as_concrete_type(&top_level_defs, meta) {
                                 Ok(field) => fields.push(field),
                                 Err(e) => errors.extend(e),
                             }
@@ -453,8 +866,36 @@ impl UntypedProgram {
             }
             enum_defs.insert(enum_name.clone(), EnumDef { variants, meta });
         }
+        enum_defs.extend(prelude_enum_defs.iter().cloned());
+
+        for (fn_name, meta) in self.duplicate_fn_defs.iter() {
+            let e = if fn_name == "main" {
+                TypeErrorEnum::DuplicateMain
+            } else {
+                TypeErrorEnum::DuplicateFnDef(fn_name.clone())
+            };
+            errors.push(Some(TypeError(e, *meta)));
+        }
+        if !self.fn_defs.contains_key("main") {
+            // there is no single location responsible for a missing main fn, so the error spans
+            // all top level definitions:
+            let metas = (self.const_defs.values().map(|def| def.meta))
+                .chain(self.struct_defs.values().map(|def| def.meta))
+                .chain(self.enum_defs.values().map(|def| def.meta))
+                .chain(self.fn_defs.values().map(|def| def.meta));
+            let meta = metas.reduce(|a, b| MetaInfo {
+                start: a.start.min(b.start),
+                end: a.end.max(b.end),
+            });
+            let meta = meta.unwrap_or(MetaInfo {
+                start: (0, 0),
+                end: (0, 0),
+            });
+            errors.push(Some(TypeError(TypeErrorEnum::MissingMain, meta)));
+        }
 
         let mut untyped_defs = Defs::new(&const_types, &struct_defs, &enum_defs);
+        untyped_defs.strict = strict;
         let mut checked_fn_defs = TypedFns::new();
         for (fn_name, fn_def) in self.fn_defs.iter() {
             untyped_defs.fns.insert(fn_name, fn_def);
@@ -464,6 +905,9 @@ impl UntypedProgram {
                 if fn_def.params.is_empty() {
                     let e = TypeErrorEnum::PubFnWithoutParams(fn_name.clone());
                     errors.push(Some(TypeError(e, fn_def.meta)));
+                } else if fn_def.type_param.is_some() {
+                    let e = TypeErrorEnum::PubFnWithTypeParam(fn_name.clone());
+                    errors.push(Some(TypeError(e, fn_def.meta)));
                 } else {
                     let typed_fn =
                         fn_def.type_check(&top_level_defs, &mut checked_fn_defs, &untyped_defs);
@@ -475,7 +919,13 @@ impl UntypedProgram {
             }
         }
         for (fn_name, fn_def) in self.fn_defs.iter() {
-            if !fn_def.is_pub && !checked_fn_defs.typed.contains_key(fn_name.as_str()) {
+            let instance_prefix = format!("{fn_name}::<");
+            let is_used = checked_fn_defs.typed.contains_key(fn_name.as_str())
+                || checked_fn_defs
+                    .typed
+                    .keys()
+                    .any(|name| name.starts_with(&instance_prefix));
+            if !fn_def.is_pub && !is_used {
                 let e = TypeErrorEnum::UnusedFn(fn_name.to_string());
                 errors.push(Some(TypeError(e, fn_def.meta)));
             }
@@ -486,19 +936,27 @@ impl UntypedProgram {
                 fn_defs.insert(fn_name, fn_def);
             }
         }
-        if errors.is_empty() {
+        let mut diagnostics = untyped_defs.diagnostics.take();
+        diagnostics.sort();
+        // the instances of a generic fn report the same diagnostics:
+        diagnostics.dedup();
+        let program = if errors.is_empty() {
             Ok(TypedProgram {
                 const_deps,
                 const_defs,
                 struct_defs,
                 enum_defs,
                 fn_defs,
+                duplicate_fn_defs: vec![],
             })
         } else {
             let mut errors: Vec<TypeError> = errors.into_iter().flatten().collect();
             errors.sort();
+            // the instances of a generic fn report the same errors:
+            errors.dedup();
             Err(errors)
-        }
+        };
+        (program, diagnostics)
     }
 }
 
@@ -509,11 +967,23 @@ impl UntypedFnDef {
         fns: &mut TypedFns,
         defs: &Defs,
     ) -> Result<TypedFnDef, TypeErrors> {
-        if fns.currently_being_checked.contains(&self.identifier) {
-            let e = TypeErrorEnum::RecursiveFnDef(self.identifier.clone());
+        self.type_check_as(&self.identifier, top_level_defs, fns, defs)
+    }
+
+    /// Type-checks the fn under the given name, which differs from the fn's identifier for
+    /// instances of generic fns.
+    fn type_check_as(
+        &self,
+        identifier: &str,
+        top_level_defs: &TopLevelTypes,
+        fns: &mut TypedFns,
+        defs: &Defs,
+    ) -> Result<TypedFnDef, TypeErrors> {
+        if fns.currently_being_checked.contains(identifier) {
+            let e = TypeErrorEnum::RecursiveFnDef(identifier.to_string());
             return Err(vec![Some(TypeError(e, self.meta))]);
         } else {
-            fns.currently_being_checked.insert(self.identifier.clone());
+            fns.currently_being_checked.insert(identifier.to_string());
         }
         let mut errors = vec![];
         let mut env = Env::new();
@@ -527,7 +997,7 @@ impl UntypedFnDef {
             } else {
                 param_identifiers.insert(param.name.clone());
             }
-            match param.ty.as_concrete_type(top_level_defs) {
+            match param.ty.as_concrete_type(top_level_defs, self.meta) {
                 Ok(ty) => {
                     env.let_in_current_scope(
                         param.name.clone(),
@@ -546,12 +1016,25 @@ impl UntypedFnDef {
             }
         }
 
+        if let Some((party, attr_meta)) = &self.reveal_to {
+            if !param_identifiers.contains(party) {
+                let e = TypeErrorEnum::UnknownRevealParty(party.clone());
+                errors.push(Some(TypeError(e, *attr_meta)));
+            }
+        }
+
         let body = type_check_block(&self.body, top_level_defs, &mut env, fns, defs);
-        fns.currently_being_checked.remove(&self.identifier);
+        fns.currently_being_checked.remove(identifier);
         env.pop();
+        if body.is_ok() {
+            for (name, meta) in env.take_unused() {
+                let e = TypeErrorEnum::UnusedVariable(name);
+                errors.push(Some(TypeError(e, meta)));
+            }
+        }
 
         match body {
-            Ok((mut body, _)) => match self.ty.as_concrete_type(top_level_defs) {
+            Ok((mut body, _)) => match self.ty.as_concrete_type(top_level_defs, self.meta) {
                 Ok(ret_ty) => {
                     if let Some(StmtEnum::Expr(ret_expr)) = body.last_mut().map(|s| &mut s.inner) {
                         if let Err(e) = check_type(ret_expr, &ret_ty) {
@@ -567,7 +1050,9 @@ impl UntypedFnDef {
                     if errors.is_empty() {
                         Ok(TypedFnDef {
                             is_pub: self.is_pub,
-                            identifier: self.identifier.clone(),
+                            identifier: identifier.to_string(),
+                            type_param: None,
+                            reveal_to: self.reveal_to.clone(),
                             params,
                             ty: ret_ty,
                             body,
@@ -637,6 +1122,13 @@ impl UntypedStmt {
                     Ok(binding) => {
                         let pattern =
                             pattern.type_check(env, fns, defs, Some(binding.ty.clone()))?;
+                        let mut identifiers = vec![];
+                        bound_identifiers(&pattern, &mut identifiers);
+                        for (identifier, meta) in identifiers {
+                            if !identifier.starts_with('_') {
+                                env.track_unused(identifier, meta);
+                            }
+                        }
                         Ok(Stmt::new(StmtEnum::Let(pattern, binding), meta))
                     }
                     Err(mut errors) => {
@@ -666,6 +1158,9 @@ impl UntypedStmt {
                             identifier.clone(),
                             (Some(binding.ty.clone()), Mutability::Mutable),
                         );
+                        if !identifier.starts_with('_') {
+                            env.track_unused(identifier.clone(), meta);
+                        }
                         Ok(Stmt::new(
                             StmtEnum::LetMut(identifier.clone(), binding),
                             meta,
@@ -834,12 +1329,28 @@ impl UntypedExpr {
                 ExprEnum::NumUnsigned(*n, *type_suffix),
                 Type::Unsigned(*type_suffix),
             ),
+            ExprEnum::NumUnsignedBytes(bytes, _) => (
+                ExprEnum::NumUnsignedBytes(bytes.clone(), UnsignedNumType::U256),
+                Type::Unsigned(UnsignedNumType::U256),
+            ),
             ExprEnum::NumSigned(n, type_suffix) => (
                 ExprEnum::NumSigned(*n, *type_suffix),
                 Type::Signed(*type_suffix),
             ),
+            ExprEnum::NumFixed(n, bits, frac) => (
+                ExprEnum::NumFixed(*n, *bits, *frac),
+                Type::Fixed(*bits, *frac),
+            ),
+            ExprEnum::Char(c) => (ExprEnum::Char(*c), Type::Char),
+            ExprEnum::StringLiteral(s) => {
+                let ty = Type::Array(Box::new(Type::Unsigned(UnsignedNumType::U8)), s.len());
+                (ExprEnum::StringLiteral(s.clone()), ty)
+            }
             ExprEnum::Identifier(identifier) => match env.get(identifier) {
-                Some((Some(ty), _mutability)) => (ExprEnum::Identifier(identifier.clone()), ty),
+                Some((Some(ty), _mutability)) => {
+                    env.mark_used(identifier);
+                    (ExprEnum::Identifier(identifier.clone()), ty)
+                }
                 Some((None, _mutability)) => {
                     return Err(vec![None]);
                 }
@@ -885,8 +1396,16 @@ impl UntypedExpr {
                     }
                 }
 
-                for field in typed_fields.iter_mut() {
-                    check_type(field, &elem_ty)?;
+                for (index, field) in typed_fields.iter_mut().enumerate() {
+                    let found = field.ty.clone();
+                    if check_type(field, &elem_ty).is_err() {
+                        let e = TypeErrorEnum::InconsistentArrayElements {
+                            expected: elem_ty,
+                            found,
+                            index,
+                        };
+                        return Err(vec![Some(TypeError(e, field.meta))]);
+                    }
                 }
                 if errors.is_empty() {
                     let ty = Type::Array(Box::new(elem_ty), array_size);
@@ -898,32 +1417,25 @@ impl UntypedExpr {
             ExprEnum::ArrayRepeatLiteral(value, size) => {
                 let value = value.type_check(top_level_defs, env, fns, defs)?;
                 let ty = Type::Array(Box::new(value.ty.clone()), *size);
+                let ty = check_size_in_bits(ty, meta)?;
                 (ExprEnum::ArrayRepeatLiteral(Box::new(value), *size), ty)
             }
             ExprEnum::ArrayRepeatLiteralConst(value, size) => match env.get(size) {
-                None => match defs.consts.get(size.as_str()) {
-                    Some(&ty) if ty == &Type::Unsigned(UnsignedNumType::Usize) => {
-                        let value = value.type_check(top_level_defs, env, fns, defs)?;
-                        let ty = Type::ArrayConst(Box::new(value.ty.clone()), size.clone());
-                        (
+                None => {
+                    let resolved_size = top_level_defs.resolve_const_size(size, meta)?;
+                    let value = value.type_check(top_level_defs, env, fns, defs)?;
+                    let elem_ty = Box::new(value.ty.clone());
+                    match resolved_size {
+                        Some(size) => (
+                            ExprEnum::ArrayRepeatLiteral(Box::new(value), size),
+                            check_size_in_bits(Type::Array(elem_ty, size), meta)?,
+                        ),
+                        None => (
                             ExprEnum::ArrayRepeatLiteralConst(Box::new(value), size.clone()),
-                            ty,
-                        )
-                    }
-                    Some(&ty) => {
-                        let e = TypeErrorEnum::UnexpectedType {
-                            expected: Type::Unsigned(UnsignedNumType::Usize),
-                            actual: ty.clone(),
-                        };
-                        return Err(vec![Some(TypeError(e, meta))]);
-                    }
-                    None => {
-                        return Err(vec![Some(TypeError(
-                            TypeErrorEnum::UnknownIdentifier(size.clone()),
-                            meta,
-                        ))]);
+                            Type::ArrayConst(elem_ty, size.clone()),
+                        ),
                     }
-                },
+                }
                 Some(_) => {
                     return Err(vec![Some(TypeError(
                         TypeErrorEnum::ArraySizeNotConst(size.clone()),
@@ -941,6 +1453,25 @@ impl UntypedExpr {
                     elem_ty,
                 )
             }
+            ExprEnum::ArraySlice(arr, from, to) => {
+                let arr = arr.type_check(top_level_defs, env, fns, defs)?;
+                let (from, to) = (*from, *to);
+                match &arr.ty {
+                    Type::Array(elem_ty, size) if from < to && to <= *size => {
+                        let ty = Type::Array(elem_ty.clone(), to - from);
+                        (ExprEnum::ArraySlice(Box::new(arr), from, to), ty)
+                    }
+                    Type::Array(_, _) | Type::ArrayConst(_, _) => {
+                        let ty = arr.ty.clone();
+                        let e = TypeErrorEnum::InvalidArraySlice { from, to, ty };
+                        return Err(vec![Some(TypeError(e, meta))]);
+                    }
+                    ty => {
+                        let e = TypeErrorEnum::ExpectedArrayType(ty.clone());
+                        return Err(vec![Some(TypeError(e, arr.meta))]);
+                    }
+                }
+            }
             ExprEnum::TupleLiteral(values) => {
                 let mut errors = vec![];
                 let mut typed_values = Vec::with_capacity(values.len());
@@ -977,21 +1508,33 @@ impl UntypedExpr {
             ExprEnum::UnaryOp(UnaryOp::Neg, x) => {
                 let x = x.type_check(top_level_defs, env, fns, defs)?;
                 let ty = x.ty.clone();
-                expect_signed_num_type(&ty, x.meta)?;
+                if !matches!(ty, Type::Fixed(_, _)) {
+                    expect_signed_num_type(&ty, x.meta)?;
+                }
                 (ExprEnum::UnaryOp(UnaryOp::Neg, Box::new(x)), ty)
             }
             ExprEnum::UnaryOp(UnaryOp::Not, x) => {
                 let x = x.type_check(top_level_defs, env, fns, defs)?;
                 let ty = x.ty.clone();
-                expect_bool_or_num_type(&ty, x.meta)?;
+                expect_bitwise_type(&ty, x.meta)?;
                 (ExprEnum::UnaryOp(UnaryOp::Not, Box::new(x)), ty)
             }
             ExprEnum::Op(op, x, y) => match op {
-                Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Mod => {
+                Op::Add
+                | Op::Sub
+                | Op::Mul
+                | Op::Div
+                | Op::Mod
+                | Op::SaturatingAdd
+                | Op::SaturatingSub
+                | Op::SaturatingMul => {
                     let mut x = x.type_check(top_level_defs, env, fns, defs)?;
                     let mut y = y.type_check(top_level_defs, env, fns, defs)?;
                     let ty = unify(&mut x, &mut y, meta)?;
-                    expect_num_type(&ty, meta)?;
+                    match (op, &ty) {
+                        (Op::Add | Op::Sub | Op::Mul, Type::Fixed(_, _)) => {}
+                        _ => expect_num_type(&ty, meta)?,
+                    }
                     (ExprEnum::Op(*op, Box::new(x), Box::new(y)), ty)
                 }
                 Op::ShortCircuitAnd | Op::ShortCircuitOr => {
@@ -1017,24 +1560,42 @@ impl UntypedExpr {
                     let mut x = x.type_check(top_level_defs, env, fns, defs)?;
                     let mut y = y.type_check(top_level_defs, env, fns, defs)?;
                     let ty = unify(&mut x, &mut y, meta)?;
-                    expect_bool_or_num_type(&ty, meta)?;
+                    expect_bitwise_type(&ty, meta)?;
                     (ExprEnum::Op(*op, Box::new(x), Box::new(y)), ty)
                 }
                 Op::GreaterThan | Op::LessThan => {
                     let mut x = x.type_check(top_level_defs, env, fns, defs)?;
                     let mut y = y.type_check(top_level_defs, env, fns, defs)?;
                     let ty = unify(&mut x, &mut y, meta)?;
-                    expect_num_type(&ty, meta)?;
+                    if ty != Type::Char && !matches!(ty, Type::Fixed(_, _)) {
+                        expect_num_type(&ty, meta)?;
+                    }
                     (ExprEnum::Op(*op, Box::new(x), Box::new(y)), Type::Bool)
                 }
                 Op::Eq | Op::NotEq => {
                     let mut x = x.type_check(top_level_defs, env, fns, defs)?;
                     let mut y = y.type_check(top_level_defs, env, fns, defs)?;
-                    unify(&mut x, &mut y, meta)?;
+                    let mut always_false = None;
+                    if defs.strict && *op == Op::Eq {
+                        for (num, literal) in [(&x, &y), (&y, &x)] {
+                            if is_literal_out_of_range(literal, &num.ty) {
+                                always_false =
+                                    Some(TypeErrorEnum::ComparisonAlwaysFalse(num.ty.clone()));
+                                break;
+                            }
+                        }
+                    }
+                    match (unify(&mut x, &mut y, meta), always_false) {
+                        // the out-of-range literal is the more helpful explanation of the error:
+                        (Err(_), Some(e)) => return Err(vec![Some(TypeError(e, meta))]),
+                        (Err(e), None) => return Err(e),
+                        (Ok(_), Some(e)) => defs.report_diagnostic(e, meta),
+                        (Ok(_), None) => {}
+                    }
                     let expr = ExprEnum::Op(*op, Box::new(x), Box::new(y));
                     (expr, Type::Bool)
                 }
-                Op::ShiftLeft | Op::ShiftRight => {
+                Op::ShiftLeft | Op::ShiftRight | Op::RotateRight | Op::RotateLeft => {
                     let x = x.type_check(top_level_defs, env, fns, defs)?;
                     let mut y = y.type_check(top_level_defs, env, fns, defs)?;
                     expect_num_type(&x.ty, x.meta)?;
@@ -1048,18 +1609,59 @@ impl UntypedExpr {
                 env.pop();
                 (ExprEnum::Block(body), ty)
             }
+            ExprEnum::FnCall(identifier, args)
+                if identifier == "map2" && !defs.fns.contains_key(identifier.as_str()) =>
+            {
+                type_check_map2(args, meta, top_level_defs, env, fns, defs)?
+            }
+            ExprEnum::FnCall(identifier, args)
+                if identifier == "filter_mask" && !defs.fns.contains_key(identifier.as_str()) =>
+            {
+                type_check_filter_mask(args, meta, top_level_defs, env, fns, defs)?
+            }
+            ExprEnum::FnCall(identifier, args)
+                if BUILTIN_FNS.contains(&identifier.as_str())
+                    && !defs.fns.contains_key(identifier.as_str()) =>
+            {
+                type_check_builtin_fn_call(identifier, args, meta, top_level_defs, env, fns, defs)?
+            }
+            ExprEnum::BuiltinFnCall(identifier, args) => {
+                if !BUILTIN_FNS.contains(&identifier.as_str()) {
+                    let e = TypeErrorEnum::UnknownIdentifier(identifier.clone());
+                    return Err(vec![Some(TypeError(e, meta))]);
+                }
+                type_check_builtin_fn_call(identifier, args, meta, top_level_defs, env, fns, defs)?
+            }
             ExprEnum::FnCall(identifier, args) => {
                 let mut errors = vec![];
-                if !fns.typed.contains_key(identifier) {
-                    if let Some(fn_def) = defs.fns.get(identifier.as_str()) {
+                let mut fn_name = identifier.clone();
+                let mut checked_args = None;
+                match defs.fns.get(identifier.as_str()) {
+                    Some(fn_def)
+                        if fn_def.type_param.is_some() && env.get(identifier).is_none() =>
+                    {
+                        let (instance, args) = type_check_generic_fn_call(
+                            fn_def,
+                            args,
+                            meta,
+                            top_level_defs,
+                            env,
+                            fns,
+                            defs,
+                        )?;
+                        fn_name = instance;
+                        checked_args = Some(args.into_iter().map(Ok).collect::<Vec<_>>());
+                    }
+                    Some(fn_def) if !fns.typed.contains_key(identifier) => {
                         let fn_def = fn_def.type_check(top_level_defs, fns, defs);
                         fns.typed.insert(identifier.clone(), fn_def.clone());
                         if let Err(e) = fn_def {
                             errors.extend(e);
                         }
                     }
+                    _ => {}
                 }
-                match (fns.typed.get(identifier), env.get(identifier)) {
+                match (fns.typed.get(&fn_name), env.get(identifier)) {
                     (Some(Ok(fn_def)), None) => {
                         let ret_ty = fn_def.ty.clone();
                         let mut fn_arg_types = Vec::with_capacity(fn_def.params.len());
@@ -1069,8 +1671,15 @@ impl UntypedExpr {
                         let mut arg_types = Vec::with_capacity(args.len());
                         let mut arg_meta = Vec::with_capacity(args.len());
                         let mut arg_exprs = Vec::with_capacity(args.len());
-                        for arg in args.iter() {
-                            match arg.type_check(top_level_defs, env, fns, defs) {
+                        let typed_args = match checked_args {
+                            Some(typed_args) => typed_args,
+                            None => args
+                                .iter()
+                                .map(|arg| arg.type_check(top_level_defs, env, fns, defs))
+                                .collect(),
+                        };
+                        for arg in typed_args {
+                            match arg {
                                 Ok(arg) => {
                                     arg_types.push(arg.ty.clone());
                                     arg_meta.push(arg.meta);
@@ -1094,7 +1703,7 @@ impl UntypedExpr {
                             }
                         }
                         if errors.is_empty() {
-                            let expr = ExprEnum::FnCall(identifier.clone(), arg_exprs);
+                            let expr = ExprEnum::FnCall(fn_name, arg_exprs);
                             (expr, ret_ty)
                         } else {
                             return Err(errors);
@@ -1126,6 +1735,12 @@ impl UntypedExpr {
                 match (condition, case_true, case_false) {
                     (Ok(mut condition), Ok(mut case_true), Ok(mut case_false)) => {
                         check_type(&mut condition, &Type::Bool)?;
+                        if defs.strict {
+                            if let Some(b) = const_bool(&condition) {
+                                let e = TypeErrorEnum::ConstantCondition(b);
+                                defs.report_diagnostic(e, condition.meta);
+                            }
+                        }
                         let ty = unify(&mut case_true, &mut case_false, meta)?;
                         let expr = ExprEnum::If(
                             Box::new(condition),
@@ -1150,10 +1765,55 @@ impl UntypedExpr {
                 }
             }
             ExprEnum::Cast(ty, expr) => {
-                let ty = ty.as_concrete_type(top_level_defs)?;
-                let expr = expr.type_check(top_level_defs, env, fns, defs)?;
-                expect_bool_or_num_type(&expr.ty, meta)?;
-                expect_bool_or_num_type(&ty, meta)?;
+                let ty = ty.as_concrete_type(top_level_defs, meta)?;
+                let mut expr = expr.type_check(top_level_defs, env, fns, defs)?;
+                let u32_ty = Type::Unsigned(UnsignedNumType::U32);
+                match (&expr.ty, &ty) {
+                    // chars can only be cast from and to u32:
+                    (Type::Char, Type::Char) => {}
+                    (Type::Char, ty) if ty == &u32_ty => {}
+                    (Type::Char, ty) => {
+                        let e = TypeErrorEnum::UnexpectedType {
+                            expected: u32_ty,
+                            actual: ty.clone(),
+                        };
+                        return Err(vec![Some(TypeError(e, meta))]);
+                    }
+                    (_, Type::Char) => {
+                        check_or_constrain_unsigned(&mut expr, UnsignedNumType::U32)?
+                    }
+                    // fixed-point numbers can only be cast from and to other numbers:
+                    (Type::Fixed(_, _), Type::Fixed(_, _)) => {}
+                    (Type::Fixed(_, _), ty) | (ty, Type::Fixed(_, _)) => {
+                        expect_num_type(ty, meta)?;
+                    }
+                    // enums can be cast to their discriminant (the index of the variant), but
+                    // numbers cannot be cast to enums:
+                    (Type::Enum(enum_name), ty) => {
+                        let variants = defs.enums.get(enum_name.as_str()).map_or(0, |v| v.len());
+                        let max = match ty {
+                            Type::Unsigned(UnsignedNumType::U256) => Some(u64::MAX),
+                            Type::Unsigned(ty) => ty.max(),
+                            Type::Signed(ty) => ty.max().map(|max| max as u64),
+                            _ => {
+                                let e = TypeErrorEnum::ExpectedNumberType(ty.clone());
+                                return Err(vec![Some(TypeError(e, meta))]);
+                            }
+                        };
+                        if max.unwrap_or(0) < variants.saturating_sub(1) as u64 {
+                            let e = TypeErrorEnum::EnumCastOutOfRange {
+                                enum_name: enum_name.clone(),
+                                variants,
+                                ty: ty.clone(),
+                            };
+                            return Err(vec![Some(TypeError(e, meta))]);
+                        }
+                    }
+                    _ => {
+                        expect_bool_or_num_type(&expr.ty, meta)?;
+                        expect_bool_or_num_type(&ty, meta)?;
+                    }
+                }
                 (ExprEnum::Cast(ty.clone(), Box::new(expr)), ty)
             }
             ExprEnum::Range((from, from_suffix), (to, to_suffix)) => {
@@ -1250,7 +1910,12 @@ impl UntypedExpr {
                     | Type::Tuple(_)
                     | Type::Struct(_)
                     | Type::Enum(_) => {}
-                    Type::Fn(_, _) | Type::Array(_, _) | Type::ArrayConst(_, _) => {
+                    Type::Char
+                    | Type::Fixed(_, _)
+                    | Type::String(_)
+                    | Type::Fn(_, _)
+                    | Type::Array(_, _)
+                    | Type::ArrayConst(_, _) => {
                         let e = TypeErrorEnum::TypeDoesNotSupportPatternMatching(ty.clone());
                         return Err(vec![Some(TypeError(e, meta))]);
                     }
@@ -1321,6 +1986,10 @@ impl UntypedExpr {
                 };
 
                 let patterns: Vec<_> = typed_clauses.iter().map(|(p, _)| p).collect();
+                if defs.strict {
+                    let unreachable = check_reachability(patterns.as_slice(), defs);
+                    defs.diagnostics.borrow_mut().extend(unreachable);
+                }
                 if let Err(e) = check_exhaustiveness(patterns.as_slice(), ty, defs, meta) {
                     errors.push(Some(e));
                 }
@@ -1376,6 +2045,56 @@ impl UntypedExpr {
                     return Err(vec![Some(TypeError(e, meta))]);
                 }
             }
+            ExprEnum::StructUpdate(name, fields, base) => {
+                if let Some((_, struct_def)) = defs.structs.get(name.as_str()) {
+                    let mut errors = vec![];
+                    let mut typed_fields = Vec::with_capacity(fields.len());
+                    for (field_name, field_value) in fields {
+                        if let Some(expected_type) = struct_def.get(field_name.as_str()) {
+                            match field_value.type_check(top_level_defs, env, fns, defs) {
+                                Ok(mut typed_field) => {
+                                    if let Err(e) = check_type(&mut typed_field, expected_type) {
+                                        errors.extend(e);
+                                    }
+                                    typed_fields.push((field_name.clone(), typed_field));
+                                }
+                                Err(e) => errors.extend(e),
+                            }
+                        } else {
+                            let e =
+                                TypeErrorEnum::UnknownStructField(name.clone(), field_name.clone());
+                            errors.push(Some(TypeError(e, meta)));
+                        }
+                    }
+                    let ty = Type::Struct(name.clone());
+                    let base = match base.type_check(top_level_defs, env, fns, defs) {
+                        Ok(base) => {
+                            if base.ty != ty {
+                                let e = TypeErrorEnum::UnexpectedType {
+                                    expected: ty.clone(),
+                                    actual: base.ty.clone(),
+                                };
+                                errors.push(Some(TypeError(e, base.meta)));
+                            }
+                            Some(base)
+                        }
+                        Err(e) => {
+                            errors.extend(e);
+                            None
+                        }
+                    };
+                    match base {
+                        Some(base) if errors.is_empty() => (
+                            ExprEnum::StructUpdate(name.clone(), typed_fields, Box::new(base)),
+                            ty,
+                        ),
+                        _ => return Err(errors),
+                    }
+                } else {
+                    let e = TypeErrorEnum::UnknownStruct(name.clone());
+                    return Err(vec![Some(TypeError(e, meta))]);
+                }
+            }
             ExprEnum::StructAccess(struct_expr, field) => {
                 let struct_expr = struct_expr.type_check(top_level_defs, env, fns, defs)?;
                 let name = expect_struct_type(&struct_expr.ty, struct_expr.meta)?;
@@ -1399,12 +2118,860 @@ impl UntypedExpr {
     }
 }
 
-impl UntypedPattern {
-    fn type_check(
-        &self,
-        env: &mut Env<(Option<Type>, Mutability)>,
-        _fns: &mut TypedFns,
-        defs: &Defs,
+/// Infers the integer type parameter of a generic fn from the args and type checks the instance of
+/// the fn for this type (once per type), returning the name of the instance and the typed args.
+fn type_check_generic_fn_call(
+    fn_def: &UntypedFnDef,
+    args: &[UntypedExpr],
+    meta: MetaInfo,
+    top_level_defs: &TopLevelTypes,
+    env: &mut Env<(Option<Type>, Mutability)>,
+    fns: &mut TypedFns,
+    defs: &Defs,
+) -> Result<(String, Vec<TypedExpr>), TypeErrors> {
+    let identifier = &fn_def.identifier;
+    let type_param = fn_def.type_param.as_deref().unwrap_or_default();
+    let mut errors = vec![];
+    let mut typed_args = Vec::with_capacity(args.len());
+    for arg in args.iter() {
+        match arg.type_check(top_level_defs, env, fns, defs) {
+            Ok(arg) => typed_args.push(arg),
+            Err(e) => errors.extend(e),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    if fn_def.params.len() != typed_args.len() {
+        let e = TypeErrorEnum::WrongNumberOfArgs {
+            expected: fn_def.params.len(),
+            actual: typed_args.len(),
+        };
+        return Err(vec![Some(TypeError(e, meta))]);
+    }
+    let mut candidates = vec![];
+    for (param, arg) in fn_def.params.iter().zip(typed_args.iter()) {
+        infer_type_arg(type_param, &param.ty, &arg.ty, &mut candidates);
+    }
+    let is_unspecified = |ty: &&Type| {
+        matches!(
+            ty,
+            Type::Unsigned(UnsignedNumType::Unspecified) | Type::Signed(SignedNumType::Unspecified)
+        )
+    };
+    let mut specified = candidates.iter().filter(|ty| !is_unspecified(ty));
+    let type_arg = match (specified.next(), candidates.first()) {
+        (Some(first), _) => match specified.find(|ty| *ty != first) {
+            Some(other) => Err(TypeErrorEnum::ConflictingTypeParam(
+                type_param.to_string(),
+                Box::new(first.clone()),
+                Box::new(other.clone()),
+            )),
+            None => Ok(first.clone()),
+        },
+        (None, Some(_)) => Ok(Type::Signed(SignedNumType::I32)),
+        (None, None) => Err(TypeErrorEnum::CannotInferTypeParam(type_param.to_string())),
+    }
+    .and_then(|ty| match ty {
+        Type::Unsigned(_) | Type::Signed(_) => Ok(ty),
+        ty => Err(TypeErrorEnum::NonIntegerTypeParam(
+            type_param.to_string(),
+            ty,
+        )),
+    });
+    let type_arg = match type_arg {
+        Ok(ty) => ty,
+        Err(e) => {
+            // the fn is still marked as checked, so that it is not reported as unused:
+            let errors = vec![Some(TypeError(e, meta))];
+            fns.typed.insert(identifier.clone(), Err(errors.clone()));
+            return Err(errors);
+        }
+    };
+    let instance = format!("{identifier}::<{type_arg}>");
+    if !fns.typed.contains_key(&instance) {
+        // instantiating a generic fn inside one of its own instances could go on forever:
+        let instance_prefix = format!("{identifier}::<");
+        if fns
+            .currently_being_checked
+            .iter()
+            .any(|name| name.starts_with(&instance_prefix))
+        {
+            let e = TypeErrorEnum::RecursiveFnDef(identifier.clone());
+            return Err(vec![Some(TypeError(e, fn_def.meta))]);
+        }
+        let mut type_args = top_level_defs.type_args.clone();
+        type_args.insert(type_param.to_string(), type_arg);
+        let instance_defs = TopLevelTypes {
+            struct_names: top_level_defs.struct_names.clone(),
+            enum_names: top_level_defs.enum_names.clone(),
+            type_args,
+            const_types: top_level_defs.const_types.clone(),
+            const_sizes: top_level_defs.const_sizes.clone(),
+        };
+        let typed_fn = fn_def.type_check_as(&instance, &instance_defs, fns, defs);
+        fns.typed.insert(instance.clone(), typed_fn);
+    }
+    Ok((instance, typed_args))
+}
+
+/// Collects the types that the type param would need to be bound to for the param type to match
+/// the type of the arg.
+fn infer_type_arg(type_param: &str, param_ty: &Type, arg_ty: &Type, candidates: &mut Vec<Type>) {
+    match (param_ty, arg_ty) {
+        (Type::UntypedTopLevelDefinition(name, _), ty) if name == type_param => {
+            candidates.push(ty.clone())
+        }
+        (Type::Array(param_elem, _), Type::Array(arg_elem, _))
+        | (Type::Array(param_elem, _), Type::ArrayConst(arg_elem, _))
+        | (Type::ArrayConst(param_elem, _), Type::Array(arg_elem, _))
+        | (Type::ArrayConst(param_elem, _), Type::ArrayConst(arg_elem, _)) => {
+            infer_type_arg(type_param, param_elem, arg_elem, candidates)
+        }
+        (Type::Tuple(param_fields), Type::Tuple(arg_fields)) => {
+            for (param_field, arg_field) in param_fields.iter().zip(arg_fields.iter()) {
+                infer_type_arg(type_param, param_field, arg_field, candidates);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Type checks `map2(a, b, f)` as an array of the calls `f(a[i], b[i])`.
+fn type_check_map2(
+    args: &[UntypedExpr],
+    meta: MetaInfo,
+    top_level_defs: &TopLevelTypes,
+    env: &mut Env<(Option<Type>, Mutability)>,
+    fns: &mut TypedFns,
+    defs: &Defs,
+) -> Result<(ExprEnum<Type>, Type), TypeErrors> {
+    let [a, b, f] = args else {
+        let e = TypeErrorEnum::WrongNumberOfArgs {
+            expected: 3,
+            actual: args.len(),
+        };
+        return Err(vec![Some(TypeError(e, meta))]);
+    };
+    let (mut stmts, calls, size) =
+        type_check_elementwise_calls(&[a, b], f, meta, top_level_defs, env, fns, defs)?;
+    let elem_ty = calls[0].1.ty.clone();
+    let calls = calls.into_iter().take(size).map(|(_, call)| call).collect();
+    let ty = Type::Array(Box::new(elem_ty), size);
+    let array = Expr::typed(ExprEnum::ArrayLiteral(calls), ty.clone(), meta);
+    stmts.push(Stmt::new(StmtEnum::Expr(array), meta));
+    Ok((ExprEnum::Block(stmts), ty))
+}
+
+/// Type checks `filter_mask(a, f)` as an array of the pairs `(a[i], f(a[i]))`, where `f` must
+/// return a bool.
+fn type_check_filter_mask(
+    args: &[UntypedExpr],
+    meta: MetaInfo,
+    top_level_defs: &TopLevelTypes,
+    env: &mut Env<(Option<Type>, Mutability)>,
+    fns: &mut TypedFns,
+    defs: &Defs,
+) -> Result<(ExprEnum<Type>, Type), TypeErrors> {
+    let [a, f] = args else {
+        let e = TypeErrorEnum::WrongNumberOfArgs {
+            expected: 2,
+            actual: args.len(),
+        };
+        return Err(vec![Some(TypeError(e, meta))]);
+    };
+    let (mut stmts, calls, size) =
+        type_check_elementwise_calls(&[a], f, meta, top_level_defs, env, fns, defs)?;
+    let mut pairs = Vec::with_capacity(size);
+    for (mut elems, call) in calls {
+        if call.ty != Type::Bool {
+            let e = TypeErrorEnum::UnexpectedType {
+                expected: Type::Bool,
+                actual: call.ty,
+            };
+            return Err(vec![Some(TypeError(e, f.meta))]);
+        }
+        let elem = elems.pop().unwrap();
+        let ty = Type::Tuple(vec![elem.ty.clone(), Type::Bool]);
+        pairs.push(Expr::typed(
+            ExprEnum::TupleLiteral(vec![elem, call]),
+            ty,
+            meta,
+        ));
+    }
+    let elem_ty = pairs[0].ty.clone();
+    pairs.truncate(size);
+    let ty = Type::Array(Box::new(elem_ty), size);
+    let array = Expr::typed(ExprEnum::ArrayLiteral(pairs), ty.clone(), meta);
+    stmts.push(Stmt::new(StmtEnum::Expr(array), meta));
+    Ok((ExprEnum::Block(stmts), ty))
+}
+
+type ElementwiseCalls = (Vec<TypedStmt>, Vec<(Vec<TypedExpr>, TypedExpr)>, usize);
+
+/// Binds the arrays (which must be of the same size) to variables and calls the top level fn
+/// `f` with the elements at each index, so that the compiler does not need to know about the
+/// builtin.
+///
+/// Returns the bindings, the elements and the call for each index and the size of the arrays.
+/// Empty arrays still return the elements and the call for index 0, so that their types can be
+/// determined.
+fn type_check_elementwise_calls(
+    arrays: &[&UntypedExpr],
+    f: &UntypedExpr,
+    meta: MetaInfo,
+    top_level_defs: &TopLevelTypes,
+    env: &mut Env<(Option<Type>, Mutability)>,
+    fns: &mut TypedFns,
+    defs: &Defs,
+) -> Result<ElementwiseCalls, TypeErrors> {
+    let ExprEnum::Identifier(fn_name) = &f.inner else {
+        return Err(vec![Some(TypeError(TypeErrorEnum::ExpectedFnName, f.meta))]);
+    };
+    let mut errors = vec![];
+    let mut typed_arrays = Vec::with_capacity(arrays.len());
+    for array in arrays {
+        match array.type_check(top_level_defs, env, fns, defs) {
+            Ok(array) => typed_arrays.push(array),
+            Err(e) => errors.extend(e),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let mut sizes = Vec::with_capacity(arrays.len());
+    for array in typed_arrays.iter() {
+        let size = match &array.ty {
+            Type::Array(_, size) => *size,
+            Type::ArrayConst(_, size) => {
+                match top_level_defs.resolve_const_size(size, array.meta)? {
+                    Some(size) => size,
+                    None => {
+                        let e = TypeErrorEnum::ArraySizeNotConst(size.clone());
+                        return Err(vec![Some(TypeError(e, array.meta))]);
+                    }
+                }
+            }
+            ty => {
+                let e = TypeErrorEnum::ExpectedArrayType(ty.clone());
+                return Err(vec![Some(TypeError(e, array.meta))]);
+            }
+        };
+        sizes.push(size);
+    }
+    let size = sizes[0];
+    let min_size = sizes.iter().copied().min().unwrap_or_default();
+    let mut size_mismatch = None;
+    for (array, &array_size) in typed_arrays.iter().zip(sizes.iter()) {
+        if array_size != size && size_mismatch.is_none() {
+            let elem_ty = expect_array_type(&array.ty, array.meta)?;
+            let e = TypeErrorEnum::UnexpectedType {
+                expected: Type::Array(Box::new(elem_ty), size),
+                actual: array.ty.clone(),
+            };
+            size_mismatch = Some(TypeError(e, array.meta));
+        }
+    }
+    // users cannot declare identifiers containing `::`, so the bindings never shadow a variable:
+    let names: Vec<String> = (0..arrays.len())
+        .map(|i| format!("elementwise::{i}"))
+        .collect();
+    env.push();
+    let mut stmts = Vec::with_capacity(arrays.len() + 1);
+    let mut array_metas = Vec::with_capacity(arrays.len());
+    for (name, array) in names.iter().zip(typed_arrays) {
+        let (ty, array_meta) = (array.ty.clone(), array.meta);
+        env.let_in_current_scope(name.clone(), (Some(ty.clone()), Mutability::Immutable));
+        let pattern = Pattern::typed(PatternEnum::Identifier(name.clone()), ty, array_meta);
+        stmts.push(Stmt::new(StmtEnum::Let(pattern, array), array_meta));
+        array_metas.push(array_meta);
+    }
+    // the call is checked even for empty (or mismatched) arrays, to determine the type of the
+    // elements and to mark the fn as used:
+    let mut calls = Vec::with_capacity(size);
+    for i in 0..min_size.max(1) {
+        let elems: Vec<UntypedExpr> = names
+            .iter()
+            .zip(array_metas.iter())
+            .map(|(name, &meta)| {
+                let array = Expr::untyped(ExprEnum::Identifier(name.clone()), meta);
+                let index = Expr::untyped(
+                    ExprEnum::NumUnsigned(i as u64, UnsignedNumType::Usize),
+                    meta,
+                );
+                Expr::untyped(
+                    ExprEnum::ArrayAccess(Box::new(array), Box::new(index)),
+                    meta,
+                )
+            })
+            .collect();
+        let call = Expr::untyped(ExprEnum::FnCall(fn_name.clone(), elems.clone()), meta);
+        let typed = elems
+            .iter()
+            .map(|elem| elem.type_check(top_level_defs, env, fns, defs))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|elems| Ok((elems, call.type_check(top_level_defs, env, fns, defs)?)));
+        match typed {
+            Ok(typed) => calls.push(typed),
+            Err(e) => {
+                env.pop();
+                return Err(e);
+            }
+        }
+    }
+    env.pop();
+    if let Some(e) = size_mismatch {
+        return Err(vec![Some(e)]);
+    }
+    Ok((stmts, calls, size))
+}
+
+/// Functions that are available in every program without being defined.
+///
+/// A top level function with the same name takes precedence over the builtin function.
+const BUILTIN_FNS: &[&str] = &[
+    "tuple_to_array",
+    "array_to_tuple",
+    "gather_bits",
+    "prefix_sum",
+    "permute",
+    "reverse",
+    "rotate",
+    "get_or",
+    "lookup_table",
+    "histogram",
+    "get",
+    "divmod",
+    "one_hot",
+    "any",
+    "all",
+    "sum",
+    "product",
+    "mean",
+    "dot",
+    "rotate_left",
+    "rotate_right",
+    "count_ones",
+    "leading_zeros",
+    "trailing_zeros",
+    "min",
+    "max",
+    "wrapping_add",
+    "wrapping_sub",
+    "wrapping_mul",
+    "assert",
+    "assert_eq",
+];
+
+fn type_check_builtin_fn_call(
+    identifier: &str,
+    args: &[UntypedExpr],
+    meta: MetaInfo,
+    top_level_defs: &TopLevelTypes,
+    env: &mut Env<(Option<Type>, Mutability)>,
+    fns: &mut TypedFns,
+    defs: &Defs,
+) -> Result<(ExprEnum<Type>, Type), TypeErrors> {
+    let mut errors = vec![];
+    let mut typed_args = Vec::with_capacity(args.len());
+    for arg in args.iter() {
+        match arg.type_check(top_level_defs, env, fns, defs) {
+            Ok(arg) => typed_args.push(arg),
+            Err(e) => errors.extend(e),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let expected_args = match identifier {
+        "tuple_to_array" | "array_to_tuple" | "prefix_sum" | "any" | "all" | "sum" | "product"
+        | "mean" | "reverse" | "count_ones" | "leading_zeros" | "trailing_zeros" => 1,
+        "gather_bits" | "permute" | "rotate" | "histogram" | "get" | "divmod" | "one_hot"
+        | "dot" | "rotate_left" | "rotate_right" | "min" | "max" | "wrapping_add"
+        | "wrapping_sub" | "wrapping_mul" | "lookup_table" => 2,
+        // the code of an assertion is optional:
+        "assert" => typed_args.len().clamp(1, 2),
+        "assert_eq" => typed_args.len().clamp(2, 3),
+        "get_or" => 3,
+        _ => unreachable!("'{identifier}' is not a builtin fn"),
+    };
+    if typed_args.len() != expected_args {
+        let e = TypeErrorEnum::WrongNumberOfArgs {
+            expected: expected_args,
+            actual: typed_args.len(),
+        };
+        return Err(vec![Some(TypeError(e, meta))]);
+    }
+    let ty = match identifier {
+        "rotate_left" | "rotate_right" => {
+            // rotations are ops, the builtin fns only exist for readability:
+            let mut y = typed_args.pop().unwrap();
+            let x = typed_args.pop().unwrap();
+            expect_num_type(&x.ty, x.meta)?;
+            check_or_constrain_unsigned(&mut y, UnsignedNumType::U8)?;
+            let op = if identifier == "rotate_left" {
+                Op::RotateLeft
+            } else {
+                Op::RotateRight
+            };
+            let ty = x.ty.clone();
+            return Ok((ExprEnum::Op(op, Box::new(x), Box::new(y)), ty));
+        }
+        "tuple_to_array" => {
+            let arg = &mut typed_args[0];
+            let tuple_ty = arg.ty.clone();
+            let fields = expect_tuple_type(&tuple_ty, arg.meta)?;
+            let Some(mut elem_ty) = fields.first().cloned() else {
+                let e = TypeErrorEnum::ExpectedHomogeneousTupleType(tuple_ty);
+                return Err(vec![Some(TypeError(e, arg.meta))]);
+            };
+            if elem_ty == Type::Unsigned(UnsignedNumType::Unspecified) {
+                if let Some(ty) = fields.iter().find(|ty| ty != &&elem_ty) {
+                    elem_ty = ty.clone();
+                }
+            }
+            if elem_ty == Type::Signed(SignedNumType::Unspecified) {
+                if let Some(ty) = fields.iter().find(|ty| {
+                    ty != &&elem_ty && ty != &&Type::Unsigned(UnsignedNumType::Unspecified)
+                }) {
+                    elem_ty = ty.clone();
+                }
+            }
+            if check_type(arg, &Type::Tuple(vec![elem_ty.clone(); fields.len()])).is_err() {
+                let e = TypeErrorEnum::ExpectedHomogeneousTupleType(tuple_ty);
+                return Err(vec![Some(TypeError(e, arg.meta))]);
+            }
+            Type::Array(Box::new(elem_ty), fields.len())
+        }
+        "array_to_tuple" => {
+            let arg = &typed_args[0];
+            match &arg.ty {
+                Type::Array(elem_ty, size) => Type::Tuple(vec![*elem_ty.clone(); *size]),
+                ty => {
+                    let e = TypeErrorEnum::ExpectedArrayType(ty.clone());
+                    return Err(vec![Some(TypeError(e, arg.meta))]);
+                }
+            }
+        }
+        "gather_bits" => {
+            let [x, mask] = typed_args.as_mut_slice() else {
+                unreachable!("gather_bits has 2 args")
+            };
+            expect_num_type(&x.ty, x.meta)?;
+            check_type(mask, &x.ty)?;
+            if !matches!(
+                mask.inner,
+                ExprEnum::NumUnsigned(_, _) | ExprEnum::NumSigned(_, _)
+            ) {
+                let e = TypeErrorEnum::ExpectedNumberLiteral;
+                return Err(vec![Some(TypeError(e, mask.meta))]);
+            }
+            x.ty.clone()
+        }
+        "reverse" => {
+            let arg = &typed_args[0];
+            expect_array_type(&arg.ty, arg.meta)?;
+            arg.ty.clone()
+        }
+        "rotate" => {
+            let [array, k] = typed_args.as_mut_slice() else {
+                unreachable!("rotate has 2 args")
+            };
+            expect_array_type(&array.ty, array.meta)?;
+            check_or_constrain_unsigned(k, UnsignedNumType::Usize)?;
+            if !matches!(k.inner, ExprEnum::NumUnsigned(_, _)) {
+                let e = TypeErrorEnum::ExpectedNumberLiteral;
+                return Err(vec![Some(TypeError(e, k.meta))]);
+            }
+            array.ty.clone()
+        }
+        "prefix_sum" => {
+            let arg = &typed_args[0];
+            let elem_ty = expect_array_type(&arg.ty, arg.meta)?;
+            expect_num_type(&elem_ty, arg.meta)?;
+            arg.ty.clone()
+        }
+        "sum" | "product" | "mean" => {
+            let arg = &typed_args[0];
+            let elem_ty = expect_array_type(&arg.ty, arg.meta)?;
+            expect_num_type(&elem_ty, arg.meta)?;
+            elem_ty
+        }
+        "dot" => {
+            let [a, b] = typed_args.as_mut_slice() else {
+                unreachable!("dot has 2 args")
+            };
+            let elem_ty_a = expect_array_type(&a.ty, a.meta)?;
+            let elem_ty_b = expect_array_type(&b.ty, b.meta)?;
+            let is_unspecified = |ty: &Type| {
+                matches!(
+                    ty,
+                    Type::Unsigned(UnsignedNumType::Unspecified)
+                        | Type::Signed(SignedNumType::Unspecified)
+                )
+            };
+            // an array of number literals without suffix adopts the element type of the other:
+            let elem_ty = if is_unspecified(&elem_ty_a) {
+                elem_ty_b.clone()
+            } else {
+                elem_ty_a.clone()
+            };
+            expect_num_type(&elem_ty, a.meta)?;
+            let with_elem_ty = |ty: &Type| match ty {
+                Type::ArrayConst(_, size) => {
+                    Type::ArrayConst(Box::new(elem_ty.clone()), size.clone())
+                }
+                Type::Array(_, size) => Type::Array(Box::new(elem_ty.clone()), *size),
+                _ => unreachable!("the args of dot must be arrays"),
+            };
+            let array_ty = with_elem_ty(&a.ty);
+            for (arg, arg_elem_ty) in [(a, elem_ty_a), (b, elem_ty_b)] {
+                let matches = if is_unspecified(&arg_elem_ty) {
+                    with_elem_ty(&arg.ty) == array_ty
+                } else {
+                    arg.ty == array_ty
+                };
+                if !matches {
+                    let e = TypeErrorEnum::UnexpectedType {
+                        expected: array_ty,
+                        actual: arg.ty.clone(),
+                    };
+                    return Err(vec![Some(TypeError(e, arg.meta))]);
+                }
+                constrain_type(arg, &array_ty)?;
+            }
+            elem_ty
+        }
+        "any" | "all" => {
+            let arg = &typed_args[0];
+            let elem_ty = expect_array_type(&arg.ty, arg.meta)?;
+            if elem_ty != Type::Bool {
+                let e = TypeErrorEnum::UnexpectedType {
+                    expected: Type::Bool,
+                    actual: elem_ty,
+                };
+                return Err(vec![Some(TypeError(e, arg.meta))]);
+            }
+            Type::Bool
+        }
+        "count_ones" => {
+            let arg = &typed_args[0];
+            if arg.ty != Type::Bool {
+                expect_num_type(&arg.ty, arg.meta)?;
+            }
+            Type::Unsigned(UnsignedNumType::Usize)
+        }
+        "leading_zeros" | "trailing_zeros" => {
+            let arg = &typed_args[0];
+            expect_num_type(&arg.ty, arg.meta)?;
+            // the zeros of a u256 can be 256, which does not fit into a u8:
+            if arg.ty == Type::Unsigned(UnsignedNumType::U256) {
+                Type::Unsigned(UnsignedNumType::U16)
+            } else {
+                Type::Unsigned(UnsignedNumType::U8)
+            }
+        }
+        "permute" => {
+            let [array, perm] = typed_args.as_mut_slice() else {
+                unreachable!("permute has 2 args")
+            };
+            let index_ty = Box::new(Type::Unsigned(UnsignedNumType::Usize));
+            let unspecified_ty = Box::new(Type::Unsigned(UnsignedNumType::Unspecified));
+            let (expected, unspecified) = match &array.ty {
+                Type::Array(_, size) => (
+                    Type::Array(index_ty, *size),
+                    Type::Array(unspecified_ty, *size),
+                ),
+                Type::ArrayConst(_, size) => (
+                    Type::ArrayConst(index_ty, size.clone()),
+                    Type::ArrayConst(unspecified_ty, size.clone()),
+                ),
+                ty => {
+                    let e = TypeErrorEnum::ExpectedArrayType(ty.clone());
+                    return Err(vec![Some(TypeError(e, array.meta))]);
+                }
+            };
+            if perm.ty != expected && perm.ty != unspecified {
+                let e = TypeErrorEnum::UnexpectedType {
+                    expected,
+                    actual: perm.ty.clone(),
+                };
+                return Err(vec![Some(TypeError(e, perm.meta))]);
+            }
+            check_type(perm, &expected)?;
+            array.ty.clone()
+        }
+        "get_or" => {
+            let [array, index, default] = typed_args.as_mut_slice() else {
+                unreachable!("get_or has 3 args")
+            };
+            let elem_ty = expect_array_type(&array.ty, array.meta)?;
+            check_or_constrain_unsigned(index, UnsignedNumType::Usize)?;
+            let is_unspecified_num = |ty: &Type| {
+                ty == &Type::Unsigned(UnsignedNumType::Unspecified)
+                    || ty == &Type::Signed(SignedNumType::Unspecified)
+            };
+            if default.ty == elem_ty {
+                elem_ty
+            } else if is_unspecified_num(&elem_ty) && expect_num_type(&default.ty, meta).is_ok() {
+                let array_ty = match &array.ty {
+                    Type::Array(_, size) => Type::Array(Box::new(default.ty.clone()), *size),
+                    Type::ArrayConst(_, size) => {
+                        Type::ArrayConst(Box::new(default.ty.clone()), size.clone())
+                    }
+                    _ => unreachable!("the first arg of get_or is an array"),
+                };
+                constrain_type(array, &array_ty)?;
+                default.ty.clone()
+            } else if is_unspecified_num(&default.ty) && expect_num_type(&elem_ty, meta).is_ok() {
+                constrain_type(default, &elem_ty)?;
+                elem_ty
+            } else {
+                let e = TypeErrorEnum::TypeMismatch(elem_ty, default.ty.clone());
+                return Err(vec![Some(TypeError(e, meta))]);
+            }
+        }
+        "lookup_table" => {
+            let [table, indexes] = typed_args.as_mut_slice() else {
+                unreachable!("lookup_table has 2 args")
+            };
+            let elem_ty = Box::new(expect_array_type(&table.ty, table.meta)?);
+            let index_ty = Box::new(Type::Unsigned(UnsignedNumType::Usize));
+            let unspecified_ty = Box::new(Type::Unsigned(UnsignedNumType::Unspecified));
+            let (expected, unspecified, ty) = match &indexes.ty {
+                Type::Array(_, size) => (
+                    Type::Array(index_ty, *size),
+                    Type::Array(unspecified_ty, *size),
+                    Type::Array(elem_ty, *size),
+                ),
+                Type::ArrayConst(_, size) => (
+                    Type::ArrayConst(index_ty, size.clone()),
+                    Type::ArrayConst(unspecified_ty, size.clone()),
+                    Type::ArrayConst(elem_ty, size.clone()),
+                ),
+                ty => {
+                    let e = TypeErrorEnum::ExpectedArrayType(ty.clone());
+                    return Err(vec![Some(TypeError(e, indexes.meta))]);
+                }
+            };
+            if indexes.ty != expected && indexes.ty != unspecified {
+                let e = TypeErrorEnum::UnexpectedType {
+                    expected,
+                    actual: indexes.ty.clone(),
+                };
+                return Err(vec![Some(TypeError(e, indexes.meta))]);
+            }
+            check_type(indexes, &expected)?;
+            ty
+        }
+        "histogram" => {
+            let [values, buckets] = typed_args.as_mut_slice() else {
+                unreachable!("histogram has 2 args")
+            };
+            let elem_ty = expect_array_type(&values.ty, values.meta)?;
+            match elem_ty {
+                Type::Unsigned(UnsignedNumType::Unspecified) => {
+                    let usize_ty = Box::new(Type::Unsigned(UnsignedNumType::Usize));
+                    let values_ty = match &values.ty {
+                        Type::Array(_, size) => Type::Array(usize_ty, *size),
+                        Type::ArrayConst(_, size) => Type::ArrayConst(usize_ty, size.clone()),
+                        _ => unreachable!("the first arg of histogram is an array"),
+                    };
+                    constrain_type(values, &values_ty)?;
+                }
+                // elements of any width (up to u256) are compared bit by bit with the buckets:
+                Type::Unsigned(_) => {}
+                ty => {
+                    let e = TypeErrorEnum::ExpectedUnsignedNumberType(ty);
+                    return Err(vec![Some(TypeError(e, values.meta))]);
+                }
+            }
+            check_or_constrain_unsigned(buckets, UnsignedNumType::Usize)?;
+            let ExprEnum::NumUnsigned(n, _) = buckets.inner else {
+                let e = TypeErrorEnum::ExpectedNumberLiteral;
+                return Err(vec![Some(TypeError(e, buckets.meta))]);
+            };
+            Type::Array(Box::new(Type::Unsigned(UnsignedNumType::Usize)), n as usize)
+        }
+        "get" => {
+            let [x, path] = typed_args.as_mut_slice() else {
+                unreachable!("get has 2 args")
+            };
+            let Some(indexes) = index_path(path) else {
+                let e = TypeErrorEnum::ExpectedIndexPath;
+                return Err(vec![Some(TypeError(e, path.meta))]);
+            };
+            let usize_ty = Type::Unsigned(UnsignedNumType::Usize);
+            check_type(path, &Type::Array(Box::new(usize_ty), indexes.len()))?;
+            let mut ty = &x.ty;
+            for i in indexes {
+                ty = match ty {
+                    Type::Tuple(fields) if (i as usize) < fields.len() => &fields[i as usize],
+                    Type::Array(elem_ty, size) if (i as usize) < *size => elem_ty,
+                    _ => {
+                        let e = TypeErrorEnum::InvalidIndexPath(ty.clone(), i);
+                        return Err(vec![Some(TypeError(e, path.meta))]);
+                    }
+                };
+            }
+            ty.clone()
+        }
+        "divmod" => {
+            let [x, y] = typed_args.as_mut_slice() else {
+                unreachable!("divmod has 2 args")
+            };
+            let ty = unify(x, y, meta)?;
+            expect_num_type(&ty, meta)?;
+            Type::Tuple(vec![ty.clone(), ty])
+        }
+        "min" | "max" | "wrapping_add" | "wrapping_sub" | "wrapping_mul" => {
+            let [x, y] = typed_args.as_mut_slice() else {
+                unreachable!("{identifier} has 2 args")
+            };
+            let ty = unify(x, y, meta)?;
+            expect_num_type(&ty, meta)?;
+            ty
+        }
+        "assert" | "assert_eq" => {
+            let code_arg = if identifier == "assert" { 1 } else { 2 };
+            if let Some(code) = typed_args.get_mut(code_arg) {
+                if !matches!(code.inner, ExprEnum::NumUnsigned(_, _)) {
+                    let e = TypeErrorEnum::ExpectedNumberLiteral;
+                    return Err(vec![Some(TypeError(e, code.meta))]);
+                }
+                check_type(code, &Type::Unsigned(UnsignedNumType::Usize))?;
+            }
+            if identifier == "assert" {
+                check_type(&mut typed_args[0], &Type::Bool)?;
+            } else {
+                let [x, y, ..] = typed_args.as_mut_slice() else {
+                    unreachable!("assert_eq has at least 2 args")
+                };
+                unify(x, y, meta)?;
+            }
+            Type::Tuple(vec![])
+        }
+        "one_hot" => {
+            let [x, consts] = typed_args.as_mut_slice() else {
+                unreachable!("one_hot has 2 args")
+            };
+            expect_num_type(&x.ty, x.meta)?;
+            let ExprEnum::ArrayLiteral(elems) = &consts.inner else {
+                let e = TypeErrorEnum::ExpectedNumberLiteral;
+                return Err(vec![Some(TypeError(e, consts.meta))]);
+            };
+            for elem in elems {
+                if !matches!(
+                    elem.inner,
+                    ExprEnum::NumUnsigned(_, _) | ExprEnum::NumSigned(_, _)
+                ) {
+                    let e = TypeErrorEnum::ExpectedNumberLiteral;
+                    return Err(vec![Some(TypeError(e, elem.meta))]);
+                }
+            }
+            let size = elems.len();
+            check_type(consts, &Type::Array(Box::new(x.ty.clone()), size))?;
+            Type::Array(Box::new(Type::Bool), size)
+        }
+        _ => unreachable!("'{identifier}' is not a builtin fn"),
+    };
+    Ok((
+        ExprEnum::BuiltinFnCall(identifier.to_string(), typed_args),
+        ty,
+    ))
+}
+
+/// Returns the indexes of a constant index path such as `[0, 2, 1]`.
+pub(crate) fn index_path(path: &TypedExpr) -> Option<Vec<u64>> {
+    let ExprEnum::ArrayLiteral(elems) = &path.inner else {
+        return None;
+    };
+    let mut indexes = Vec::with_capacity(elems.len());
+    for elem in elems {
+        let ExprEnum::NumUnsigned(i, _) = elem.inner else {
+            return None;
+        };
+        indexes.push(i);
+    }
+    Some(indexes)
+}
+
+/// Replaces the type reached by following the index path with the specified type.
+fn with_type_at_index_path(ty: &Type, indexes: &[u64], replacement: &Type) -> Type {
+    match (ty, indexes.split_first()) {
+        (_, None) => replacement.clone(),
+        (Type::Tuple(fields), Some((&i, indexes))) => {
+            let mut fields = fields.clone();
+            if let Some(field) = fields.get_mut(i as usize) {
+                *field = with_type_at_index_path(field, indexes, replacement);
+            }
+            Type::Tuple(fields)
+        }
+        (Type::Array(elem_ty, size), Some((_, indexes))) => {
+            let elem_ty = with_type_at_index_path(elem_ty, indexes, replacement);
+            Type::Array(Box::new(elem_ty), *size)
+        }
+        (ty, _) => ty.clone(),
+    }
+}
+
+/// Returns the number of bits of a bool or a number type with a known size.
+fn primitive_bits(ty: &Type) -> Option<usize> {
+    match ty {
+        Type::Bool => Some(1),
+        Type::Unsigned(UnsignedNumType::U8) | Type::Signed(SignedNumType::I8) => Some(8),
+        Type::Unsigned(UnsignedNumType::U16) | Type::Signed(SignedNumType::I16) => Some(16),
+        Type::Unsigned(UnsignedNumType::U32 | UnsignedNumType::Usize)
+        | Type::Signed(SignedNumType::I32) => Some(32),
+        Type::Unsigned(UnsignedNumType::U64) | Type::Signed(SignedNumType::I64) => Some(64),
+        Type::Unsigned(UnsignedNumType::U256) => Some(256),
+        _ => None,
+    }
+}
+
+/// Returns `true` if the unsigned type can represent every count from 0 up to the bits of `ty`.
+fn can_hold_count(count_ty: UnsignedNumType, ty: &Type) -> bool {
+    match (
+        primitive_bits(&Type::Unsigned(count_ty)),
+        primitive_bits(ty),
+    ) {
+        (Some(count_bits), Some(bits)) => (bits as u64) < 1 << count_bits.min(63),
+        _ => false,
+    }
+}
+
+/// Collects the identifiers (and their locations) that are bound by the pattern.
+fn bound_identifiers(pattern: &TypedPattern, identifiers: &mut Vec<(String, MetaInfo)>) {
+    let Pattern(pattern, meta, _) = pattern;
+    match pattern {
+        PatternEnum::Identifier(identifier) => identifiers.push((identifier.clone(), *meta)),
+        PatternEnum::Tuple(fields) | PatternEnum::EnumTuple(_, _, fields) => {
+            for field in fields {
+                bound_identifiers(field, identifiers);
+            }
+        }
+        PatternEnum::Struct(_, fields) | PatternEnum::StructIgnoreRemaining(_, fields) => {
+            for (_, field) in fields {
+                bound_identifiers(field, identifiers);
+            }
+        }
+        PatternEnum::Wildcard
+        | PatternEnum::True
+        | PatternEnum::False
+        | PatternEnum::NumUnsigned(_, _)
+        | PatternEnum::NumSigned(_, _)
+        | PatternEnum::EnumUnit(_, _)
+        | PatternEnum::UnsignedInclusiveRange(_, _, _)
+        | PatternEnum::SignedInclusiveRange(_, _, _) => {}
+    }
+}
+
+impl UntypedPattern {
+    fn type_check(
+        &self,
+        env: &mut Env<(Option<Type>, Mutability)>,
+        _fns: &mut TypedFns,
+        defs: &Defs,
         ty: Option<Type>,
     ) -> Result<TypedPattern, TypeErrors> {
         let Pattern(pattern, meta, _) = self;
@@ -1414,6 +2981,7 @@ impl UntypedPattern {
                 env.let_in_current_scope(s.clone(), (ty.clone(), Mutability::Immutable));
                 PatternEnum::Identifier(s.clone())
             }
+            PatternEnum::Wildcard => PatternEnum::Wildcard,
             PatternEnum::True => match &ty {
                 Some(Type::Bool) => PatternEnum::True,
                 Some(ty) => {
@@ -1442,7 +3010,7 @@ impl UntypedPattern {
             },
             PatternEnum::NumUnsigned(n, suffix) => {
                 if let Some(ty) = &ty {
-                    expect_num_type(ty, meta)?;
+                    expect_pattern_num_type(ty, meta)?;
                     PatternEnum::NumUnsigned(*n, *suffix)
                 } else {
                     return Err(vec![None]);
@@ -1458,7 +3026,7 @@ impl UntypedPattern {
             }
             PatternEnum::UnsignedInclusiveRange(from, to, suffix) => {
                 if let Some(ty) = &ty {
-                    expect_num_type(ty, meta)?;
+                    expect_pattern_num_type(ty, meta)?;
                     PatternEnum::UnsignedInclusiveRange(*from, *to, *suffix)
                 } else {
                     return Err(vec![None]);
@@ -1648,11 +3216,7 @@ fn check_exhaustiveness(
     meta: MetaInfo,
 ) -> Result<(), TypeError> {
     let patterns: Vec<Vec<TypedPattern>> = patterns.iter().map(|&p| vec![p.clone()]).collect();
-    let wildcard_pattern = vec![Pattern::typed(
-        PatternEnum::Identifier("_".to_string()),
-        ty.clone(),
-        meta,
-    )];
+    let wildcard_pattern = vec![Pattern::typed(PatternEnum::Wildcard, ty.clone(), meta)];
     let witnesses = usefulness(patterns, wildcard_pattern, defs);
     if !witnesses.is_empty() {
         let e = TypeErrorEnum::PatternsAreNotExhaustive(witnesses);
@@ -1662,6 +3226,20 @@ fn check_exhaustiveness(
     }
 }
 
+// A pattern is unreachable if it is not useful with respect to all the patterns before it, using
+// the same algorithm as for the exhaustiveness check.
+fn check_reachability(patterns: &[&TypedPattern], defs: &Defs) -> Vec<TypeError> {
+    let mut errors = vec![];
+    for (i, &pattern) in patterns.iter().enumerate() {
+        let previous: Vec<PatternStack> = patterns[..i].iter().map(|&p| vec![p.clone()]).collect();
+        if usefulness(previous, vec![pattern.clone()], defs).is_empty() {
+            let Pattern(_, meta, _) = pattern;
+            errors.push(TypeError(TypeErrorEnum::UnreachablePattern, *meta));
+        }
+    }
+    errors
+}
+
 #[derive(Debug, Clone)]
 enum Ctor {
     True,
@@ -1673,6 +3251,7 @@ enum Ctor {
     Variant(String, String, Option<Vec<Type>>),
     Array(Box<Type>, usize),
     ArrayConst(Box<Type>, String),
+    String(usize),
 }
 
 type PatternStack = Vec<TypedPattern>;
@@ -1687,19 +3266,19 @@ fn specialize(ctor: &Ctor, pattern: &[TypedPattern]) -> Vec<PatternStack> {
     let Pattern(head_enum, meta, _) = head;
     match ctor {
         Ctor::True => match head_enum {
-            PatternEnum::Identifier(_) | PatternEnum::True => {
+            PatternEnum::Identifier(_) | PatternEnum::Wildcard | PatternEnum::True => {
                 vec![tail.collect()]
             }
             _ => vec![],
         },
         Ctor::False => match head_enum {
-            PatternEnum::Identifier(_) | PatternEnum::False => {
+            PatternEnum::Identifier(_) | PatternEnum::Wildcard | PatternEnum::False => {
                 vec![tail.collect()]
             }
             _ => vec![],
         },
         Ctor::UnsignedInclusiveRange(_, min, max) => match head_enum {
-            PatternEnum::Identifier(_) => vec![tail.collect()],
+            PatternEnum::Identifier(_) | PatternEnum::Wildcard => vec![tail.collect()],
             PatternEnum::NumUnsigned(n, _) if n == min && n == max => vec![tail.collect()],
             PatternEnum::UnsignedInclusiveRange(n_min, n_max, _)
                 if n_min <= min && max <= n_max =>
@@ -1709,7 +3288,7 @@ fn specialize(ctor: &Ctor, pattern: &[TypedPattern]) -> Vec<PatternStack> {
             _ => vec![],
         },
         Ctor::SignedInclusiveRange(_, min, max) => match head_enum {
-            PatternEnum::Identifier(_) => vec![tail.collect()],
+            PatternEnum::Identifier(_) | PatternEnum::Wildcard => vec![tail.collect()],
             PatternEnum::NumUnsigned(n, _)
                 if *min >= 0 && *max >= 0 && *n == *min as u64 && *n == *max as u64 =>
             {
@@ -1727,10 +3306,10 @@ fn specialize(ctor: &Ctor, pattern: &[TypedPattern]) -> Vec<PatternStack> {
             _ => vec![],
         },
         Ctor::Tuple(field_types) => match head_enum {
-            PatternEnum::Identifier(_) => {
+            PatternEnum::Identifier(_) | PatternEnum::Wildcard => {
                 let mut fields = Vec::with_capacity(field_types.len());
                 for ty in field_types {
-                    let wildcard = PatternEnum::Identifier("_".to_string());
+                    let wildcard = PatternEnum::Wildcard;
                     let p = Pattern::typed(wildcard, ty.clone(), *meta);
                     fields.push(p);
                 }
@@ -1741,15 +3320,15 @@ fn specialize(ctor: &Ctor, pattern: &[TypedPattern]) -> Vec<PatternStack> {
             }
             _ => vec![],
         },
-        Ctor::Array(_, _) | Ctor::ArrayConst(_, _) => match head_enum {
-            PatternEnum::Identifier(_) => vec![tail.collect()],
+        Ctor::Array(_, _) | Ctor::ArrayConst(_, _) | Ctor::String(_) => match head_enum {
+            PatternEnum::Identifier(_) | PatternEnum::Wildcard => vec![tail.collect()],
             _ => vec![],
         },
         Ctor::Struct(struct_name, field_types) => match head_enum {
-            PatternEnum::Identifier(_) => {
+            PatternEnum::Identifier(_) | PatternEnum::Wildcard => {
                 let mut fields = Vec::with_capacity(field_types.len());
                 for (_, ty) in field_types {
-                    let wildcard = PatternEnum::Identifier("_".to_string());
+                    let wildcard = PatternEnum::Wildcard;
                     let p = Pattern::typed(wildcard, ty.clone(), *meta);
                     fields.push(p);
                 }
@@ -1768,11 +3347,11 @@ fn specialize(ctor: &Ctor, pattern: &[TypedPattern]) -> Vec<PatternStack> {
             _ => vec![],
         },
         Ctor::Variant(_, v1, fields) => match head_enum {
-            PatternEnum::Identifier(_) => {
+            PatternEnum::Identifier(_) | PatternEnum::Wildcard => {
                 let field_types = fields.as_deref().unwrap_or_default();
                 let mut fields = Vec::with_capacity(field_types.len());
                 for ty in field_types {
-                    let wildcard = PatternEnum::Identifier("_".to_string());
+                    let wildcard = PatternEnum::Wildcard;
                     let p = Pattern::typed(wildcard, ty.clone(), *meta);
                     fields.push(p);
                 }
@@ -1857,18 +3436,14 @@ fn split_signed_range(
         let Pattern(head_enum, _, _) = head;
         match head_enum {
             PatternEnum::NumUnsigned(n, _) => split_points.push(*n as i128),
-            PatternEnum::NumSigned(n, _) if *n >= 0 => split_points.push(*n as i128),
+            PatternEnum::NumSigned(n, _) => split_points.push(*n as i128),
             PatternEnum::UnsignedInclusiveRange(min, max, _) => {
                 split_points.push(*min as i128);
                 split_points.push(*max as i128 + 1);
             }
             PatternEnum::SignedInclusiveRange(min, max, _) => {
-                if *min >= 0 {
-                    split_points.push(*min as i128);
-                }
-                if *max >= 0 {
-                    split_points.push(*max as i128 + 1);
-                }
+                split_points.push(*min as i128);
+                split_points.push(*max as i128 + 1);
             }
             _ => {}
         }
@@ -1888,7 +3463,7 @@ fn split_signed_range(
             ranges.push(Ctor::SignedInclusiveRange(
                 ty,
                 range[0] as i64,
-                range[1] as i64 - 1,
+                (range[1] - 1) as i64,
             ));
         }
     }
@@ -1900,8 +3475,13 @@ fn split_ctor(patterns: &[PatternStack], q: &[TypedPattern], defs: &Defs) -> Vec
     let Pattern(head_enum, _, ty) = head;
     match ty {
         Type::Bool => vec![Ctor::True, Ctor::False],
+        // chars, fixed-point numbers and u256 numbers can only be bound to identifiers, which are
+        // handled like a u32 that matches all:
+        Type::Char | Type::Fixed(_, _) | Type::Unsigned(UnsignedNumType::U256) => {
+            split_unsigned_range(UnsignedNumType::U32, patterns, 0, u32::MAX as u64)
+        }
         Type::Unsigned(ty) => match head_enum {
-            PatternEnum::Identifier(_) => {
+            PatternEnum::Identifier(_) | PatternEnum::Wildcard => {
                 split_unsigned_range(*ty, patterns, 0, ty.max().unwrap_or(u32::MAX as u64))
             }
             PatternEnum::NumUnsigned(n, _) => {
@@ -1913,13 +3493,12 @@ fn split_ctor(patterns: &[PatternStack], q: &[TypedPattern], defs: &Defs) -> Vec
             _ => panic!("cannot split {head_enum:?} for type {ty:?}"),
         },
         Type::Signed(ty) => match head_enum {
-            PatternEnum::Identifier(_) => {
-                vec![Ctor::SignedInclusiveRange(
-                    *ty,
-                    ty.min().unwrap_or(i32::MIN as i64),
-                    ty.max().unwrap_or(i32::MAX as i64),
-                )]
-            }
+            PatternEnum::Identifier(_) | PatternEnum::Wildcard => split_signed_range(
+                *ty,
+                patterns,
+                ty.min().unwrap_or(i32::MIN as i64),
+                ty.max().unwrap_or(i32::MAX as i64),
+            ),
             PatternEnum::NumUnsigned(n, _) => {
                 vec![Ctor::SignedInclusiveRange(*ty, *n as i64, *n as i64)]
             }
@@ -1953,6 +3532,7 @@ fn split_ctor(patterns: &[PatternStack], q: &[TypedPattern], defs: &Defs) -> Vec
             vec![Ctor::Tuple(fields.clone())]
         }
         Type::Array(elem_ty, size) => vec![Ctor::Array(elem_ty.clone(), *size)],
+        Type::String(max_len) => vec![Ctor::String(*max_len)],
         Type::ArrayConst(elem_ty, size) => vec![Ctor::ArrayConst(elem_ty.clone(), size.clone())],
         Type::Fn(_, _) => {
             panic!("Type {ty:?} does not support pattern matching")
@@ -2045,15 +3625,19 @@ fn usefulness(patterns: Vec<PatternStack>, q: PatternStack, defs: &Defs) -> Vec<
                         Ctor::Array(elem_ty, size) => witness.insert(
                             0,
                             Pattern::typed(
-                                PatternEnum::Identifier("_".to_string()),
+                                PatternEnum::Wildcard,
                                 Type::Array(elem_ty.clone(), *size),
                                 meta,
                             ),
                         ),
+                        Ctor::String(max_len) => witness.insert(
+                            0,
+                            Pattern::typed(PatternEnum::Wildcard, Type::String(*max_len), meta),
+                        ),
                         Ctor::ArrayConst(elem_ty, size) => witness.insert(
                             0,
                             Pattern::typed(
-                                PatternEnum::Identifier("_".to_string()),
+                                PatternEnum::Wildcard,
                                 Type::ArrayConst(elem_ty.clone(), size.clone()),
                                 meta,
                             ),
@@ -2067,6 +3651,89 @@ fn usefulness(patterns: Vec<PatternStack>, q: PatternStack, defs: &Defs) -> Vec<
     }
 }
 
+/// Rejects the type if its size in bits does not fit into a usize.
+fn check_size_in_bits(ty: Type, meta: MetaInfo) -> Result<Type, TypeErrors> {
+    if min_size_in_bits(&ty).is_some() {
+        Ok(ty)
+    } else {
+        Err(vec![Some(TypeError(TypeErrorEnum::TypeTooLarge(ty), meta))])
+    }
+}
+
+// Structs and enums are counted as zero bits, because their defs might not be checked yet:
+fn min_size_in_bits(ty: &Type) -> Option<usize> {
+    match ty {
+        Type::Bool => Some(1),
+        Type::Unsigned(UnsignedNumType::Usize) => Some(USIZE_BITS),
+        Type::Unsigned(UnsignedNumType::U8) | Type::Signed(SignedNumType::I8) => Some(8),
+        Type::Unsigned(UnsignedNumType::U16) | Type::Signed(SignedNumType::I16) => Some(16),
+        Type::Unsigned(UnsignedNumType::U32) | Type::Signed(SignedNumType::I32) => Some(32),
+        Type::Char => Some(32),
+        Type::Unsigned(UnsignedNumType::U64) | Type::Signed(SignedNumType::I64) => Some(64),
+        Type::Unsigned(UnsignedNumType::U256) => Some(256),
+        Type::Unsigned(UnsignedNumType::Unspecified) | Type::Signed(SignedNumType::Unspecified) => {
+            Some(32)
+        }
+        Type::Fixed(bits, _) => Some(*bits as usize),
+        Type::String(max_len) => max_len.checked_mul(8)?.checked_add(USIZE_BITS),
+        Type::Array(elem, size) => min_size_in_bits(elem)?.checked_mul(*size),
+        Type::ArrayConst(elem, _) => min_size_in_bits(elem),
+        Type::Tuple(fields) => fields.iter().try_fold(0usize, |size, field| {
+            size.checked_add(min_size_in_bits(field)?)
+        }),
+        Type::Fn(_, _)
+        | Type::Struct(_)
+        | Type::Enum(_)
+        | Type::UntypedTopLevelDefinition(_, _) => Some(0),
+    }
+}
+
+fn is_literal_out_of_range(literal: &TypedExpr, ty: &Type) -> bool {
+    let n = match literal.inner {
+        ExprEnum::NumUnsigned(n, UnsignedNumType::Unspecified) => n as i128,
+        ExprEnum::NumSigned(n, SignedNumType::Unspecified) => n as i128,
+        _ => return false,
+    };
+    let (min, max) = match ty {
+        Type::Unsigned(ty) => match ty.max() {
+            Some(max) => (0, max as i128),
+            None => return false,
+        },
+        Type::Signed(ty) => match (ty.min(), ty.max()) {
+            (Some(min), Some(max)) => (min as i128, max as i128),
+            _ => return false,
+        },
+        _ => return false,
+    };
+    n < min || n > max
+}
+
+fn const_bool(expr: &TypedExpr) -> Option<bool> {
+    match &expr.inner {
+        ExprEnum::True => Some(true),
+        ExprEnum::False => Some(false),
+        ExprEnum::UnaryOp(UnaryOp::Not, x) => const_bool(x).map(|x| !x),
+        ExprEnum::Op(op, x, y) if x.ty == Type::Bool => {
+            let (x, y) = (const_bool(x), const_bool(y));
+            match (op, x, y) {
+                (Op::BitAnd | Op::ShortCircuitAnd, Some(false), _)
+                | (Op::BitAnd | Op::ShortCircuitAnd, _, Some(false)) => Some(false),
+                (Op::BitOr | Op::ShortCircuitOr, Some(true), _)
+                | (Op::BitOr | Op::ShortCircuitOr, _, Some(true)) => Some(true),
+                (_, Some(x), Some(y)) => match op {
+                    Op::BitAnd | Op::ShortCircuitAnd => Some(x & y),
+                    Op::BitOr | Op::ShortCircuitOr => Some(x | y),
+                    Op::BitXor | Op::NotEq => Some(x ^ y),
+                    Op::Eq => Some(x == y),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
 fn expect_array_type(ty: &Type, meta: MetaInfo) -> Result<Type, TypeErrors> {
     match ty {
         Type::Array(elem, _) | Type::ArrayConst(elem, _) => Ok(*elem.clone()),
@@ -2107,6 +3774,17 @@ fn expect_num_type(ty: &Type, meta: MetaInfo) -> Result<(), TypeErrors> {
     }
 }
 
+/// Number patterns are only supported for numbers that fit into 64 bits.
+fn expect_pattern_num_type(ty: &Type, meta: MetaInfo) -> Result<(), TypeErrors> {
+    match ty {
+        Type::Unsigned(UnsignedNumType::U256) => Err(vec![Some(TypeError(
+            TypeErrorEnum::TypeDoesNotSupportPatternMatching(ty.clone()),
+            meta,
+        ))]),
+        _ => expect_num_type(ty, meta),
+    }
+}
+
 fn expect_signed_num_type(ty: &Type, meta: MetaInfo) -> Result<(), TypeErrors> {
     match ty {
         Type::Signed(_) => Ok(()),
@@ -2130,6 +3808,14 @@ fn expect_bool_or_num_type(ty: &Type, meta: MetaInfo) -> Result<(), TypeErrors>
     ))])
 }
 
+// Bitwise ops are also applied element-wise to bool arrays, which are used as bitmasks:
+fn expect_bitwise_type(ty: &Type, meta: MetaInfo) -> Result<(), TypeErrors> {
+    match ty {
+        Type::Array(elem_ty, _) | Type::ArrayConst(elem_ty, _) if **elem_ty == Type::Bool => Ok(()),
+        ty => expect_bool_or_num_type(ty, meta),
+    }
+}
+
 pub(crate) fn check_or_constrain_unsigned(
     expr: &mut TypedExpr,
     expected: UnsignedNumType,
@@ -2208,6 +3894,16 @@ pub(crate) fn check_or_constrain_signed(
 
 pub(crate) fn constrain_type(expr: &mut TypedExpr, expected: &Type) -> Result<(), TypeErrors> {
     match (&mut expr.inner, expected) {
+        (ExprEnum::StringLiteral(s), Type::String(max_len)) if s.len() > *max_len => {
+            let e = TypeErrorEnum::StringTooLong {
+                max_len: *max_len,
+                actual_len: s.len(),
+            };
+            return Err(vec![Some(TypeError(e, expr.meta))]);
+        }
+        (ExprEnum::StringLiteral(_), Type::String(_)) => {}
+        // string literals are byte arrays of a fixed size unless a string is expected:
+        (ExprEnum::StringLiteral(_), _) => return Ok(()),
         (ExprEnum::ArrayLiteral(elems), Type::Array(elem_ty, _) | Type::ArrayConst(elem_ty, _)) => {
             for elem in elems {
                 constrain_type(elem, elem_ty)?;
@@ -2217,11 +3913,102 @@ pub(crate) fn constrain_type(expr: &mut TypedExpr, expected: &Type) -> Result<()
             ExprEnum::ArrayRepeatLiteral(elem, _) | ExprEnum::ArrayRepeatLiteralConst(elem, _),
             Type::Array(elem_ty, _) | Type::ArrayConst(elem_ty, _),
         ) => constrain_type(elem, elem_ty)?,
+        (ExprEnum::ArraySlice(array, _, _), Type::Array(elem_ty, _)) => {
+            if let Type::Array(_, size) = array.ty {
+                constrain_type(array, &Type::Array(elem_ty.clone(), size))?;
+            }
+        }
         (ExprEnum::TupleLiteral(elems), Type::Tuple(elem_tys)) if elems.len() == elem_tys.len() => {
             for (elem, elem_ty) in elems.iter_mut().zip(elem_tys) {
                 constrain_type(elem, elem_ty)?;
             }
         }
+        (ExprEnum::BuiltinFnCall(identifier, args), Type::Array(elem_ty, size))
+            if identifier == "tuple_to_array" && args.len() == 1 =>
+        {
+            constrain_type(&mut args[0], &Type::Tuple(vec![*elem_ty.clone(); *size]))?
+        }
+        (ExprEnum::BuiltinFnCall(identifier, args), Type::Tuple(elem_tys))
+            if identifier == "array_to_tuple" && args.len() == 1 && !elem_tys.is_empty() =>
+        {
+            constrain_type(
+                &mut args[0],
+                &Type::Array(Box::new(elem_tys[0].clone()), elem_tys.len()),
+            )?
+        }
+        (ExprEnum::BuiltinFnCall(identifier, args), ty)
+            if matches!(
+                identifier.as_str(),
+                "gather_bits"
+                    | "prefix_sum"
+                    | "min"
+                    | "max"
+                    | "wrapping_add"
+                    | "wrapping_sub"
+                    | "wrapping_mul"
+            ) && !args.is_empty() =>
+        {
+            for arg in args {
+                constrain_type(arg, ty)?;
+            }
+        }
+        (ExprEnum::BuiltinFnCall(identifier, args), ty)
+            if matches!(identifier.as_str(), "permute" | "reverse" | "rotate")
+                && !args.is_empty() =>
+        {
+            constrain_type(&mut args[0], ty)?
+        }
+        (ExprEnum::BuiltinFnCall(identifier, args), Type::Array(elem_ty, _))
+            if identifier == "lookup_table" && args.len() == 2 =>
+        {
+            if let Type::Array(_, size) = args[0].ty {
+                constrain_type(&mut args[0], &Type::Array(elem_ty.clone(), size))?;
+            }
+        }
+        (ExprEnum::BuiltinFnCall(identifier, args), ty)
+            if matches!(identifier.as_str(), "sum" | "product" | "mean") && args.len() == 1 =>
+        {
+            if let Type::Array(_, size) = args[0].ty {
+                constrain_type(&mut args[0], &Type::Array(Box::new(ty.clone()), size))?;
+            }
+        }
+        (ExprEnum::BuiltinFnCall(identifier, args), ty)
+            if identifier == "dot" && args.len() == 2 =>
+        {
+            for arg in args {
+                if let Type::Array(_, size) = arg.ty {
+                    constrain_type(arg, &Type::Array(Box::new(ty.clone()), size))?;
+                }
+            }
+        }
+        (ExprEnum::BuiltinFnCall(identifier, args), ty)
+            if identifier == "get_or" && args.len() == 3 =>
+        {
+            if let Type::Array(_, size) = args[0].ty {
+                constrain_type(&mut args[0], &Type::Array(Box::new(ty.clone()), size))?;
+            }
+            constrain_type(&mut args[2], ty)?
+        }
+        // the number of set bits can be returned as any unsigned type that can hold the count:
+        (ExprEnum::BuiltinFnCall(identifier, args), Type::Unsigned(ty))
+            if identifier == "count_ones"
+                && args.len() == 1
+                && can_hold_count(*ty, &args[0].ty) => {}
+        (ExprEnum::BuiltinFnCall(identifier, args), Type::Tuple(elem_tys))
+            if identifier == "divmod" && elem_tys.len() == 2 =>
+        {
+            for arg in args {
+                constrain_type(arg, &elem_tys[0])?;
+            }
+        }
+        (ExprEnum::BuiltinFnCall(identifier, args), ty)
+            if identifier == "get" && args.len() == 2 =>
+        {
+            if let Some(indexes) = index_path(&args[1]) {
+                let x_ty = with_type_at_index_path(&args[0].ty, &indexes, ty);
+                constrain_type(&mut args[0], &x_ty)?
+            }
+        }
         (ExprEnum::Match(_, clauses), ty) => {
             for (_, body) in clauses {
                 constrain_type(body, ty)?;
@@ -2236,13 +4023,18 @@ pub(crate) fn constrain_type(expr: &mut TypedExpr, expected: &Type) -> Result<()
             | Op::Mul
             | Op::Div
             | Op::Mod
+            | Op::SaturatingAdd
+            | Op::SaturatingSub
+            | Op::SaturatingMul
             | Op::BitAnd
             | Op::BitXor
             | Op::BitOr => {
                 constrain_type(a, ty)?;
                 constrain_type(b, ty)?;
             }
-            Op::ShiftLeft | Op::ShiftRight => constrain_type(a, ty)?,
+            Op::ShiftLeft | Op::ShiftRight | Op::RotateRight | Op::RotateLeft => {
+                constrain_type(a, ty)?
+            }
             Op::GreaterThan
             | Op::LessThan
             | Op::Eq
@@ -2310,6 +4102,16 @@ fn unify(e1: &mut TypedExpr, e2: &mut TypedExpr, m: MetaInfo) -> Result<Type, Ty
             check_or_constrain_signed(e2, *ty1)?;
             Type::Signed(*ty1)
         }
+        (Type::String(_), _) if matches!(e2.inner, ExprEnum::StringLiteral(_)) => {
+            let ty = e1.ty.clone();
+            check_type(e2, &ty)?;
+            ty
+        }
+        (_, Type::String(_)) if matches!(e1.inner, ExprEnum::StringLiteral(_)) => {
+            let ty = e2.ty.clone();
+            check_type(e1, &ty)?;
+            ty
+        }
         _ => {
             let e = TypeErrorEnum::TypeMismatch(e1.ty.clone(), e2.ty.clone());
             return Err(vec![Some(TypeError(e, m))]);
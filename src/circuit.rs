@@ -1,7 +1,13 @@
 //! The [`Circuit`] representation used by the compiler.
 
-use crate::{compile::wires_as_unsigned, env::Env, token::MetaInfo};
-use std::collections::HashMap;
+use crate::{
+    compile::{unsigned_as_wires, wires_as_unsigned, LargeInline},
+    env::Env,
+    eval::EvalError,
+    literal::BitOrder,
+    token::MetaInfo,
+};
+use std::{cmp::max, collections::HashMap, io::Write};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -11,11 +17,18 @@ use serde::{Deserialize, Serialize};
 // 1. Constant evaluation (e.g. x ^ 0 == x; x & 1 == x; x & 0 == 0)
 // 2. Sub-expression sharing (wires are re-used if a gate with the same type and inputs exists)
 // 3. Pruning of useless gates (gates that are not part of the output nor used by other gates)
+//
+// Each of them can be disabled using a `CircuitBuilderConfig`.
 
-const PRINT_OPTIMIZATION_RATIO: bool = false;
-const MAX_GATES: usize = u32::MAX as usize;
+pub(crate) const MAX_GATES: usize = u32::MAX as usize;
 
 /// Data type to uniquely identify gates.
+///
+/// A gate index is the position of a wire in the circuit: the input wires come first (in the order
+/// of the parties), followed by the intermediate gates in the order of [`Circuit::gates`]. Indices
+/// are thus fully determined by the circuit itself and remain valid when a circuit is serialized
+/// and later deserialized. They are not guaranteed to be stable across compiler versions, so a
+/// cached circuit should always be stored together with the program it was compiled from.
 pub type GateIndex = usize;
 
 /// Description of a gate executed under MPC.
@@ -81,6 +94,16 @@ pub struct Circuit {
     pub output_gates: Vec<GateIndex>,
 }
 
+/// How the output bits of a circuit are grouped when the circuit is exported or imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputLayout {
+    /// All output bits are the output of the circuit, as in most hand-built circuits.
+    Plain,
+    /// The output bits start with the panic bits of a circuit compiled by Garble (see
+    /// [`PanicResult`]), followed by the actual output of the program.
+    WithPanic,
+}
+
 /// An input wire or a gate operating on them.
 pub enum Wire {
     /// An input wire, with its value coming directly from one of the parties.
@@ -110,6 +133,53 @@ pub enum CircuitError {
     PartyIndexOutOfBounds,
 }
 
+/// Errors occurring while parsing a circuit in the Bristol Fashion format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CircuitParseError {
+    /// The header (number of gates and wires, inputs and outputs) on the given line is malformed.
+    MalformedHeader(usize),
+    /// The gate on the given line is malformed.
+    MalformedGate(usize),
+    /// The gate on the given line uses a wire that is out of range or not yet assigned, or
+    /// assigns a wire that was already assigned.
+    InvalidWire(usize, usize),
+    /// The gate on the given line is not supported.
+    UnsupportedGate(usize, String),
+    /// The number of gates does not match the header.
+    UnexpectedNumberOfGates {
+        /// The number of gates specified in the header.
+        expected: usize,
+        /// The number of gates in the circuit.
+        actual: usize,
+    },
+}
+
+impl std::error::Error for CircuitParseError {}
+
+impl std::fmt::Display for CircuitParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CircuitParseError::MalformedHeader(line) => {
+                f.write_fmt(format_args!("Malformed header on line {line}"))
+            }
+            CircuitParseError::MalformedGate(line) => {
+                f.write_fmt(format_args!("Malformed gate on line {line}"))
+            }
+            CircuitParseError::InvalidWire(line, wire) => {
+                f.write_fmt(format_args!("Invalid wire {wire} on line {line}"))
+            }
+            CircuitParseError::UnsupportedGate(line, gate) => {
+                f.write_fmt(format_args!("Unsupported gate '{gate}' on line {line}"))
+            }
+            CircuitParseError::UnexpectedNumberOfGates { expected, actual } => {
+                f.write_fmt(format_args!(
+                    "Expected {expected} gates as specified by the header, but found {actual}"
+                ))
+            }
+        }
+    }
+}
+
 impl Circuit {
     /// Returns all the wires (inputs + gates) in the circuit, in ascending order.
     pub fn wires(&self) -> Vec<Wire> {
@@ -138,6 +208,78 @@ impl Circuit {
             .count()
     }
 
+    /// Returns the number of gates of each kind, the number of input and output bits and the
+    /// AND depth of the circuit.
+    pub fn gate_stats(&self) -> GateStats {
+        let input_bits: usize = self.input_gates.iter().sum();
+        let mut stats = GateStats {
+            and_gates: 0,
+            xor_gates: 0,
+            not_gates: 0,
+            input_bits,
+            output_bits: self.output_gates.len(),
+            and_depth: 0,
+        };
+        let mut depths = vec![0; input_bits + self.gates.len()];
+        for (i, gate) in self.gates.iter().enumerate() {
+            depths[input_bits + i] = match *gate {
+                Gate::Xor(x, y) => {
+                    stats.xor_gates += 1;
+                    max(depths[x], depths[y])
+                }
+                Gate::And(x, y) => {
+                    stats.and_gates += 1;
+                    max(depths[x], depths[y]) + 1
+                }
+                Gate::Not(x) => {
+                    stats.not_gates += 1;
+                    depths[x]
+                }
+            };
+        }
+        for &o in self.output_gates.iter() {
+            stats.and_depth = max(stats.and_depth, depths[o]);
+        }
+        stats
+    }
+
+    /// Returns the `(output, x, y)` wires of each AND gate in the circuit, in ascending order.
+    ///
+    /// Since the gates are topologically sorted, the AND gates are returned in the order in which
+    /// they can be evaluated, which makes it easy to plan the interactive rounds of an MPC engine.
+    pub fn and_gate_indices(&self) -> impl Iterator<Item = (GateIndex, GateIndex, GateIndex)> + '_ {
+        let input_len: usize = self.input_gates.iter().sum();
+        self.gates
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, gate)| match *gate {
+                Gate::And(x, y) => Some((input_len + i, x, y)),
+                Gate::Xor(_, _) | Gate::Not(_) => None,
+            })
+    }
+
+    /// Returns the fanout of each wire (inputs + gates, in ascending order), i.e. the number of
+    /// gates that read the wire plus the number of times it is referenced as an output.
+    pub fn gate_fanout(&self) -> Vec<usize> {
+        let input_len: usize = self.input_gates.iter().sum();
+        let mut fanout = vec![0; input_len + self.gates.len()];
+        for gate in self.gates.iter() {
+            match *gate {
+                Gate::Xor(x, y) | Gate::And(x, y) => {
+                    fanout[x] += 1;
+                    if x != y {
+                        fanout[y] += 1;
+                    }
+                }
+                Gate::Not(x) => fanout[x] += 1,
+            }
+        }
+        for &w in self.output_gates.iter() {
+            fanout[w] += 1;
+        }
+        fanout
+    }
+
     /// Checks that the circuit only uses valid wires, includes no cycles, has outputs, etc.
     pub fn validate(&self) -> Result<(), CircuitError> {
         let wires = self.wires();
@@ -178,13 +320,97 @@ impl Circuit {
     /// Assumes that the inputs have been previously type-checked and **panics** if the number of
     /// parties or the bits of a particular party do not match the circuit.
     pub fn eval(&self, inputs: &[Vec<bool>]) -> Vec<bool> {
-        let mut input_len = 0;
-        for p in self.input_gates.iter() {
-            input_len += p;
+        let wires = self.eval_wires(inputs);
+        let mut output_packed: Vec<bool> = Vec::with_capacity(self.output_gates.len());
+        for output_gate in &self.output_gates {
+            output_packed.push(wires[*output_gate]);
+        }
+        output_packed
+    }
+
+    /// Evaluates the circuit with the specified inputs (with one `Vec<bool>` per party), keeping
+    /// the values of all wires so that they can be inspected for debugging.
+    ///
+    /// Assumes that the inputs have been previously type-checked and **panics** if the number of
+    /// parties or the bits of a particular party do not match the circuit.
+    pub fn eval_trace(&self, inputs: &[Vec<bool>]) -> EvalTrace<'_> {
+        EvalTrace {
+            circuit: self,
+            wires: self.eval_wires(inputs),
         }
-        let mut output = vec![None; input_len + self.gates.len()];
-        let inputs: Vec<_> = inputs.iter().map(|inputs| inputs.iter()).collect();
-        let mut i = 0;
+    }
+
+    /// Checks whether both circuits compute the same outputs, using the default
+    /// [`EquivalenceConfig`].
+    ///
+    /// See [`Circuit::is_equivalent_with`] for details.
+    pub fn is_equivalent(&self, other: &Circuit) -> EquivalenceResult {
+        self.is_equivalent_with(other, EquivalenceConfig::default())
+    }
+
+    /// Checks whether both circuits compute the same outputs, by evaluating them on the same inputs
+    /// and returning the first input for which their outputs differ.
+    ///
+    /// If the circuits have at most [`EquivalenceConfig::max_exhaustive_bits`] input bits, all
+    /// possible inputs are evaluated and the result is a proof of equivalence. Otherwise only
+    /// [`EquivalenceConfig::random_inputs`] pseudo-random inputs are evaluated, so that a circuit
+    /// which differs only for a few inputs might not be detected.
+    ///
+    /// The outputs of circuits compiled by Garble include the location of a panic in the source
+    /// code, so programs that panic in different places are not equivalent unless their panic
+    /// tracking has been removed using [`Circuit::remove_panic_tracking`].
+    pub fn is_equivalent_with(
+        &self,
+        other: &Circuit,
+        config: EquivalenceConfig,
+    ) -> EquivalenceResult {
+        if self.input_gates != other.input_gates
+            || self.output_gates.len() != other.output_gates.len()
+        {
+            return EquivalenceResult::IncompatibleWidths;
+        }
+        let input_len: usize = self.input_gates.iter().sum();
+        let exhaustive =
+            input_len <= config.max_exhaustive_bits && input_len < usize::BITS as usize;
+        let inputs_checked = if exhaustive {
+            1 << input_len
+        } else {
+            config.random_inputs
+        };
+        let mut rng = SplitMix64(config.seed);
+        let mut bits = vec![false; input_len];
+        for i in 0..inputs_checked {
+            for (j, bit) in bits.iter_mut().enumerate() {
+                *bit = if exhaustive {
+                    (i >> j) & 1 == 1
+                } else {
+                    rng.next_u64() & 1 == 1
+                };
+            }
+            let mut inputs = Vec::with_capacity(self.input_gates.len());
+            let mut remaining = &bits[..];
+            for &party_bits in self.input_gates.iter() {
+                let (party_inputs, rest) = remaining.split_at(party_bits);
+                inputs.push(party_inputs.to_vec());
+                remaining = rest;
+            }
+            let left = self.eval(&inputs);
+            let right = other.eval(&inputs);
+            if left != right {
+                return EquivalenceResult::Counterexample {
+                    inputs,
+                    left,
+                    right,
+                };
+            }
+        }
+        EquivalenceResult::Equivalent {
+            inputs_checked,
+            exhaustive,
+        }
+    }
+
+    fn eval_wires(&self, inputs: &[Vec<bool>]) -> Vec<bool> {
         if self.input_gates.len() != inputs.len() {
             panic!(
                 "Circuit was built for {} parties, but found {} inputs",
@@ -192,6 +418,8 @@ impl Circuit {
                 inputs.len()
             );
         }
+        let input_len: usize = self.input_gates.iter().sum();
+        let mut wires = Vec::with_capacity(input_len + self.gates.len());
         for (p, &input_gates) in self.input_gates.iter().enumerate() {
             if input_gates != inputs[p].len() {
                 panic!(
@@ -201,26 +429,240 @@ impl Circuit {
                     inputs[p].len()
                 );
             }
-            for bit in inputs[p].as_slice() {
-                output[i] = Some(*bit);
-                i += 1;
+            wires.extend_from_slice(&inputs[p]);
+        }
+        for gate in self.gates.iter() {
+            let output_bit = match *gate {
+                Gate::Xor(x, y) => wires[x] ^ wires[y],
+                Gate::And(x, y) => wires[x] & wires[y],
+                Gate::Not(x) => !wires[x],
+            };
+            wires.push(output_bit);
+        }
+        wires
+    }
+
+    /// Removes the panic tracking from a circuit compiled by Garble, so that the circuit never
+    /// reports a panic, and prunes all gates that were only needed to detect panics.
+    pub fn remove_panic_tracking(self) -> Circuit {
+        let input_len: usize = self.input_gates.iter().sum();
+        // The first two gates of every circuit are constant false and constant true:
+        let const_wire = |w: GateIndex| if w == 0 { input_len } else { input_len + 1 };
+        let ok = PanicResult::ok();
+        let mut output_gates = Vec::with_capacity(self.output_gates.len());
+        output_gates.push(const_wire(ok.has_panicked));
+        output_gates.extend(ok.panic_type.into_iter().map(const_wire));
+        output_gates.extend(ok.start_line.into_iter().map(const_wire));
+        output_gates.extend(ok.start_column.into_iter().map(const_wire));
+        output_gates.extend(ok.end_line.into_iter().map(const_wire));
+        output_gates.extend(ok.end_column.into_iter().map(const_wire));
+        output_gates.extend(ok.panic_code.into_iter().map(const_wire));
+        output_gates.extend_from_slice(&self.output_gates[PANIC_RESULT_SIZE_IN_BITS..]);
+        Circuit {
+            input_gates: self.input_gates,
+            gates: self.gates,
+            output_gates,
+        }
+        .prune_unused_gates()
+    }
+
+    /// Combines several independent circuits into a single circuit that evaluates all of them, for
+    /// example to evaluate several programs in a single run of an MPC protocol.
+    ///
+    /// The input bits of each party are the input bits of the party for each circuit, in the order
+    /// of the circuits (circuits with fewer parties do not receive inputs from the other parties).
+    /// The output starts with the panic information of the first circuit that panicked, so that
+    /// any panic surfaces as if it came from a single circuit, followed by the outputs of all
+    /// circuits without their panic information. The constant wires are shared by all circuits.
+    ///
+    /// **Panics** if no circuits are specified or if a circuit does not output panic information
+    /// (which all circuits compiled by Garble do).
+    pub fn concat(circuits: &[Circuit]) -> Circuit {
+        assert!(
+            !circuits.is_empty(),
+            "Cannot concatenate an empty list of circuits"
+        );
+        let parties = circuits.iter().map(|c| c.input_gates.len()).max().unwrap();
+        let mut input_gates = vec![0; parties];
+        for c in circuits {
+            for (p, &bits) in c.input_gates.iter().enumerate() {
+                input_gates[p] += bits;
+            }
+        }
+        let input_len: usize = input_gates.iter().sum();
+        let mut next_input = Vec::with_capacity(parties);
+        let mut party_start = 0;
+        for &bits in input_gates.iter() {
+            next_input.push(party_start);
+            party_start += bits;
+        }
+        let (const_false, const_true) = (input_len, input_len + 1);
+        let mut gates = vec![Gate::Xor(0, 0), Gate::Not(const_false)];
+        let push = |gates: &mut Vec<Gate>, gate: Gate| {
+            gates.push(gate);
+            input_len + gates.len() - 1
+        };
+        let mut panics = Vec::with_capacity(circuits.len());
+        let mut output_gates = vec![];
+        for c in circuits {
+            assert!(
+                c.output_gates.len() >= PANIC_RESULT_SIZE_IN_BITS,
+                "Circuit does not output any panic information"
+            );
+            let mut wires = Vec::with_capacity(c.input_gates.iter().sum::<usize>() + c.gates.len());
+            for (p, &bits) in c.input_gates.iter().enumerate() {
+                wires.extend(next_input[p]..next_input[p] + bits);
+                next_input[p] += bits;
+            }
+            for gate in c.gates.iter() {
+                let wire = match *gate {
+                    Gate::Xor(x, y) if x == y => const_false,
+                    Gate::Not(x) if wires[x] == const_false => const_true,
+                    Gate::Not(x) if wires[x] == const_true => const_false,
+                    Gate::Xor(x, y) => push(&mut gates, Gate::Xor(wires[x], wires[y])),
+                    Gate::And(x, y) => push(&mut gates, Gate::And(wires[x], wires[y])),
+                    Gate::Not(x) => push(&mut gates, Gate::Not(wires[x])),
+                };
+                wires.push(wire);
+            }
+            let (panic, output) = c.output_gates.split_at(PANIC_RESULT_SIZE_IN_BITS);
+            panics.push(panic.iter().map(|&w| wires[w]).collect::<Vec<_>>());
+            output_gates.extend(output.iter().map(|&w| wires[w]));
+        }
+        // an earlier panic takes precedence over the panics of all later circuits:
+        let mut panic = panics.pop().unwrap();
+        for earlier in panics.into_iter().rev() {
+            let has_panicked = earlier[0];
+            for (w, &earlier) in panic.iter_mut().zip(earlier.iter()).skip(1) {
+                // mux(s, a, b) = b ^ (s & (a ^ b)):
+                let diff = push(&mut gates, Gate::Xor(earlier, *w));
+                let selected = push(&mut gates, Gate::And(has_panicked, diff));
+                *w = push(&mut gates, Gate::Xor(*w, selected));
             }
+            // or(a, b) = (a ^ b) ^ (a & b):
+            let xor = push(&mut gates, Gate::Xor(has_panicked, panic[0]));
+            let and = push(&mut gates, Gate::And(has_panicked, panic[0]));
+            panic[0] = push(&mut gates, Gate::Xor(xor, and));
+        }
+        panic.extend(output_gates);
+        Circuit {
+            input_gates,
+            gates,
+            output_gates: panic,
         }
-        for (w, gate) in self.gates.iter().enumerate() {
-            let w = w + i;
-            let output_bit = match gate {
-                Gate::Xor(x, y) => output[*x].unwrap() ^ output[*y].unwrap(),
-                Gate::And(x, y) => output[*x].unwrap() & output[*y].unwrap(),
-                Gate::Not(x) => !output[*x].unwrap(),
+    }
+
+    /// Rewrites the circuit to use fewer AND gates (at the cost of additional XOR gates), which
+    /// are the only gates that need communication in garbled circuit protocols using Free-XOR.
+    ///
+    /// The following rewrites are applied whenever the AND gates are not used anywhere else:
+    ///
+    /// - `(a & c) ^ (b & c)` becomes `c & (a ^ b)`
+    /// - `(a & s) ^ (b & !s)` (a multiplexer) becomes `b ^ (s & (a ^ b))`
+    ///
+    /// Returns the rewritten circuit and the number of AND gates that were removed.
+    pub fn minimize_and_gates(self) -> (Circuit, usize) {
+        let and_gates_before = self.and_gates();
+        let input_len: usize = self.input_gates.iter().sum();
+        let fanout = self.gate_fanout();
+        let gate_at = |w: GateIndex| {
+            if w >= input_len {
+                Some(&self.gates[w - input_len])
+            } else {
+                None
+            }
+        };
+        let mut rewired = Vec::with_capacity(input_len + self.gates.len());
+        rewired.extend(0..input_len);
+        let mut gates = Vec::with_capacity(self.gates.len());
+        for gate in self.gates.iter() {
+            let rewritten = match *gate {
+                Gate::Xor(x, y) if fanout[x] == 1 && fanout[y] == 1 => {
+                    match (gate_at(x), gate_at(y)) {
+                        (Some(&Gate::And(a1, a2)), Some(&Gate::And(b1, b2))) => {
+                            let is_not =
+                                |n: GateIndex, s: GateIndex| gate_at(n) == Some(&Gate::Not(s));
+                            let mut rewritten = None;
+                            for (c, a) in [(a1, a2), (a2, a1)] {
+                                for (d, b) in [(b1, b2), (b2, b1)] {
+                                    if rewritten.is_some() {
+                                        break;
+                                    }
+                                    if c == d {
+                                        gates.push(Gate::Xor(rewired[a], rewired[b]));
+                                        let a_xor_b = input_len + gates.len() - 1;
+                                        rewritten = Some(Gate::And(rewired[c], a_xor_b));
+                                    } else if is_not(d, c) || is_not(c, d) {
+                                        // (a & s) ^ (b & !s) == b ^ (s & (a ^ b)), with s = c:
+                                        let (s, a, b) =
+                                            if is_not(d, c) { (c, a, b) } else { (d, b, a) };
+                                        gates.push(Gate::Xor(rewired[a], rewired[b]));
+                                        let a_xor_b = input_len + gates.len() - 1;
+                                        gates.push(Gate::And(rewired[s], a_xor_b));
+                                        let selected = input_len + gates.len() - 1;
+                                        rewritten = Some(Gate::Xor(rewired[b], selected));
+                                    }
+                                }
+                            }
+                            rewritten
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
             };
-            output[w] = Some(output_bit);
+            gates.push(rewritten.unwrap_or_else(|| match *gate {
+                Gate::Xor(x, y) => Gate::Xor(rewired[x], rewired[y]),
+                Gate::And(x, y) => Gate::And(rewired[x], rewired[y]),
+                Gate::Not(x) => Gate::Not(rewired[x]),
+            }));
+            rewired.push(input_len + gates.len() - 1);
         }
+        let circuit = Circuit {
+            input_gates: self.input_gates,
+            gates,
+            output_gates: self.output_gates.iter().map(|&w| rewired[w]).collect(),
+        }
+        .prune_unused_gates();
+        let removed = and_gates_before - circuit.and_gates();
+        (circuit, removed)
+    }
 
-        let mut output_packed: Vec<bool> = Vec::with_capacity(self.output_gates.len());
-        for output_gate in &self.output_gates {
-            output_packed.push(output[*output_gate].unwrap());
+    fn prune_unused_gates(self) -> Circuit {
+        let input_len: usize = self.input_gates.iter().sum();
+        let mut used_gates = vec![false; self.gates.len()];
+        let mut output_gate_stack = self.output_gates.clone();
+        while let Some(w) = output_gate_stack.pop() {
+            if w >= input_len && !used_gates[w - input_len] {
+                used_gates[w - input_len] = true;
+                match self.gates[w - input_len] {
+                    Gate::Xor(x, y) | Gate::And(x, y) => output_gate_stack.extend([x, y]),
+                    Gate::Not(x) => output_gate_stack.push(x),
+                }
+            }
+        }
+        let mut shifted_wires = Vec::with_capacity(input_len + self.gates.len());
+        shifted_wires.extend(0..input_len);
+        let mut gates = Vec::with_capacity(self.gates.len());
+        for (gate, used) in self.gates.into_iter().zip(used_gates) {
+            shifted_wires.push(input_len + gates.len());
+            if used {
+                gates.push(match gate {
+                    Gate::Xor(x, y) => Gate::Xor(shifted_wires[x], shifted_wires[y]),
+                    Gate::And(x, y) => Gate::And(shifted_wires[x], shifted_wires[y]),
+                    Gate::Not(x) => Gate::Not(shifted_wires[x]),
+                });
+            }
+        }
+        Circuit {
+            input_gates: self.input_gates,
+            gates,
+            output_gates: self
+                .output_gates
+                .into_iter()
+                .map(|w| shifted_wires[w])
+                .collect(),
         }
-        output_packed
     }
 
     /// Returns the number of gates in the circuit as a formatted string.
@@ -245,6 +687,511 @@ impl Circuit {
             self.gates.len() / 1000
         )
     }
+
+    /// Exports the circuit in the Bristol Fashion format, with one input value per party.
+    ///
+    /// See [`Circuit::write_bristol`] for the layout of the output values.
+    pub fn to_bristol(&self, order: BitOrder, layout: OutputLayout) -> String {
+        let mut bristol = vec![];
+        self.write_bristol(order, layout, &mut bristol)
+            .expect("writing to a Vec cannot fail");
+        String::from_utf8(bristol).expect("the Bristol Fashion format is ASCII")
+    }
+
+    /// Writes the circuit in the Bristol Fashion format to the writer, with one input value per
+    /// party.
+    ///
+    /// `Gate::Xor`, `Gate::And` and `Gate::Not` are written as `XOR`, `AND` and `INV` gates, which
+    /// keep their wire indices. Bristol Fashion has no constant wires, but the constant false and
+    /// true wires of a circuit compiled by Garble are the ordinary gates `Xor(0, 0)` and `Not(n)`
+    /// and are thus exported like any other gate.
+    ///
+    /// With [`OutputLayout::WithPanic`], the outputs start with the panic bits (like the outputs of
+    /// every circuit compiled by Garble), which are exported as a separate output value after the
+    /// actual output of the program. The panic value consists of 1 bit indicating whether a panic
+    /// occurred, followed by the panic reason, start line, start column, end line, end column and
+    /// the code of a failed assertion (see [`PanicResult`]), each as a Garble `usize` of 32 bits
+    /// and always most significant bit first, for a total of 193 bits. With
+    /// [`OutputLayout::Plain`], all outputs are exported as a single output value.
+    ///
+    /// Bristol Fashion requires the output wires to be the last wires of the circuit, so every
+    /// output is copied to a new wire using an `EQW` gate. With [`BitOrder::LsbFirst`], the input
+    /// bits of each party and the bits of the program output are reversed, which matches
+    /// [`crate::literal::Literal::as_bits_with_order`] for parties that provide a single number.
+    ///
+    /// **Panics** if the layout is [`OutputLayout::WithPanic`] but the circuit has fewer output
+    /// bits than the panic bits.
+    pub fn write_bristol<W: Write>(
+        &self,
+        order: BitOrder,
+        layout: OutputLayout,
+        w: &mut W,
+    ) -> std::io::Result<()> {
+        let input_len: usize = self.input_gates.iter().sum();
+        let mut input_wires = Vec::with_capacity(input_len);
+        for &party_len in self.input_gates.iter() {
+            let offset = input_wires.len();
+            let mut party_wires: Vec<GateIndex> = (offset..offset + party_len).collect();
+            order.apply_to_wires(&mut party_wires);
+            input_wires.extend(party_wires);
+        }
+        let wire = |w: GateIndex| if w < input_len { input_wires[w] } else { w };
+        let num_wires = input_len + self.gates.len() + self.output_gates.len();
+        let num_gates = self.gates.len() + self.output_gates.len();
+        write!(w, "{num_gates} {num_wires}\n{}", self.input_gates.len())?;
+        for party_len in self.input_gates.iter() {
+            write!(w, " {party_len}")?;
+        }
+        let (panic, output) = self.split_panic_outputs(layout);
+        let mut output = output.to_vec();
+        order.apply_to_wires(&mut output);
+        let mut output_values = vec![output];
+        if layout == OutputLayout::WithPanic {
+            output_values.push(panic.to_vec());
+        }
+        write!(w, "\n{}", output_values.len())?;
+        for output in output_values.iter() {
+            write!(w, " {}", output.len())?;
+        }
+        write!(w, "\n\n")?;
+        for (i, gate) in self.gates.iter().enumerate() {
+            let out = input_len + i;
+            match *gate {
+                Gate::Xor(x, y) => writeln!(w, "2 1 {} {} {out} XOR", wire(x), wire(y))?,
+                Gate::And(x, y) => writeln!(w, "2 1 {} {} {out} AND", wire(x), wire(y))?,
+                Gate::Not(x) => writeln!(w, "1 1 {} {out} INV", wire(x))?,
+            }
+        }
+        let first_output = input_len + self.gates.len();
+        for (i, o) in output_values.into_iter().flatten().enumerate() {
+            writeln!(w, "1 1 {} {} EQW", wire(o), first_output + i)?;
+        }
+        Ok(())
+    }
+
+    // Splits the output wires into the panic bits (if any) and the actual output of the program:
+    fn split_panic_outputs(&self, layout: OutputLayout) -> (&[GateIndex], &[GateIndex]) {
+        match layout {
+            OutputLayout::Plain => (&[], &self.output_gates),
+            OutputLayout::WithPanic => {
+                assert!(
+                    self.output_gates.len() >= PANIC_RESULT_SIZE_IN_BITS,
+                    "circuit has fewer output bits than the panic bits"
+                );
+                self.output_gates.split_at(PANIC_RESULT_SIZE_IN_BITS)
+            }
+        }
+    }
+
+    /// Exports the circuit as a Graphviz `digraph`, for visualizing small circuits.
+    ///
+    /// Every input wire, every gate and every output is a node, with an edge for each wire that a
+    /// gate or output uses. Input wires are colored by party and gates are labeled as `XOR`, `AND`
+    /// or `NOT`. Like in [`Circuit::write_bristol`], the first outputs of a circuit with
+    /// [`OutputLayout::WithPanic`] are the panic bits, which are highlighted differently than the
+    /// actual output of the program.
+    ///
+    /// Returns [`CircuitError::MaxCircuitSizeExceeded`] if the circuit has more than `max_gates`
+    /// gates, since the resulting graphs quickly become too large to be rendered.
+    ///
+    /// **Panics** if the layout is [`OutputLayout::WithPanic`] but the circuit has fewer output
+    /// bits than the panic bits.
+    pub fn to_dot(
+        &self,
+        layout: OutputLayout,
+        max_gates: Option<usize>,
+    ) -> Result<String, CircuitError> {
+        const PARTY_COLORS: [&str; 6] = [
+            "lightblue",
+            "palegreen",
+            "khaki",
+            "plum",
+            "lightsalmon",
+            "lightcyan",
+        ];
+        if let Some(max_gates) = max_gates {
+            if self.gates.len() > max_gates {
+                return Err(CircuitError::MaxCircuitSizeExceeded);
+            }
+        }
+        let mut dot = String::from("digraph circuit {\n");
+        let mut wire = 0;
+        for (party, &party_len) in self.input_gates.iter().enumerate() {
+            let color = PARTY_COLORS[party % PARTY_COLORS.len()];
+            for bit in 0..party_len {
+                dot += &format!(
+                    "  w{wire} [label=\"party {party}, bit {bit}\", style=filled, fillcolor={color}];\n"
+                );
+                wire += 1;
+            }
+        }
+        for gate in self.gates.iter() {
+            let (label, inputs) = match *gate {
+                Gate::Xor(x, y) => ("XOR", vec![x, y]),
+                Gate::And(x, y) => ("AND", vec![x, y]),
+                Gate::Not(x) => ("NOT", vec![x]),
+            };
+            dot += &format!("  w{wire} [label=\"{label}\", shape=box];\n");
+            for input in inputs {
+                dot += &format!("  w{input} -> w{wire};\n");
+            }
+            wire += 1;
+        }
+        let panic_bits = self.split_panic_outputs(layout).0.len();
+        for (i, output) in self.output_gates.iter().enumerate() {
+            let (label, color) = if i < panic_bits {
+                (format!("panic {i}"), "red")
+            } else {
+                (format!("output {}", i - panic_bits), "gold")
+            };
+            dot += &format!(
+                "  o{i} [label=\"{label}\", shape=doublecircle, style=filled, fillcolor={color}];\n"
+            );
+            dot += &format!("  w{output} -> o{i};\n");
+        }
+        dot += "}\n";
+        Ok(dot)
+    }
+
+    /// Parses a circuit in the Bristol Fashion format, with each input value as one party.
+    ///
+    /// `XOR`, `AND` and `INV` gates are imported as [`Gate::Xor`], [`Gate::And`] and [`Gate::Not`],
+    /// while `EQW` (copying a wire) and `EQ` (assigning a constant) gates are resolved to the
+    /// copied wire or to one of the constant wires. Like every circuit compiled by Garble, the
+    /// imported circuit starts with a constant false and a constant true gate.
+    ///
+    /// With [`OutputLayout::Plain`], the outputs of the imported circuit are the output values of
+    /// the Bristol circuit, in order. With [`OutputLayout::WithPanic`], the last output value must
+    /// consist of the panic bits and becomes the start of the outputs, so that a circuit exported
+    /// with [`Circuit::to_bristol`] and [`BitOrder::MsbFirst`] is imported with the same outputs.
+    pub fn from_bristol(bristol: &str, layout: OutputLayout) -> Result<Circuit, CircuitParseError> {
+        let mut lines = bristol
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.split_whitespace().collect::<Vec<_>>()))
+            .filter(|(_, parts)| !parts.is_empty());
+        let mut header = |expected_len: Option<usize>| {
+            let Some((line, parts)) = lines.next() else {
+                return Err(CircuitParseError::MalformedHeader(bristol.lines().count()));
+            };
+            let mut numbers = vec![];
+            for part in parts {
+                let n = part.parse::<usize>();
+                numbers.push(n.map_err(|_| CircuitParseError::MalformedHeader(line))?);
+            }
+            let expected_len = expected_len.unwrap_or_else(|| numbers[0].saturating_add(1));
+            if numbers.len() != expected_len {
+                return Err(CircuitParseError::MalformedHeader(line));
+            }
+            Ok((line, numbers))
+        };
+        let sum = |line: usize, numbers: &[usize]| {
+            let mut sum: usize = 0;
+            for n in numbers {
+                sum = sum
+                    .checked_add(*n)
+                    .ok_or(CircuitParseError::MalformedHeader(line))?;
+            }
+            Ok(sum)
+        };
+        let (sizes_line, sizes) = header(Some(2))?;
+        let (num_gates, num_wires) = (sizes[0], sizes[1]);
+        let (inputs_line, input_gates) = header(None)?;
+        let input_gates = input_gates[1..].to_vec();
+        let (outputs_line, output_values) = header(None)?;
+        let output_values = output_values[1..].to_vec();
+        let input_len = sum(inputs_line, &input_gates)?;
+        let output_len = sum(outputs_line, &output_values)?;
+        if input_len == 0 {
+            return Err(CircuitParseError::MalformedHeader(inputs_line));
+        }
+        match input_len.checked_add(output_len) {
+            Some(len) if len <= num_wires && num_wires <= MAX_GATES => {}
+            _ => return Err(CircuitParseError::MalformedHeader(sizes_line)),
+        }
+        let panic_len = match layout {
+            OutputLayout::Plain => 0,
+            OutputLayout::WithPanic => match output_values.last() {
+                Some(&len) if len == PANIC_RESULT_SIZE_IN_BITS => len,
+                _ => return Err(CircuitParseError::MalformedHeader(outputs_line)),
+            },
+        };
+
+        let mut wires: Vec<Option<GateIndex>> = vec![None; num_wires];
+        for (w, wire) in wires.iter_mut().enumerate().take(input_len) {
+            *wire = Some(w);
+        }
+        let mut gates = vec![Gate::Xor(0, 0), Gate::Not(input_len)];
+        let (const_false, const_true) = (input_len, input_len + 1);
+        let mut parsed_gates = 0;
+        for (line, parts) in lines {
+            parsed_gates += 1;
+            let Some((&gate, numbers)) = parts.split_last() else {
+                return Err(CircuitParseError::MalformedGate(line));
+            };
+            let mut wire_numbers = vec![];
+            for part in numbers {
+                let n = part.parse::<usize>();
+                wire_numbers.push(n.map_err(|_| CircuitParseError::MalformedGate(line))?);
+            }
+            let (num_in, num_out) = match wire_numbers.as_slice() {
+                [num_in, num_out, ..] => (*num_in, *num_out),
+                _ => return Err(CircuitParseError::MalformedGate(line)),
+            };
+            let expected_len = num_in.checked_add(num_out).and_then(|n| n.checked_add(2));
+            if num_out != 1 || Some(wire_numbers.len()) != expected_len {
+                return Err(CircuitParseError::MalformedGate(line));
+            }
+            let inputs = &wire_numbers[2..2 + num_in];
+            let out = wire_numbers[2 + num_in];
+            let wire = |w: usize| match wires.get(w) {
+                Some(Some(w)) => Ok(*w),
+                _ => Err(CircuitParseError::InvalidWire(line, w)),
+            };
+            let assigned = match (gate, inputs) {
+                ("XOR", &[x, y]) => {
+                    gates.push(Gate::Xor(wire(x)?, wire(y)?));
+                    input_len + gates.len() - 1
+                }
+                ("AND", &[x, y]) => {
+                    gates.push(Gate::And(wire(x)?, wire(y)?));
+                    input_len + gates.len() - 1
+                }
+                ("INV", &[x]) => {
+                    gates.push(Gate::Not(wire(x)?));
+                    input_len + gates.len() - 1
+                }
+                ("EQW", &[x]) => wire(x)?,
+                ("EQ", &[0]) => const_false,
+                ("EQ", &[1]) => const_true,
+                ("XOR" | "AND" | "INV" | "EQW" | "EQ", _) => {
+                    return Err(CircuitParseError::MalformedGate(line))
+                }
+                (gate, _) => {
+                    return Err(CircuitParseError::UnsupportedGate(line, gate.to_string()))
+                }
+            };
+            match wires.get_mut(out) {
+                Some(w @ None) => *w = Some(assigned),
+                _ => return Err(CircuitParseError::InvalidWire(line, out)),
+            }
+        }
+        if parsed_gates != num_gates {
+            return Err(CircuitParseError::UnexpectedNumberOfGates {
+                expected: num_gates,
+                actual: parsed_gates,
+            });
+        }
+
+        let mut output_gates = Vec::with_capacity(output_len);
+        for (w, wire) in wires.iter().enumerate().skip(num_wires - output_len) {
+            let Some(wire) = wire else {
+                return Err(CircuitParseError::InvalidWire(bristol.lines().count(), w));
+            };
+            output_gates.push(*wire);
+        }
+        // the panic bits are exported after the output of the program, but come first in Garble:
+        output_gates.rotate_right(panic_len);
+        Ok(Circuit {
+            input_gates,
+            gates,
+            output_gates,
+        })
+    }
+}
+
+/// The values of all wires of an evaluated circuit, as returned by [`Circuit::eval_trace`].
+#[derive(Debug, Clone)]
+pub struct EvalTrace<'a> {
+    circuit: &'a Circuit,
+    wires: Vec<bool>,
+}
+
+/// A gate of an evaluated circuit, together with the values of its input and output wires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TracedGate<'a> {
+    /// The wire of the gate.
+    pub wire: GateIndex,
+    /// The gate, specifying its input wires.
+    pub gate: &'a Gate,
+    /// The values of the input wires of the gate (with the second value being `None` for NOT).
+    pub inputs: (bool, Option<bool>),
+    /// The value of the output wire of the gate.
+    pub output: bool,
+}
+
+impl<'a> EvalTrace<'a> {
+    /// Returns the value of the wire (input or gate), or `None` if the wire does not exist.
+    pub fn value(&self, wire: GateIndex) -> Option<bool> {
+        self.wires.get(wire).copied()
+    }
+
+    /// Returns the values of the output gates, which are identical to the result of
+    /// [`Circuit::eval`].
+    pub fn outputs(&self) -> Vec<bool> {
+        self.circuit
+            .output_gates
+            .iter()
+            .map(|&w| self.wires[w])
+            .collect()
+    }
+
+    /// Returns all gates (excluding the inputs) in the order of their evaluation, together with
+    /// the values of their input and output wires.
+    pub fn gates(&self) -> impl Iterator<Item = TracedGate<'a>> + '_ {
+        let input_len: usize = self.circuit.input_gates.iter().sum();
+        let circuit: &'a Circuit = self.circuit;
+        circuit
+            .gates
+            .iter()
+            .enumerate()
+            .map(move |(i, gate)| TracedGate {
+                wire: input_len + i,
+                gate,
+                inputs: match *gate {
+                    Gate::Xor(x, y) | Gate::And(x, y) => (self.wires[x], Some(self.wires[y])),
+                    Gate::Not(x) => (self.wires[x], None),
+                },
+                output: self.wires[input_len + i],
+            })
+    }
+}
+
+/// Controls how two circuits are compared by [`Circuit::is_equivalent_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EquivalenceConfig {
+    /// Circuits with at most this many input bits (of all parties) are evaluated on all inputs.
+    pub max_exhaustive_bits: usize,
+    /// The number of random inputs that larger circuits are evaluated on.
+    pub random_inputs: usize,
+    /// The seed of the pseudo-random inputs, so that a comparison can be reproduced.
+    pub seed: u64,
+}
+
+impl Default for EquivalenceConfig {
+    fn default() -> Self {
+        Self {
+            max_exhaustive_bits: 16,
+            random_inputs: 10_000,
+            seed: 0,
+        }
+    }
+}
+
+/// The result of comparing two circuits using [`Circuit::is_equivalent`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EquivalenceResult {
+    /// Both circuits produced the same outputs for all of the inputs that were checked.
+    Equivalent {
+        /// The number of inputs that both circuits were evaluated on.
+        inputs_checked: usize,
+        /// Whether all possible inputs were checked (instead of only random inputs).
+        exhaustive: bool,
+    },
+    /// The circuits produced different outputs for the inputs (with one `Vec<bool>` per party).
+    Counterexample {
+        /// The input bits that distinguish the circuits.
+        inputs: Vec<Vec<bool>>,
+        /// The output of the first circuit.
+        left: Vec<bool>,
+        /// The output of the second circuit.
+        right: Vec<bool>,
+    },
+    /// The circuits differ in the number of parties, input bits per party or output bits.
+    IncompatibleWidths,
+}
+
+impl EquivalenceResult {
+    /// Returns `true` if no inputs were found that distinguish the circuits.
+    pub fn is_equivalent(&self) -> bool {
+        matches!(self, EquivalenceResult::Equivalent { .. })
+    }
+}
+
+// A small pseudo-random number generator, so that random inputs do not require a dependency:
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// The number of gates, inputs and outputs of a circuit, as returned by [`Circuit::gate_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GateStats {
+    /// The number of AND gates, which are the expensive gates in most MPC protocols.
+    pub and_gates: usize,
+    /// The number of XOR gates.
+    pub xor_gates: usize,
+    /// The number of NOT gates.
+    pub not_gates: usize,
+    /// The number of input bits of all parties.
+    pub input_bits: usize,
+    /// The number of output bits (including the bits used to report panics).
+    pub output_bits: usize,
+    /// The multiplicative depth of the circuit, i.e. the number of AND gates on the longest path
+    /// from an input to an output, which determines the number of communication rounds in many
+    /// MPC protocols.
+    pub and_depth: usize,
+}
+
+/// The longest chain of AND gates in a circuit, together with the locations in the source code that
+/// produced the AND gates along the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CriticalPath {
+    /// The number of AND gates on the longest chain of AND gates in the circuit.
+    pub and_depth: usize,
+    /// The source code locations of the operations that contributed AND gates to the chain,
+    /// ordered from the inputs to the outputs of the circuit.
+    pub locations: Vec<MetaInfo>,
+}
+
+/// The number of gates that were avoided by each of the optimizations applied during compilation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OptimizationReport {
+    /// The number of gates in the optimized circuit.
+    pub gates: usize,
+    /// The number of gates that were replaced by a constant or by one of their inputs.
+    pub gates_folded: usize,
+    /// The number of gates that were shared with an identical, previously created gate.
+    pub gates_shared: usize,
+    /// The number of gates that were removed because they did not contribute to the output.
+    pub gates_pruned: usize,
+}
+
+/// Controls which optimizations are applied while a circuit is being built.
+///
+/// The default applies all optimizations up to a depth of 2, which keeps the compilation time
+/// linear in the number of gates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBuilderConfig {
+    /// How many levels of gates the constant evaluation looks at: 0 disables it, 1 only looks at
+    /// the inputs of a gate, 2 also looks through negated inputs (e.g. x & !x == 0), and anything
+    /// higher cancels out inputs that appear twice in trees of XOR gates of up to this depth (e.g.
+    /// (x ^ y) ^ x == y), at the cost of a slower compilation.
+    pub max_optimization_depth: u32,
+    /// Whether wires are re-used if a gate with the same type and inputs already exists.
+    pub dedup: bool,
+    /// Whether gates that are not part of the output nor used by other gates are removed.
+    pub prune: bool,
+    /// Whether gates are replaced by constants or by one of their inputs where possible.
+    pub rewrite: bool,
+}
+
+impl Default for CircuitBuilderConfig {
+    fn default() -> Self {
+        Self {
+            max_optimization_depth: 2,
+            dedup: true,
+            prune: true,
+            rewrite: true,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -260,17 +1207,35 @@ pub(crate) struct CircuitBuilder {
     gates: Vec<BuilderGate>,
     cache: HashMap<BuilderGate, GateIndex>,
     negated: HashMap<GateIndex, GateIndex>,
-    gates_optimized: usize,
+    gates_folded: usize,
+    gates_shared: usize,
+    gates_pruned: usize,
     gate_counter: usize,
     panic_gates: PanicResult,
     consts: HashMap<String, usize>,
+    current_meta: MetaInfo,
+    gate_metas: Option<Vec<MetaInfo>>,
+    panic_sites: Vec<(PanicReason, MetaInfo)>,
+    inline_threshold: Option<usize>,
+    large_inlines: Vec<LargeInline>,
+    fn_calls: HashMap<(String, Vec<Vec<GateIndex>>), FnCallResult>,
+    config: CircuitBuilderConfig,
+}
+
+// The output wires of an inlined fn call, which can be reused for calls with the same arg wires:
+#[derive(Debug, Clone)]
+struct FnCallResult {
+    output: Vec<GateIndex>,
+    gates: usize,
+    can_panic: bool,
 }
 
 pub(crate) const USIZE_BITS: usize = 32;
-const PANIC_RESULT_SIZE_IN_BITS: usize = 1 + 5 * USIZE_BITS;
+const PANIC_RESULT_SIZE_IN_BITS: usize = 1 + 6 * USIZE_BITS;
 
 /// A collection of wires that carry information about whether and where a panic occurred.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PanicResult {
     /// A boolean wire indicating whether a panic has occurred.
     pub has_panicked: GateIndex,
@@ -284,6 +1249,8 @@ pub struct PanicResult {
     pub end_line: [GateIndex; USIZE_BITS],
     /// The (encoded) last column of the last line in the source code where the panic occurred.
     pub end_column: [GateIndex; USIZE_BITS],
+    /// The (encoded) code of a failed assertion, or 0 if no code was specified.
+    pub panic_code: [GateIndex; USIZE_BITS],
 }
 
 impl PanicResult {
@@ -296,21 +1263,28 @@ impl PanicResult {
             start_column: [0; USIZE_BITS],
             end_line: [0; USIZE_BITS],
             end_column: [0; USIZE_BITS],
+            panic_code: [0; USIZE_BITS],
         }
     }
 }
 
 /// A decoded panic, indicating why and where a panic occurred.
+///
+/// If several panics could have occurred for the same inputs, this is always the panic that a
+/// sequential evaluation of the program would have hit first (with operands evaluated from left
+/// to right before the operation itself).
 #[derive(Debug, Clone)]
 pub struct EvalPanic {
     /// The reason why the panic occurred.
     pub reason: PanicReason,
     /// The location in the source code where the panic occurred.
     pub panicked_at: MetaInfo,
+    /// The code of a failed assertion (such as `assert(x, 2)`), or 0 if no code was specified.
+    pub code: usize,
 }
 
 impl EvalPanic {
-    pub(crate) fn parse(bits: &[bool]) -> Result<&[bool], EvalPanic> {
+    pub(crate) fn parse(bits: &[bool]) -> Result<&[bool], EvalError> {
         let has_panicked = bits[0];
         let panic_type: [bool; USIZE_BITS] = bits[1..USIZE_BITS + 1].try_into().unwrap();
         let start_line: [bool; USIZE_BITS] = bits[USIZE_BITS + 1..(2 * USIZE_BITS) + 1]
@@ -325,9 +1299,15 @@ impl EvalPanic {
         let end_column: [bool; USIZE_BITS] = bits[(4 * USIZE_BITS) + 1..(5 * USIZE_BITS) + 1]
             .try_into()
             .unwrap();
-        let reason = PanicReason::from_num(wires_as_unsigned(&panic_type) as usize);
+        let panic_code: [bool; USIZE_BITS] = bits[(5 * USIZE_BITS) + 1..(6 * USIZE_BITS) + 1]
+            .try_into()
+            .unwrap();
         if has_panicked {
-            Err(EvalPanic {
+            let reason = wires_as_unsigned(&panic_type) as usize;
+            let Some(reason) = PanicReason::from_num(reason) else {
+                return Err(EvalError::InvalidPanicReason(reason));
+            };
+            Err(EvalError::Panic(EvalPanic {
                 reason,
                 panicked_at: MetaInfo {
                     start: (
@@ -339,15 +1319,17 @@ impl EvalPanic {
                         wires_as_unsigned(&end_column) as usize,
                     ),
                 },
-            })
+                code: wires_as_unsigned(&panic_code) as usize,
+            }))
         } else {
-            Ok(&bits[5 * USIZE_BITS + 1..])
+            Ok(&bits[6 * USIZE_BITS + 1..])
         }
     }
 }
 
 /// The reason why a panic occurred.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PanicReason {
     /// Arithmetic overflow.
     Overflow,
@@ -355,6 +1337,8 @@ pub enum PanicReason {
     DivByZero,
     /// Array out of bounds access.
     OutOfBounds,
+    /// Failed `assert` or `assert_eq`.
+    AssertionFailed,
 }
 
 impl std::fmt::Display for PanicReason {
@@ -363,17 +1347,19 @@ impl std::fmt::Display for PanicReason {
             PanicReason::Overflow => "Overflow",
             PanicReason::DivByZero => "Division By Zero",
             PanicReason::OutOfBounds => "Array Access Out Of Bounds",
+            PanicReason::AssertionFailed => "Assertion Failed",
         })
     }
 }
 
 impl PanicReason {
-    fn from_num(n: usize) -> Self {
+    fn from_num(n: usize) -> Option<Self> {
         match n {
-            1 => PanicReason::Overflow,
-            2 => PanicReason::DivByZero,
-            3 => PanicReason::OutOfBounds,
-            r => panic!("Invalid panic reason: {r}"),
+            1 => Some(PanicReason::Overflow),
+            2 => Some(PanicReason::DivByZero),
+            3 => Some(PanicReason::OutOfBounds),
+            4 => Some(PanicReason::AssertionFailed),
+            _ => None,
         }
     }
 
@@ -382,13 +1368,18 @@ impl PanicReason {
             PanicReason::Overflow => 1,
             PanicReason::DivByZero => 2,
             PanicReason::OutOfBounds => 3,
+            PanicReason::AssertionFailed => 4,
         };
         unsigned_as_usize_bits(n)
     }
 }
 
 impl CircuitBuilder {
-    pub fn new(input_gates: Vec<usize>, consts: HashMap<String, usize>) -> Self {
+    pub fn new_with_config(
+        input_gates: Vec<usize>,
+        consts: HashMap<String, usize>,
+        config: CircuitBuilderConfig,
+    ) -> Self {
         let mut gate_counter = 2; // for const true and false
         for input_gates_of_party in input_gates.iter() {
             gate_counter += input_gates_of_party;
@@ -399,10 +1390,153 @@ impl CircuitBuilder {
             gates: vec![],
             cache: HashMap::new(),
             negated: HashMap::new(),
-            gates_optimized: 0,
+            gates_folded: 0,
+            gates_shared: 0,
+            gates_pruned: 0,
             gate_counter,
             panic_gates: PanicResult::ok(),
             consts,
+            current_meta: MetaInfo {
+                start: (0, 0),
+                end: (0, 0),
+            },
+            gate_metas: None,
+            panic_sites: vec![],
+            inline_threshold: None,
+            large_inlines: vec![],
+            fn_calls: HashMap::new(),
+            config,
+        }
+    }
+
+    // Records the source location of every gate that is pushed from now on, which is only needed
+    // for reporting the critical path (and is thus not maintained when pruning gates):
+    pub fn track_gate_metas(&mut self) {
+        self.gate_metas = Some(vec![self.current_meta; self.gates.len()]);
+    }
+
+    // Records every fn call whose inlined body adds more than `threshold` gates to the circuit:
+    pub fn warn_on_large_inline(&mut self, threshold: usize) {
+        self.inline_threshold = Some(threshold);
+    }
+
+    // The number of (non-optimized) gates pushed so far, used to measure the cost of inlining:
+    pub fn gates_pushed(&self) -> usize {
+        self.gates.len()
+    }
+
+    // Calls of the same fn at the same call site (e.g. in a loop) are merged into a single report:
+    pub fn report_inline(&mut self, fn_name: &str, call_site: MetaInfo, gates: usize) {
+        match self.inline_threshold {
+            Some(threshold) if gates > threshold => {}
+            _ => return,
+        }
+        let existing = self
+            .large_inlines
+            .iter_mut()
+            .find(|r| r.fn_name == fn_name && r.call_site == call_site);
+        if let Some(report) = existing {
+            report.calls += 1;
+            report.gates += gates;
+        } else {
+            self.large_inlines.push(LargeInline {
+                fn_name: fn_name.to_string(),
+                call_site,
+                calls: 1,
+                gates,
+            });
+        }
+    }
+
+    pub fn large_inlines(&self) -> &[LargeInline] {
+        &self.large_inlines
+    }
+
+    // Returns the source location of the expression that pushes the AND gates that are part of the
+    // built circuit (after unused gates are pruned) over the budget, requires tracked gate metas:
+    pub fn and_budget_exceeded_at(
+        &self,
+        output_gates: &[GateIndex],
+        max_and_gates: usize,
+    ) -> Option<MetaInfo> {
+        let metas = self
+            .gate_metas
+            .as_ref()
+            .expect("gate metas must be tracked to check the AND budget");
+        let used_gates = if self.config.prune {
+            self.used_gates(output_gates)
+        } else {
+            vec![true; self.gates.len()]
+        };
+        let mut and_gates = 0;
+        for (i, gate) in self.gates.iter().enumerate() {
+            if let (BuilderGate::And(_, _), true) = (gate, used_gates[i]) {
+                and_gates += 1;
+                if and_gates > max_and_gates {
+                    return Some(metas[i]);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn enter_meta(&mut self, meta: MetaInfo) -> MetaInfo {
+        std::mem::replace(&mut self.current_meta, meta)
+    }
+
+    pub fn critical_path(&self, output_gates: &[GateIndex]) -> CriticalPath {
+        let shift = self.shift;
+        let mut and_depths = vec![0; self.gates.len()];
+        let and_depth = |and_depths: &[usize], w: GateIndex| {
+            if w < shift {
+                0
+            } else {
+                and_depths[w - shift]
+            }
+        };
+        for (i, gate) in self.gates.iter().enumerate() {
+            and_depths[i] = match *gate {
+                BuilderGate::Xor(x, y) => max(and_depth(&and_depths, x), and_depth(&and_depths, y)),
+                BuilderGate::And(x, y) => {
+                    max(and_depth(&and_depths, x), and_depth(&and_depths, y)) + 1
+                }
+            };
+        }
+        let mut outputs = output_gates.to_vec();
+        outputs.push(self.panic_gates.has_panicked);
+        outputs.extend(self.panic_gates.panic_type.iter());
+        outputs.extend(self.panic_gates.start_line.iter());
+        outputs.extend(self.panic_gates.start_column.iter());
+        outputs.extend(self.panic_gates.end_line.iter());
+        outputs.extend(self.panic_gates.end_column.iter());
+        outputs.extend(self.panic_gates.panic_code.iter());
+        let mut w = outputs
+            .into_iter()
+            .max_by_key(|&w| and_depth(&and_depths, w))
+            .unwrap_or(0);
+        let depth = and_depth(&and_depths, w);
+        let mut locations = vec![];
+        while and_depth(&and_depths, w) > 0 {
+            let gate = self.gates[w - shift];
+            let (x, y) = match gate {
+                BuilderGate::Xor(x, y) | BuilderGate::And(x, y) => (x, y),
+            };
+            if let (BuilderGate::And(_, _), Some(metas)) = (gate, &self.gate_metas) {
+                let meta = metas[w - shift];
+                if locations.last() != Some(&meta) {
+                    locations.push(meta);
+                }
+            }
+            w = if and_depth(&and_depths, x) >= and_depth(&and_depths, y) {
+                x
+            } else {
+                y
+            };
+        }
+        locations.reverse();
+        CriticalPath {
+            and_depth: depth,
+            locations,
         }
     }
 
@@ -411,17 +1545,18 @@ impl CircuitBuilder {
     }
 
     // Pruning of useless gates (gates that are not part of the output nor used by other gates):
-    fn remove_unused_gates(&mut self, output_gates: Vec<GateIndex>) -> Vec<GateIndex> {
+    fn used_gates(&self, output_gates: &[GateIndex]) -> Vec<bool> {
         // To find all unused gates, we start at the output gates and recursively mark all their
         // inputs (and their inputs, etc.) as used:
         let shift = self.shift;
-        let mut output_gate_stack = output_gates.clone();
+        let mut output_gate_stack = output_gates.to_vec();
         output_gate_stack.push(self.panic_gates.has_panicked);
         output_gate_stack.extend(self.panic_gates.panic_type.iter());
         output_gate_stack.extend(self.panic_gates.start_line.iter());
         output_gate_stack.extend(self.panic_gates.start_column.iter());
         output_gate_stack.extend(self.panic_gates.end_line.iter());
         output_gate_stack.extend(self.panic_gates.end_column.iter());
+        output_gate_stack.extend(self.panic_gates.panic_code.iter());
         let mut used_gates = vec![false; self.gates.len()];
         while let Some(gate_index) = output_gate_stack.pop() {
             if gate_index >= shift {
@@ -439,6 +1574,12 @@ impl CircuitBuilder {
                 }
             }
         }
+        used_gates
+    }
+
+    fn remove_unused_gates(&mut self, output_gates: Vec<GateIndex>) -> Vec<GateIndex> {
+        let shift = self.shift;
+        let used_gates = self.used_gates(&output_gates);
         let mut unused_gates = 0;
         let mut unused_before_gate = vec![0; self.gates.len()];
         for (w, used) in used_gates.iter().enumerate() {
@@ -481,13 +1622,16 @@ impl CircuitBuilder {
         for w in self.panic_gates.end_column.iter_mut() {
             *w = shift_gate_index_if_necessary(*w);
         }
+        for w in self.panic_gates.panic_code.iter_mut() {
+            *w = shift_gate_index_if_necessary(*w);
+        }
         let mut without_unused_gates = Vec::with_capacity(self.gates.len() - unused_gates);
         for (w, &used) in used_gates.iter().enumerate() {
             if used {
                 without_unused_gates.push(self.gates[w]);
             }
         }
-        self.gates_optimized += unused_gates;
+        self.gates_pruned += unused_gates;
         self.gates = without_unused_gates;
         // The indices of the output gates might have become invalid due to shifting the gates
         // around, so we need to shift the output indices as well:
@@ -503,13 +1647,25 @@ impl CircuitBuilder {
             .collect()
     }
 
-    pub fn build(mut self, output_gates: Vec<GateIndex>) -> Circuit {
-        let output_gates = self.remove_unused_gates(output_gates);
+    pub fn build(self, output_gates: Vec<GateIndex>) -> Circuit {
+        self.build_with_report(output_gates).0
+    }
 
-        if PRINT_OPTIMIZATION_RATIO && self.gates_optimized > 0 {
-            let optimized = self.gates_optimized * 100 / (self.gates.len() + self.gates_optimized);
-            println!("Optimizations removed {optimized}% of all generated gates");
-        }
+    pub fn build_with_report(
+        mut self,
+        output_gates: Vec<GateIndex>,
+    ) -> (Circuit, OptimizationReport) {
+        let output_gates = if self.config.prune {
+            self.remove_unused_gates(output_gates)
+        } else {
+            output_gates
+        };
+        let report = OptimizationReport {
+            gates: 0,
+            gates_folded: self.gates_folded,
+            gates_shared: self.gates_shared,
+            gates_pruned: self.gates_pruned,
+        };
 
         // All of the following shifts are necessary to translate between the intermediate circuit
         // representation (where indices 0 and 1 always refer to constant false and true values) and
@@ -567,17 +1723,40 @@ impl CircuitBuilder {
         panic_and_output.extend(shift_indexes_if_necessary(self.panic_gates.start_column));
         panic_and_output.extend(shift_indexes_if_necessary(self.panic_gates.end_line));
         panic_and_output.extend(shift_indexes_if_necessary(self.panic_gates.end_column));
+        panic_and_output.extend(shift_indexes_if_necessary(self.panic_gates.panic_code));
 
         panic_and_output.extend(output_gates.into_iter().map(shift_gate_index_if_necessary));
 
-        Circuit {
+        let report = OptimizationReport {
+            gates: gates.len(),
+            ..report
+        };
+        let circuit = Circuit {
             input_gates: self.input_gates,
             gates,
             output_gates: panic_and_output,
-        }
+        };
+        (circuit, report)
     }
 
+    // Only the first panic is ever reported: once `has_panicked` is set, the reason and location
+    // of any later panic are ignored. Since operands are compiled left to right before the
+    // operation itself, "first" means first in the order of a sequential evaluation of the program.
     pub fn push_panic_if(&mut self, cond: GateIndex, reason: PanicReason, meta: MetaInfo) {
+        self.push_panic_with_code_if(cond, reason, 0, meta)
+    }
+
+    // Like `push_panic_if`, but also reports the code of the panic (used by assertions).
+    pub fn push_panic_with_code_if(
+        &mut self,
+        cond: GateIndex,
+        reason: PanicReason,
+        code: usize,
+        meta: MetaInfo,
+    ) {
+        if cond != 0 && !self.panic_sites.contains(&(reason.clone(), meta)) {
+            self.panic_sites.push((reason.clone(), meta));
+        }
         let already_panicked = self.panic_gates.has_panicked;
         self.panic_gates.has_panicked = self.push_or(self.panic_gates.has_panicked, cond);
         let current = PanicResult {
@@ -587,6 +1766,7 @@ impl CircuitBuilder {
             start_column: unsigned_as_usize_bits(meta.start.1 as u64),
             end_line: unsigned_as_usize_bits(meta.end.0 as u64),
             end_column: unsigned_as_usize_bits(meta.end.1 as u64),
+            panic_code: unsigned_as_usize_bits(code as u64),
         };
         for i in 0..self.panic_gates.start_line.len() {
             self.panic_gates.start_line[i] = self.push_mux(
@@ -609,6 +1789,11 @@ impl CircuitBuilder {
                 self.panic_gates.end_column[i],
                 current.end_column[i],
             );
+            self.panic_gates.panic_code[i] = self.push_mux(
+                already_panicked,
+                self.panic_gates.panic_code[i],
+                current.panic_code[i],
+            );
         }
         for i in 0..current.panic_type.len() {
             self.panic_gates.panic_type[i] = self.push_mux(
@@ -619,11 +1804,52 @@ impl CircuitBuilder {
         }
     }
 
+    // All distinct locations that might panic (ignoring conditions that are constant false), in
+    // the order in which they were compiled:
+    pub fn panic_sites(&self) -> &[(PanicReason, MetaInfo)] {
+        &self.panic_sites
+    }
+
+    // Fns are pure, so a call with the same arg wires as a previous call has the same outputs
+    // (returned together with the number of gates that the previous call pushed):
+    pub fn cached_fn_call(
+        &self,
+        fn_name: &str,
+        args: &[Vec<GateIndex>],
+    ) -> Option<(Vec<GateIndex>, usize)> {
+        let key = (fn_name.to_string(), args.to_vec());
+        self.fn_calls
+            .get(&key)
+            .filter(|_| self.config.dedup)
+            .map(|call| (call.output.clone(), call.gates))
+    }
+
+    pub fn cache_fn_call(
+        &mut self,
+        fn_name: &str,
+        args: Vec<Vec<GateIndex>>,
+        output: Vec<GateIndex>,
+        gates: usize,
+        can_panic: bool,
+    ) {
+        if self.config.dedup {
+            let call = FnCallResult {
+                output,
+                gates,
+                can_panic,
+            };
+            self.fn_calls.insert((fn_name.to_string(), args), call);
+        }
+    }
+
     pub fn peek_panic(&self) -> &PanicResult {
         &self.panic_gates
     }
 
     pub fn replace_panic_with(&mut self, p: PanicResult) -> PanicResult {
+        // a cached call might have panicked in a branch that the new panic state discards, so
+        // that a later call with the same args needs to be compiled again to panic correctly:
+        self.fn_calls.retain(|_, call| !call.can_panic);
         std::mem::replace(&mut self.panic_gates, p)
     }
 
@@ -652,6 +1878,9 @@ impl CircuitBuilder {
         for (i, (&if_true, &if_false)) in t.end_column.iter().zip(f.end_column.iter()).enumerate() {
             panic_gates.end_column[i] = self.push_mux(condition, if_true, if_false);
         }
+        for (i, (&if_true, &if_false)) in t.panic_code.iter().zip(f.panic_code.iter()).enumerate() {
+            panic_gates.panic_code[i] = self.push_mux(condition, if_true, if_false);
+        }
         panic_gates
     }
 
@@ -664,7 +1893,7 @@ impl CircuitBuilder {
         if a.0.len() != b.0.len() {
             panic!("Cannot mux environments with different scopes: {a:?} vs. {b:?}");
         }
-        let mut muxed = Env(vec![]);
+        let mut muxed = Env::empty();
         for (a, b) in a.0.iter().zip(b.0.iter()) {
             muxed.push();
             if a.len() != a.len() || !a.keys().all(|k| b.contains_key(k)) {
@@ -686,15 +1915,19 @@ impl CircuitBuilder {
         muxed
     }
 
-    // - Constant evaluation (e.g. x ^ 0 == x; x ^ x == 0)
-    // - Sub-expression sharing (wires are re-used if a gate with the same type and inputs exists)
+    // Constant evaluation (e.g. x ^ 0 == x; x ^ x == 0)
     fn optimize_xor(&self, x: GateIndex, y: GateIndex) -> Option<GateIndex> {
+        if !self.config.rewrite || self.config.max_optimization_depth == 0 {
+            return None;
+        }
         if x == 0 {
             return Some(y);
         } else if y == 0 {
             return Some(x);
         } else if x == y {
             return Some(0);
+        } else if self.config.max_optimization_depth < 2 {
+            return None;
         } else if let Some(&x_negated) = self.negated.get(&x) {
             if x_negated == y {
                 return Some(1);
@@ -708,22 +1941,22 @@ impl CircuitBuilder {
                 return Some(y_negated);
             }
         }
-        // Sub-expression sharing:
-        if let Some(&wire) = self.cache.get(&BuilderGate::Xor(x, y)) {
-            return Some(wire);
-        }
         None
     }
 
-    // - Constant evaluation (e.g. x & x == x; x & 1 == x; x & 0 == 0)
-    // - Sub-expression sharing (wires are re-used if a gate with the same type and inputs exists)
+    // Constant evaluation (e.g. x & x == x; x & 1 == x; x & 0 == 0)
     fn optimize_and(&self, x: GateIndex, y: GateIndex) -> Option<GateIndex> {
+        if !self.config.rewrite || self.config.max_optimization_depth == 0 {
+            return None;
+        }
         if x == 0 || y == 0 {
             return Some(0);
         } else if x == 1 {
             return Some(y);
         } else if y == 1 || x == y {
             return Some(x);
+        } else if self.config.max_optimization_depth < 2 {
+            return None;
         } else if let Some(&x_negated) = self.negated.get(&x) {
             if x_negated == y {
                 return Some(0);
@@ -733,21 +1966,78 @@ impl CircuitBuilder {
                 return Some(0);
             }
         }
-        // Sub-expression sharing:
-        if let Some(&wire) = self.cache.get(&BuilderGate::And(x, y)) {
-            return Some(wire);
-        }
         None
     }
 
+    // Cancels out the inputs that appear an even number of times in the XOR trees below x and y,
+    // looking at most `max_optimization_depth` levels deep:
+    fn cancel_xor_inputs(&mut self, x: GateIndex, y: GateIndex) -> Option<GateIndex> {
+        if !self.config.rewrite || self.config.max_optimization_depth <= 2 {
+            return None;
+        }
+        let mut inputs = vec![];
+        let levels = self.config.max_optimization_depth - 1;
+        self.collect_xor_inputs(x, levels, &mut inputs);
+        self.collect_xor_inputs(y, levels, &mut inputs);
+        let mut occurrences: HashMap<GateIndex, usize> = HashMap::new();
+        for &input in inputs.iter() {
+            *occurrences.entry(input).or_default() += 1;
+        }
+        if occurrences.values().all(|&n| n == 1) {
+            return None;
+        }
+        let mut result = 0;
+        for input in inputs {
+            if occurrences.get(&input).copied().unwrap_or_default() % 2 == 1 {
+                occurrences.remove(&input);
+                // the remaining inputs are unique, so they can be combined without cancelling:
+                result = match self.optimize_xor(result, input) {
+                    Some(optimized) => optimized,
+                    None => self.push_xor_gate(result, input),
+                };
+            }
+        }
+        Some(result)
+    }
+
+    fn collect_xor_inputs(&self, x: GateIndex, levels: u32, inputs: &mut Vec<GateIndex>) {
+        match x.checked_sub(self.shift).and_then(|i| self.gates.get(i)) {
+            Some(&BuilderGate::Xor(a, b)) if levels > 0 => {
+                self.collect_xor_inputs(a, levels - 1, inputs);
+                self.collect_xor_inputs(b, levels - 1, inputs);
+            }
+            _ => inputs.push(x),
+        }
+    }
+
     pub fn push_xor(&mut self, x: GateIndex, y: GateIndex) -> GateIndex {
         if let Some(optimized) = self.optimize_xor(x, y) {
-            self.gates_optimized += 1;
+            self.gates_folded += 1;
             optimized
+        } else if let Some(cancelled) = self.cancel_xor_inputs(x, y) {
+            self.gates_folded += 1;
+            cancelled
+        } else {
+            self.push_xor_gate(x, y)
+        }
+    }
+
+    fn push_xor_gate(&mut self, x: GateIndex, y: GateIndex) -> GateIndex {
+        if let Some(&shared) = self
+            .cache
+            .get(&BuilderGate::Xor(x, y))
+            .filter(|_| self.config.dedup)
+        {
+            // Sub-expression sharing (wires are re-used if a gate with the same inputs exists):
+            self.gates_shared += 1;
+            shared
         } else {
             let gate = BuilderGate::Xor(x, y);
             self.gate_counter += 1;
             self.gates.push(gate);
+            if let Some(gate_metas) = &mut self.gate_metas {
+                gate_metas.push(self.current_meta);
+            }
             let gate_index = self.gate_counter - 1;
             self.cache.insert(gate, gate_index);
             if x == 1 {
@@ -764,12 +2054,23 @@ impl CircuitBuilder {
 
     pub fn push_and(&mut self, x: GateIndex, y: GateIndex) -> GateIndex {
         if let Some(optimized) = self.optimize_and(x, y) {
-            self.gates_optimized += 1;
+            self.gates_folded += 1;
             optimized
+        } else if let Some(&shared) = self
+            .cache
+            .get(&BuilderGate::And(x, y))
+            .filter(|_| self.config.dedup)
+        {
+            // Sub-expression sharing (wires are re-used if a gate with the same inputs exists):
+            self.gates_shared += 1;
+            shared
         } else {
             let gate = BuilderGate::And(x, y);
             self.gate_counter += 1;
             self.gates.push(gate);
+            if let Some(gate_metas) = &mut self.gate_metas {
+                gate_metas.push(self.current_meta);
+            }
             self.cache.insert(gate, self.gate_counter - 1);
             self.gate_counter - 1
         }
@@ -862,6 +2163,83 @@ impl CircuitBuilder {
         neg
     }
 
+    /// Returns the product, whether it overflowed and whether it is negative (always `0` for
+    /// unsigned numbers).
+    pub fn push_multiplication_circuit(
+        &mut self,
+        x: &[GateIndex],
+        y: &[GateIndex],
+        is_signed: bool,
+    ) -> (Vec<GateIndex>, GateIndex, GateIndex) {
+        assert_eq!(x.len(), y.len());
+        let bits = x.len();
+        let mut x = x.to_vec();
+        let mut y = y.to_vec();
+
+        let is_result_neg = if is_signed {
+            let is_x_negative = x[0];
+            let is_y_negative = y[0];
+            let x_negated = self.push_negation_circuit(&x);
+            let y_negated = self.push_negation_circuit(&y);
+            for (i, w) in x.iter_mut().enumerate() {
+                *w = self.push_mux(is_x_negative, x_negated[i], *w);
+            }
+            for (i, w) in y.iter_mut().enumerate() {
+                *w = self.push_mux(is_y_negative, y_negated[i], *w);
+            }
+            self.push_xor(is_x_negative, is_y_negative)
+        } else {
+            0
+        };
+        let mut sums: Vec<Vec<GateIndex>> = vec![vec![0; bits]; bits];
+        let mut carries: Vec<Vec<GateIndex>> = vec![vec![0; bits]; bits];
+        let lsb_index = bits - 1;
+        for i in (0..bits).rev() {
+            for j in (0..bits).rev() {
+                let carry = if j == lsb_index { 0 } else { carries[i][j + 1] };
+                let z = if i == lsb_index {
+                    0
+                } else if j == 0 {
+                    carries[i + 1][j]
+                } else {
+                    sums[i + 1][j - 1]
+                };
+                let (sum, carry) = self.push_multiplier(x[i], y[j], z, carry);
+                sums[i][j] = sum;
+                carries[i][j] = carry;
+            }
+        }
+        let mut overflow = carries[0][0];
+        for (i, &w) in sums[0].iter().enumerate() {
+            if i != lsb_index {
+                overflow = self.push_or(overflow, w);
+            }
+        }
+        let mut result = vec![0; bits];
+        for (i, s) in sums.into_iter().enumerate() {
+            result[i] = s[lsb_index];
+        }
+        if is_signed {
+            let mut all_bits_except_msb_are_zero = 1;
+            for &w in result.iter().skip(1) {
+                let not_w = self.push_not(w);
+                all_bits_except_msb_are_zero = self.push_and(all_bits_except_msb_are_zero, not_w);
+            }
+            // a magnitude with only the msb set is representable only as the minimum (negative)
+            // number, for example -128 for i8, but not 128:
+            let is_min = self.push_and(all_bits_except_msb_are_zero, is_result_neg);
+            let is_not_min = self.push_not(is_min);
+            let result_is_signed = result[0];
+            let too_large_for_signed_representation = self.push_and(result_is_signed, is_not_min);
+            overflow = self.push_or(overflow, too_large_for_signed_representation);
+            let result_negated = self.push_negation_circuit(&result);
+            for (i, w) in result.iter_mut().enumerate() {
+                *w = self.push_mux(is_result_neg, result_negated[i], *w);
+            }
+        }
+        (result, overflow, is_result_neg)
+    }
+
     pub fn push_subtraction_circuit(
         &mut self,
         x: &[GateIndex],
@@ -894,6 +2272,42 @@ impl CircuitBuilder {
         (sum, overflow)
     }
 
+    // Divides an unsigned number by a constant, using a multiplication by a precomputed "magic
+    // number" instead of a (much larger) division circuit: for `x < 2^w` and `l = ceil(log2(d))`,
+    // `x / d == (x * ceil(2^(w + l) / d)) >> (w + l)`, see Granlund & Montgomery, "Division by
+    // Invariant Integers using Multiplication".
+    pub fn push_constant_division_circuit(&mut self, x: &[GateIndex], d: u64) -> Vec<GateIndex> {
+        let bits = x.len();
+        let log_d = (u64::BITS - d.saturating_sub(1).leading_zeros()) as usize;
+        let shift = bits + log_d;
+        if d == 0 || shift >= 127 {
+            let d = unsigned_as_wires(d, bits);
+            return self.push_unsigned_division_circuit(x, &d).0;
+        }
+        let magic = ((1u128 << shift) + d as u128 - 1) / d as u128;
+        let magic_bits = (u128::BITS - magic.leading_zeros()) as usize;
+        let product_bits = bits + magic_bits;
+        let mut product = vec![0; product_bits];
+        for k in 0..magic_bits {
+            if (magic >> k) & 1 == 1 {
+                // x << k, as a number of `product_bits` bits (MSB first):
+                let mut shifted = vec![0; product_bits - bits - k];
+                shifted.extend_from_slice(x);
+                shifted.resize(product_bits, 0);
+                product = self.push_addition_circuit(&product, &shifted).0;
+            }
+        }
+        let mut quotient = product[..product_bits - shift].to_vec();
+        if quotient.len() > bits {
+            quotient.drain(..quotient.len() - bits);
+        } else {
+            let mut extended = vec![0; bits - quotient.len()];
+            extended.extend(quotient);
+            quotient = extended;
+        }
+        quotient
+    }
+
     pub fn push_unsigned_division_circuit(
         &mut self,
         x: &[GateIndex],
@@ -10,8 +10,13 @@ use crate::{
         ConstExpr, ConstExprEnum, EnumDef, ExprEnum, Op, Pattern, PatternEnum, StmtEnum, StructDef,
         Type, UnaryOp, VariantExprEnum,
     },
-    circuit::{Circuit, CircuitBuilder, GateIndex, PanicReason, PanicResult, USIZE_BITS},
+    check::index_path,
+    circuit::{
+        Circuit, CircuitBuilder, CircuitBuilderConfig, CriticalPath, GateIndex, OptimizationReport,
+        PanicReason, PanicResult, MAX_GATES, USIZE_BITS,
+    },
     env::Env,
+    eval::resolve_const_type,
     literal::Literal,
     token::{MetaInfo, SignedNumType, UnsignedNumType},
     TypedExpr, TypedFnDef, TypedPattern, TypedProgram, TypedStmt,
@@ -26,6 +31,20 @@ pub enum CompilerError {
     InvalidLiteralType(Literal, Type),
     /// The constant was declared in the program but not provided during compilation.
     MissingConstant(String, String, MetaInfo),
+    /// The compiled circuit has more gates than the specified limit.
+    GateLimitExceeded {
+        /// The maximum number of gates allowed in the circuit.
+        limit: usize,
+        /// The number of gates in the compiled circuit.
+        gates: usize,
+    },
+    /// Compiling the program pushed more AND gates than allowed by the budget.
+    AndBudgetExceeded {
+        /// The maximum number of AND gates allowed in the circuit.
+        limit: usize,
+        /// The location of the expression that pushed the first AND gate over the limit.
+        reached_at: MetaInfo,
+    },
 }
 
 impl PartialOrd for CompilerError {
@@ -46,14 +65,38 @@ impl Ord for CompilerError {
                 CompilerError::InvalidLiteralType(literal1, _),
                 CompilerError::InvalidLiteralType(literal2, _),
             ) => literal1.cmp(literal2),
-            (CompilerError::InvalidLiteralType(_, _), CompilerError::MissingConstant(_, _, _)) => {
-                std::cmp::Ordering::Less
-            }
+            (
+                CompilerError::InvalidLiteralType(_, _),
+                CompilerError::MissingConstant(_, _, _)
+                | CompilerError::GateLimitExceeded { .. }
+                | CompilerError::AndBudgetExceeded { .. },
+            ) => std::cmp::Ordering::Less,
             (
                 CompilerError::MissingConstant(_, _, meta1),
                 CompilerError::MissingConstant(_, _, meta2),
             ) => meta1.cmp(meta2),
+            (
+                CompilerError::MissingConstant(_, _, _),
+                CompilerError::GateLimitExceeded { .. } | CompilerError::AndBudgetExceeded { .. },
+            ) => std::cmp::Ordering::Less,
             (CompilerError::MissingConstant(_, _, _), _) => std::cmp::Ordering::Greater,
+            (
+                CompilerError::GateLimitExceeded { gates: gates1, .. },
+                CompilerError::GateLimitExceeded { gates: gates2, .. },
+            ) => gates1.cmp(gates2),
+            (CompilerError::GateLimitExceeded { .. }, CompilerError::AndBudgetExceeded { .. }) => {
+                std::cmp::Ordering::Less
+            }
+            (CompilerError::GateLimitExceeded { .. }, _) => std::cmp::Ordering::Greater,
+            (
+                CompilerError::AndBudgetExceeded {
+                    reached_at: meta1, ..
+                },
+                CompilerError::AndBudgetExceeded {
+                    reached_at: meta2, ..
+                },
+            ) => meta1.cmp(meta2),
+            (CompilerError::AndBudgetExceeded { .. }, _) => std::cmp::Ordering::Greater,
         }
     }
 }
@@ -70,12 +113,91 @@ impl std::fmt::Display for CompilerError {
             CompilerError::MissingConstant(party, identifier, _) => f.write_fmt(format_args!(
                 "The constant {party}::{identifier} was declared in the program but never provided"
             )),
+            CompilerError::GateLimitExceeded { limit, gates } => f.write_fmt(format_args!(
+                "The compiled circuit has {gates} gates, which exceeds the limit of {limit} gates"
+            )),
+            CompilerError::AndBudgetExceeded { limit, .. } => f.write_fmt(format_args!(
+                "The compiled circuit exceeds the budget of {limit} AND gates"
+            )),
         }
     }
 }
 
+/// Options that control diagnostics and limits checked during compilation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompileOptions {
+    /// Report every fn call whose inlined body contributes more than this many gates.
+    pub warn_on_large_inline: usize,
+    /// Reject circuits with more AND gates (after unused gates are pruned) than this budget,
+    /// pointing to the expression that pushed the circuit over the budget.
+    pub max_and_gates: Option<usize>,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            warn_on_large_inline: usize::MAX,
+            max_and_gates: None,
+        }
+    }
+}
+
+/// A fn call whose inlined body contributed more gates than allowed by
+/// [`CompileOptions::warn_on_large_inline`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LargeInline {
+    /// The name of the called fn.
+    pub fn_name: String,
+    /// The location of the call.
+    pub call_site: MetaInfo,
+    /// How often the call was inlined (e.g. once per loop iteration).
+    pub calls: usize,
+    /// The total number of gates contributed by all inlined calls, including nested calls (a call
+    /// that reuses the outputs of an earlier call with the same args counts the gates of that call).
+    pub gates: usize,
+}
+
+impl std::fmt::Display for LargeInline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let LargeInline {
+            fn_name,
+            calls,
+            gates,
+            ..
+        } = self;
+        f.write_fmt(format_args!(
+            "Inlining '{fn_name}' {calls} time(s) added {gates} gates to the circuit"
+        ))
+    }
+}
+
 type CompiledProgram<'a> = (Circuit, &'a TypedFnDef, HashMap<String, usize>);
 
+type PanicSites = Vec<(PanicReason, MetaInfo)>;
+
+type InputSchema = Vec<(usize, String, Type, usize)>;
+
+type ResolvedConsts = (
+    HashMap<String, usize>,
+    HashMap<String, u64>,
+    HashMap<String, i64>,
+);
+
+type CompiledBuilder<'a> = (
+    CircuitBuilder,
+    Vec<GateIndex>,
+    &'a TypedFnDef,
+    HashMap<String, usize>,
+);
+
+// How the circuit builder is set up for the different ways of compiling a program:
+#[derive(Debug, Clone, Copy, Default)]
+struct BuilderSetup {
+    config: CircuitBuilderConfig,
+    track_gate_metas: bool,
+    warn_on_large_inline: Option<usize>,
+}
+
 impl TypedProgram {
     /// Compiles the (type-checked) program, producing a circuit of gates.
     ///
@@ -95,7 +217,185 @@ impl TypedProgram {
         fn_name: &str,
         consts: HashMap<String, HashMap<String, Literal>>,
     ) -> Result<CompiledProgram, Vec<CompilerError>> {
-        let mut env = Env::new();
+        self.compile_with_gate_limit(fn_name, consts, MAX_GATES)
+    }
+
+    /// Compiles the (type-checked) program with provided constants, producing a circuit of gates,
+    /// or an error if the circuit has more than `max_gates` gates.
+    ///
+    /// Assumes that the input program has been correctly type-checked and **panics** if
+    /// incompatible types are found that should have been caught by the type-checker.
+    pub fn compile_with_gate_limit(
+        &self,
+        fn_name: &str,
+        consts: HashMap<String, HashMap<String, Literal>>,
+        max_gates: usize,
+    ) -> Result<CompiledProgram<'_>, Vec<CompilerError>> {
+        let (compiled, _) =
+            self.compile_with_gate_limit_and_panic_sites(fn_name, consts, max_gates)?;
+        Ok(compiled)
+    }
+
+    /// Compiles the (type-checked) program with provided constants, producing a circuit of gates
+    /// together with all the source locations that might cause the circuit to panic (and the
+    /// reason), which are collected during the compilation.
+    ///
+    /// Assumes that the input program has been correctly type-checked and **panics** if
+    /// incompatible types are found that should have been caught by the type-checker.
+    pub fn compile_with_panic_sites(
+        &self,
+        fn_name: &str,
+        consts: HashMap<String, HashMap<String, Literal>>,
+    ) -> Result<(CompiledProgram<'_>, PanicSites), Vec<CompilerError>> {
+        self.compile_with_gate_limit_and_panic_sites(fn_name, consts, MAX_GATES)
+    }
+
+    fn compile_with_gate_limit_and_panic_sites(
+        &self,
+        fn_name: &str,
+        consts: HashMap<String, HashMap<String, Literal>>,
+        max_gates: usize,
+    ) -> Result<(CompiledProgram<'_>, PanicSites), Vec<CompilerError>> {
+        let (circuit, output_gates, fn_def, const_sizes) =
+            self.compile_to_builder(fn_name, consts, BuilderSetup::default())?;
+        let panic_sites = circuit.panic_sites().to_vec();
+        let circuit = circuit.build(output_gates);
+        if circuit.gates.len() > max_gates {
+            return Err(vec![CompilerError::GateLimitExceeded {
+                limit: max_gates,
+                gates: circuit.gates.len(),
+            }]);
+        }
+        Ok(((circuit, fn_def, const_sizes), panic_sites))
+    }
+
+    /// Compiles the (type-checked) program with provided constants, producing a circuit of gates,
+    /// or an error pointing to the expression that pushed the circuit over `max_and_gates` AND gates.
+    ///
+    /// Assumes that the input program has been correctly type-checked and **panics** if
+    /// incompatible types are found that should have been caught by the type-checker.
+    pub fn compile_with_budget(
+        &self,
+        fn_name: &str,
+        consts: HashMap<String, HashMap<String, Literal>>,
+        max_and_gates: usize,
+    ) -> Result<CompiledProgram<'_>, Vec<CompilerError>> {
+        let options = CompileOptions {
+            max_and_gates: Some(max_and_gates),
+            ..Default::default()
+        };
+        let (compiled, _) = self.compile_with_options(fn_name, consts, &options)?;
+        Ok(compiled)
+    }
+
+    /// Compiles the (type-checked) program with provided constants, producing a circuit of gates
+    /// together with all fn calls that are more expensive to inline than specified by the options,
+    /// or an error if the circuit exceeds the AND budget of the options.
+    ///
+    /// Assumes that the input program has been correctly type-checked and **panics** if
+    /// incompatible types are found that should have been caught by the type-checker.
+    pub fn compile_with_options(
+        &self,
+        fn_name: &str,
+        consts: HashMap<String, HashMap<String, Literal>>,
+        options: &CompileOptions,
+    ) -> Result<(CompiledProgram<'_>, Vec<LargeInline>), Vec<CompilerError>> {
+        let setup = BuilderSetup {
+            track_gate_metas: options.max_and_gates.is_some(),
+            warn_on_large_inline: Some(options.warn_on_large_inline),
+            ..Default::default()
+        };
+        let (circuit, output_gates, fn_def, const_sizes) =
+            self.compile_to_builder(fn_name, consts, setup)?;
+        if let Some(limit) = options.max_and_gates {
+            if let Some(reached_at) = circuit.and_budget_exceeded_at(&output_gates, limit) {
+                return Err(vec![CompilerError::AndBudgetExceeded { limit, reached_at }]);
+            }
+        }
+        let large_inlines = circuit.large_inlines().to_vec();
+        let circuit = circuit.build(output_gates);
+        Ok(((circuit, fn_def, const_sizes), large_inlines))
+    }
+
+    /// Compiles the (type-checked) program with provided constants and reports the critical path
+    /// of the resulting circuit, i.e. its longest chain of AND gates.
+    ///
+    /// Assumes that the input program has been correctly type-checked and **panics** if
+    /// incompatible types are found that should have been caught by the type-checker.
+    pub fn critical_path(
+        &self,
+        fn_name: &str,
+        consts: HashMap<String, HashMap<String, Literal>>,
+    ) -> Result<CriticalPath, Vec<CompilerError>> {
+        let setup = BuilderSetup {
+            track_gate_metas: true,
+            ..Default::default()
+        };
+        let (circuit, output_gates, _, _) = self.compile_to_builder(fn_name, consts, setup)?;
+        Ok(circuit.critical_path(&output_gates))
+    }
+
+    /// Compiles the (type-checked) program with provided constants and reports how many gates were
+    /// avoided by constant evaluation, sub-expression sharing and pruning of unused gates.
+    ///
+    /// Assumes that the input program has been correctly type-checked and **panics** if
+    /// incompatible types are found that should have been caught by the type-checker.
+    pub fn optimization_report(
+        &self,
+        fn_name: &str,
+        consts: HashMap<String, HashMap<String, Literal>>,
+    ) -> Result<OptimizationReport, Vec<CompilerError>> {
+        let (circuit, output_gates, _, _) =
+            self.compile_to_builder(fn_name, consts, BuilderSetup::default())?;
+        Ok(circuit.build_with_report(output_gates).1)
+    }
+
+    /// Compiles the (type-checked) program with provided constants, applying only the circuit
+    /// optimizations enabled in the config, and reports how many gates they avoided.
+    ///
+    /// Assumes that the input program has been correctly type-checked and **panics** if
+    /// incompatible types are found that should have been caught by the type-checker.
+    pub fn compile_with_config(
+        &self,
+        fn_name: &str,
+        consts: HashMap<String, HashMap<String, Literal>>,
+        config: CircuitBuilderConfig,
+    ) -> Result<(CompiledProgram<'_>, OptimizationReport), Vec<CompilerError>> {
+        let setup = BuilderSetup {
+            config,
+            ..Default::default()
+        };
+        let (circuit, output_gates, fn_def, const_sizes) =
+            self.compile_to_builder(fn_name, consts, setup)?;
+        let (circuit, report) = circuit.build_with_report(output_gates);
+        Ok(((circuit, fn_def, const_sizes), report))
+    }
+
+    /// Lists the inputs of the fn in the order of the input wires of the compiled circuit: for each
+    /// parameter the index of the party providing it, its name, its type (with any array sizes
+    /// resolved using the provided constants) and its size in bits.
+    pub fn input_schema(
+        &self,
+        fn_name: &str,
+        consts: HashMap<String, HashMap<String, Literal>>,
+    ) -> Result<InputSchema, Vec<CompilerError>> {
+        let (const_sizes, _, _) = self.resolve_consts(&consts)?;
+        let Some(fn_def) = self.fn_defs.get(fn_name) else {
+            return Err(vec![CompilerError::FnNotFound(fn_name.to_string())]);
+        };
+        let mut schema = Vec::with_capacity(fn_def.params.len());
+        for (party, param) in fn_def.params.iter().enumerate() {
+            let ty = resolve_const_type(&param.ty, &const_sizes);
+            let size = ty.size_in_bits_for_defs(self, &const_sizes);
+            schema.push((party, param.name.clone(), ty, size));
+        }
+        Ok(schema)
+    }
+
+    fn resolve_consts(
+        &self,
+        consts: &HashMap<String, HashMap<String, Literal>>,
+    ) -> Result<ResolvedConsts, Vec<CompilerError>> {
         let mut const_sizes = HashMap::new();
         let mut consts_unsigned = HashMap::new();
         let mut consts_signed = HashMap::new();
@@ -140,58 +440,6 @@ impl TypedProgram {
             errs.sort();
             return Err(errs);
         }
-        fn resolve_const_expr_unsigned(
-            ConstExpr(expr, _): &ConstExpr,
-            consts_unsigned: &HashMap<String, u64>,
-        ) -> u64 {
-            match expr {
-                ConstExprEnum::NumUnsigned(n, _) => *n,
-                ConstExprEnum::ExternalValue { party, identifier } => *consts_unsigned
-                    .get(&format!("{party}::{identifier}"))
-                    .unwrap(),
-                ConstExprEnum::Max(args) => {
-                    let mut result = 0;
-                    for arg in args {
-                        result = max(result, resolve_const_expr_unsigned(arg, consts_unsigned));
-                    }
-                    result
-                }
-                ConstExprEnum::Min(args) => {
-                    let mut result = u64::MAX;
-                    for arg in args {
-                        result = min(result, resolve_const_expr_unsigned(arg, consts_unsigned));
-                    }
-                    result
-                }
-                expr => panic!("Not an unsigned const expr: {expr:?}"),
-            }
-        }
-        fn resolve_const_expr_signed(
-            ConstExpr(expr, _): &ConstExpr,
-            consts_signed: &HashMap<String, i64>,
-        ) -> i64 {
-            match expr {
-                ConstExprEnum::NumSigned(n, _) => *n,
-                ConstExprEnum::ExternalValue { party, identifier } => *consts_signed
-                    .get(&format!("{party}::{identifier}"))
-                    .unwrap(),
-                ConstExprEnum::Max(args) => {
-                    let mut result = 0;
-                    for arg in args {
-                        result = max(result, resolve_const_expr_signed(arg, consts_signed));
-                    }
-                    result
-                }
-                ConstExprEnum::Min(args) => {
-                    let mut result = i64::MAX;
-                    for arg in args {
-                        result = min(result, resolve_const_expr_signed(arg, consts_signed));
-                    }
-                    result
-                }
-                expr => panic!("Not an unsigned const expr: {expr:?}"),
-            }
-        }
         for (const_name, const_def) in self.const_defs.iter() {
             if let Type::Unsigned(UnsignedNumType::Usize) = const_def.ty {
                 if let ConstExpr(ConstExprEnum::ExternalValue { party, identifier }, _) =
@@ -204,7 +452,17 @@ impl TypedProgram {
                 const_sizes.insert(const_name.clone(), n as usize);
             }
         }
+        Ok((const_sizes, consts_unsigned, consts_signed))
+    }
 
+    fn compile_to_builder(
+        &self,
+        fn_name: &str,
+        consts: HashMap<String, HashMap<String, Literal>>,
+        setup: BuilderSetup,
+    ) -> Result<CompiledBuilder<'_>, Vec<CompilerError>> {
+        let mut env = Env::new();
+        let (const_sizes, consts_unsigned, consts_signed) = self.resolve_consts(&consts)?;
         let mut errs = vec![];
         for (party, deps) in self.const_deps.iter() {
             for (c, (ty, _)) in deps {
@@ -249,7 +507,14 @@ impl TypedProgram {
             input_gates.push(type_size);
             env.let_in_current_scope(param.name.clone(), wires);
         }
-        let mut circuit = CircuitBuilder::new(input_gates, const_sizes.clone());
+        let mut circuit =
+            CircuitBuilder::new_with_config(input_gates, const_sizes.clone(), setup.config);
+        if setup.track_gate_metas {
+            circuit.track_gate_metas();
+        }
+        if let Some(threshold) = setup.warn_on_large_inline {
+            circuit.warn_on_large_inline(threshold);
+        }
         for (const_name, const_def) in self.const_defs.iter() {
             let ConstExpr(expr, _) = &const_def.value;
             match expr {
@@ -319,10 +584,69 @@ impl TypedProgram {
                         env.let_in_current_scope(const_name.clone(), bits);
                     }
                 }
+                ConstExprEnum::Const(_) | ConstExprEnum::Op(_, _, _) => {
+                    unreachable!("const refs and ops are folded during type checking")
+                }
+            }
+        }
+        let folded = self.clone().fold_constants();
+        let body = &folded.fn_defs[fn_name].body;
+        let output_gates = compile_block(body, &folded, &mut env, &mut circuit);
+        Ok((circuit, output_gates, fn_def, const_sizes))
+    }
+}
+
+fn resolve_const_expr_unsigned(
+    ConstExpr(expr, _): &ConstExpr,
+    consts_unsigned: &HashMap<String, u64>,
+) -> u64 {
+    match expr {
+        ConstExprEnum::NumUnsigned(n, _) => *n,
+        ConstExprEnum::ExternalValue { party, identifier } => *consts_unsigned
+            .get(&format!("{party}::{identifier}"))
+            .unwrap(),
+        ConstExprEnum::Max(args) => {
+            let mut result = 0;
+            for arg in args {
+                result = max(result, resolve_const_expr_unsigned(arg, consts_unsigned));
+            }
+            result
+        }
+        ConstExprEnum::Min(args) => {
+            let mut result = u64::MAX;
+            for arg in args {
+                result = min(result, resolve_const_expr_unsigned(arg, consts_unsigned));
             }
+            result
         }
-        let output_gates = compile_block(&fn_def.body, self, &mut env, &mut circuit);
-        Ok((circuit.build(output_gates), fn_def, const_sizes))
+        expr => panic!("Not an unsigned const expr: {expr:?}"),
+    }
+}
+
+fn resolve_const_expr_signed(
+    ConstExpr(expr, _): &ConstExpr,
+    consts_signed: &HashMap<String, i64>,
+) -> i64 {
+    match expr {
+        ConstExprEnum::NumSigned(n, _) => *n,
+        ConstExprEnum::ExternalValue { party, identifier } => *consts_signed
+            .get(&format!("{party}::{identifier}"))
+            .unwrap(),
+        ConstExprEnum::Max(args) => {
+            let mut result = 0;
+            for arg in args {
+                result = max(result, resolve_const_expr_signed(arg, consts_signed));
+            }
+            result
+        }
+        ConstExprEnum::Min(args) => {
+            let mut result = i64::MAX;
+            for arg in args {
+                result = min(result, resolve_const_expr_signed(arg, consts_signed));
+            }
+            result
+        }
+        expr => panic!("Not an unsigned const expr: {expr:?}"),
     }
 }
 
@@ -421,20 +745,22 @@ impl TypedStmt {
                     }
                     _ => panic!("Found a non-array value in an array access expr"),
                 };
-                env.push();
                 let array = array.compile(prg, env, circuit);
 
                 let mut i = 0;
                 while i < array.len() {
+                    // each iteration gets a fresh scope, so that bindings in the body do not
+                    // shadow outer variables in the next iteration:
+                    env.push();
                     let binding = &array[i..i + elem_in_bits];
                     pattern.compile(binding, prg, env, circuit);
 
                     for stmt in body {
                         stmt.compile(prg, env, circuit);
                     }
+                    env.pop();
                     i += elem_in_bits;
                 }
-                env.pop();
                 vec![]
             }
             StmtEnum::JoinLoop(pattern, join_ty, (a, b), body) => {
@@ -550,6 +876,18 @@ impl TypedExpr {
         prg: &TypedProgram,
         env: &mut Env<Vec<GateIndex>>,
         circuit: &mut CircuitBuilder,
+    ) -> Vec<GateIndex> {
+        let outer_meta = circuit.enter_meta(self.meta);
+        let wires = self.compile_expr(prg, env, circuit);
+        circuit.enter_meta(outer_meta);
+        wires
+    }
+
+    fn compile_expr(
+        &self,
+        prg: &TypedProgram,
+        env: &mut Env<Vec<GateIndex>>,
+        circuit: &mut CircuitBuilder,
     ) -> Vec<GateIndex> {
         let meta = self.meta;
         let ty = &self.ty;
@@ -570,6 +908,12 @@ impl TypedExpr {
                 );
                 bits.into_iter().map(|b| b as usize).collect()
             }
+            ExprEnum::NumUnsignedBytes(bytes, _) => {
+                let size = ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+                let mut bits = Vec::with_capacity(size);
+                be_bytes_to_bits(bytes, size, &mut bits);
+                bits.into_iter().map(|b| b as usize).collect()
+            }
             ExprEnum::NumSigned(n, _) => {
                 let mut bits =
                     Vec::with_capacity(ty.size_in_bits_for_defs(prg, circuit.const_sizes()));
@@ -580,6 +924,22 @@ impl TypedExpr {
                 );
                 bits.into_iter().map(|b| b as usize).collect()
             }
+            ExprEnum::NumFixed(n, bits, _) => signed_as_wires(*n, *bits as usize),
+            ExprEnum::Char(c) => unsigned_as_wires(*c as u64, 32),
+            ExprEnum::StringLiteral(s) => {
+                let mut wires = vec![];
+                if let Type::String(max_len) = ty {
+                    wires.extend(unsigned_as_wires(s.len() as u64, USIZE_BITS));
+                    wires.reserve(max_len * 8);
+                }
+                for b in s.bytes() {
+                    wires.extend(unsigned_as_wires(b as u64, 8));
+                }
+                if let Type::String(max_len) = ty {
+                    wires.resize(USIZE_BITS + max_len * 8, 0);
+                }
+                wires
+            }
             ExprEnum::Identifier(s) => env.get(s).unwrap(),
             ExprEnum::ArrayLiteral(elems) => {
                 let mut wires =
@@ -627,50 +987,12 @@ impl TypedExpr {
                     _ => panic!("Found a non-array value in an array access expr"),
                 };
                 let elem_bits = ty.size_in_bits_for_defs(prg, circuit.const_sizes());
-                let mut array = array.compile(prg, env, circuit);
-                let mut index = index.compile(prg, env, circuit);
-                let index_bits = Type::Unsigned(UnsignedNumType::Usize)
-                    .size_in_bits_for_defs(prg, circuit.const_sizes());
-                extend_to_bits(
-                    &mut index,
-                    &Type::Unsigned(UnsignedNumType::Usize),
-                    index_bits,
-                );
-                let out_of_bounds_elem = 1;
-                for mux_layer in (0..index.len()).rev() {
-                    let mut muxed_array = Vec::new();
-                    let s = index[mux_layer];
-                    let mut i = 0;
-                    while i < array.len() {
-                        for _ in 0..elem_bits {
-                            if i + elem_bits < array.len() {
-                                let a0 = array[i];
-                                let a1 = array[i + elem_bits];
-                                muxed_array.push(circuit.push_mux(s, a1, a0));
-                            } else if i < array.len() {
-                                let a0 = array[i];
-                                muxed_array.push(circuit.push_mux(s, out_of_bounds_elem, a0));
-                            }
-                            i += 1;
-                        }
-                        i += elem_bits;
-                    }
-                    array = muxed_array;
-                }
-                let mut array_len = Vec::with_capacity(index_bits);
-                unsigned_to_bits(num_elems as u64, index_bits, &mut array_len);
-                let array_len: Vec<usize> = array_len.into_iter().map(|b| b as usize).collect();
-                let (index_less_than_array_len, _) =
-                    circuit.push_comparator_circuit(index_bits, &index, false, &array_len, false);
-                let out_of_bounds = circuit.push_not(index_less_than_array_len);
+                let array = array.compile(prg, env, circuit);
+                let index = index.compile(prg, env, circuit);
+                let (elem, out_of_bounds) =
+                    compile_array_access(array, index, num_elems, elem_bits, prg, circuit);
                 circuit.push_panic_if(out_of_bounds, PanicReason::OutOfBounds, meta);
-                if array.is_empty() {
-                    // accessing a 0-size array will result in a panic, but we still need to return
-                    // an element of a valid size (even though it will not be used)
-                    vec![0; elem_bits]
-                } else {
-                    array
-                }
+                elem
             }
             ExprEnum::TupleLiteral(tuple) => {
                 let mut wires =
@@ -680,6 +1002,17 @@ impl TypedExpr {
                 }
                 wires
             }
+            ExprEnum::ArraySlice(array, from, to) => {
+                // the bounds are constant, so slicing just selects the wires of the elements:
+                let elem_size = match &array.ty {
+                    Type::Array(elem_ty, _) => {
+                        elem_ty.size_in_bits_for_defs(prg, circuit.const_sizes())
+                    }
+                    _ => panic!("Expected an array type, but found {:?}", array.meta),
+                };
+                let array = array.compile(prg, env, circuit);
+                array[from * elem_size..to * elem_size].to_vec()
+            }
             ExprEnum::TupleAccess(tuple, index) => {
                 let (wires_before, wires_at_index) = match &tuple.ty {
                     Type::Tuple(values) => {
@@ -733,7 +1066,11 @@ impl TypedExpr {
 
                 vec![circuit.push_or(x[0], y[0])]
             }
-            ExprEnum::Op(op @ (Op::ShiftLeft | Op::ShiftRight), x, y) => {
+            ExprEnum::Op(
+                op @ (Op::ShiftLeft | Op::ShiftRight | Op::RotateRight | Op::RotateLeft),
+                x,
+                y,
+            ) => {
                 let x_is_signed = is_signed(&x.ty);
                 let x = x.compile(prg, env, circuit);
                 let y = y.compile(prg, env, circuit);
@@ -744,43 +1081,110 @@ impl TypedExpr {
                 } else {
                     0
                 };
+                let max_filled_bits = match bits {
+                    8 => 3,
+                    16 => 4,
+                    32 => 5,
+                    64 => 6,
+                    256 => 8,
+                    bits => panic!("Unexpected number of bits to be shifted: {bits}"),
+                };
+                // rotating by a multiple of the bit width is a no-op, so only the lower layers
+                // of the rotation amount need to be considered:
+                let is_rotation = matches!(op, Op::RotateRight | Op::RotateLeft);
+                let layers = if is_rotation { max_filled_bits } else { 8 };
                 let mut shift = 1;
                 let mut bits_unshifted = x;
-                for layer in (0..8).rev() {
+                for layer in (8 - layers..8).rev() {
                     let s = y[layer];
                     let mut bits_shifted = vec![0; bits];
                     for i in 0..bits {
                         let unshifted = bits_unshifted[i];
-                        let shifted = if op == &Op::ShiftLeft {
-                            if i + shift >= bits {
-                                0
-                            } else {
-                                bits_unshifted[i + shift]
+                        let shifted = match op {
+                            Op::ShiftLeft => {
+                                if i + shift >= bits {
+                                    0
+                                } else {
+                                    bits_unshifted[i + shift]
+                                }
                             }
-                        } else if i < shift {
-                            bit_to_shift_in
-                        } else {
-                            bits_unshifted[i - shift]
+                            Op::ShiftRight => {
+                                if i < shift {
+                                    bit_to_shift_in
+                                } else {
+                                    bits_unshifted[i - shift]
+                                }
+                            }
+                            Op::RotateLeft => bits_unshifted[(i + shift) % bits],
+                            _ => bits_unshifted[(i + bits - shift) % bits],
                         };
                         bits_shifted[i] = circuit.push_mux(s, shifted, unshifted);
                     }
                     shift *= 2;
                     bits_unshifted = bits_shifted;
                 }
-                let max_filled_bits = match bits {
-                    8 => 3,
-                    16 => 4,
-                    32 => 5,
-                    64 => 6,
-                    bits => panic!("Unexpected number of bits to be shifted: {bits}"),
-                };
-                let mut overflow = 0;
-                for &w in y[..(8 - max_filled_bits)].iter() {
-                    overflow = circuit.push_or(overflow, w);
+                if !is_rotation {
+                    let mut overflow = 0;
+                    for &w in y[..(8 - max_filled_bits)].iter() {
+                        overflow = circuit.push_or(overflow, w);
+                    }
+                    circuit.push_panic_if(overflow, PanicReason::Overflow, meta);
                 }
-                circuit.push_panic_if(overflow, PanicReason::Overflow, meta);
                 bits_unshifted
             }
+            ExprEnum::Op(op @ (Op::BitAnd | Op::BitOr | Op::BitXor), _, _)
+                if associative_chain(self, *op).len() > 2 =>
+            {
+                // chains like `a & b & c & d` are compiled as a balanced tree, which only needs
+                // an AND depth of log2(n) instead of n - 1:
+                let mut operands = vec![];
+                for operand in associative_chain(self, *op) {
+                    operands.push((operand.compile(prg, env, circuit), &operand.ty));
+                }
+                let bits = operands.iter().map(|(x, _)| x.len()).max().unwrap_or(0);
+                let mut operands: Vec<Vec<GateIndex>> = operands
+                    .into_iter()
+                    .map(|(mut x, ty)| {
+                        extend_to_bits(&mut x, ty, bits);
+                        x
+                    })
+                    .collect();
+                while operands.len() > 1 {
+                    let mut next = Vec::with_capacity(operands.len() / 2 + 1);
+                    for pair in operands.chunks(2) {
+                        let [x, y] = pair else {
+                            next.push(pair[0].clone());
+                            continue;
+                        };
+                        let mut output_bits = vec![0; bits];
+                        for i in 0..bits {
+                            output_bits[i] = match op {
+                                Op::BitAnd => circuit.push_and(x[i], y[i]),
+                                Op::BitOr => circuit.push_or(x[i], y[i]),
+                                _ => circuit.push_xor(x[i], y[i]),
+                            };
+                        }
+                        next.push(output_bits);
+                    }
+                    operands = next;
+                }
+                operands.pop().unwrap()
+            }
+            ExprEnum::Op(Op::Add, _, _)
+                if matches!(ty, Type::Unsigned(_))
+                    && associative_chain(self, Op::Add).len() > 2 =>
+            {
+                // the partial sums of unsigned numbers can only grow, so chains like
+                // `a + b + c + d` overflow iff their total overflows and are compiled like
+                // `sum([a, b, c, d])`, reporting any overflow for the whole chain:
+                let mut operands = vec![];
+                for operand in associative_chain(self, Op::Add) {
+                    operands.push(operand.compile(prg, env, circuit));
+                }
+                let bits = ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+                let operands = operands.iter().map(|x| x.as_slice()).collect();
+                push_checked_sum(circuit, operands, bits, false, meta)
+            }
             ExprEnum::Op(op, x, y) => {
                 let ty_x = &x.ty;
                 let ty_y = &y.ty;
@@ -828,78 +1232,53 @@ impl TypedExpr {
                         sum
                     }
                     Op::Mul => {
-                        let is_result_neg = if is_signed(ty) {
-                            let is_x_negative = x[0];
-                            let is_y_negative = y[0];
-                            let x_negated = circuit.push_negation_circuit(&x);
-                            let y_negated = circuit.push_negation_circuit(&y);
-                            for (i, w) in x.iter_mut().enumerate() {
-                                *w = circuit.push_mux(is_x_negative, x_negated[i], *w);
-                            }
-                            for (i, w) in y.iter_mut().enumerate() {
-                                *w = circuit.push_mux(is_y_negative, y_negated[i], *w);
-                            }
-                            circuit.push_xor(is_x_negative, is_y_negative)
+                        if let Type::Fixed(_, frac) = ty {
+                            push_fixed_multiplication(circuit, &x, &y, *frac, meta)
                         } else {
-                            0
-                        };
-                        let mut sums: Vec<Vec<GateIndex>> = vec![vec![0; bits]; bits];
-                        let mut carries: Vec<Vec<GateIndex>> = vec![vec![0; bits]; bits];
-                        let lsb_index = bits - 1;
-                        for i in (0..bits).rev() {
-                            for j in (0..bits).rev() {
-                                let carry = if j == lsb_index { 0 } else { carries[i][j + 1] };
-                                let z = if i == lsb_index {
-                                    0
-                                } else if j == 0 {
-                                    carries[i + 1][j]
-                                } else {
-                                    sums[i + 1][j - 1]
-                                };
-                                let (sum, carry) = circuit.push_multiplier(x[i], y[j], z, carry);
-                                sums[i][j] = sum;
-                                carries[i][j] = carry;
-                            }
-                        }
-                        let mut overflow = carries[0][0];
-                        for (i, &w) in sums[0].iter().enumerate() {
-                            if i != lsb_index {
-                                overflow = circuit.push_or(overflow, w);
-                            }
+                            let (product, overflow, _) =
+                                circuit.push_multiplication_circuit(&x, &y, is_signed(ty));
+                            circuit.push_panic_if(overflow, PanicReason::Overflow, meta);
+                            product
                         }
-                        let mut result = vec![0; bits];
-                        for (i, s) in sums.into_iter().enumerate() {
-                            result[i] = s[lsb_index];
-                        }
-                        if is_signed(ty) {
-                            let mut all_bits_except_msb_are_zero = 1;
-                            for &w in result.iter().skip(1) {
-                                let not_w = circuit.push_not(w);
-                                all_bits_except_msb_are_zero =
-                                    circuit.push_and(all_bits_except_msb_are_zero, not_w);
+                    }
+                    Op::SaturatingAdd | Op::SaturatingSub | Op::SaturatingMul => {
+                        let signed = is_signed(ty);
+                        let (result, overflow, is_bound_neg) = match op {
+                            Op::SaturatingAdd => {
+                                let (sum, carry, carry_prev) =
+                                    circuit.push_addition_circuit(&x, &y);
+                                if signed {
+                                    // overflows only if both operands have the same sign as x:
+                                    (sum, circuit.push_xor(carry, carry_prev), x[0])
+                                } else {
+                                    (sum, carry, 0)
+                                }
                             }
-                            let result_is_signed = result[0];
-                            let not_all_bits_except_msb_are_zero =
-                                circuit.push_not(all_bits_except_msb_are_zero);
-                            let too_large_for_signed_representation = circuit
-                                .push_and(result_is_signed, not_all_bits_except_msb_are_zero);
-                            overflow =
-                                circuit.push_or(overflow, too_large_for_signed_representation);
-                            let result_negated = circuit.push_negation_circuit(&result);
-                            for (i, w) in result.iter_mut().enumerate() {
-                                *w = circuit.push_mux(is_result_neg, result_negated[i], *w);
+                            Op::SaturatingSub => {
+                                let (diff, overflow) =
+                                    circuit.push_subtraction_circuit(&x, &y, signed);
+                                // overflows only if the operands have different signs:
+                                (diff, overflow, if signed { x[0] } else { 1 })
                             }
+                            _ => circuit.push_multiplication_circuit(&x, &y, signed),
+                        };
+                        // the bound is MIN (10...0) if negative and MAX (01...1) if positive for
+                        // signed numbers, or MIN (0...0) / MAX (1...1) for unsigned numbers:
+                        let bound_msb = if signed {
+                            is_bound_neg
+                        } else {
+                            circuit.push_not(is_bound_neg)
+                        };
+                        let bound_rest = circuit.push_not(is_bound_neg);
+                        let mut output_bits = vec![0; bits];
+                        for (i, w) in result.into_iter().enumerate() {
+                            let bound = if i == 0 { bound_msb } else { bound_rest };
+                            output_bits[i] = circuit.push_mux(overflow, bound, w);
                         }
-                        circuit.push_panic_if(overflow, PanicReason::Overflow, meta);
-                        result
+                        output_bits
                     }
                     Op::Div => {
-                        let mut all_zero = 1;
-                        for b in y.iter() {
-                            let eq = circuit.push_eq(*b, 0);
-                            all_zero = circuit.push_and(all_zero, eq);
-                        }
-                        circuit.push_panic_if(all_zero, PanicReason::DivByZero, meta);
+                        push_division_panics(circuit, &x, &y, is_signed(ty), meta);
                         if is_signed(ty) {
                             circuit.push_signed_division_circuit(&mut x, &mut y).0
                         } else {
@@ -907,12 +1286,7 @@ impl TypedExpr {
                         }
                     }
                     Op::Mod => {
-                        let mut all_zero = 1;
-                        for b in y.iter() {
-                            let eq = circuit.push_eq(*b, 0);
-                            all_zero = circuit.push_and(all_zero, eq);
-                        }
-                        circuit.push_panic_if(all_zero, PanicReason::DivByZero, meta);
+                        push_division_panics(circuit, &x, &y, is_signed(ty), meta);
                         if is_signed(ty) {
                             circuit.push_signed_division_circuit(&mut x, &mut y).1
                         } else {
@@ -932,11 +1306,7 @@ impl TypedExpr {
                         }
                     }
                     Op::Eq | Op::NotEq => {
-                        let mut acc = 1;
-                        for i in 0..bits {
-                            let eq = circuit.push_eq(x[i], y[i]);
-                            acc = circuit.push_and(acc, eq);
-                        }
+                        let acc = push_value_eq(circuit, ty_x, &x, &y);
                         match op {
                             Op::Eq => vec![acc],
                             Op::NotEq => vec![circuit.push_not(acc)],
@@ -955,24 +1325,39 @@ impl TypedExpr {
                     Op::ShiftRight => {
                         unreachable!("handled in the match clause one level up")
                     }
+                    Op::RotateRight | Op::RotateLeft => {
+                        unreachable!("handled in the match clause one level up")
+                    }
                 }
             }
             ExprEnum::Block(stmts) => compile_block(stmts, prg, env, circuit),
+            ExprEnum::BuiltinFnCall(identifier, args) => {
+                compile_builtin_fn_call(identifier, args, ty, meta, prg, env, circuit)
+            }
             ExprEnum::FnCall(identifier, args) => {
                 let fn_def = prg.fn_defs.get(identifier).unwrap();
-                let mut bindings = Vec::with_capacity(fn_def.params.len());
-                for (param, arg) in fn_def.params.iter().zip(args) {
+                let mut arg_wires = Vec::with_capacity(fn_def.params.len());
+                for arg in args {
                     env.push();
-                    let arg = arg.compile(prg, env, circuit);
-                    bindings.push((param.name.clone(), arg));
+                    arg_wires.push(arg.compile(prg, env, circuit));
                     env.pop();
                 }
+                if let Some((output, gates)) = circuit.cached_fn_call(identifier, &arg_wires) {
+                    circuit.report_inline(identifier, meta, gates);
+                    return output;
+                }
                 env.push();
-                for (var, binding) in bindings {
-                    env.let_in_current_scope(var.clone(), binding);
+                for (param, binding) in fn_def.params.iter().zip(arg_wires.iter()) {
+                    env.let_in_current_scope(param.name.clone(), binding.clone());
                 }
+                let gates_before_body = circuit.gates_pushed();
+                let has_panicked_before_body = circuit.peek_panic().has_panicked;
                 let body = compile_block(&fn_def.body, prg, env, circuit);
+                let gates = circuit.gates_pushed() - gates_before_body;
+                circuit.report_inline(identifier, meta, gates);
                 env.pop();
+                let can_panic = circuit.peek_panic().has_panicked != has_panicked_before_body;
+                circuit.cache_fn_call(identifier, arg_wires, body.clone(), gates, can_panic);
                 body
             }
             ExprEnum::If(condition, case_true, case_false) => {
@@ -1006,6 +1391,34 @@ impl TypedExpr {
                 let ty_expr = &expr.ty;
                 let mut expr = expr.compile(prg, env, circuit);
                 let size_after_cast = ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+                // enums are cast to their discriminant, which is encoded as the tag of the enum:
+                if let Type::Enum(enum_name) = ty_expr {
+                    expr.truncate(enum_tag_size(prg.enum_defs.get(enum_name).unwrap()));
+                }
+                // fixed-point numbers are rescaled to the fractional bits of the target type,
+                // rounding towards negative infinity if fractional bits are dropped:
+                let frac_before = if let Type::Fixed(_, frac) = ty_expr {
+                    *frac
+                } else {
+                    0
+                };
+                let frac_after = if let Type::Fixed(_, frac) = ty {
+                    *frac
+                } else {
+                    0
+                };
+                if frac_after > frac_before {
+                    expr.resize(expr.len() + (frac_after - frac_before) as usize, 0);
+                } else if frac_after < frac_before {
+                    let msb = expr[0];
+                    expr.truncate(
+                        expr.len()
+                            .saturating_sub((frac_before - frac_after) as usize),
+                    );
+                    if expr.is_empty() {
+                        expr.push(msb);
+                    }
+                }
 
                 match size_after_cast.cmp(&expr.len()) {
                     std::cmp::Ordering::Equal => expr,
@@ -1114,7 +1527,840 @@ impl TypedExpr {
                 }
                 wires
             }
+            ExprEnum::StructUpdate(struct_name, fields, base) => {
+                let fields: HashMap<_, _> = fields.iter().cloned().collect();
+                let struct_def = prg.struct_defs.get(struct_name.as_str()).unwrap();
+                let base = base.compile(prg, env, circuit);
+                let mut wires = Vec::with_capacity(base.len());
+                for (field_name, field_ty) in struct_def.fields.iter() {
+                    let field_size = field_ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+                    if let Some(value) = fields.get(field_name) {
+                        wires.extend(value.compile(prg, env, circuit));
+                    } else {
+                        wires.extend_from_slice(&base[wires.len()..wires.len() + field_size]);
+                    }
+                }
+                wires
+            }
+        }
+    }
+}
+
+// Returns the accessed element, together with a wire indicating whether the index is out of bounds:
+fn compile_array_access(
+    mut array: Vec<GateIndex>,
+    mut index: Vec<GateIndex>,
+    num_elems: usize,
+    elem_bits: usize,
+    prg: &TypedProgram,
+    circuit: &mut CircuitBuilder,
+) -> (Vec<GateIndex>, GateIndex) {
+    let index_bits =
+        Type::Unsigned(UnsignedNumType::Usize).size_in_bits_for_defs(prg, circuit.const_sizes());
+    extend_to_bits(
+        &mut index,
+        &Type::Unsigned(UnsignedNumType::Usize),
+        index_bits,
+    );
+    let out_of_bounds_elem = 1;
+    for mux_layer in (0..index.len()).rev() {
+        let mut muxed_array = Vec::new();
+        let s = index[mux_layer];
+        let mut i = 0;
+        while i < array.len() {
+            for _ in 0..elem_bits {
+                if i + elem_bits < array.len() {
+                    let a0 = array[i];
+                    let a1 = array[i + elem_bits];
+                    muxed_array.push(circuit.push_mux(s, a1, a0));
+                } else if i < array.len() {
+                    let a0 = array[i];
+                    muxed_array.push(circuit.push_mux(s, out_of_bounds_elem, a0));
+                }
+                i += 1;
+            }
+            i += elem_bits;
+        }
+        array = muxed_array;
+    }
+    let mut array_len = Vec::with_capacity(index_bits);
+    unsigned_to_bits(num_elems as u64, index_bits, &mut array_len);
+    let array_len: Vec<usize> = array_len.into_iter().map(|b| b as usize).collect();
+    let (index_less_than_array_len, _) =
+        circuit.push_comparator_circuit(index_bits, &index, false, &array_len, false);
+    let out_of_bounds = circuit.push_not(index_less_than_array_len);
+    if array.is_empty() {
+        // accessing a 0-size array is always out of bounds, but we still need to return an
+        // element of a valid size (even though it will not be used)
+        (vec![0; elem_bits], out_of_bounds)
+    } else {
+        (array, out_of_bounds)
+    }
+}
+
+/// Looks up a single index in a table, like [`compile_array_access`], but only muxes over the
+/// low bits that are needed to address the table.
+///
+/// All higher bits of the index are reduced to a single out-of-bounds flag, which then selects
+/// the same `out_of_bounds_elem` sentinel as an array access would. Each index still needs its own
+/// mux tree, no gates are shared between lookups in the same table.
+fn compile_table_lookup(
+    table: &[GateIndex],
+    index: &[GateIndex],
+    num_elems: usize,
+    elem_bits: usize,
+    circuit: &mut CircuitBuilder,
+) -> (Vec<GateIndex>, GateIndex) {
+    let out_of_bounds_elem = 1;
+    let select_bits = (usize::BITS - num_elems.saturating_sub(1).leading_zeros()) as usize;
+    let (high_bits, low_bits) = index.split_at(index.len() - select_bits);
+    // pad the table to a power of 2, so that every mux layer halves the number of elements:
+    let mut array = table.to_vec();
+    array.resize(elem_bits << select_bits, out_of_bounds_elem);
+    for &s in low_bits.iter().rev() {
+        array = array
+            .chunks_exact(2 * elem_bits)
+            .flat_map(|pair| {
+                let (a0, a1) = pair.split_at(elem_bits);
+                a0.iter()
+                    .zip(a1)
+                    .map(|(&a0, &a1)| circuit.push_mux(s, a1, a0))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+    }
+    let out_of_bounds = if num_elems == 1 << select_bits {
+        // for a table of 2^n elements, the index is out of bounds iff any high bit is set:
+        high_bits
+            .iter()
+            .fold(0, |any_set, &bit| circuit.push_or(any_set, bit))
+    } else {
+        let mut array_len = Vec::with_capacity(index.len());
+        unsigned_to_bits(num_elems as u64, index.len(), &mut array_len);
+        let array_len: Vec<usize> = array_len.into_iter().map(|b| b as usize).collect();
+        let (index_less_than_array_len, _) =
+            circuit.push_comparator_circuit(index.len(), index, false, &array_len, false);
+        circuit.push_not(index_less_than_array_len)
+    };
+    let elem = array
+        .into_iter()
+        .map(|bit| circuit.push_mux(out_of_bounds, out_of_bounds_elem, bit))
+        .collect();
+    (elem, out_of_bounds)
+}
+
+/// Panics if `y` is zero or if a signed division overflows, which only happens for `MIN / -1`.
+fn push_division_panics(
+    circuit: &mut CircuitBuilder,
+    x: &[GateIndex],
+    y: &[GateIndex],
+    signed: bool,
+    meta: MetaInfo,
+) {
+    let mut all_zero = 1;
+    for b in y.iter() {
+        let eq = circuit.push_eq(*b, 0);
+        all_zero = circuit.push_and(all_zero, eq);
+    }
+    circuit.push_panic_if(all_zero, PanicReason::DivByZero, meta);
+    if signed {
+        // MIN is 10...0, -1 is 11...1:
+        let mut overflow = x[0];
+        for (i, (&x, &y)) in x.iter().zip(y.iter()).enumerate() {
+            let x_matches = if i == 0 { x } else { circuit.push_not(x) };
+            overflow = circuit.push_and(overflow, x_matches);
+            overflow = circuit.push_and(overflow, y);
+        }
+        circuit.push_panic_if(overflow, PanicReason::Overflow, meta);
+    }
+}
+
+/// Multiplies two fixed-point numbers at double width (where the product cannot overflow) and
+/// shifts the product right by `frac` bits, rounding towards negative infinity.
+///
+/// Panics if the shifted product does not fit into the width of the operands.
+fn push_fixed_multiplication(
+    circuit: &mut CircuitBuilder,
+    x: &[GateIndex],
+    y: &[GateIndex],
+    frac: u8,
+    meta: MetaInfo,
+) -> Vec<GateIndex> {
+    let bits = x.len();
+    let frac = frac as usize;
+    let mut x = x.to_vec();
+    let mut y = y.to_vec();
+    extend_to_bits(&mut x, &Type::Fixed(bits as u8, frac as u8), bits * 2);
+    extend_to_bits(&mut y, &Type::Fixed(bits as u8, frac as u8), bits * 2);
+    let (product, _, _) = circuit.push_multiplication_circuit(&x, &y, false);
+    let result = product[bits - frac..bits * 2 - frac].to_vec();
+    let mut overflow = 0;
+    for &w in product[..bits - frac].iter() {
+        let differs_from_sign = circuit.push_xor(w, result[0]);
+        overflow = circuit.push_or(overflow, differs_from_sign);
+    }
+    circuit.push_panic_if(overflow, PanicReason::Overflow, meta);
+    result
+}
+
+/// Counts the set bits as a balanced tree of additions, returning the count as `size` bits.
+fn push_count_ones(
+    circuit: &mut CircuitBuilder,
+    bits: &[GateIndex],
+    size: usize,
+) -> Vec<GateIndex> {
+    let mut sums: Vec<Vec<GateIndex>> = bits.iter().map(|&bit| vec![bit]).collect();
+    while sums.len() > 1 {
+        let mut next = Vec::with_capacity(sums.len() / 2 + 1);
+        for pair in sums.chunks(2) {
+            match pair {
+                [x, y] => {
+                    // each sum is extended by 1 bit so that the carry is never lost:
+                    let bits = x.len().max(y.len()) + 1;
+                    let mut x_extended = vec![0; bits - x.len()];
+                    x_extended.extend_from_slice(x);
+                    let mut y_extended = vec![0; bits - y.len()];
+                    y_extended.extend_from_slice(y);
+                    next.push(circuit.push_addition_circuit(&x_extended, &y_extended).0);
+                }
+                [x] => next.push(x.clone()),
+                _ => unreachable!("chunks of 2 have 1 or 2 elements"),
+            }
+        }
+        sums = next;
+    }
+    let count = sums.pop().unwrap_or_default();
+    let mut wires = vec![0; size.saturating_sub(count.len())];
+    wires.extend_from_slice(&count[count.len().saturating_sub(size)..]);
+    wires
+}
+
+/// Adds (or subtracts) `2^k` to the constant `bits` (LSB first), modulo `2^bits.len()`.
+fn add_constant_bit(bits: &mut [bool], k: usize, subtract: bool) {
+    // subtracting 2^k is the same as adding 2^len - 2^k, which has all bits from k to len set:
+    let mut carry = false;
+    for (i, b) in bits.iter_mut().enumerate() {
+        let addend = if subtract { i >= k } else { i == k };
+        let sum = *b as u8 + addend as u8 + carry as u8;
+        *b = sum & 1 == 1;
+        carry = sum > 1;
+    }
+}
+
+/// Returns the sum and the carry of the 3 bits, where the carry is the majority of the 3 bits,
+/// which only needs a single AND.
+fn push_full_adder(
+    circuit: &mut CircuitBuilder,
+    x: GateIndex,
+    y: GateIndex,
+    z: GateIndex,
+) -> (GateIndex, GateIndex) {
+    let x_z = circuit.push_xor(x, z);
+    let y_z = circuit.push_xor(y, z);
+    let sum = circuit.push_xor(x_z, y);
+    let both_differ = circuit.push_and(x_z, y_z);
+    let carry = circuit.push_xor(both_differ, z);
+    (sum, carry)
+}
+
+/// Sums the rows (all of the same width) using a tree of carry-save adders, which turn 3 bits of
+/// the same column into 2 without propagating any carries, so that only the final addition needs
+/// a carry chain. Constant 0 bits are dropped, so that only full adders are needed.
+fn push_carry_save_sum(
+    circuit: &mut CircuitBuilder,
+    rows: Vec<Vec<GateIndex>>,
+    width: usize,
+) -> Vec<GateIndex> {
+    // the bits of each column, starting with the least significant column:
+    let mut columns: Vec<Vec<GateIndex>> = (0..width)
+        .map(|k| {
+            let column = rows.iter().map(|row| row[width - 1 - k]);
+            column.filter(|&bit| bit != 0).collect()
+        })
+        .collect();
+    while columns.iter().any(|column| column.len() > 2) {
+        let mut next = vec![vec![]; width];
+        for (k, column) in columns.iter().enumerate() {
+            let mut triples = column.chunks_exact(3);
+            for triple in &mut triples {
+                let (sum, carry) = push_full_adder(circuit, triple[0], triple[1], triple[2]);
+                next[k].push(sum);
+                if k + 1 < width {
+                    next[k + 1].push(carry);
+                }
+            }
+            next[k].extend_from_slice(triples.remainder());
+        }
+        columns = next;
+    }
+    // the final carry chain uses the same full adders, so that each column adds a single AND:
+    let mut sum = vec![0; width];
+    let mut carry = 0;
+    for (k, column) in columns.iter().enumerate() {
+        let x = column.first().copied().unwrap_or(0);
+        let y = column.get(1).copied().unwrap_or(0);
+        (sum[width - 1 - k], carry) = push_full_adder(circuit, x, y, carry);
+    }
+    sum
+}
+
+/// Sums the operands (all of the same width) using a tree of carry-save adders and panics if the
+/// sum overflows.
+fn push_checked_sum(
+    circuit: &mut CircuitBuilder,
+    operands: Vec<&[GateIndex]>,
+    bits: usize,
+    signed: bool,
+    meta: MetaInfo,
+) -> Vec<GateIndex> {
+    // the sum of n operands needs at most ceil(log2(n)) additional bits, so that the additions
+    // can never overflow, only the final sum needs to be checked:
+    let n = operands.len();
+    let extra_bits = (usize::BITS - n.saturating_sub(1).leading_zeros()) as usize;
+    let width = bits + extra_bits;
+    let rows: Vec<Vec<GateIndex>> = operands
+        .into_iter()
+        .map(|operand| {
+            let msb = if signed { operand[0] } else { 0 };
+            let mut extended = vec![msb; extra_bits];
+            extended.extend_from_slice(operand);
+            extended
+        })
+        .collect();
+    let sum = push_carry_save_sum(circuit, rows, width);
+    let (extension, truncated) = sum.split_at(extra_bits);
+    let msb = if signed { truncated[0] } else { 0 };
+    let mut overflow = 0;
+    for &bit in extension {
+        let differs = circuit.push_xor(bit, msb);
+        overflow = circuit.push_or(overflow, differs);
+    }
+    circuit.push_panic_if(overflow, PanicReason::Overflow, meta);
+    truncated.to_vec()
+}
+
+fn compile_builtin_fn_call(
+    identifier: &str,
+    args: &[TypedExpr],
+    ty: &Type,
+    meta: MetaInfo,
+    prg: &TypedProgram,
+    env: &mut Env<Vec<GateIndex>>,
+    circuit: &mut CircuitBuilder,
+) -> Vec<GateIndex> {
+    match identifier {
+        "tuple_to_array" | "array_to_tuple" => {
+            // tuples and arrays of the same elements share the same wire layout
+            args[0].compile(prg, env, circuit)
+        }
+        "gather_bits" => {
+            // a constant mask only selects wires, so no gates are needed:
+            let x = args[0].compile(prg, env, circuit);
+            let mask = match args[1].inner {
+                ExprEnum::NumUnsigned(n, _) => n,
+                ExprEnum::NumSigned(n, _) => n as u64,
+                _ => unreachable!("the mask of gather_bits must be a number literal"),
+            };
+            let bits = x.len();
+            let mut gathered = vec![0; bits];
+            let mut j = 0;
+            for i in 0..bits {
+                if mask.checked_shr(i as u32).unwrap_or(0) & 1 == 1 {
+                    gathered[bits - 1 - j] = x[bits - 1 - i];
+                    j += 1;
+                }
+            }
+            gathered
+        }
+        "any" | "all" => {
+            let mut bits = args[0].compile(prg, env, circuit);
+            if bits.is_empty() {
+                return vec![if identifier == "all" { 1 } else { 0 }];
+            }
+            // reduce pairwise as a balanced tree, so that the AND depth is only log2(n):
+            while bits.len() > 1 {
+                bits = bits
+                    .chunks(2)
+                    .map(|pair| match pair {
+                        [x, y] if identifier == "all" => circuit.push_and(*x, *y),
+                        [x, y] => circuit.push_or(*x, *y),
+                        [x] => *x,
+                        _ => unreachable!("chunks of 2 have 1 or 2 elements"),
+                    })
+                    .collect();
+            }
+            bits
+        }
+        "sum" => {
+            let array = args[0].compile(prg, env, circuit);
+            let (Type::Array(elem_ty, _) | Type::ArrayConst(elem_ty, _)) = &args[0].ty else {
+                unreachable!("the arg of sum must be an array")
+            };
+            let elem_size = elem_ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+            let elems = array.chunks_exact(elem_size).collect();
+            push_checked_sum(circuit, elems, elem_size, is_signed(elem_ty), meta)
+        }
+        "product" => {
+            let array = args[0].compile(prg, env, circuit);
+            let (Type::Array(elem_ty, _) | Type::ArrayConst(elem_ty, _)) = &args[0].ty else {
+                unreachable!("the arg of product must be an array")
+            };
+            let elem_size = elem_ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+            let signed = is_signed(elem_ty);
+            let mut products: Vec<Vec<GateIndex>> = array
+                .chunks_exact(elem_size)
+                .map(|elem| elem.to_vec())
+                .collect();
+            // multiply the elements as a balanced tree, panicking if any partial product
+            // overflows, just like a chain of `*` would:
+            let mut overflow = 0;
+            while products.len() > 1 {
+                let mut next = Vec::with_capacity(products.len() / 2 + 1);
+                for pair in products.chunks(2) {
+                    match pair {
+                        [x, y] => {
+                            let (product, overflowed, _) =
+                                circuit.push_multiplication_circuit(x, y, signed);
+                            overflow = circuit.push_or(overflow, overflowed);
+                            next.push(product);
+                        }
+                        [x] => next.push(x.clone()),
+                        _ => unreachable!("chunks of 2 have 1 or 2 elements"),
+                    }
+                }
+                products = next;
+            }
+            circuit.push_panic_if(overflow, PanicReason::Overflow, meta);
+            products
+                .pop()
+                .unwrap_or_else(|| unsigned_as_wires(1, elem_size))
+        }
+        "prefix_sum" => {
+            let array = args[0].compile(prg, env, circuit);
+            let (Type::Array(elem_ty, _) | Type::ArrayConst(elem_ty, _)) = &args[0].ty else {
+                unreachable!("the arg of prefix_sum must be an array")
+            };
+            let elem_size = elem_ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+            let n = array.len() / elem_size;
+            // sums of up to n elements need at most ceil(log2(n)) additional bits, so that the
+            // additions below can never overflow, only the final sums need to be checked:
+            let extra_bits = (usize::BITS - n.saturating_sub(1).leading_zeros()) as usize;
+            let mut sums: Vec<Vec<GateIndex>> = array
+                .chunks_exact(elem_size)
+                .map(|elem| {
+                    let msb = if is_signed(elem_ty) { elem[0] } else { 0 };
+                    let mut extended = vec![msb; extra_bits];
+                    extended.extend_from_slice(elem);
+                    extended
+                })
+                .collect();
+            // work-efficient (Brent-Kung) parallel prefix network, first the up-sweep...
+            let mut d = 1;
+            while d < n {
+                for i in (2 * d - 1..n).step_by(2 * d) {
+                    sums[i] = circuit.push_addition_circuit(&sums[i - d], &sums[i]).0;
+                }
+                d *= 2;
+            }
+            // ...then the down-sweep:
+            d /= 4;
+            while d > 0 {
+                for i in (3 * d - 1..n).step_by(2 * d) {
+                    sums[i] = circuit.push_addition_circuit(&sums[i - d], &sums[i]).0;
+                }
+                d /= 2;
+            }
+            let mut overflow = 0;
+            let mut wires = Vec::with_capacity(array.len());
+            for sum in sums {
+                let (extension, truncated) = sum.split_at(extra_bits);
+                let msb = if is_signed(elem_ty) { truncated[0] } else { 0 };
+                for &bit in extension {
+                    let differs = circuit.push_xor(bit, msb);
+                    overflow = circuit.push_or(overflow, differs);
+                }
+                wires.extend_from_slice(truncated);
+            }
+            circuit.push_panic_if(overflow, PanicReason::Overflow, meta);
+            wires
+        }
+        "count_ones" => {
+            let bits = args[0].compile(prg, env, circuit);
+            let size = ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+            push_count_ones(circuit, &bits, size)
+        }
+        "leading_zeros" | "trailing_zeros" => {
+            let mut bits = args[0].compile(prg, env, circuit);
+            if identifier == "trailing_zeros" {
+                bits.reverse();
+            }
+            // a prefix-OR over the bits (starting at the MSB) is monotone, so the zeros counted
+            // are exactly the zeros before the first set bit:
+            let mut seen_one = 0;
+            let mut zeros = Vec::with_capacity(bits.len());
+            for bit in bits {
+                seen_one = circuit.push_or(seen_one, bit);
+                zeros.push(circuit.push_not(seen_one));
+            }
+            let size = ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+            push_count_ones(circuit, &zeros, size)
+        }
+        "mean" => {
+            let array = args[0].compile(prg, env, circuit);
+            let (Type::Array(elem_ty, _) | Type::ArrayConst(elem_ty, _)) = &args[0].ty else {
+                unreachable!("the arg of mean must be an array")
+            };
+            let elem_size = elem_ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+            let n = array.len() / elem_size;
+            if n == 0 {
+                circuit.push_panic_if(1, PanicReason::DivByZero, meta);
+                return vec![0; elem_size];
+            }
+            // the sum of n elements needs at most ceil(log2(n)) additional bits (plus 1 bit so
+            // that the absolute value of a negative sum always fits):
+            let signed = is_signed(elem_ty);
+            let extra_bits =
+                (usize::BITS - n.saturating_sub(1).leading_zeros()) as usize + signed as usize;
+            let mut sums: Vec<Vec<GateIndex>> = array
+                .chunks_exact(elem_size)
+                .map(|elem| {
+                    let msb = if signed { elem[0] } else { 0 };
+                    let mut extended = vec![msb; extra_bits];
+                    extended.extend_from_slice(elem);
+                    extended
+                })
+                .collect();
+            // sum the elements as a balanced tree of additions:
+            while sums.len() > 1 {
+                let mut next = Vec::with_capacity(sums.len() / 2 + 1);
+                for pair in sums.chunks(2) {
+                    match pair {
+                        [x, y] => next.push(circuit.push_addition_circuit(x, y).0),
+                        [x] => next.push(x.clone()),
+                        _ => unreachable!("chunks of 2 have 1 or 2 elements"),
+                    }
+                }
+                sums = next;
+            }
+            let sum = sums.pop().unwrap();
+            // like `/`, the mean is truncated towards zero, so negative sums are divided as
+            // positive numbers and negated afterwards:
+            let is_negative = if signed { sum[0] } else { 0 };
+            let negated = circuit.push_negation_circuit(&sum);
+            let abs: Vec<GateIndex> = sum
+                .iter()
+                .zip(negated.iter())
+                .map(|(&x, &neg)| circuit.push_mux(is_negative, neg, x))
+                .collect();
+            let quotient = circuit.push_constant_division_circuit(&abs, n as u64);
+            let negated = circuit.push_negation_circuit(&quotient);
+            let mean: Vec<GateIndex> = quotient
+                .iter()
+                .zip(negated.iter())
+                .map(|(&x, &neg)| circuit.push_mux(is_negative, neg, x))
+                .collect();
+            mean[extra_bits..].to_vec()
+        }
+        "dot" => {
+            let a = args[0].compile(prg, env, circuit);
+            let b = args[1].compile(prg, env, circuit);
+            let (Type::Array(elem_ty, _) | Type::ArrayConst(elem_ty, _)) = &args[0].ty else {
+                unreachable!("the args of dot must be arrays")
+            };
+            let elem_size = elem_ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+            let n = a.len().checked_div(elem_size).unwrap_or(0);
+            // a product needs at most twice the bits of its factors and the sum of n products at
+            // most ceil(log2(n)) additional bits, so that the sum below is exact modulo 2^width:
+            let extra_bits = (usize::BITS - n.saturating_sub(1).leading_zeros()) as usize;
+            let width = 2 * elem_size + extra_bits;
+            // only the sum is widened, the partial products only cover the 2 * elem_size bits of
+            // each product. Signed products use the Baugh-Wooley scheme, which negates the partial
+            // products of exactly one sign bit, so that `-b * 2^k` becomes `!b * 2^k - 2^k`. All
+            // constant bits are collected in a single row (LSB first):
+            let signed = is_signed(elem_ty);
+            let mut constant = vec![false; width];
+            // every bit of every element of b contributes one row of partial products, rows with
+            // only constant bits are skipped and ANDs with constant bits are folded, which
+            // specializes the multiplication whenever one of the arrays is public:
+            let mut rows: Vec<Vec<GateIndex>> = vec![];
+            for (x, y) in a.chunks_exact(elem_size).zip(b.chunks_exact(elem_size)) {
+                for j in 0..elem_size {
+                    let y_j = y[elem_size - 1 - j];
+                    let mut row = vec![0; width];
+                    for i in 0..elem_size {
+                        let mut bit = circuit.push_and(x[elem_size - 1 - i], y_j);
+                        if signed && ((i == elem_size - 1) != (j == elem_size - 1)) {
+                            bit = circuit.push_not(bit);
+                            add_constant_bit(&mut constant, i + j, true);
+                        }
+                        if bit == 1 {
+                            add_constant_bit(&mut constant, i + j, false);
+                        } else {
+                            row[width - 1 - (i + j)] = bit;
+                        }
+                    }
+                    if row.iter().any(|&bit| bit != 0) {
+                        rows.push(row);
+                    }
+                }
+            }
+            if constant.iter().any(|&bit| bit) {
+                rows.push(constant.iter().rev().map(|&bit| bit as GateIndex).collect());
+            }
+            let sum = push_carry_save_sum(circuit, rows, width);
+            let (extension, truncated) = sum.split_at(width - elem_size);
+            let msb = if is_signed(elem_ty) { truncated[0] } else { 0 };
+            let mut overflow = 0;
+            for &bit in extension {
+                let differs = circuit.push_xor(bit, msb);
+                overflow = circuit.push_or(overflow, differs);
+            }
+            circuit.push_panic_if(overflow, PanicReason::Overflow, meta);
+            truncated.to_vec()
+        }
+        "reverse" | "rotate" => {
+            // the indices are known at compile time, so the elements are only rearranged:
+            let array = args[0].compile(prg, env, circuit);
+            let (Type::Array(elem_ty, _) | Type::ArrayConst(elem_ty, _)) = &args[0].ty else {
+                unreachable!("the arg of {identifier} must be an array")
+            };
+            let elem_size = elem_ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+            if elem_size == 0 {
+                return array;
+            }
+            let mut elems: Vec<&[GateIndex]> = array.chunks_exact(elem_size).collect();
+            if identifier == "reverse" {
+                elems.reverse();
+            } else if !elems.is_empty() {
+                let ExprEnum::NumUnsigned(k, _) = args[1].inner else {
+                    unreachable!("the number of positions of rotate must be a number literal")
+                };
+                let k = (k % elems.len() as u64) as usize;
+                elems.rotate_left(k);
+            }
+            elems.concat()
+        }
+        "permute" => {
+            // Moving each element to its (secret) position is equivalent to sorting the elements
+            // by their positions, which is done using a bitonic sorting network:
+            let array = args[0].compile(prg, env, circuit);
+            let perm = args[1].compile(prg, env, circuit);
+            let n = perm.len() / USIZE_BITS;
+            let elem_size = array.len().checked_div(n).unwrap_or(0);
+            let num_elems = n.next_power_of_two();
+            // each element is prefixed by a bit marking padding elements (which will be sorted to
+            // the end) and its target position:
+            let key_size = 1 + USIZE_BITS;
+            let mut sorted = Vec::with_capacity(num_elems);
+            for i in 0..n {
+                let mut elem = vec![0];
+                elem.extend_from_slice(&perm[i * USIZE_BITS..(i + 1) * USIZE_BITS]);
+                elem.extend_from_slice(&array[i * elem_size..(i + 1) * elem_size]);
+                sorted.push(elem);
+            }
+            for _ in n..num_elems {
+                let mut elem = vec![0; key_size + elem_size];
+                elem[0] = 1;
+                sorted.push(elem);
+            }
+            let mut k = 2;
+            while k <= num_elems {
+                let mut j = k / 2;
+                while j > 0 {
+                    for i in 0..num_elems {
+                        let l = i ^ j;
+                        if l > i {
+                            let (min, max) = circuit.push_sorter(key_size, &sorted[i], &sorted[l]);
+                            if i & k == 0 {
+                                (sorted[i], sorted[l]) = (min, max);
+                            } else {
+                                (sorted[i], sorted[l]) = (max, min);
+                            }
+                        }
+                    }
+                    j /= 2;
+                }
+                k *= 2;
+            }
+            let mut wires = Vec::with_capacity(array.len());
+            for elem in sorted.into_iter().take(n) {
+                wires.extend_from_slice(&elem[key_size..]);
+            }
+            wires
+        }
+        "get_or" => {
+            let num_elems = match &args[0].ty {
+                Type::Array(_, size) => *size,
+                Type::ArrayConst(_, size) => *circuit.const_sizes().get(size).unwrap(),
+                _ => unreachable!("the first arg of get_or must be an array"),
+            };
+            let array = args[0].compile(prg, env, circuit);
+            let index = args[1].compile(prg, env, circuit);
+            let default = args[2].compile(prg, env, circuit);
+            let (elem, out_of_bounds) =
+                compile_array_access(array, index, num_elems, default.len(), prg, circuit);
+            elem.into_iter()
+                .zip(default)
+                .map(|(elem, default)| circuit.push_mux(out_of_bounds, default, elem))
+                .collect()
+        }
+        "lookup_table" => {
+            let (num_elems, elem_ty) = match &args[0].ty {
+                Type::Array(elem_ty, size) => (*size, elem_ty),
+                Type::ArrayConst(elem_ty, size) => {
+                    (*circuit.const_sizes().get(size).unwrap(), elem_ty)
+                }
+                _ => unreachable!("the first arg of lookup_table must be an array"),
+            };
+            let elem_bits = elem_ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+            let table = args[0].compile(prg, env, circuit);
+            let indexes = args[1].compile(prg, env, circuit);
+            let mut wires = vec![];
+            let mut out_of_bounds = 0;
+            for index in indexes.chunks_exact(USIZE_BITS) {
+                let (elem, index_out_of_bounds) =
+                    compile_table_lookup(&table, index, num_elems, elem_bits, circuit);
+                wires.extend(elem);
+                out_of_bounds = circuit.push_or(out_of_bounds, index_out_of_bounds);
+            }
+            circuit.push_panic_if(out_of_bounds, PanicReason::OutOfBounds, meta);
+            wires
+        }
+        "histogram" => {
+            let (Type::Array(elem_ty, _) | Type::ArrayConst(elem_ty, _)) = &args[0].ty else {
+                unreachable!("the first arg of histogram must be an array")
+            };
+            let ExprEnum::NumUnsigned(buckets, _) = args[1].inner else {
+                unreachable!("the buckets of histogram must be a number literal")
+            };
+            let values = args[0].compile(prg, env, circuit);
+            let elem_size = elem_ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+            let mut counts = vec![vec![0; USIZE_BITS]; buckets as usize];
+            for value in values.chunks_exact(elem_size) {
+                // obliviously add 1 to the bucket matching the value (if any):
+                for (bucket, count) in counts.iter_mut().enumerate() {
+                    if elem_size < usize::BITS as usize && bucket >> elem_size != 0 {
+                        break;
+                    }
+                    let mut is_match = 1;
+                    for (i, &bit) in value.iter().enumerate() {
+                        // elements can be wider than a bucket index (e.g. u256), so the shift
+                        // must not overflow:
+                        let shift = (elem_size - 1 - i) as u32;
+                        let bit = if bucket.checked_shr(shift).unwrap_or(0) & 1 == 1 {
+                            bit
+                        } else {
+                            circuit.push_not(bit)
+                        };
+                        is_match = circuit.push_and(is_match, bit);
+                    }
+                    let mut increment = vec![0; USIZE_BITS];
+                    increment[USIZE_BITS - 1] = is_match;
+                    *count = circuit.push_addition_circuit(count, &increment).0;
+                }
+            }
+            counts.concat()
+        }
+        "get" => {
+            // a constant index path only selects wires, so no gates are needed:
+            let x = args[0].compile(prg, env, circuit);
+            let Some(indexes) = index_path(&args[1]) else {
+                unreachable!("the path of get must be an array literal of number literals")
+            };
+            let mut ty = &args[0].ty;
+            let mut offset = 0;
+            for i in indexes {
+                let i = i as usize;
+                ty = match ty {
+                    Type::Tuple(fields) => {
+                        for field in &fields[..i] {
+                            offset += field.size_in_bits_for_defs(prg, circuit.const_sizes());
+                        }
+                        &fields[i]
+                    }
+                    Type::Array(elem_ty, _) => {
+                        offset += i * elem_ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+                        elem_ty
+                    }
+                    _ => unreachable!("the path of get must match the type of the value"),
+                };
+            }
+            let size = ty.size_in_bits_for_defs(prg, circuit.const_sizes());
+            x[offset..offset + size].to_vec()
+        }
+        "divmod" => {
+            // quotient and remainder are both outputs of the same division circuit:
+            let mut x = args[0].compile(prg, env, circuit);
+            let mut y = args[1].compile(prg, env, circuit);
+            push_division_panics(circuit, &x, &y, is_signed(&args[0].ty), meta);
+            let (mut quotient, remainder) = if is_signed(&args[0].ty) {
+                circuit.push_signed_division_circuit(&mut x, &mut y)
+            } else {
+                circuit.push_unsigned_division_circuit(&x, &y)
+            };
+            quotient.extend(remainder);
+            quotient
+        }
+        "min" | "max" => {
+            // both operands share a single comparator, the result is selected bit by bit:
+            let x = args[0].compile(prg, env, circuit);
+            let y = args[1].compile(prg, env, circuit);
+            let signed = is_signed(ty);
+            let (lt, _) = circuit.push_comparator_circuit(x.len(), &x, signed, &y, signed);
+            let (smaller, larger) = if identifier == "min" { (x, y) } else { (y, x) };
+            smaller
+                .into_iter()
+                .zip(larger)
+                .map(|(a, b)| circuit.push_mux(lt, a, b))
+                .collect()
+        }
+        "wrapping_add" | "wrapping_sub" | "wrapping_mul" => {
+            // same circuits as the arithmetic operators, but the overflow bit is never checked:
+            let x = args[0].compile(prg, env, circuit);
+            let y = args[1].compile(prg, env, circuit);
+            match identifier {
+                "wrapping_add" => circuit.push_addition_circuit(&x, &y).0,
+                "wrapping_sub" => circuit.push_subtraction_circuit(&x, &y, is_signed(ty)).0,
+                _ => circuit.push_multiplication_circuit(&x, &y, is_signed(ty)).0,
+            }
+        }
+        "assert" | "assert_eq" => {
+            let (holds, code) = if identifier == "assert" {
+                (args[0].compile(prg, env, circuit)[0], args.get(1))
+            } else {
+                let x = args[0].compile(prg, env, circuit);
+                let y = args[1].compile(prg, env, circuit);
+                (push_value_eq(circuit, &args[0].ty, &x, &y), args.get(2))
+            };
+            let code = match code.map(|code| &code.inner) {
+                Some(ExprEnum::NumUnsigned(code, _)) => *code as usize,
+                Some(_) => unreachable!("the code of an assertion must be a number literal"),
+                None => 0,
+            };
+            let failed = circuit.push_not(holds);
+            circuit.push_panic_with_code_if(failed, PanicReason::AssertionFailed, code, meta);
+            vec![]
+        }
+        "one_hot" => {
+            let x = args[0].compile(prg, env, circuit);
+            let ExprEnum::ArrayLiteral(consts) = &args[1].inner else {
+                unreachable!("the constants of one_hot must be an array literal")
+            };
+            let mut bits = Vec::with_capacity(consts.len());
+            for c in consts {
+                // the bits of the constant are constant wires, so each comparison is just an AND
+                // of the (possibly negated) bits of x:
+                let c = c.compile(prg, env, circuit);
+                let mut is_match = 1;
+                for (&x, &c) in x.iter().zip(c.iter()) {
+                    let eq = circuit.push_eq(x, c);
+                    is_match = circuit.push_and(is_match, eq);
+                }
+                bits.push(is_match);
+            }
+            bits
         }
+        _ => unreachable!("'{identifier}' is not a builtin fn"),
     }
 }
 
@@ -1132,6 +2378,7 @@ impl TypedPattern {
                 env.let_in_current_scope(s.clone(), match_expr.to_vec());
                 1
             }
+            PatternEnum::Wildcard => 1,
             PatternEnum::True => {
                 assert_eq!(match_expr.len(), 1);
                 match_expr[0]
@@ -1269,13 +2516,24 @@ impl Type {
             Type::Unsigned(UnsignedNumType::U8) | Type::Signed(SignedNumType::I8) => 8,
             Type::Unsigned(UnsignedNumType::U16) | Type::Signed(SignedNumType::I16) => 16,
             Type::Unsigned(UnsignedNumType::U32) | Type::Signed(SignedNumType::I32) => 32,
+            Type::Char => 32,
+            Type::String(max_len) => max_len
+                .checked_mul(8)
+                .and_then(|bits| bits.checked_add(USIZE_BITS))
+                .expect("the type checker rejects string types that are too large"),
             Type::Unsigned(UnsignedNumType::U64) | Type::Signed(SignedNumType::I64) => 64,
+            Type::Unsigned(UnsignedNumType::U256) => 256,
+            Type::Fixed(bits, _) => *bits as usize,
             Type::Unsigned(UnsignedNumType::Unspecified)
             | Type::Signed(SignedNumType::Unspecified) => 32,
-            Type::Array(elem, size) => elem.size_in_bits_for_defs(prg, const_sizes) * size,
-            Type::ArrayConst(elem, size) => {
-                elem.size_in_bits_for_defs(prg, const_sizes) * const_sizes.get(size).unwrap()
-            }
+            Type::Array(elem, size) => elem
+                .size_in_bits_for_defs(prg, const_sizes)
+                .checked_mul(*size)
+                .expect("the type checker rejects array types that are too large"),
+            Type::ArrayConst(elem, size) => elem
+                .size_in_bits_for_defs(prg, const_sizes)
+                .checked_mul(*const_sizes.get(size).unwrap())
+                .expect("the type checker rejects array types that are too large"),
             Type::Tuple(values) => {
                 let mut size = 0;
                 for v in values {
@@ -1305,6 +2563,10 @@ pub(crate) fn struct_size(
     total_size
 }
 
+/// Returns the discriminant of the variant, which is its index in the enum def.
+///
+/// Enums are encoded as their discriminant (using [`enum_tag_size`] bits, most significant bit
+/// first), followed by the fields of the variant, which is also how `as` casts read the tag.
 pub(crate) fn enum_tag_number(enum_def: &EnumDef, variant: &str) -> usize {
     for (i, def) in enum_def.variants.iter().enumerate() {
         if def.variant_name() == variant {
@@ -1342,13 +2604,15 @@ pub(crate) fn enum_max_size(
 
 pub(crate) fn unsigned_to_bits(n: u64, size: usize, bits: &mut Vec<bool>) {
     for i in 0..size {
-        bits.push((n >> (size - 1 - i) & 1) == 1);
+        let shifted = n.checked_shr((size - 1 - i) as u32).unwrap_or(0);
+        bits.push((shifted & 1) == 1);
     }
 }
 
 pub(crate) fn signed_to_bits(n: i64, size: usize, bits: &mut Vec<bool>) {
     for i in 0..size {
-        bits.push((n >> (size - 1 - i) & 1) == 1);
+        // shifting by 63 or more bits leaves only the sign:
+        bits.push((n >> (size - 1 - i).min(63) & 1) == 1);
     }
 }
 
@@ -1364,6 +2628,28 @@ pub(crate) fn signed_as_wires(n: i64, size: usize) -> Vec<usize> {
     bits.into_iter().map(|b| b as usize).collect()
 }
 
+/// Encodes big-endian bytes as `size` bits (MSB first), dropping or zero-extending the bytes.
+pub(crate) fn be_bytes_to_bits(bytes: &[u8], size: usize, bits: &mut Vec<bool>) {
+    let available = bytes.len() * 8;
+    for i in 0..size {
+        match (available + i).checked_sub(size) {
+            Some(bit) => bits.push((bytes[bit / 8] >> (7 - bit % 8)) & 1 == 1),
+            None => bits.push(false),
+        }
+    }
+}
+
+/// Decodes bits (MSB first) as big-endian bytes, padding the first byte with zeros if necessary.
+pub(crate) fn bits_as_be_bytes(bits: &[bool]) -> Vec<u8> {
+    let mut bytes = vec![0; (bits.len() + 7) / 8];
+    let padding = bytes.len() * 8 - bits.len();
+    for (i, &bit) in bits.iter().enumerate() {
+        let i = padding + i;
+        bytes[i / 8] |= (bit as u8) << (7 - i % 8);
+    }
+    bytes
+}
+
 pub(crate) fn wires_as_unsigned(wires: &[bool]) -> u64 {
     let mut n = 0;
     for (i, output) in wires.iter().copied().enumerate() {
@@ -1372,6 +2658,56 @@ pub(crate) fn wires_as_unsigned(wires: &[bool]) -> u64 {
     n
 }
 
+/// Compares two values of the same type, which are equal if all of their bits are equal (except
+/// for the padding of strings).
+fn push_value_eq(
+    circuit: &mut CircuitBuilder,
+    ty: &Type,
+    x: &[GateIndex],
+    y: &[GateIndex],
+) -> GateIndex {
+    if let Type::String(max_len) = ty {
+        push_string_eq(circuit, x, y, *max_len)
+    } else {
+        let mut acc = 1;
+        for (&x, &y) in x.iter().zip(y.iter()) {
+            let eq = circuit.push_eq(x, y);
+            acc = circuit.push_and(acc, eq);
+        }
+        acc
+    }
+}
+
+/// Compares the lengths of both strings and their bytes up to the length, ignoring the padding.
+fn push_string_eq(
+    circuit: &mut CircuitBuilder,
+    x: &[GateIndex],
+    y: &[GateIndex],
+    max_len: usize,
+) -> GateIndex {
+    let (x_len, x_bytes) = x.split_at(USIZE_BITS);
+    let (y_len, y_bytes) = y.split_at(USIZE_BITS);
+    let mut acc = 1;
+    for i in 0..USIZE_BITS {
+        let eq = circuit.push_eq(x_len[i], y_len[i]);
+        acc = circuit.push_and(acc, eq);
+    }
+    for i in 0..max_len {
+        let mut byte_eq = 1;
+        for b in i * 8..(i + 1) * 8 {
+            let eq = circuit.push_eq(x_bytes[b], y_bytes[b]);
+            byte_eq = circuit.push_and(byte_eq, eq);
+        }
+        let index = unsigned_as_wires(i as u64, USIZE_BITS);
+        let (_, is_within_len) =
+            circuit.push_comparator_circuit(USIZE_BITS, x_len, false, &index, false);
+        let is_padding = circuit.push_not(is_within_len);
+        let byte_ok = circuit.push_or(byte_eq, is_padding);
+        acc = circuit.push_and(acc, byte_ok);
+    }
+    acc
+}
+
 fn extend_to_bits(v: &mut Vec<usize>, ty: &Type, bits: usize) {
     if v.is_empty() {
         v.resize(bits, 0);
@@ -1380,14 +2716,32 @@ fn extend_to_bits(v: &mut Vec<usize>, ty: &Type, bits: usize) {
         let old_size = v.len();
         v.resize(bits, 0);
         v.copy_within(0..old_size, bits - old_size);
-        if let Type::Signed(_) = ty {
-            v[0..old_size].fill(msb);
+        if is_signed(ty) {
+            v[0..bits - old_size].fill(msb);
         } else {
-            v[0..old_size].fill(0);
+            v[0..bits - old_size].fill(0);
+        }
+    }
+}
+
+/// Returns the operands of a chain of the same operator, such as `[a, b, c]` for `a & b & c`.
+///
+/// Bitwise operators are re-associated, since they are associative at the bit level and can never
+/// panic. Additions are only re-associated for unsigned numbers, where a chain overflows iff its
+/// total overflows. Signed additions and multiplications are never re-associated, because a later
+/// operand can undo the overflow of a partial result (such as `100i8 + 100i8 + -100i8`, or a
+/// later factor of 0), so re-associating them could change whether the chain panics.
+fn associative_chain(expr: &TypedExpr, op: Op) -> Vec<&TypedExpr> {
+    match &expr.inner {
+        ExprEnum::Op(op_x, x, y) if *op_x == op && x.ty == expr.ty && y.ty == expr.ty => {
+            let mut operands = associative_chain(x, op);
+            operands.extend(associative_chain(y, op));
+            operands
         }
+        _ => vec![expr],
     }
 }
 
 fn is_signed(ty: &Type) -> bool {
-    matches!(ty, Type::Signed(_))
+    matches!(ty, Type::Signed(_) | Type::Fixed(_, _))
 }
@@ -1,12 +1,25 @@
 //! Simple helper for lexical scopes used by [`crate::check()`] and [`crate::compile()`].
 use std::collections::{btree_map::Entry, BTreeMap};
 
+use crate::token::MetaInfo;
+
 #[derive(Debug, Clone)]
-pub(crate) struct Env<T: Clone>(pub(crate) Vec<BTreeMap<String, T>>);
+pub(crate) struct Env<T: Clone>(pub(crate) Vec<BTreeMap<String, T>>, UnusedBindings);
+
+/// Bindings that are tracked (see [`Env::track_unused`]) but have not been used (yet).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct UnusedBindings {
+    scopes: Vec<BTreeMap<String, MetaInfo>>,
+    out_of_scope: Vec<(String, MetaInfo)>,
+}
 
 impl<T: Clone + std::fmt::Debug> Env<T> {
     pub(crate) fn new() -> Self {
-        Self(vec![BTreeMap::new()])
+        Self(vec![BTreeMap::new()], UnusedBindings::default())
+    }
+
+    pub(crate) fn empty() -> Self {
+        Self(vec![], UnusedBindings::default())
     }
 
     pub(crate) fn get(&self, identifier: &str) -> Option<T> {
@@ -32,11 +45,46 @@ impl<T: Clone + std::fmt::Debug> Env<T> {
         panic!("Could not find existing binding for '{identifier}'");
     }
 
+    /// Reports the binding in the current scope as unused unless [`Env::mark_used`] is called
+    /// before the binding goes out of scope or is shadowed in the same scope.
+    pub(crate) fn track_unused(&mut self, identifier: String, meta: MetaInfo) {
+        let unused = &mut self.1;
+        while unused.scopes.len() < self.0.len() {
+            unused.scopes.push(BTreeMap::new());
+        }
+        let scope = unused.scopes.last_mut().unwrap();
+        if let Some(shadowed) = scope.insert(identifier.clone(), meta) {
+            unused.out_of_scope.push((identifier, shadowed));
+        }
+    }
+
+    pub(crate) fn mark_used(&mut self, identifier: &str) {
+        for (i, bindings) in self.0.iter().enumerate().rev() {
+            if bindings.contains_key(identifier) {
+                if let Some(scope) = self.1.scopes.get_mut(i) {
+                    scope.remove(identifier);
+                }
+                return;
+            }
+        }
+    }
+
+    /// Returns all tracked bindings that went out of scope without being used.
+    pub(crate) fn take_unused(&mut self) -> Vec<(String, MetaInfo)> {
+        std::mem::take(&mut self.1.out_of_scope)
+    }
+
     pub(crate) fn push(&mut self) {
         self.0.push(BTreeMap::new());
     }
 
     pub(crate) fn pop(&mut self) {
+        let depth = self.0.len();
         self.0.pop().unwrap();
+        let unused = &mut self.1;
+        if unused.scopes.len() >= depth {
+            let scope = unused.scopes.remove(depth - 1);
+            unused.out_of_scope.extend(scope);
+        }
     }
 }
@@ -0,0 +1,415 @@
+//! Estimates the size of the circuit of a [`crate::ast::Program`] without compiling it.
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::{ConstExpr, ConstExprEnum, ExprEnum, Op, StmtEnum, Type, UnaryOp, VariantExprEnum},
+    compile::CompilerError,
+    env::Env,
+    token::UnsignedNumType,
+    TypedExpr, TypedFnDef, TypedProgram, TypedStmt,
+};
+
+/// An approximation of the number of gates that a program will compile to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GateEstimate {
+    /// The estimated number of gates of all kinds.
+    pub gates: usize,
+    /// The estimated number of AND gates, which are usually the most expensive gates in MPC.
+    pub and_gates: usize,
+}
+
+impl GateEstimate {
+    fn add(&mut self, gates: usize, and_gates: usize) {
+        self.gates += gates;
+        self.and_gates += and_gates;
+    }
+
+    fn add_estimate(&mut self, other: GateEstimate) {
+        self.add(other.gates, other.and_gates);
+    }
+
+    fn times(self, n: usize) -> GateEstimate {
+        GateEstimate {
+            gates: self.gates * n,
+            and_gates: self.and_gates * n,
+        }
+    }
+}
+
+impl TypedProgram {
+    /// Estimates the number of gates of the compiled function by summing the approximate cost of
+    /// each operation (e.g. `n²` gates for an `n`-bit multiplication), without building a circuit.
+    ///
+    /// Builtin fns are estimated based on the circuits they compile to. The estimate ignores the
+    /// optimizations done during compilation (such as constant folding), so it tends to
+    /// overestimate programs that operate on constants. Sizes that depend on external constants
+    /// are unknown and assumed to be 1.
+    pub fn estimate_gates(&self, fn_name: &str) -> Result<GateEstimate, Vec<CompilerError>> {
+        let Some(fn_def) = self.fn_defs.get(fn_name) else {
+            return Err(vec![CompilerError::FnNotFound(fn_name.to_string())]);
+        };
+        let mut const_sizes = HashMap::new();
+        for (const_name, const_def) in self.const_defs.iter() {
+            if let Type::Unsigned(UnsignedNumType::Usize) = const_def.ty {
+                let size = resolve_const_size(&const_def.value).unwrap_or(1);
+                const_sizes.insert(const_name.clone(), size);
+            }
+        }
+        let mut estimator = Estimator {
+            prg: self,
+            const_sizes,
+            env: Env::new(),
+        };
+        Ok(estimator.fn_body(fn_def))
+    }
+}
+
+fn resolve_const_size(ConstExpr(expr, _): &ConstExpr) -> Option<usize> {
+    match expr {
+        ConstExprEnum::NumUnsigned(n, _) => Some(*n as usize),
+        ConstExprEnum::Max(args) => args.iter().map(resolve_const_size).max().flatten(),
+        ConstExprEnum::Min(args) => args.iter().map(resolve_const_size).min().flatten(),
+        _ => None,
+    }
+}
+
+struct Estimator<'a> {
+    prg: &'a TypedProgram,
+    const_sizes: HashMap<String, usize>,
+    // the types of variables, needed to estimate the cost of assigning to an array element
+    env: Env<Type>,
+}
+
+impl Estimator<'_> {
+    fn bits(&self, ty: &Type) -> usize {
+        ty.size_in_bits_for_defs(self.prg, &self.const_sizes)
+    }
+
+    fn array_len(&self, ty: &Type) -> usize {
+        match ty {
+            Type::Array(_, size) => *size,
+            Type::ArrayConst(_, size) => *self.const_sizes.get(size).unwrap_or(&1),
+            _ => 1,
+        }
+    }
+
+    fn fn_body(&mut self, fn_def: &TypedFnDef) -> GateEstimate {
+        self.env.push();
+        for param in fn_def.params.iter() {
+            self.env
+                .let_in_current_scope(param.name.clone(), param.ty.clone());
+        }
+        let estimate = self.block(&fn_def.body);
+        self.env.pop();
+        estimate
+    }
+
+    fn block(&mut self, stmts: &[TypedStmt]) -> GateEstimate {
+        self.env.push();
+        let mut estimate = GateEstimate::default();
+        for stmt in stmts {
+            estimate.add_estimate(self.stmt(stmt));
+        }
+        self.env.pop();
+        estimate
+    }
+
+    fn stmt(&mut self, stmt: &TypedStmt) -> GateEstimate {
+        match &stmt.inner {
+            StmtEnum::LetMut(identifier, expr) => {
+                self.env
+                    .let_in_current_scope(identifier.clone(), expr.ty.clone());
+                self.expr(expr)
+            }
+            StmtEnum::Let(_, expr) | StmtEnum::VarAssign(_, expr) | StmtEnum::Expr(expr) => {
+                self.expr(expr)
+            }
+            StmtEnum::ArrayAssign(identifier, index, value) => {
+                let mut estimate = self.expr(index);
+                estimate.add_estimate(self.expr(value));
+                if !is_literal(index) {
+                    // every element is conditionally overwritten with the new value:
+                    let bits = match self.env.get(identifier) {
+                        Some(array_ty) => self.bits(&array_ty),
+                        None => self.bits(&value.ty),
+                    };
+                    estimate.add(8 * bits, 4 * bits);
+                }
+                estimate
+            }
+            StmtEnum::ForEachLoop(_, array, body) => {
+                let mut estimate = self.expr(array);
+                estimate.add_estimate(self.block(body).times(self.array_len(&array.ty)));
+                estimate
+            }
+            StmtEnum::JoinLoop(_, join_ty, (a, b), body) => {
+                let mut estimate = self.expr(a);
+                estimate.add_estimate(self.expr(b));
+                let n = self.array_len(&a.ty) + self.array_len(&b.ty);
+                // the join sorts both arrays using a bitonic sorter, comparing the join keys:
+                let log_n = log2(n);
+                let comparisons = n * log_n * (log_n + 1) / 4;
+                let key_bits = self.bits(join_ty);
+                let elem_bits = self.bits(&a.ty) / self.array_len(&a.ty).max(1);
+                estimate.add(
+                    comparisons * (12 * key_bits + 6 * elem_bits),
+                    comparisons * (6 * key_bits + 2 * elem_bits),
+                );
+                estimate.add_estimate(self.block(body).times(n));
+                estimate
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &TypedExpr) -> GateEstimate {
+        let mut estimate = GateEstimate::default();
+        match &expr.inner {
+            ExprEnum::True
+            | ExprEnum::False
+            | ExprEnum::NumUnsigned(_, _)
+            | ExprEnum::NumUnsignedBytes(_, _)
+            | ExprEnum::NumSigned(_, _)
+            | ExprEnum::NumFixed(_, _, _)
+            | ExprEnum::Char(_)
+            | ExprEnum::StringLiteral(_)
+            | ExprEnum::Identifier(_)
+            | ExprEnum::Range(_, _) => {}
+            ExprEnum::ArrayLiteral(elems) | ExprEnum::TupleLiteral(elems) => {
+                for elem in elems {
+                    estimate.add_estimate(self.expr(elem));
+                }
+            }
+            ExprEnum::ArrayRepeatLiteral(elem, _)
+            | ExprEnum::ArrayRepeatLiteralConst(elem, _)
+            | ExprEnum::ArraySlice(elem, _, _)
+            | ExprEnum::TupleAccess(elem, _)
+            | ExprEnum::StructAccess(elem, _)
+            | ExprEnum::Cast(_, elem) => estimate.add_estimate(self.expr(elem)),
+            ExprEnum::ArrayAccess(array, index) => {
+                estimate.add_estimate(self.expr(array));
+                estimate.add_estimate(self.expr(index));
+                if !is_literal(index) {
+                    // selecting an element from the array requires a mux for every bit:
+                    let bits = self.bits(&array.ty);
+                    estimate.add(8 * bits, 4 * bits);
+                }
+            }
+            ExprEnum::StructLiteral(_, fields) => {
+                for (_, field) in fields {
+                    estimate.add_estimate(self.expr(field));
+                }
+            }
+            ExprEnum::StructUpdate(_, fields, value) => {
+                estimate.add_estimate(self.expr(value));
+                for (_, field) in fields {
+                    estimate.add_estimate(self.expr(field));
+                }
+            }
+            ExprEnum::EnumLiteral(_, _, VariantExprEnum::Unit) => {}
+            ExprEnum::EnumLiteral(_, _, VariantExprEnum::Tuple(fields)) => {
+                for field in fields {
+                    estimate.add_estimate(self.expr(field));
+                }
+            }
+            ExprEnum::Match(value, clauses) => {
+                estimate.add_estimate(self.expr(value));
+                let value_bits = self.bits(&value.ty);
+                let bits = self.bits(&expr.ty);
+                for (_, clause) in clauses {
+                    // each clause compares the value against the pattern and muxes the result:
+                    estimate.add_estimate(self.expr(clause));
+                    estimate.add(3 * value_bits + 3 * bits, value_bits + bits);
+                }
+            }
+            ExprEnum::UnaryOp(op, x) => {
+                estimate.add_estimate(self.expr(x));
+                let n = self.bits(&x.ty);
+                match op {
+                    UnaryOp::Not => estimate.add(n, 0),
+                    UnaryOp::Neg => estimate.add(7 * n, 3 * n),
+                }
+            }
+            ExprEnum::Op(op, x, y) => {
+                estimate.add_estimate(self.expr(x));
+                estimate.add_estimate(self.expr(y));
+                let n = self.bits(&x.ty);
+                match op {
+                    Op::Add => estimate.add(7 * n, 3 * n),
+                    Op::Sub => estimate.add(10 * n, 4 * n),
+                    // fixed-point numbers are multiplied at double width:
+                    Op::Mul if matches!(x.ty, Type::Fixed(_, _)) => {
+                        estimate.add(32 * n * n, 16 * n * n)
+                    }
+                    Op::Mul => estimate.add(8 * n * n, 4 * n * n),
+                    // the saturating ops mux each bit of the result with the saturation bound:
+                    Op::SaturatingAdd => estimate.add(10 * n, 4 * n),
+                    Op::SaturatingSub => estimate.add(13 * n, 5 * n),
+                    Op::SaturatingMul => estimate.add(8 * n * n + 3 * n, 4 * n * n + n),
+                    Op::Div | Op::Mod => estimate.add(6 * n * n, 3 * n * n),
+                    Op::GreaterThan | Op::LessThan => estimate.add(12 * n, 6 * n),
+                    Op::Eq | Op::NotEq => estimate.add(3 * n, n),
+                    Op::BitAnd => estimate.add(n, n),
+                    Op::BitXor => estimate.add(n, 0),
+                    Op::BitOr => estimate.add(3 * n, n),
+                    // every layer muxes all bits, shifts always use one layer for each of the 8
+                    // bits of the shift amount and check the unused bits for an overflow:
+                    Op::ShiftLeft | Op::ShiftRight => {
+                        let unused_bits = 8usize.saturating_sub(log2(n));
+                        estimate.add(4 * n * 8 + 3 * unused_bits, 2 * n * 8 + unused_bits)
+                    }
+                    Op::RotateRight | Op::RotateLeft => {
+                        estimate.add(4 * n * log2(n), 2 * n * log2(n))
+                    }
+                    Op::ShortCircuitAnd => estimate.add(1, 1),
+                    Op::ShortCircuitOr => estimate.add(3, 1),
+                }
+            }
+            ExprEnum::Block(stmts) => estimate.add_estimate(self.block(stmts)),
+            ExprEnum::FnCall(identifier, args) => {
+                for arg in args {
+                    estimate.add_estimate(self.expr(arg));
+                }
+                let fn_def = self.prg.fn_defs.get(identifier).unwrap();
+                estimate.add_estimate(self.fn_body(fn_def));
+            }
+            ExprEnum::BuiltinFnCall(identifier, args) => {
+                for arg in args {
+                    estimate.add_estimate(self.expr(arg));
+                }
+                estimate.add_estimate(self.builtin_fn_call(identifier, args));
+            }
+            ExprEnum::If(condition, case_true, case_false) => {
+                estimate.add_estimate(self.expr(condition));
+                estimate.add_estimate(self.expr(case_true));
+                estimate.add_estimate(self.expr(case_false));
+                let bits = self.bits(&expr.ty);
+                estimate.add(3 * bits, bits);
+            }
+        }
+        estimate
+    }
+
+    fn builtin_fn_call(&self, identifier: &str, args: &[TypedExpr]) -> GateEstimate {
+        let mut estimate = GateEstimate::default();
+        let Some(arg) = args.first() else {
+            return estimate;
+        };
+        let n = self.bits(&arg.ty);
+        // the number of elements and the size of each element if the first arg is an array:
+        let len = self.array_len(&arg.ty);
+        let elem = n / len.max(1);
+        match identifier {
+            // builtins that only rearrange wires:
+            "tuple_to_array" | "array_to_tuple" | "gather_bits" | "reverse" | "rotate" | "get" => {}
+            "all" => estimate.add(n, n),
+            "any" => estimate.add(3 * n, n),
+            // carry-save adders with a single AND each, followed by a single addition:
+            "sum" => {
+                let width = elem + log2(len);
+                estimate.add(5 * len * width + 7 * width, len * width + 3 * width);
+            }
+            "dot" => {
+                let width = 2 * elem + log2(len);
+                let partial_products = len * elem * elem;
+                estimate.add(
+                    6 * partial_products + 7 * width,
+                    2 * partial_products + 3 * width,
+                );
+            }
+            "product" => {
+                let multiplications = len.saturating_sub(1);
+                estimate.add(
+                    8 * multiplications * elem * elem,
+                    4 * multiplications * elem * elem,
+                );
+            }
+            "prefix_sum" => {
+                // the up- and down-sweep each need fewer than len additions:
+                let width = elem + log2(len);
+                estimate.add(2 * len * 7 * width, 2 * len * 3 * width);
+            }
+            "mean" => {
+                // the sum is divided by a constant, which is much cheaper than a division by a variable:
+                let width = elem + log2(len) + 1;
+                let additions = len.saturating_sub(1);
+                estimate.add(
+                    additions * 7 * width + width * width,
+                    additions * 3 * width + width * width / 2,
+                );
+            }
+            "permute" => {
+                // a bitonic sorter, comparing the target positions (plus a padding bit):
+                let n = len.next_power_of_two();
+                let log_n = log2(n);
+                let comparisons = n * log_n * (log_n + 1) / 4;
+                let key_bits = 1 + self.bits(&Type::Unsigned(UnsignedNumType::Usize));
+                estimate.add(
+                    comparisons * (12 * key_bits + 6 * elem),
+                    comparisons * (6 * key_bits + 2 * elem),
+                );
+            }
+            "get_or" => {
+                // an array access, checking the index against the length to select the default:
+                let index_bits = args.get(1).map_or(0, |index| self.bits(&index.ty));
+                estimate.add(
+                    8 * n + 12 * index_bits + 4 * elem,
+                    4 * n + 6 * index_bits + 2 * elem,
+                );
+            }
+            "lookup_table" => {
+                // an array access for every index:
+                let indexes = args.get(1).map_or(0, |indexes| self.array_len(&indexes.ty));
+                estimate.add(8 * n * indexes, 4 * n * indexes);
+            }
+            "histogram" => {
+                // every value is compared against every (constant) bucket and the count incremented:
+                let buckets = match args.get(1).map(|buckets| &buckets.inner) {
+                    Some(ExprEnum::NumUnsigned(buckets, _)) => *buckets as usize,
+                    _ => 1,
+                };
+                // the counts start as constants, so only the bits needed for len are added:
+                let count_bits = log2(len + 1);
+                let comparisons = len * buckets;
+                estimate.add(
+                    comparisons * (elem + 2 * count_bits),
+                    comparisons * (elem + count_bits),
+                );
+            }
+            "divmod" => estimate.add(6 * n * n, 3 * n * n),
+            "one_hot" => {
+                let consts = args.get(1).map_or(0, |consts| self.array_len(&consts.ty));
+                estimate.add(consts * 3 * n, consts * n);
+            }
+            "rotate_left" | "rotate_right" => estimate.add(4 * n * log2(n), 2 * n * log2(n)),
+            // a tree of additions, for the zeros after computing a prefix-OR of the bits:
+            "count_ones" => estimate.add(7 * n, 3 * n),
+            "leading_zeros" | "trailing_zeros" => estimate.add(11 * n, 4 * n),
+            // a single comparison, muxing each bit of the result:
+            "min" | "max" => estimate.add(12 * n + 4 * n, 6 * n + 2 * n),
+            "wrapping_add" => estimate.add(7 * n, 3 * n),
+            "wrapping_sub" => estimate.add(10 * n, 4 * n),
+            // the upper half of the product is only needed to detect overflows:
+            "wrapping_mul" => estimate.add(4 * n * n, 2 * n * n),
+            "assert" => estimate.add(1, 0),
+            "assert_eq" => estimate.add(3 * n + 1, n),
+            _ => {}
+        }
+        estimate
+    }
+}
+
+fn is_literal(expr: &TypedExpr) -> bool {
+    matches!(
+        expr.inner,
+        ExprEnum::NumUnsigned(_, _) | ExprEnum::NumUnsignedBytes(_, _) | ExprEnum::NumSigned(_, _)
+    )
+}
+
+fn log2(n: usize) -> usize {
+    let mut bits = 0;
+    while (1 << bits) < n {
+        bits += 1;
+    }
+    bits
+}
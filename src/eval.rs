@@ -5,7 +5,7 @@ use std::{collections::HashMap, fmt::Debug};
 use crate::{
     ast::Type,
     circuit::{Circuit, EvalPanic, USIZE_BITS},
-    compile::{signed_to_bits, unsigned_to_bits},
+    compile::{bits_as_be_bytes, signed_to_bits, unsigned_to_bits},
     literal::Literal,
     token::{SignedNumType, UnsignedNumType},
     CompileTimeError, TypedFnDef, TypedProgram,
@@ -52,8 +52,17 @@ pub enum EvalError {
     LiteralParseError(CompileTimeError),
     /// The circuit does not have an input argument with the given index.
     InvalidArgIndex(usize),
+    /// The number of literals does not match the parameters of the party.
+    UnexpectedNumberOfPartyValues {
+        /// The index of the party.
+        party: usize,
+        /// The expected number of literals.
+        expected: usize,
+        /// The actual number of literals.
+        actual: usize,
+    },
     /// The literal is not of the expected parameter type.
-    InvalidLiteralType(Literal, Type),
+    InvalidLiteralType(Box<Literal>, Type),
     /// The number of output bits does not match the expected type.
     OutputTypeMismatch {
         /// The expected output type.
@@ -63,6 +72,17 @@ pub enum EvalError {
     },
     /// The evaluation panicked, for example due to an integer overflow or div by zero.
     Panic(EvalPanic),
+    /// The evaluation panicked, but the reason of the panic is not a known panic reason, which
+    /// can only happen for circuits that were not compiled from a Garble program.
+    InvalidPanicReason(usize),
+    /// The output is not revealed to the party with the given index.
+    OutputNotRevealed(usize),
+    /// An `f64` was used as a fixed-point number, but the parameter or output has another type.
+    ExpectedFixedType(Type),
+    /// The `f64` is NaN or out of range for the fixed-point parameter type.
+    FixedOutOfRange(f64, Type),
+    /// An unsigned number was requested, but the output has another type.
+    ExpectedUnsignedType(Type),
 }
 
 impl std::error::Error for EvalError {}
@@ -80,6 +100,13 @@ impl std::fmt::Display for EvalError {
             EvalError::InvalidArgIndex(i) => {
                 f.write_fmt(format_args!("The circuit does not an input argument with index {i}"))
             }
+            EvalError::UnexpectedNumberOfPartyValues {
+                party,
+                expected,
+                actual,
+            } => f.write_fmt(format_args!(
+                "Expected {expected} literal(s) for party {party}, but found {actual}"
+            )),
             EvalError::InvalidLiteralType(literal, ty) => {
                 f.write_fmt(format_args!("The argument literal is not of type {ty}: '{literal}'"))
             }
@@ -92,6 +119,21 @@ impl std::fmt::Display for EvalError {
             EvalError::Panic(p) => {
                 p.fmt(f)
             }
+            EvalError::InvalidPanicReason(reason) => {
+                f.write_fmt(format_args!("The circuit panicked with an invalid panic reason: {reason}"))
+            }
+            EvalError::OutputNotRevealed(party) => {
+                f.write_fmt(format_args!("The output is not revealed to party {party}"))
+            }
+            EvalError::ExpectedFixedType(ty) => {
+                f.write_fmt(format_args!("Expected a fixed-point type, but found {ty}"))
+            }
+            EvalError::FixedOutOfRange(n, ty) => {
+                f.write_fmt(format_args!("The number {n} is out of range for {ty}"))
+            }
+            EvalError::ExpectedUnsignedType(ty) => {
+                f.write_fmt(format_args!("Expected an unsigned number type, but found {ty}"))
+            }
         }
     }
 }
@@ -187,6 +229,21 @@ impl<'a> Evaluator<'a> {
         signed_to_bits(n, 64, inputs);
     }
 
+    /// Encodes an f64 as a number of the party's fixed-point parameter type and sets it as the
+    /// party's input, rounding as described in [`Literal::from_f64`].
+    pub fn set_f64(&mut self, n: f64) -> Result<(), EvalError> {
+        let Some(param) = self.main_fn.params.get(self.inputs.len()) else {
+            return Err(EvalError::UnexpectedNumberOfParties);
+        };
+        let Type::Fixed(bits, frac) = param.ty else {
+            return Err(EvalError::ExpectedFixedType(param.ty.clone()));
+        };
+        match Literal::from_f64(n, bits, frac) {
+            Some(literal) => self.set_literal(literal),
+            None => Err(EvalError::FixedOutOfRange(n, param.ty.clone())),
+        }
+    }
+
     /// Encodes a literal (with enums looked up in the program) and sets it as the party's input.
     pub fn set_literal(&mut self, literal: Literal) -> Result<(), EvalError> {
         if self.inputs.len() < self.main_fn.params.len() {
@@ -200,13 +257,29 @@ impl<'a> Evaluator<'a> {
                     .extend(literal.as_bits(self.program, self.const_sizes));
                 Ok(())
             } else {
-                Err(EvalError::InvalidLiteralType(literal, ty.clone()))
+                Err(EvalError::InvalidLiteralType(Box::new(literal), ty.clone()))
             }
         } else {
             Err(EvalError::UnexpectedNumberOfParties)
         }
     }
 
+    /// Sets already encoded bits as the party's input, for example bits produced by
+    /// [`Literal::as_bits`] on another machine.
+    ///
+    /// Only the number of bits is checked, the bits are otherwise fed into the circuit as-is.
+    pub fn set_bits(&mut self, bits: &[bool]) -> Result<(), EvalError> {
+        let party = self.inputs.len();
+        match self.circuit.input_gates.get(party) {
+            Some(&expected) if expected == bits.len() => {
+                self.inputs.push(bits.to_vec());
+                Ok(())
+            }
+            Some(_) => Err(EvalError::UnexpectedNumberOfInputsFromParty(party)),
+            None => Err(EvalError::UnexpectedNumberOfParties),
+        }
+    }
+
     /// Parses a literal (with enums looked up in the program) and sets it as the party's input.
     pub fn parse_literal(&mut self, literal: &str) -> Result<(), EvalError> {
         if self.inputs.len() < self.main_fn.params.len() {
@@ -359,14 +432,23 @@ impl<'a> TryFrom<EvalOutput<'a>> for i64 {
     }
 }
 
+impl<'a> TryFrom<EvalOutput<'a>> for f64 {
+    type Error = EvalError;
+
+    fn try_from(value: EvalOutput) -> Result<Self, Self::Error> {
+        let ty = value.main_fn.ty.clone();
+        value
+            .into_literal()?
+            .as_f64()
+            .ok_or(EvalError::ExpectedFixedType(ty))
+    }
+}
+
 impl<'a> TryFrom<EvalOutput<'a>> for Vec<bool> {
     type Error = EvalError;
 
     fn try_from(value: EvalOutput) -> Result<Self, Self::Error> {
-        match EvalPanic::parse(&value.output) {
-            Ok(output) => Ok(output.to_vec()),
-            Err(panic) => Err(EvalError::Panic(panic)),
-        }
+        EvalPanic::parse(&value.output).map(|output| output.to_vec())
     }
 }
 
@@ -410,6 +492,25 @@ impl<'a> EvalOutput<'a> {
         }
     }
 
+    /// Decodes an unsigned number of any size (such as a `u256`) as big-endian bytes, using as
+    /// many bytes as needed for the bits of the number type.
+    pub fn get_biguint(&self) -> Result<Vec<u8>, EvalError> {
+        let ty = &self.main_fn.ty;
+        let Type::Unsigned(_) = ty else {
+            return Err(EvalError::ExpectedUnsignedType(ty.clone()));
+        };
+        let output = EvalPanic::parse(&self.output)?;
+        let size = ty.size_in_bits_for_defs(self.program, &self.const_sizes);
+        if output.len() == size {
+            Ok(bits_as_be_bytes(output))
+        } else {
+            Err(EvalError::OutputTypeMismatch {
+                expected: ty.clone(),
+                actual_bits: output.len(),
+            })
+        }
+    }
+
     /// Decodes the evaluated result as a literal (with enums looked up in the program).
     pub fn into_literal(self) -> Result<Literal, EvalError> {
         let ret_ty = &self.main_fn.ty;
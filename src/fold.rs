@@ -0,0 +1,259 @@
+//! Folds operations on constants in a [`crate::ast::Program`] before it is compiled.
+
+use crate::{
+    ast::{ExprEnum, Op, StmtEnum, Type, UnaryOp, VariantExprEnum},
+    literal::Literal,
+    token::{SignedNumType, UnsignedNumType},
+    TypedExpr, TypedProgram, TypedStmt,
+};
+
+impl TypedProgram {
+    /// Replaces all operations whose operands are literals (as well as casts of literals and `if`
+    /// expressions with a literal condition) with their result, so that no gates need to be
+    /// generated for them.
+    ///
+    /// Operations that would panic at runtime, such as overflowing additions or divisions by zero,
+    /// are left unchanged, so that the compiled circuit still reports the panic.
+    ///
+    /// Constants are always folded when a program is compiled, so there is no need to call this
+    /// method before compiling.
+    pub fn fold_constants(mut self) -> TypedProgram {
+        for fn_def in self.fn_defs.values_mut() {
+            fold_block(&mut fn_def.body);
+        }
+        self
+    }
+}
+
+fn fold_block(stmts: &mut [TypedStmt]) {
+    for stmt in stmts {
+        match &mut stmt.inner {
+            StmtEnum::Let(_, expr)
+            | StmtEnum::LetMut(_, expr)
+            | StmtEnum::VarAssign(_, expr)
+            | StmtEnum::Expr(expr) => fold_expr(expr),
+            StmtEnum::ArrayAssign(_, index, value) => {
+                fold_expr(index);
+                fold_expr(value);
+            }
+            StmtEnum::ForEachLoop(_, array, body) => {
+                fold_expr(array);
+                fold_block(body);
+            }
+            StmtEnum::JoinLoop(_, _, (a, b), body) => {
+                fold_expr(a);
+                fold_expr(b);
+                fold_block(body);
+            }
+        }
+    }
+}
+
+fn fold_expr(expr: &mut TypedExpr) {
+    match &mut expr.inner {
+        ExprEnum::True
+        | ExprEnum::False
+        | ExprEnum::NumUnsigned(_, _)
+        | ExprEnum::NumUnsignedBytes(_, _)
+        | ExprEnum::NumSigned(_, _)
+        | ExprEnum::NumFixed(_, _, _)
+        | ExprEnum::Char(_)
+        | ExprEnum::StringLiteral(_)
+        | ExprEnum::Identifier(_)
+        | ExprEnum::Range(_, _)
+        | ExprEnum::EnumLiteral(_, _, VariantExprEnum::Unit) => {}
+        ExprEnum::ArrayLiteral(elems)
+        | ExprEnum::TupleLiteral(elems)
+        | ExprEnum::FnCall(_, elems)
+        | ExprEnum::BuiltinFnCall(_, elems)
+        | ExprEnum::EnumLiteral(_, _, VariantExprEnum::Tuple(elems)) => {
+            for elem in elems {
+                fold_expr(elem);
+            }
+        }
+        ExprEnum::ArrayRepeatLiteral(elem, _)
+        | ExprEnum::ArrayRepeatLiteralConst(elem, _)
+        | ExprEnum::ArraySlice(elem, _, _)
+        | ExprEnum::TupleAccess(elem, _)
+        | ExprEnum::StructAccess(elem, _)
+        | ExprEnum::UnaryOp(_, elem)
+        | ExprEnum::Cast(_, elem) => fold_expr(elem),
+        ExprEnum::ArrayAccess(x, y) | ExprEnum::Op(_, x, y) => {
+            fold_expr(x);
+            fold_expr(y);
+        }
+        ExprEnum::StructLiteral(_, fields) => {
+            for (_, field) in fields {
+                fold_expr(field);
+            }
+        }
+        ExprEnum::StructUpdate(_, fields, value) => {
+            fold_expr(value);
+            for (_, field) in fields {
+                fold_expr(field);
+            }
+        }
+        ExprEnum::Match(value, clauses) => {
+            fold_expr(value);
+            for (_, clause) in clauses {
+                fold_expr(clause);
+            }
+        }
+        ExprEnum::Block(stmts) => fold_block(stmts),
+        ExprEnum::If(condition, case_true, case_false) => {
+            fold_expr(condition);
+            fold_expr(case_true);
+            fold_expr(case_false);
+            // the branch that is not taken cannot panic, so it can be dropped entirely:
+            let taken = match condition.inner {
+                ExprEnum::True => case_true,
+                ExprEnum::False => case_false,
+                _ => return,
+            };
+            *expr = (**taken).clone();
+            return;
+        }
+    }
+    let folded = match &expr.inner {
+        ExprEnum::UnaryOp(op, x) => fold_unary_op(*op, x, &expr.ty),
+        ExprEnum::Op(op, x, y) => fold_op(*op, x, y, &expr.ty),
+        ExprEnum::Cast(ty, x) => fold_cast(ty, x),
+        _ => None,
+    };
+    if let Some(folded) = folded {
+        expr.inner = folded;
+    }
+}
+
+/// A literal operand, with numbers of all types represented as `i128`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Const {
+    Bool(bool),
+    Num(i128),
+}
+
+fn as_const(expr: &TypedExpr) -> Option<Const> {
+    match expr.inner {
+        ExprEnum::True => Some(Const::Bool(true)),
+        ExprEnum::False => Some(Const::Bool(false)),
+        ExprEnum::NumUnsigned(n, _) => Some(Const::Num(n as i128)),
+        ExprEnum::NumSigned(n, _) => Some(Const::Num(n as i128)),
+        _ => None,
+    }
+}
+
+/// Returns the literal of the given type, or `None` if the number does not fit into the type.
+fn from_const(c: Const, ty: &Type) -> Option<ExprEnum<Type>> {
+    match (c, ty) {
+        (Const::Bool(true), Type::Bool) => Some(ExprEnum::True),
+        (Const::Bool(false), Type::Bool) => Some(ExprEnum::False),
+        (Const::Num(n), Type::Unsigned(ty)) if n >= 0 && n <= ty.max()? as i128 => {
+            Some(ExprEnum::NumUnsigned(n as u64, *ty))
+        }
+        (Const::Num(n), Type::Signed(ty)) if n >= ty.min()? as i128 && n <= ty.max()? as i128 => {
+            Some(ExprEnum::NumSigned(n as i64, *ty))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the number of bits of a number type with a known size.
+fn num_bits(ty: &Type) -> Option<u32> {
+    match ty {
+        Type::Unsigned(UnsignedNumType::Unspecified) | Type::Signed(SignedNumType::Unspecified) => {
+            None
+        }
+        Type::Unsigned(ty) => Some(128 - (ty.max()? as i128).leading_zeros()),
+        Type::Signed(ty) => Some(128 - (ty.max()? as i128).leading_zeros() + 1),
+        _ => None,
+    }
+}
+
+fn is_sized_primitive(ty: &Type) -> bool {
+    ty == &Type::Bool || num_bits(ty).is_some()
+}
+
+/// Clamps the number to the minimum or maximum of the type, like a saturating op in a circuit.
+fn saturate(n: i128, ty: &Type) -> Option<i128> {
+    match ty {
+        Type::Unsigned(ty) => Some(n.clamp(0, ty.max()? as i128)),
+        Type::Signed(ty) => Some(n.clamp(ty.min()? as i128, ty.max()? as i128)),
+        _ => None,
+    }
+}
+
+/// Truncates the number to the bits of the type, like a number that is shifted in a circuit.
+fn wrap(n: i128, ty: &Type) -> Option<i128> {
+    let shift = 128 - num_bits(ty)?;
+    match ty {
+        Type::Signed(_) => Some((n << shift) >> shift),
+        _ => Some(((n << shift) as u128 >> shift) as i128),
+    }
+}
+
+fn fold_unary_op(op: UnaryOp, x: &TypedExpr, ty: &Type) -> Option<ExprEnum<Type>> {
+    let result = match (op, as_const(x)?) {
+        (UnaryOp::Not, Const::Bool(b)) => Const::Bool(!b),
+        (UnaryOp::Not, Const::Num(n)) => Const::Num(wrap(!n, ty)?),
+        (UnaryOp::Neg, Const::Num(n)) => Const::Num(-n),
+        (UnaryOp::Neg, Const::Bool(_)) => return None,
+    };
+    from_const(result, ty)
+}
+
+fn fold_op(op: Op, x: &TypedExpr, y: &TypedExpr, ty: &Type) -> Option<ExprEnum<Type>> {
+    if !is_sized_primitive(&x.ty) {
+        return None;
+    }
+    let result = match (op, as_const(x)?, as_const(y)?) {
+        (Op::Add, Const::Num(x), Const::Num(y)) => Const::Num(x + y),
+        (Op::Sub, Const::Num(x), Const::Num(y)) => Const::Num(x - y),
+        (Op::Mul, Const::Num(x), Const::Num(y)) => Const::Num(x.checked_mul(y)?),
+        (Op::SaturatingAdd, Const::Num(x), Const::Num(y)) => Const::Num(saturate(x + y, ty)?),
+        (Op::SaturatingSub, Const::Num(x), Const::Num(y)) => Const::Num(saturate(x - y, ty)?),
+        (Op::SaturatingMul, Const::Num(x), Const::Num(y)) => {
+            Const::Num(saturate(x.checked_mul(y)?, ty)?)
+        }
+        (Op::Div, Const::Num(x), Const::Num(y)) => Const::Num(x.checked_div(y)?),
+        (Op::Mod, Const::Num(x), Const::Num(y)) => Const::Num(x.checked_rem(y)?),
+        (Op::BitAnd, Const::Num(x), Const::Num(y)) => Const::Num(x & y),
+        (Op::BitOr, Const::Num(x), Const::Num(y)) => Const::Num(x | y),
+        (Op::BitXor, Const::Num(x), Const::Num(y)) => Const::Num(x ^ y),
+        (Op::BitAnd | Op::ShortCircuitAnd, Const::Bool(x), Const::Bool(y)) => Const::Bool(x & y),
+        (Op::BitOr | Op::ShortCircuitOr, Const::Bool(x), Const::Bool(y)) => Const::Bool(x | y),
+        (Op::BitXor, Const::Bool(x), Const::Bool(y)) => Const::Bool(x ^ y),
+        (Op::GreaterThan, Const::Num(x), Const::Num(y)) => Const::Bool(x > y),
+        (Op::LessThan, Const::Num(x), Const::Num(y)) => Const::Bool(x < y),
+        (Op::Eq, x, y) => Const::Bool(x == y),
+        (Op::NotEq, x, y) => Const::Bool(x != y),
+        // shifting by the bit width (or more) panics:
+        (Op::ShiftLeft, Const::Num(x), Const::Num(y)) if y < num_bits(ty)? as i128 => {
+            Const::Num(wrap(x << y, ty)?)
+        }
+        (Op::ShiftRight, Const::Num(x), Const::Num(y)) if y < num_bits(ty)? as i128 => {
+            Const::Num(x >> y)
+        }
+        _ => return None,
+    };
+    from_const(result, ty)
+}
+
+fn fold_cast(ty: &Type, x: &TypedExpr) -> Option<ExprEnum<Type>> {
+    if !is_sized_primitive(ty) || !is_sized_primitive(&x.ty) {
+        return None;
+    }
+    let literal = match x.inner {
+        ExprEnum::True => Literal::True,
+        ExprEnum::False => Literal::False,
+        ExprEnum::NumUnsigned(n, ty) => Literal::NumUnsigned(n, ty),
+        ExprEnum::NumSigned(n, ty) => Literal::NumSigned(n, ty),
+        _ => return None,
+    };
+    match literal.cast(ty).ok()? {
+        Literal::True => Some(ExprEnum::True),
+        Literal::False => Some(ExprEnum::False),
+        Literal::NumUnsigned(n, ty) => Some(ExprEnum::NumUnsigned(n, ty)),
+        Literal::NumSigned(n, ty) => Some(ExprEnum::NumSigned(n, ty)),
+        _ => None,
+    }
+}
@@ -43,7 +43,7 @@
 
 use ast::{Expr, FnDef, Pattern, Program, Stmt, Type};
 use check::TypeError;
-use circuit::Circuit;
+use circuit::{Circuit, PanicReason};
 use compile::CompilerError;
 use eval::{resolve_const_type, EvalError, Evaluator};
 use literal::Literal;
@@ -85,9 +85,12 @@ pub mod check;
 pub mod circuit;
 pub mod compile;
 pub mod env;
+pub mod estimate;
 pub mod eval;
+pub mod fold;
 pub mod literal;
 pub mod parse;
+pub mod pretty;
 pub mod scan;
 pub mod token;
 
@@ -96,17 +99,50 @@ pub fn check(prg: &str) -> Result<TypedProgram, Error> {
     Ok(scan(prg)?.parse()?.type_check()?)
 }
 
+/// Scans, parses and type-checks a program in strict mode, see
+/// [`crate::ast::Program::type_check_strict`].
+pub fn check_strict(prg: &str) -> Result<TypedProgram, Error> {
+    Ok(scan(prg)?.parse()?.type_check_strict()?)
+}
+
+/// Scans, parses and type-checks a program, returning the typed AST together with the diagnostics
+/// of strict mode, for tools such as IDE integrations that need the inferred types.
+///
+/// Errors that prevent the program from being type-checked are returned as an error, whereas
+/// the errors that are only reported in strict mode (see
+/// [`crate::ast::Program::type_check_strict`]) are returned as diagnostics of the typed AST.
+pub fn analyze(prg: &str) -> Result<AnalyzedProgram, Error> {
+    let (program, diagnostics) = scan(prg)?.parse()?.type_check_with_diagnostics(true);
+    let program = program?;
+    Ok(AnalyzedProgram {
+        program,
+        diagnostics,
+    })
+}
+
+/// The result of analyzing a Garble program, see [`crate::analyze`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AnalyzedProgram {
+    /// The type-checked representation of the full program.
+    pub program: TypedProgram,
+    /// Likely logic bugs that do not prevent the program from being compiled.
+    pub diagnostics: Vec<TypeError>,
+}
+
 /// Scans, parses, type-checks and then compiles the `"main"` fn of a program to a boolean circuit.
 pub fn compile(prg: &str) -> Result<GarbleProgram, Error> {
     let program = check(prg)?;
-    let (circuit, main) = program.compile("main")?;
+    let ((circuit, main, const_sizes), panic_sites) =
+        program.compile_with_panic_sites("main", HashMap::new())?;
     let main = main.clone();
     Ok(GarbleProgram {
         program,
         main,
         circuit,
         consts: HashMap::new(),
-        const_sizes: HashMap::new(),
+        const_sizes,
+        panic_sites,
     })
 }
 
@@ -116,7 +152,8 @@ pub fn compile_with_constants(
     consts: HashMap<String, HashMap<String, Literal>>,
 ) -> Result<GarbleProgram, Error> {
     let program = check(prg)?;
-    let (circuit, main, const_sizes) = program.compile_with_constants("main", consts.clone())?;
+    let ((circuit, main, const_sizes), panic_sites) =
+        program.compile_with_panic_sites("main", consts.clone())?;
     let main = main.clone();
     Ok(GarbleProgram {
         program,
@@ -124,6 +161,7 @@ pub fn compile_with_constants(
         circuit,
         consts,
         const_sizes,
+        panic_sites,
     })
 }
 
@@ -141,6 +179,8 @@ pub struct GarbleProgram {
     pub consts: HashMap<String, HashMap<String, Literal>>,
     /// The values of usize constants used for compiling the circuit.
     pub const_sizes: HashMap<String, usize>,
+    /// All the source locations that might cause the circuit to panic, with the reason.
+    pub panic_sites: Vec<(PanicReason, MetaInfo)>,
 }
 
 /// An input argument for a Garble program and circuit.
@@ -164,7 +204,7 @@ impl GarbleProgram {
         };
         let ty = resolve_const_type(&param.ty, &self.const_sizes);
         if !literal.is_of_type(&self.program, &ty) {
-            return Err(EvalError::InvalidLiteralType(literal, ty));
+            return Err(EvalError::InvalidLiteralType(Box::new(literal), ty));
         }
         Ok(GarbleArgument(literal, &self.program, &self.const_sizes))
     }
@@ -183,10 +223,82 @@ impl GarbleProgram {
         Ok(GarbleArgument(literal, &self.program, &self.const_sizes))
     }
 
+    /// Encodes the literals as the exact input bits of the party with the given index, validating
+    /// the number and types of the literals.
+    ///
+    /// Each party provides the argument of exactly one parameter of the `"main"` fn. If the
+    /// parameter is a tuple, `values` must contain one literal for each of the tuple's fields (in
+    /// declaration order), otherwise exactly one literal. The bits of all literals are
+    /// concatenated.
+    pub fn encode_party_input(
+        &self,
+        party: usize,
+        values: &[Literal],
+    ) -> Result<Vec<bool>, EvalError> {
+        let Some(param) = self.main.params.get(party) else {
+            return Err(EvalError::InvalidArgIndex(party));
+        };
+        let ty = resolve_const_type(&param.ty, &self.const_sizes);
+        let tys = match ty {
+            Type::Tuple(fields) => fields,
+            ty => vec![ty],
+        };
+        if values.len() != tys.len() {
+            return Err(EvalError::UnexpectedNumberOfPartyValues {
+                party,
+                expected: tys.len(),
+                actual: values.len(),
+            });
+        }
+        let mut bits = vec![];
+        for (literal, ty) in values.iter().zip(tys) {
+            if !literal.is_of_type(&self.program, &ty) {
+                return Err(EvalError::InvalidLiteralType(Box::new(literal.clone()), ty));
+            }
+            bits.extend(literal.as_bits(&self.program, &self.const_sizes));
+        }
+        Ok(bits)
+    }
+
     /// Tries to convert the circuit output back to a Garble literal.
     pub fn parse_output(&self, bits: &[bool]) -> Result<Literal, EvalError> {
         Literal::from_result_bits(&self.program, &self.main.ty, bits, &self.const_sizes)
     }
+
+    /// Returns the indices of the parties that receive the output of the circuit.
+    ///
+    /// The output is revealed to all parties, unless `main` is annotated with `#[reveal(param)]`,
+    /// in which case only the party providing the input `param` receives the output.
+    pub fn output_parties(&self) -> Vec<usize> {
+        match &self.main.reveal_to {
+            Some((party, _)) => self
+                .main
+                .params
+                .iter()
+                .position(|param| &param.name == party)
+                .into_iter()
+                .collect(),
+            None => (0..self.main.params.len()).collect(),
+        }
+    }
+
+    /// Tries to convert the circuit output back to a Garble literal on behalf of the given party,
+    /// failing if the output is not revealed to the party.
+    pub fn parse_output_for_party(
+        &self,
+        party: usize,
+        bits: &[bool],
+    ) -> Result<Literal, EvalError> {
+        if !self.output_parties().contains(&party) {
+            return Err(EvalError::OutputNotRevealed(party));
+        }
+        self.parse_output(bits)
+    }
+
+    /// Returns all the source locations that might cause the circuit to panic, with the reason.
+    pub fn panic_sites(&self) -> &[(PanicReason, MetaInfo)] {
+        &self.panic_sites
+    }
 }
 
 impl GarbleArgument<'_> {
@@ -274,9 +386,14 @@ impl EvalError {
             EvalError::Panic(panic) => {
                 let mut msg = "".to_string();
                 let meta = panic.panicked_at;
+                let code = if panic.code != 0 {
+                    format!(" (code {})", panic.code)
+                } else {
+                    "".to_string()
+                };
                 writeln!(
                     msg,
-                    "Panic due to {} on line {}:{}.\n",
+                    "Panic due to {}{code} on line {}:{}.\n",
                     panic.reason,
                     meta.start.0 + 1,
                     meta.start.1 + 1
@@ -326,9 +443,10 @@ impl CompileTimeError {
             CompileTimeError::CompilerError(errs) => {
                 for e in errs {
                     match e {
-                        CompilerError::MissingConstant(_, _, meta) => {
-                            errs_for_display.push(("Compiler error", format!("{e}"), Some(*meta)))
-                        }
+                        CompilerError::MissingConstant(_, _, meta)
+                        | CompilerError::AndBudgetExceeded {
+                            reached_at: meta, ..
+                        } => errs_for_display.push(("Compiler error", format!("{e}"), Some(*meta))),
                         e => errs_for_display.push(("Compiler error", format!("{e}"), None)),
                     }
                 }
@@ -357,6 +475,35 @@ impl CompileTimeError {
     }
 }
 
+impl ScanError {
+    /// Returns a human-readable error description, showing where the error occurred in the source.
+    pub fn prettify(&self, prg: &str) -> String {
+        CompileTimeError::ScanErrors(vec![self.clone()]).prettify(prg)
+    }
+}
+
+impl ParseError {
+    /// Returns a human-readable error description, showing where the error occurred in the source.
+    pub fn prettify(&self, prg: &str) -> String {
+        CompileTimeError::ParseError(vec![self.clone()]).prettify(prg)
+    }
+}
+
+impl TypeError {
+    /// Returns a human-readable error description, showing where the error occurred in the source.
+    pub fn prettify(&self, prg: &str) -> String {
+        CompileTimeError::TypeError(vec![self.clone()]).prettify(prg)
+    }
+}
+
+impl CompilerError {
+    /// Returns a human-readable error description, showing where the error occurred in the source
+    /// (if the error can be attributed to a location).
+    pub fn prettify(&self, prg: &str) -> String {
+        CompileTimeError::CompilerError(vec![self.clone()]).prettify(prg)
+    }
+}
+
 fn prettify_meta(prg: &str, meta: MetaInfo) -> String {
     let mut msg = "".to_string();
     if prg.is_empty() {
@@ -11,13 +11,16 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     ast::{Expr, ExprEnum, Type, Variant, VariantExprEnum},
-    check::{check_type, Defs, TopLevelTypes, TypeError, TypedFns},
-    circuit::EvalPanic,
-    compile::{enum_max_size, enum_tag_number, enum_tag_size, signed_to_bits, unsigned_to_bits},
+    check::{check_type, usize_const_sizes, Defs, TopLevelTypes, TypeError, TypedFns},
+    circuit::{EvalPanic, GateIndex, USIZE_BITS},
+    compile::{
+        be_bytes_to_bits, bits_as_be_bytes, enum_max_size, enum_tag_number, enum_tag_size,
+        signed_to_bits, unsigned_to_bits, wires_as_unsigned,
+    },
     env::Env,
     eval::EvalError,
     scan::scan,
-    token::{SignedNumType, UnsignedNumType},
+    token::{format_fixed, SignedNumType, UnsignedNumType},
     CompileTimeError, TypedExpr, TypedProgram,
 };
 
@@ -32,8 +35,18 @@ pub enum Literal {
     False,
     /// Unsigned number literal.
     NumUnsigned(u64, UnsignedNumType),
+    /// Unsigned number literal that does not fit into a `u64`, as big-endian bytes without leading
+    /// zeros (smaller numbers always use [`Literal::NumUnsigned`]).
+    NumUnsignedBytes(Vec<u8>, UnsignedNumType),
     /// Signed number literal.
     NumSigned(i64, SignedNumType),
+    /// Fixed-point number literal (scaled by `2^frac`) with its total and fractional bits.
+    NumFixed(i64, u8, u8),
+    /// Char literal (decoded as `char::REPLACEMENT_CHARACTER` if the bits are not a valid char).
+    Char(char),
+    /// String literal with the specified maximum length in bytes (decoded lossily if the bytes
+    /// are not valid UTF-8).
+    String(String, usize),
     /// Array "repeat expression", which specifies 1 element, to be repeated a number of times.
     ArrayRepeat(Box<Literal>, usize),
     /// Array literal which explicitly specifies all of its elements.
@@ -48,6 +61,54 @@ pub enum Literal {
     Range((u64, UnsignedNumType), (u64, UnsignedNumType)),
 }
 
+/// The order in which the bits of a number are encoded as wires.
+///
+/// Garble circuits always use [`BitOrder::MsbFirst`], [`BitOrder::LsbFirst`] is only provided to
+/// make it easier to compare external encodings against the encoding used by Garble, for example
+/// when exporting circuits using [`crate::circuit::Circuit::to_bristol`].
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BitOrder {
+    /// The most significant bit of a number comes first.
+    MsbFirst,
+    /// The least significant bit of a number comes first.
+    LsbFirst,
+}
+
+impl BitOrder {
+    fn apply(self, bits: &mut [bool]) {
+        if self == BitOrder::LsbFirst {
+            bits.reverse();
+        }
+    }
+
+    pub(crate) fn apply_to_wires(self, wires: &mut [GateIndex]) {
+        if self == BitOrder::LsbFirst {
+            wires.reverse();
+        }
+    }
+}
+
+/// An error that occurred while casting a literal with [`Literal::cast`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CastError {
+    /// The literal cannot be cast to the type, because only booleans and numbers can be cast (as
+    /// well as chars from and to `u32`).
+    UnsupportedCast(Box<Literal>, Type),
+}
+
+impl std::error::Error for CastError {}
+
+impl Display for CastError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CastError::UnsupportedCast(literal, ty) => f.write_fmt(format_args!(
+                "The literal '{literal}' cannot be cast to {ty}"
+            )),
+        }
+    }
+}
+
 /// A variant literal (either of unit type or containing fields), used by [`Literal::Enum`].
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -60,11 +121,21 @@ pub enum VariantLiteral {
 
 impl Literal {
     /// Parses the str as a literal of the specified type, looking up enum defs in the program.
+    ///
+    /// A fixed-point number can also be given as a decimal number without a `fx<bits>_<frac>`
+    /// suffix, which is parsed as an `f64` and then rounded as described in
+    /// [`Literal::from_f64`]. Numbers of type `u256` (including numbers nested in arrays, tuples,
+    /// etc.) can be larger than `u64::MAX`.
     pub fn parse(
         checked: &TypedProgram,
         ty: &Type,
         literal: &str,
     ) -> Result<Self, CompileTimeError> {
+        if let (Type::Fixed(bits, frac), Ok(n)) = (ty, literal.trim().parse::<f64>()) {
+            if let Some(literal) = Literal::from_f64(n, *bits, *frac) {
+                return Ok(literal);
+            }
+        }
         let mut struct_names = HashSet::with_capacity(checked.struct_defs.len());
         let mut enum_names = HashSet::with_capacity(checked.enum_defs.len());
         struct_names.extend(checked.struct_defs.keys());
@@ -72,6 +143,9 @@ impl Literal {
         let top_level_defs = TopLevelTypes {
             struct_names,
             enum_names,
+            type_args: HashMap::new(),
+            const_types: checked.const_defs.iter().map(|(n, c)| (n, &c.ty)).collect(),
+            const_sizes: usize_const_sizes(checked.const_defs.keys(), &checked.const_defs),
         };
         let mut env = Env::new();
         let mut fns = TypedFns::new();
@@ -101,7 +175,17 @@ impl Literal {
             (Literal::True, Type::Bool) => true,
             (Literal::False, Type::Bool) => true,
             (Literal::NumUnsigned(_, ty1), Type::Unsigned(ty2)) if ty1 == ty2 => true,
+            (Literal::NumUnsignedBytes(bytes, ty1), Type::Unsigned(ty2)) if ty1 == ty2 => {
+                Literal::from_be_bytes(bytes, *ty1).is_some()
+            }
             (Literal::NumSigned(_, ty1), Type::Signed(ty2)) if ty1 == ty2 => true,
+            (Literal::NumFixed(_, bits1, frac1), Type::Fixed(bits2, frac2)) => {
+                bits1 == bits2 && frac1 == frac2
+            }
+            (Literal::Char(_), Type::Char) => true,
+            (Literal::String(s, max_len1), Type::String(max_len2)) => {
+                max_len1 == max_len2 && s.len() <= *max_len1
+            }
             (Literal::ArrayRepeat(elem, size1), Type::Array(elem_ty, size2)) => {
                 size1 == size2 && elem.is_of_type(checked, elem_ty)
             }
@@ -176,10 +260,8 @@ impl Literal {
         bits: &[bool],
         const_sizes: &HashMap<String, usize>,
     ) -> Result<Self, EvalError> {
-        match EvalPanic::parse(bits) {
-            Ok(bits) => Literal::from_unwrapped_bits(checked, ty, bits, const_sizes),
-            Err(panic) => Err(EvalError::Panic(panic)),
-        }
+        let bits = EvalPanic::parse(bits)?;
+        Literal::from_unwrapped_bits(checked, ty, bits, const_sizes)
     }
 
     /// Decodes the bits as a literal of the specified type, looking up enum defs in the program.
@@ -193,6 +275,21 @@ impl Literal {
         ty: &Type,
         bits: &[bool],
         const_sizes: &HashMap<String, usize>,
+    ) -> Result<Self, EvalError> {
+        Literal::from_unwrapped_bits_with_order(checked, ty, bits, const_sizes, BitOrder::MsbFirst)
+    }
+
+    /// Decodes the bits as a literal of the specified type using the specified bit order for
+    /// numbers (and enum tags), looking up enum defs in the program.
+    ///
+    /// This is the inverse of [`Literal::as_bits_with_order`], see
+    /// [`Literal::from_unwrapped_bits`] for the expected format of `bits`.
+    pub fn from_unwrapped_bits_with_order(
+        checked: &TypedProgram,
+        ty: &Type,
+        bits: &[bool],
+        const_sizes: &HashMap<String, usize>,
+        order: BitOrder,
     ) -> Result<Self, EvalError> {
         match ty {
             Type::Bool => {
@@ -209,14 +306,52 @@ impl Literal {
                     })
                 }
             }
-            Type::Unsigned(unsigned_ty) => {
-                let size = ty.size_in_bits_for_defs(checked, const_sizes);
-                if bits.len() == size {
+            Type::Char => {
+                if bits.len() == 32 {
+                    let mut bits = bits.to_vec();
+                    order.apply(&mut bits);
                     let mut n = 0;
                     for (i, output) in bits.iter().copied().enumerate() {
-                        n |= (output as u64) << (size - 1 - i);
+                        n |= (output as u32) << (31 - i);
+                    }
+                    Ok(Literal::Char(
+                        char::from_u32(n).unwrap_or(char::REPLACEMENT_CHARACTER),
+                    ))
+                } else {
+                    Err(EvalError::OutputTypeMismatch {
+                        expected: ty.clone(),
+                        actual_bits: bits.len(),
+                    })
+                }
+            }
+            Type::String(max_len) => {
+                if bits.len() == USIZE_BITS + max_len * 8 {
+                    let mut len_bits = bits[..USIZE_BITS].to_vec();
+                    order.apply(&mut len_bits);
+                    let len = wires_as_unsigned(&len_bits) as usize;
+                    let mut bytes = Vec::with_capacity(*max_len);
+                    for byte_bits in bits[USIZE_BITS..].chunks(8).take(len) {
+                        let mut byte_bits = byte_bits.to_vec();
+                        order.apply(&mut byte_bits);
+                        bytes.push(wires_as_unsigned(&byte_bits) as u8);
                     }
-                    Ok(Literal::NumUnsigned(n, *unsigned_ty))
+                    let s = String::from_utf8_lossy(&bytes).into_owned();
+                    Ok(Literal::String(s, *max_len))
+                } else {
+                    Err(EvalError::OutputTypeMismatch {
+                        expected: ty.clone(),
+                        actual_bits: bits.len(),
+                    })
+                }
+            }
+            Type::Unsigned(unsigned_ty) => {
+                let size = ty.size_in_bits_for_defs(checked, const_sizes);
+                if bits.len() == size {
+                    let mut bits = bits.to_vec();
+                    order.apply(&mut bits);
+                    let bytes = bits_as_be_bytes(&bits);
+                    Ok(Literal::from_be_bytes(&bytes, *unsigned_ty)
+                        .expect("the decoded bits always fit into the type"))
                 } else {
                     Err(EvalError::OutputTypeMismatch {
                         expected: ty.clone(),
@@ -227,6 +362,8 @@ impl Literal {
             Type::Signed(signed_ty) => {
                 let size = ty.size_in_bits_for_defs(checked, const_sizes);
                 if bits.len() == size {
+                    let mut bits = bits.to_vec();
+                    order.apply(&mut bits);
                     let mut n = 0;
                     for (i, output) in bits.iter().copied().enumerate() {
                         n |= (output as i64) << (size - 1 - i);
@@ -245,17 +382,37 @@ impl Literal {
                     })
                 }
             }
+            Type::Fixed(size, frac) => {
+                if bits.len() == *size as usize {
+                    let mut bits = bits.to_vec();
+                    order.apply(&mut bits);
+                    let n = bits.iter().fold(0u64, |n, &bit| (n << 1) | (bit as u64));
+                    // sign-extend the bits to 64 bits:
+                    let shift = 64 - *size as u32;
+                    Ok(Literal::NumFixed(
+                        ((n << shift) as i64) >> shift,
+                        *size,
+                        *frac,
+                    ))
+                } else {
+                    Err(EvalError::OutputTypeMismatch {
+                        expected: ty.clone(),
+                        actual_bits: bits.len(),
+                    })
+                }
+            }
             Type::Array(ty, size) => {
                 let ty_size = ty.size_in_bits_for_defs(checked, const_sizes);
                 let mut elems = vec![];
                 let mut i = 0;
                 for _ in 0..*size {
                     let bits = &bits[i..i + ty_size];
-                    elems.push(Literal::from_unwrapped_bits(
+                    elems.push(Literal::from_unwrapped_bits_with_order(
                         checked,
                         ty,
                         bits,
                         const_sizes,
+                        order,
                     )?);
                     i += ty_size;
                 }
@@ -268,11 +425,12 @@ impl Literal {
                 let mut i = 0;
                 for _ in 0..*size {
                     let bits = &bits[i..i + ty_size];
-                    elems.push(Literal::from_unwrapped_bits(
+                    elems.push(Literal::from_unwrapped_bits_with_order(
                         checked,
                         ty,
                         bits,
                         const_sizes,
+                        order,
                     )?);
                     i += ty_size;
                 }
@@ -284,11 +442,12 @@ impl Literal {
                 for ty in field_types {
                     let ty_size = ty.size_in_bits_for_defs(checked, const_sizes);
                     let bits = &bits[i..i + ty_size];
-                    fields.push(Literal::from_unwrapped_bits(
+                    fields.push(Literal::from_unwrapped_bits_with_order(
                         checked,
                         ty,
                         bits,
                         const_sizes,
+                        order,
                     )?);
                     i += ty_size;
                 }
@@ -301,7 +460,13 @@ impl Literal {
                 for (field_name, ty) in struct_def.fields.iter() {
                     let ty_size = ty.size_in_bits_for_defs(checked, const_sizes);
                     let bits = &bits[i..i + ty_size];
-                    let value = Literal::from_unwrapped_bits(checked, ty, bits, const_sizes)?;
+                    let value = Literal::from_unwrapped_bits_with_order(
+                        checked,
+                        ty,
+                        bits,
+                        const_sizes,
+                        order,
+                    )?;
                     fields.push((field_name.clone(), value));
                     i += ty_size;
                 }
@@ -310,8 +475,10 @@ impl Literal {
             Type::Enum(enum_name) => {
                 let enum_def = checked.enum_defs.get(enum_name).unwrap();
                 let tag_size = enum_tag_size(enum_def);
+                let mut tag_bits = bits[..tag_size].to_vec();
+                order.apply(&mut tag_bits);
                 let mut tag_number = 0;
-                for (i, output) in bits.iter().copied().take(tag_size).enumerate() {
+                for (i, output) in tag_bits.into_iter().enumerate() {
                     tag_number += (output as usize) << (tag_size - 1 - i);
                 }
                 let variant = &enum_def.variants[tag_number];
@@ -325,11 +492,12 @@ impl Literal {
                         let mut fields = Vec::with_capacity(field_types.len());
                         let mut i = tag_size;
                         for ty in field_types {
-                            let field = Literal::from_unwrapped_bits(
+                            let field = Literal::from_unwrapped_bits_with_order(
                                 checked,
                                 ty,
                                 &bits[i..i + ty.size_in_bits_for_defs(checked, const_sizes)],
                                 const_sizes,
+                                order,
                             )?;
                             fields.push(field);
                             i += ty.size_in_bits_for_defs(checked, const_sizes);
@@ -350,11 +518,149 @@ impl Literal {
         }
     }
 
+    /// Casts a boolean, number or char literal to the specified type, with the same semantics as
+    /// an `as` cast in a Garble program: numbers are truncated to their least significant bits if
+    /// the type is smaller and sign- or zero-extended (depending on the literal) if it is larger.
+    pub fn cast(&self, to: &Type) -> Result<Literal, CastError> {
+        let unsupported = || CastError::UnsupportedCast(Box::new(self.clone()), to.clone());
+        let (mut bits, is_signed) = match self {
+            Literal::True => (vec![true], false),
+            Literal::False => (vec![false], false),
+            Literal::NumUnsigned(n, ty) => {
+                let mut bits = vec![];
+                unsigned_to_bits(*n, primitive_size(&Type::Unsigned(*ty)), &mut bits);
+                (bits, false)
+            }
+            Literal::NumUnsignedBytes(bytes, ty) => {
+                let mut bits = vec![];
+                be_bytes_to_bits(bytes, primitive_size(&Type::Unsigned(*ty)), &mut bits);
+                (bits, false)
+            }
+            Literal::NumSigned(n, ty) => {
+                let mut bits = vec![];
+                signed_to_bits(*n, primitive_size(&Type::Signed(*ty)), &mut bits);
+                (bits, true)
+            }
+            Literal::Char(c) => match to {
+                Type::Char | Type::Unsigned(UnsignedNumType::U32) => {
+                    let mut bits = vec![];
+                    unsigned_to_bits(*c as u64, 32, &mut bits);
+                    (bits, false)
+                }
+                _ => return Err(unsupported()),
+            },
+            _ => return Err(unsupported()),
+        };
+        let size = match to {
+            Type::Bool | Type::Unsigned(_) | Type::Signed(_) => primitive_size(to),
+            Type::Char => match self {
+                Literal::Char(_)
+                | Literal::NumUnsigned(_, UnsignedNumType::U32 | UnsignedNumType::Unspecified) => {
+                    32
+                }
+                _ => return Err(unsupported()),
+            },
+            _ => return Err(unsupported()),
+        };
+        if size < bits.len() {
+            bits.drain(..bits.len() - size);
+        } else if size > bits.len() {
+            let msb = is_signed && bits[0];
+            let mut extended = vec![msb; size - bits.len()];
+            extended.extend(bits);
+            bits = extended;
+        }
+        let n = bits.iter().fold(0u64, |n, &bit| (n << 1) | (bit as u64));
+        Ok(match to {
+            Type::Bool => Literal::from(bits[0]),
+            Type::Char => {
+                Literal::Char(char::from_u32(n as u32).unwrap_or(char::REPLACEMENT_CHARACTER))
+            }
+            Type::Unsigned(ty) => Literal::from_be_bytes(&bits_as_be_bytes(&bits), *ty)
+                .expect("the bits have been truncated to the size of the type"),
+            Type::Signed(ty) => {
+                // sign-extend the bits to 64 bits:
+                let shift = 64 - size;
+                Literal::NumSigned(((n << shift) as i64) >> shift, *ty)
+            }
+            _ => unreachable!("only primitive types are valid cast targets"),
+        })
+    }
+
+    /// Converts a number given as big-endian bytes to an unsigned literal of the specified type,
+    /// which is needed for numbers that do not fit into a `u64`, such as large `u256` numbers.
+    ///
+    /// Returns `None` if the number does not fit into the type.
+    pub fn from_be_bytes(bytes: &[u8], ty: UnsignedNumType) -> Option<Literal> {
+        let leading_zeros = bytes.iter().take_while(|&&b| b == 0).count();
+        let bytes = &bytes[leading_zeros..];
+        let bits = bytes
+            .first()
+            .map_or(0, |b| bytes.len() * 8 - b.leading_zeros() as usize);
+        if bits > primitive_size(&Type::Unsigned(ty)) {
+            None
+        } else if bytes.len() <= 8 {
+            let n = bytes.iter().fold(0u64, |n, &b| (n << 8) | b as u64);
+            Some(Literal::NumUnsigned(n, ty))
+        } else {
+            Some(Literal::NumUnsignedBytes(bytes.to_vec(), ty))
+        }
+    }
+
+    /// Converts the number to the nearest fixed-point literal of type `fx<bits>_<frac>`, rounding
+    /// ties away from zero.
+    ///
+    /// Returns `None` if the number is NaN or out of range for the type, or if the type is not a
+    /// valid fixed-point type (with at most 64 bits, of which at most `bits` are fractional).
+    pub fn from_f64(n: f64, bits: u8, frac: u8) -> Option<Literal> {
+        if bits == 0 || bits > 64 || frac > bits {
+            return None;
+        }
+        // scaling by a power of 2 is exact (unless it overflows, which is out of range anyway):
+        let scaled = (n * 2f64.powi(frac as i32)).round();
+        let bound = 2f64.powi(bits as i32 - 1);
+        if scaled >= -bound && scaled < bound {
+            Some(Literal::NumFixed(scaled as i64, bits, frac))
+        } else {
+            None
+        }
+    }
+
+    /// Converts a fixed-point literal to the nearest `f64`, rounding ties to even.
+    ///
+    /// The conversion is exact if the number has at most 53 significant bits. Returns `None` if
+    /// the literal is not a fixed-point number.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Literal::NumFixed(n, _, frac) => Some(*n as f64 / 2f64.powi(*frac as i32)),
+            _ => None,
+        }
+    }
+
     /// Encodes the literal as bits, looking up enum defs in the program.
+    ///
+    /// The bits are the exact sequence of input wires that would be fed into a circuit, which
+    /// encodes numbers with their most significant bit first. Array elements, tuple fields and
+    /// struct fields are concatenated in order, while enums are encoded as their tag (the index of
+    /// the variant, using as few bits as needed for all variants) followed by the fields of the
+    /// variant, padded with `false` to the size of the largest variant.
+    ///
+    /// The bits can be decoded using [`Literal::from_unwrapped_bits`].
     pub fn as_bits(
         &self,
         checked: &TypedProgram,
         const_sizes: &HashMap<String, usize>,
+    ) -> Vec<bool> {
+        self.as_bits_with_order(checked, const_sizes, BitOrder::MsbFirst)
+    }
+
+    /// Encodes the literal as bits using the specified bit order for numbers (and enum tags),
+    /// looking up enum defs in the program.
+    pub fn as_bits_with_order(
+        &self,
+        checked: &TypedProgram,
+        const_sizes: &HashMap<String, usize>,
+        order: BitOrder,
     ) -> Vec<bool> {
         match self {
             Literal::True => vec![true],
@@ -363,16 +669,50 @@ impl Literal {
                 let size = Type::Unsigned(*ty).size_in_bits_for_defs(checked, const_sizes);
                 let mut bits = vec![];
                 unsigned_to_bits(*n, size, &mut bits);
+                order.apply(&mut bits);
+                bits
+            }
+            Literal::NumUnsignedBytes(bytes, ty) => {
+                let size = Type::Unsigned(*ty).size_in_bits_for_defs(checked, const_sizes);
+                let mut bits = vec![];
+                be_bytes_to_bits(bytes, size, &mut bits);
+                order.apply(&mut bits);
                 bits
             }
             Literal::NumSigned(n, ty) => {
                 let size = Type::Signed(*ty).size_in_bits_for_defs(checked, const_sizes);
                 let mut bits = vec![];
                 signed_to_bits(*n, size, &mut bits);
+                order.apply(&mut bits);
+                bits
+            }
+            Literal::NumFixed(n, size, _) => {
+                let mut bits = vec![];
+                signed_to_bits(*n, *size as usize, &mut bits);
+                order.apply(&mut bits);
+                bits
+            }
+            Literal::Char(c) => {
+                let mut bits = vec![];
+                unsigned_to_bits(*c as u64, 32, &mut bits);
+                order.apply(&mut bits);
+                bits
+            }
+            Literal::String(s, max_len) => {
+                let mut bits = Vec::with_capacity(USIZE_BITS + max_len * 8);
+                unsigned_to_bits(s.len() as u64, USIZE_BITS, &mut bits);
+                order.apply(&mut bits);
+                for b in s.bytes() {
+                    let mut byte_bits = vec![];
+                    unsigned_to_bits(b as u64, 8, &mut byte_bits);
+                    order.apply(&mut byte_bits);
+                    bits.extend(byte_bits);
+                }
+                bits.resize(USIZE_BITS + max_len * 8, false);
                 bits
             }
             Literal::ArrayRepeat(elem, size) => {
-                let elem = elem.as_bits(checked, const_sizes);
+                let elem = elem.as_bits_with_order(checked, const_sizes, order);
                 let elem_size = elem.len();
                 let mut bits = vec![false; elem_size * size];
                 for i in 0..*size {
@@ -383,21 +723,21 @@ impl Literal {
             Literal::Array(elems) => {
                 let mut bits = vec![];
                 for elem in elems {
-                    bits.extend(elem.as_bits(checked, const_sizes))
+                    bits.extend(elem.as_bits_with_order(checked, const_sizes, order))
                 }
                 bits
             }
             Literal::Tuple(fields) => {
                 let mut bits = vec![];
                 for f in fields {
-                    bits.extend(f.as_bits(checked, const_sizes))
+                    bits.extend(f.as_bits_with_order(checked, const_sizes, order))
                 }
                 bits
             }
             Literal::Struct(_, fields) => {
                 let mut bits = vec![];
                 for (_, f) in fields {
-                    bits.extend(f.as_bits(checked, const_sizes))
+                    bits.extend(f.as_bits_with_order(checked, const_sizes, order))
                 }
                 bits
             }
@@ -410,12 +750,13 @@ impl Literal {
                 for (i, wire) in wires.iter_mut().enumerate().take(tag_size) {
                     *wire = (tag_number >> (tag_size - i - 1)) & 1 == 1;
                 }
+                order.apply(&mut wires[..tag_size]);
                 let mut w = tag_size;
                 match variant {
                     VariantLiteral::Unit => {}
                     VariantLiteral::Tuple(fields) => {
                         for f in fields {
-                            let f = f.as_bits(checked, const_sizes);
+                            let f = f.as_bits_with_order(checked, const_sizes, order);
                             wires[w..w + f.len()].copy_from_slice(&f);
                             w += f.len();
                         }
@@ -428,7 +769,10 @@ impl Literal {
                 let elem_size = Type::Unsigned(*min_ty).size_in_bits_for_defs(checked, const_sizes);
                 let mut bits = Vec::with_capacity(elems.len() * elem_size);
                 for elem in elems {
-                    unsigned_to_bits(elem as u64, elem_size, &mut bits);
+                    let mut elem_bits = Vec::with_capacity(elem_size);
+                    unsigned_to_bits(elem as u64, elem_size, &mut elem_bits);
+                    order.apply(&mut elem_bits);
+                    bits.extend(elem_bits);
                 }
                 bits
             }
@@ -442,9 +786,15 @@ impl Display for Literal {
             Literal::True => write!(f, "true"),
             Literal::False => write!(f, "false"),
             Literal::NumUnsigned(n, _) => write!(f, "{n}"),
+            Literal::NumUnsignedBytes(bytes, _) => write!(f, "{}", be_bytes_as_decimal(bytes)),
             Literal::NumSigned(n, _) => {
                 write!(f, "{n}")
             }
+            Literal::NumFixed(n, bits, frac) => {
+                write!(f, "{}fx{bits}_{frac}", format_fixed(*n, *frac))
+            }
+            Literal::Char(c) => write!(f, "{c:?}"),
+            Literal::String(s, _) => write!(f, "{s:?}"),
             Literal::ArrayRepeat(elem, size) => write!(f, "[{elem}; {size}]"),
             Literal::Array(elems) => {
                 write!(f, "[")?;
@@ -520,6 +870,11 @@ impl TypedExpr {
                     panic!("Literal type is not a number type: {ty:?}")
                 }
             }
+            ExprEnum::NumUnsignedBytes(bytes, _) => match ty {
+                Type::Unsigned(ty) => Literal::from_be_bytes(&bytes, ty)
+                    .expect("the type checker only accepts numbers that fit into the type"),
+                _ => panic!("Literal type is not an unsigned number type: {ty:?}"),
+            },
             ExprEnum::NumSigned(n, _) => {
                 if let Type::Unsigned(ty) = ty {
                     Literal::NumUnsigned(n as u64, ty)
@@ -529,6 +884,12 @@ impl TypedExpr {
                     panic!("Literal type is not a number type: {ty:?}")
                 }
             }
+            ExprEnum::NumFixed(n, bits, frac) => Literal::NumFixed(n, bits, frac),
+            ExprEnum::Char(c) => Literal::Char(c),
+            ExprEnum::StringLiteral(s) => match ty {
+                Type::String(max_len) => Literal::String(s, max_len),
+                _ => Literal::Array(s.bytes().map(Literal::from).collect()),
+            },
             ExprEnum::ArrayRepeatLiteral(elem, size) => {
                 Literal::ArrayRepeat(Box::new(elem.into_literal()), size)
             }
@@ -560,6 +921,12 @@ impl TypedExpr {
     }
 }
 
+impl From<char> for Literal {
+    fn from(c: char) -> Self {
+        Literal::Char(c)
+    }
+}
+
 impl From<bool> for Literal {
     fn from(b: bool) -> Self {
         if b {
@@ -617,3 +984,57 @@ impl From<i64> for Literal {
         Literal::NumSigned(n, SignedNumType::I64)
     }
 }
+
+/// Parses a number of arbitrary size with digits in the given radix as big-endian bytes.
+pub(crate) fn digits_as_be_bytes(digits: &str, radix: u32) -> Option<Vec<u8>> {
+    if digits.is_empty() || !digits.chars().all(|d| d.is_digit(radix)) {
+        return None;
+    }
+    // the bytes are least significant first while multiplying by the radix:
+    let mut bytes: Vec<u8> = vec![];
+    for digit in digits.chars() {
+        let mut carry = digit.to_digit(radix)?;
+        for byte in bytes.iter_mut() {
+            let n = *byte as u32 * radix + carry;
+            *byte = n as u8;
+            carry = n >> 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    bytes.reverse();
+    Some(bytes)
+}
+
+/// Formats big-endian bytes as a decimal number.
+pub(crate) fn be_bytes_as_decimal(bytes: &[u8]) -> String {
+    let mut n = bytes.to_vec();
+    let mut digits = vec![];
+    while n.iter().any(|&b| b != 0) {
+        let mut remainder = 0u32;
+        for byte in n.iter_mut() {
+            let x = (remainder << 8) | *byte as u32;
+            *byte = (x / 10) as u8;
+            remainder = x % 10;
+        }
+        digits.push(char::from(b'0' + remainder as u8));
+    }
+    if digits.is_empty() {
+        return "0".to_string();
+    }
+    digits.iter().rev().collect()
+}
+
+fn primitive_size(ty: &Type) -> usize {
+    match ty {
+        Type::Bool => 1,
+        Type::Unsigned(UnsignedNumType::U8) | Type::Signed(SignedNumType::I8) => 8,
+        Type::Unsigned(UnsignedNumType::U16) | Type::Signed(SignedNumType::I16) => 16,
+        Type::Unsigned(UnsignedNumType::U64) | Type::Signed(SignedNumType::I64) => 64,
+        Type::Unsigned(UnsignedNumType::U256) => 256,
+        Type::Unsigned(UnsignedNumType::Usize) => USIZE_BITS,
+        _ => 32,
+    }
+}
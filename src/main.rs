@@ -60,12 +60,14 @@ fn run(file: PathBuf, inputs: Vec<String>, function: String) -> Result<(), std::
         eprintln!("{}", e.prettify(&prg));
         exit(65);
     });
-    let (circuit, main_fn) = program.compile(&function).unwrap_or_else(|errs| {
-        for e in errs {
-            eprintln!("{e}");
-        }
-        exit(65);
-    });
+    let (circuit, main_fn, const_sizes) = program
+        .compile_with_constants(&function, HashMap::new())
+        .unwrap_or_else(|errs| {
+            for e in errs {
+                eprintln!("{e}");
+            }
+            exit(65);
+        });
 
     let mut arguments: Vec<String> = Vec::with_capacity(inputs.len());
 
@@ -84,7 +86,6 @@ fn run(file: PathBuf, inputs: Vec<String>, function: String) -> Result<(), std::
         arguments.push(input);
     }
 
-    let const_sizes = HashMap::new();
     let mut evaluator = Evaluator::new(&program, main_fn, &circuit, &const_sizes);
     let main_params = &evaluator.main_fn.params;
     if main_params.len() != arguments.len() {
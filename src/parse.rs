@@ -8,7 +8,7 @@ use crate::{
         PatternEnum, Program, Stmt, StmtEnum, StructDef, Type, UnaryOp, Variant, VariantExprEnum,
     },
     scan::Tokens,
-    token::{MetaInfo, SignedNumType, Token, TokenEnum, UnsignedNumType},
+    token::{parse_fixed_suffix, MetaInfo, SignedNumType, Token, TokenEnum, UnsignedNumType},
     UntypedExpr, UntypedFnDef, UntypedPattern, UntypedProgram, UntypedStmt,
 };
 
@@ -24,6 +24,8 @@ pub enum ParseErrorEnum {
     InvalidTopLevelDef,
     /// Arrays of the specified size are not supported.
     InvalidArraySize,
+    /// The maximum length of a string type must be a number literal.
+    InvalidStringLength,
     /// The min or max value of the range expression is invalid.
     InvalidRangeExpr,
     /// The pattern is not valid.
@@ -42,6 +44,10 @@ pub enum ParseErrorEnum {
     ExpectedIdentifier,
     /// Expected a method call or a field access.
     ExpectedMethodCallOrFieldAccess,
+    /// The attribute is not valid or not followed by a pub fn definition.
+    InvalidAttribute,
+    /// Function types cannot be used as the types of values.
+    InvalidFnType,
     /// Found an unexpected token.
     Expected(TokenEnum),
 }
@@ -58,6 +64,9 @@ impl std::fmt::Display for ParseErrorEnum {
                     "Invalid array size (must be a constant number <= {max})"
                 ))
             }
+            ParseErrorEnum::InvalidStringLength => {
+                f.write_str("Invalid string length (must be a constant number)")
+            }
             ParseErrorEnum::InvalidRangeExpr => f.write_str("Invalid range expression"),
             ParseErrorEnum::InvalidPattern => f.write_str("Invalid pattern"),
             ParseErrorEnum::InvalidLiteral => f.write_str("Invalid literal"),
@@ -69,6 +78,12 @@ impl std::fmt::Display for ParseErrorEnum {
             ParseErrorEnum::ExpectedMethodCallOrFieldAccess => {
                 f.write_str("Expected a method call or field access")
             }
+            ParseErrorEnum::InvalidAttribute => f.write_str(
+                "Invalid attribute, only '#[reveal(param)]' is supported before a pub fn",
+            ),
+            ParseErrorEnum::InvalidFnType => {
+                f.write_str("Function types are not supported, all types must have a size in bits")
+            }
             ParseErrorEnum::Expected(token) => f.write_fmt(format_args!("Expected '{token}'")),
         }
     }
@@ -119,17 +134,33 @@ impl Parser {
             TokenEnum::KeywordStruct,
             TokenEnum::KeywordEnum,
             TokenEnum::KeywordConst,
+            TokenEnum::Hash,
         ];
         let mut const_defs = HashMap::new();
         let mut struct_defs = HashMap::new();
         let mut enum_defs = HashMap::new();
         let mut fn_defs = HashMap::new();
+        let mut duplicate_fn_defs = vec![];
         let mut is_pub = None;
+        let mut reveal_to = None;
         while let Some(Token(token_enum, meta)) = self.advance() {
+            if let Some((_, attr_meta)) = &reveal_to {
+                if !matches!(token_enum, TokenEnum::KeywordPub | TokenEnum::KeywordFn) {
+                    self.push_error(ParseErrorEnum::InvalidAttribute, *attr_meta);
+                    reveal_to = None;
+                }
+            }
             match token_enum {
                 TokenEnum::KeywordPub if is_pub.is_none() => {
                     is_pub = Some(meta);
                 }
+                TokenEnum::Hash if is_pub.is_none() && reveal_to.is_none() => {
+                    if let Ok(attr) = self.parse_reveal_attribute(meta) {
+                        reveal_to = Some(attr);
+                    } else {
+                        self.consume_until_one_of(&top_level_keywords);
+                    }
+                }
                 TokenEnum::KeywordConst => {
                     if let Ok((const_name, const_def)) = self.parse_const_def(meta) {
                         const_defs.insert(const_name, const_def);
@@ -155,13 +186,24 @@ impl Parser {
                     is_pub = None;
                 }
                 TokenEnum::KeywordFn => {
-                    if let Ok(fn_def) = self.parse_fn_def(is_pub.is_some(), is_pub.unwrap_or(meta))
+                    if let Ok(mut fn_def) =
+                        self.parse_fn_def(is_pub.is_some(), is_pub.unwrap_or(meta))
                     {
+                        if let Some((_, attr_meta)) = &reveal_to {
+                            if !fn_def.is_pub {
+                                self.push_error(ParseErrorEnum::InvalidAttribute, *attr_meta);
+                            }
+                        }
+                        fn_def.reveal_to = reveal_to.take();
+                        if fn_defs.contains_key(&fn_def.identifier) {
+                            duplicate_fn_defs.push((fn_def.identifier.clone(), fn_def.meta));
+                        }
                         fn_defs.insert(fn_def.identifier.clone(), fn_def);
                     } else {
                         self.consume_until_one_of(&top_level_keywords);
                     }
                     is_pub = None;
+                    reveal_to = None;
                 }
                 _ => {
                     self.push_error(ParseErrorEnum::InvalidTopLevelDef, meta);
@@ -176,6 +218,7 @@ impl Parser {
                 struct_defs,
                 enum_defs,
                 fn_defs,
+                duplicate_fn_defs,
             });
         }
         Err(self.errors)
@@ -191,7 +234,7 @@ impl Parser {
 
         self.expect(&TokenEnum::Eq)?;
 
-        let Ok(expr) = self.parse_primary() else {
+        let Ok(expr) = self.parse_expr() else {
             self.push_error(ParseErrorEnum::InvalidTopLevelDef, start);
             return Err(());
         };
@@ -233,6 +276,31 @@ impl Parser {
                         Ok(ConstExpr(ConstExprEnum::Min(const_exprs), expr.meta))
                     }
                 }
+                ExprEnum::Identifier(identifier) => {
+                    Ok(ConstExpr(ConstExprEnum::Const(identifier), expr.meta))
+                }
+                ExprEnum::Op(
+                    op @ (Op::Add
+                    | Op::Sub
+                    | Op::Mul
+                    | Op::Div
+                    | Op::Mod
+                    | Op::BitAnd
+                    | Op::BitOr
+                    | Op::BitXor),
+                    x,
+                    y,
+                ) => match (parse_const_expr(*x), parse_const_expr(*y)) {
+                    (Ok(x), Ok(y)) => Ok(ConstExpr(
+                        ConstExprEnum::Op(op, Box::new(x), Box::new(y)),
+                        expr.meta,
+                    )),
+                    (x, y) => {
+                        let mut errs = x.err().unwrap_or_default();
+                        errs.extend(y.err().unwrap_or_default());
+                        Err(errs)
+                    }
+                },
                 _ => Err(vec![(ParseErrorEnum::InvalidConstExpr, expr.meta)]),
             }
         }
@@ -304,7 +372,7 @@ impl Parser {
         let (variant_name, _) = self.expect_identifier()?;
         if self.next_matches(&TokenEnum::LeftParen).is_some() {
             let mut fields = vec![];
-            if let Some(Token(TokenEnum::Identifier(_), _)) = self.tokens.peek() {
+            if !self.peek(&TokenEnum::RightParen) {
                 let (ty, _) = self.parse_type()?;
                 fields.push(ty);
             }
@@ -322,11 +390,36 @@ impl Parser {
         }
     }
 
+    fn parse_reveal_attribute(&mut self, start: MetaInfo) -> Result<(String, MetaInfo), ()> {
+        // # was already consumed by the top-level parser
+
+        // [reveal(<param>)]
+        self.expect(&TokenEnum::LeftBracket)?;
+        let (attr, attr_meta) = self.expect_identifier()?;
+        if attr != "reveal" {
+            self.push_error(ParseErrorEnum::InvalidAttribute, attr_meta);
+            return Err(());
+        }
+        self.expect(&TokenEnum::LeftParen)?;
+        let (party, _) = self.expect_identifier()?;
+        self.expect(&TokenEnum::RightParen)?;
+        let end = self.expect(&TokenEnum::RightBracket)?;
+        Ok((party, join_meta(start, end)))
+    }
+
     fn parse_fn_def(&mut self, is_pub: bool, start: MetaInfo) -> Result<UntypedFnDef, ()> {
         // fn keyword was already consumed by the top-level parser
 
         let (identifier, _) = self.expect_identifier()?;
 
+        // <T>
+        let mut type_param = None;
+        if self.next_matches(&TokenEnum::LessThan).is_some() {
+            let (param, _) = self.expect_identifier()?;
+            self.expect(&TokenEnum::GreaterThan)?;
+            type_param = Some(param);
+        }
+
         // ( ... )
         self.expect(&TokenEnum::LeftParen)?;
         let mut params = vec![];
@@ -349,6 +442,8 @@ impl Parser {
             is_pub,
             ty,
             identifier,
+            type_param,
+            reveal_to: None,
             params,
             body,
             meta,
@@ -461,18 +556,7 @@ impl Parser {
                         if !self.peek(&TokenEnum::RightBrace) && !self.peek(&TokenEnum::Comma) {
                             self.expect(&TokenEnum::Semicolon)?;
                         }
-                        let binary_op = Expr::untyped(
-                            ExprEnum::Op(
-                                op,
-                                Box::new(Expr::untyped(
-                                    ExprEnum::Identifier(identifier.clone()),
-                                    identifier_meta,
-                                )),
-                                Box::new(value),
-                            ),
-                            meta,
-                        );
-                        Stmt::new(StmtEnum::VarAssign(identifier, binary_op), meta)
+                        desugar_compound_assignment(identifier, identifier_meta, op, value, meta)
                     } else {
                         let expr = Expr::untyped(ExprEnum::Identifier(identifier), meta);
                         if !self.peek(&TokenEnum::RightBrace) && !self.peek(&TokenEnum::Comma) {
@@ -705,8 +789,13 @@ impl Parser {
     }
 
     fn parse_shift(&mut self) -> Result<UntypedExpr, ()> {
-        // <<, >>
-        let ops = vec![TokenEnum::DoubleLessThan, TokenEnum::DoubleGreaterThan];
+        // <<, >>, >>>, <<<
+        let ops = vec![
+            TokenEnum::DoubleLessThan,
+            TokenEnum::DoubleGreaterThan,
+            TokenEnum::TripleGreaterThan,
+            TokenEnum::TripleLessThan,
+        ];
         let mut x = self.parse_term()?;
         while let Some((token, _)) = self.next_matches_one_of(&ops) {
             let y = self.parse_term()?;
@@ -714,6 +803,8 @@ impl Parser {
             let op = match token {
                 TokenEnum::DoubleLessThan => Op::ShiftLeft,
                 TokenEnum::DoubleGreaterThan => Op::ShiftRight,
+                TokenEnum::TripleGreaterThan => Op::RotateRight,
+                TokenEnum::TripleLessThan => Op::RotateLeft,
                 _ => unreachable!(),
             };
             x = Expr::untyped(ExprEnum::Op(op, Box::new(x), Box::new(y)), meta);
@@ -722,8 +813,13 @@ impl Parser {
     }
 
     fn parse_term(&mut self) -> Result<UntypedExpr, ()> {
-        // +, -
-        let ops = vec![TokenEnum::Plus, TokenEnum::Minus];
+        // +, -, +|, -|
+        let ops = vec![
+            TokenEnum::Plus,
+            TokenEnum::Minus,
+            TokenEnum::PlusBar,
+            TokenEnum::MinusBar,
+        ];
         let mut x = self.parse_factor()?;
         while let Some((token, _)) = self.next_matches_one_of(&ops) {
             let y = self.parse_factor()?;
@@ -731,6 +827,8 @@ impl Parser {
             let op = match token {
                 TokenEnum::Plus => Op::Add,
                 TokenEnum::Minus => Op::Sub,
+                TokenEnum::PlusBar => Op::SaturatingAdd,
+                TokenEnum::MinusBar => Op::SaturatingSub,
                 _ => unreachable!(),
             };
             x = Expr::untyped(ExprEnum::Op(op, Box::new(x), Box::new(y)), meta);
@@ -739,8 +837,13 @@ impl Parser {
     }
 
     fn parse_factor(&mut self) -> Result<UntypedExpr, ()> {
-        // *, /, %
-        let ops = vec![TokenEnum::Star, TokenEnum::Slash, TokenEnum::Percent];
+        // *, /, %, *|
+        let ops = vec![
+            TokenEnum::Star,
+            TokenEnum::Slash,
+            TokenEnum::Percent,
+            TokenEnum::StarBar,
+        ];
         let mut x = self.parse_cast()?;
         while let Some((token, _)) = self.next_matches_one_of(&ops) {
             let y = self.parse_cast()?;
@@ -749,6 +852,7 @@ impl Parser {
                 TokenEnum::Star => Op::Mul,
                 TokenEnum::Percent => Op::Mod,
                 TokenEnum::Slash => Op::Div,
+                TokenEnum::StarBar => Op::SaturatingMul,
                 _ => unreachable!(),
             };
             x = Expr::untyped(ExprEnum::Op(op, Box::new(x), Box::new(y)), meta);
@@ -984,6 +1088,8 @@ impl Parser {
                                 meta,
                             ))
                         }
+                    } else if identifier == "_" {
+                        Ok(Pattern::untyped(PatternEnum::Wildcard, meta))
                     } else {
                         Ok(Pattern::untyped(PatternEnum::Identifier(identifier), meta))
                     }
@@ -1007,18 +1113,21 @@ impl Parser {
                                 self.advance();
 
                                 let meta = join_meta(meta, meta_end);
-                                Ok(Pattern::untyped(
-                                    PatternEnum::UnsignedInclusiveRange(
-                                        n,
-                                        if is_inclusive {
-                                            range_end
-                                        } else {
-                                            range_end - 1
-                                        },
-                                        type_suffix,
-                                    ),
-                                    meta,
-                                ))
+                                let max = if is_inclusive {
+                                    Some(range_end)
+                                } else {
+                                    range_end.checked_sub(1)
+                                };
+                                match max {
+                                    Some(max) if max >= n => Ok(Pattern::untyped(
+                                        PatternEnum::UnsignedInclusiveRange(n, max, type_suffix),
+                                        meta,
+                                    )),
+                                    _ => {
+                                        self.push_error(ParseErrorEnum::InvalidRangeExpr, meta);
+                                        Err(())
+                                    }
+                                }
                             } else {
                                 self.push_error_for_next(ParseErrorEnum::InvalidRangeExpr);
                                 Err(())
@@ -1042,32 +1151,39 @@ impl Parser {
                     if self.peek(&TokenEnum::DoubleDot) || self.peek(&TokenEnum::DoubleDotEquals) {
                         let is_inclusive = self.peek(&TokenEnum::DoubleDotEquals);
                         self.advance();
-                        if let Some(Token(
-                            TokenEnum::SignedNum(range_end, type_suffix_end),
-                            meta_end,
-                        )) = self.tokens.peek()
-                        {
-                            if type_suffix == *type_suffix_end {
-                                let range_end = *range_end;
-                                let meta_end = *meta_end;
-                                self.advance();
+                        // the end of a range starting at a negative number can also be a
+                        // non-negative number without a suffix, as in `-10..0`:
+                        let range_end = match self.tokens.peek() {
+                            Some(Token(
+                                TokenEnum::SignedNum(range_end, type_suffix_end),
+                                meta_end,
+                            )) if type_suffix == *type_suffix_end => Some((*range_end, *meta_end)),
+                            Some(Token(
+                                TokenEnum::UnsignedNum(range_end, UnsignedNumType::Unspecified),
+                                meta_end,
+                            )) if *range_end <= i64::MAX as u64 => {
+                                Some((*range_end as i64, *meta_end))
+                            }
+                            _ => None,
+                        };
+                        if let Some((range_end, meta_end)) = range_end {
+                            self.advance();
 
-                                let meta = join_meta(meta, meta_end);
-                                Ok(Pattern::untyped(
-                                    PatternEnum::SignedInclusiveRange(
-                                        n,
-                                        if is_inclusive {
-                                            range_end
-                                        } else {
-                                            range_end - 1
-                                        },
-                                        type_suffix,
-                                    ),
-                                    meta,
-                                ))
+                            let meta = join_meta(meta, meta_end);
+                            let max = if is_inclusive {
+                                Some(range_end)
                             } else {
-                                self.push_error_for_next(ParseErrorEnum::InvalidRangeExpr);
-                                Err(())
+                                range_end.checked_sub(1)
+                            };
+                            match max {
+                                Some(max) if max >= n => Ok(Pattern::untyped(
+                                    PatternEnum::SignedInclusiveRange(n, max, type_suffix),
+                                    meta,
+                                )),
+                                _ => {
+                                    self.push_error(ParseErrorEnum::InvalidRangeExpr, meta);
+                                    Err(())
+                                }
                             }
                         } else {
                             self.push_error_for_next(ParseErrorEnum::InvalidRangeExpr);
@@ -1177,6 +1293,17 @@ impl Parser {
                     let i = *i;
                     let meta = *meta;
                     self.advance();
+                    if self.next_matches(&TokenEnum::DoubleDot).is_some() {
+                        let Some(Token(TokenEnum::UnsignedNum(to, _), _)) = self.advance() else {
+                            self.push_error(ParseErrorEnum::InvalidRangeExpr, meta);
+                            return Err(());
+                        };
+                        let end = self.expect(&TokenEnum::RightBracket)?;
+                        let meta = join_meta(expr.meta, end);
+                        let (from, to) = (i as usize, to as usize);
+                        expr = Expr::untyped(ExprEnum::ArraySlice(Box::new(expr), from, to), meta);
+                        continue;
+                    }
                     let index =
                         Expr::untyped(ExprEnum::NumUnsigned(i, UnsignedNumType::Usize), meta);
                     let end = self.expect(&TokenEnum::RightBracket)?;
@@ -1187,8 +1314,16 @@ impl Parser {
                     let index = self.parse_expr()?;
                     let end = self.expect(&TokenEnum::RightBracket)?;
                     let meta = join_meta(expr.meta, end);
-                    expr =
-                        Expr::untyped(ExprEnum::ArrayAccess(Box::new(expr), Box::new(index)), meta);
+                    expr = match index.inner {
+                        ExprEnum::Range((from, _), (to, _)) => {
+                            let (from, to) = (from as usize, to as usize);
+                            Expr::untyped(ExprEnum::ArraySlice(Box::new(expr), from, to), meta)
+                        }
+                        _ => Expr::untyped(
+                            ExprEnum::ArrayAccess(Box::new(expr), Box::new(index)),
+                            meta,
+                        ),
+                    };
                 }
             } else if self.next_matches(&TokenEnum::Dot).is_some() {
                 let peeked = self.tokens.peek();
@@ -1274,7 +1409,10 @@ impl Parser {
                         && self.struct_literals_allowed
                     {
                         let mut fields = vec![];
-                        if !self.peek(&TokenEnum::RightBrace) {
+                        let mut base = None;
+                        if self.next_matches(&TokenEnum::DoubleDot).is_some() {
+                            base = Some(self.parse_expr()?);
+                        } else if !self.peek(&TokenEnum::RightBrace) {
                             let (name, name_meta) = self.expect_identifier()?;
                             let value = if self.peek(&TokenEnum::Comma)
                                 || self.peek(&TokenEnum::RightBrace)
@@ -1289,6 +1427,10 @@ impl Parser {
                                 if self.peek(&TokenEnum::RightBrace) {
                                     break;
                                 }
+                                if self.next_matches(&TokenEnum::DoubleDot).is_some() {
+                                    base = Some(self.parse_expr()?);
+                                    break;
+                                }
                                 let (name, name_meta) = self.expect_identifier()?;
                                 let value = if self.peek(&TokenEnum::Comma)
                                     || self.peek(&TokenEnum::RightBrace)
@@ -1303,7 +1445,14 @@ impl Parser {
                         }
                         self.expect(&TokenEnum::RightBrace)?;
                         fields.sort_by(|(f1, _), (f2, _)| f1.cmp(f2));
-                        Expr::untyped(ExprEnum::StructLiteral(identifier, fields), meta)
+                        if let Some(base) = base {
+                            Expr::untyped(
+                                ExprEnum::StructUpdate(identifier, fields, Box::new(base)),
+                                meta,
+                            )
+                        } else {
+                            Expr::untyped(ExprEnum::StructLiteral(identifier, fields), meta)
+                        }
                     } else {
                         self.push_error(ParseErrorEnum::InvalidLiteral, meta);
                         return Err(());
@@ -1334,9 +1483,17 @@ impl Parser {
                     Expr::untyped(ExprEnum::NumUnsigned(n, n_suffix), meta)
                 }
             }
+            TokenEnum::UnsignedBigNum(bytes, type_suffix) => {
+                Expr::untyped(ExprEnum::NumUnsignedBytes(bytes, type_suffix), meta)
+            }
             TokenEnum::SignedNum(n, type_suffix) => {
                 Expr::untyped(ExprEnum::NumSigned(n, type_suffix), meta)
             }
+            TokenEnum::FixedNum(n, bits, frac) => {
+                Expr::untyped(ExprEnum::NumFixed(n, bits, frac), meta)
+            }
+            TokenEnum::Char(c) => Expr::untyped(ExprEnum::Char(c), meta),
+            TokenEnum::Str(s) => Expr::untyped(ExprEnum::StringLiteral(s), meta),
             TokenEnum::LeftParen => {
                 if !self.peek(&TokenEnum::RightParen) {
                     let expr = if only_literal_children {
@@ -1451,6 +1608,25 @@ impl Parser {
             let meta_end = self.expect(&TokenEnum::RightParen)?;
             let meta = join_meta(meta, meta_end);
             Ok((Type::Tuple(fields), meta))
+        } else if let Some(meta) = self.next_matches(&TokenEnum::KeywordFn) {
+            self.expect(&TokenEnum::LeftParen)?;
+            let mut params = vec![];
+            if !self.peek(&TokenEnum::RightParen) {
+                let (ty, _) = self.parse_type()?;
+                params.push(ty);
+                while self.next_matches(&TokenEnum::Comma).is_some() {
+                    let (ty, _) = self.parse_type()?;
+                    params.push(ty);
+                }
+            }
+            self.expect(&TokenEnum::RightParen)?;
+            self.expect(&TokenEnum::Arrow)?;
+            let (ret_ty, meta_end) = self.parse_type()?;
+            let meta = join_meta(meta, meta_end);
+            // fns are not values, so fn types are parsed only to report them at their span:
+            self.errors
+                .push(ParseError(ParseErrorEnum::InvalidFnType, meta));
+            Ok((Type::Fn(params, Box::new(ret_ty)), meta))
         } else if let Some(meta) = self.next_matches(&TokenEnum::LeftBracket) {
             let (ty, _) = self.parse_type()?;
             self.expect(&TokenEnum::Semicolon)?;
@@ -1488,11 +1664,37 @@ impl Parser {
                 "u16" => Type::Unsigned(UnsignedNumType::U16),
                 "u32" => Type::Unsigned(UnsignedNumType::U32),
                 "u64" => Type::Unsigned(UnsignedNumType::U64),
+                "u256" => Type::Unsigned(UnsignedNumType::U256),
                 "i8" => Type::Signed(SignedNumType::I8),
                 "i16" => Type::Signed(SignedNumType::I16),
                 "i32" => Type::Signed(SignedNumType::I32),
                 "i64" => Type::Signed(SignedNumType::I64),
-                identifier => Type::UntypedTopLevelDefinition(identifier.to_string(), meta),
+                "char" => Type::Char,
+                "String" if self.next_matches(&TokenEnum::LessThan).is_some() => {
+                    // String<max_len>
+                    let max_len = match self.tokens.peek().cloned() {
+                        Some(Token(
+                            TokenEnum::UnsignedNum(
+                                n,
+                                UnsignedNumType::Unspecified | UnsignedNumType::Usize,
+                            ),
+                            _,
+                        )) => {
+                            self.advance();
+                            n as usize
+                        }
+                        _ => {
+                            self.push_error_for_next(ParseErrorEnum::InvalidStringLength);
+                            return Err(());
+                        }
+                    };
+                    let meta_end = self.expect(&TokenEnum::GreaterThan)?;
+                    return Ok((Type::String(max_len), join_meta(meta, meta_end)));
+                }
+                identifier => match parse_fixed_suffix(identifier) {
+                    Some((bits, frac)) => Type::Fixed(bits, frac),
+                    None => Type::UntypedTopLevelDefinition(identifier.to_string(), meta),
+                },
             };
             Ok((ty, meta))
         }
@@ -1653,6 +1855,21 @@ impl Parser {
     }
 }
 
+// Desugars `x op= value` into `x = x op value`. Every synthetic node gets the span of the code
+// that it was derived from (the read of `x` gets the span of `x`, the op the span of the whole
+// statement), so that type errors and panics point at the code that the user actually wrote.
+fn desugar_compound_assignment(
+    identifier: String,
+    identifier_meta: MetaInfo,
+    op: Op,
+    value: UntypedExpr,
+    meta: MetaInfo,
+) -> UntypedStmt {
+    let read = Expr::untyped(ExprEnum::Identifier(identifier.clone()), identifier_meta);
+    let binary_op = Expr::untyped(ExprEnum::Op(op, Box::new(read), Box::new(value)), meta);
+    Stmt::new(StmtEnum::VarAssign(identifier, binary_op), meta)
+}
+
 fn join_expr_meta(x: &UntypedExpr, y: &UntypedExpr) -> MetaInfo {
     join_meta(x.meta, y.meta)
 }
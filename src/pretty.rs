@@ -0,0 +1,463 @@
+//! Prints a type-checked [`crate::ast::Program`] as source-like text, with the inferred types of
+//! bindings added as comments.
+
+use std::fmt::Write;
+
+use crate::{
+    ast::{
+        ConstExpr, ConstExprEnum, ExprEnum, Mutability, Op, StmtEnum, Type, UnaryOp,
+        VariantExprEnum,
+    },
+    check::is_prelude_enum,
+    literal::be_bytes_as_decimal,
+    token::{format_fixed, SignedNumType, UnsignedNumType},
+    TypedExpr, TypedFnDef, TypedProgram, TypedStmt,
+};
+
+const INDENT: &str = "    ";
+
+impl std::fmt::Display for TypedProgram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // top level defs are stored in hash maps, so they are sorted to get a deterministic output:
+        let mut defs = vec![];
+        let mut const_defs: Vec<_> = self.const_defs.iter().collect();
+        const_defs.sort_by_key(|(name, _)| *name);
+        for (name, const_def) in const_defs {
+            defs.push(format!(
+                "const {name}: {} = {};\n",
+                const_def.ty, const_def.value
+            ));
+        }
+        let mut struct_defs: Vec<_> = self.struct_defs.iter().collect();
+        struct_defs.sort_by_key(|(name, _)| *name);
+        for (name, struct_def) in struct_defs {
+            let mut def = format!("struct {name} {{\n");
+            for (field_name, field_ty) in struct_def.fields.iter() {
+                def += &format!("{INDENT}{field_name}: {field_ty},\n");
+            }
+            def += "}\n";
+            defs.push(def);
+        }
+        // enums of the prelude are available in every program and thus never printed:
+        let mut enum_defs: Vec<_> = self
+            .enum_defs
+            .iter()
+            .filter(|(name, enum_def)| !is_prelude_enum(name, enum_def))
+            .collect();
+        enum_defs.sort_by_key(|(name, _)| *name);
+        for (name, enum_def) in enum_defs {
+            let mut def = format!("enum {name} {{\n");
+            for variant in enum_def.variants.iter() {
+                def += INDENT;
+                def += variant.variant_name();
+                if let Some(fields) = variant.types() {
+                    let fields: Vec<_> = fields.iter().map(|ty| ty.to_string()).collect();
+                    def += &format!("({})", fields.join(", "));
+                }
+                def += ",\n";
+            }
+            def += "}\n";
+            defs.push(def);
+        }
+        let mut fn_defs: Vec<_> = self.fn_defs.iter().collect();
+        fn_defs.sort_by_key(|(name, _)| *name);
+        for (_, fn_def) in fn_defs {
+            let mut def = String::new();
+            write_fn_def(&mut def, fn_def);
+            defs.push(def);
+        }
+        f.write_str(&defs.join("\n"))
+    }
+}
+
+impl std::fmt::Display for TypedExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::new();
+        write_expr(&mut out, self, 0, 0);
+        f.write_str(&out)
+    }
+}
+
+impl std::fmt::Display for ConstExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ConstExpr(expr, _) = self;
+        match expr {
+            ConstExprEnum::True => f.write_str("true"),
+            ConstExprEnum::False => f.write_str("false"),
+            ConstExprEnum::NumUnsigned(n, suffix) => {
+                f.write_fmt(format_args!("{n}{}", unsigned_suffix(*suffix)))
+            }
+            ConstExprEnum::NumSigned(n, suffix) => {
+                f.write_fmt(format_args!("{n}{}", signed_suffix(*suffix)))
+            }
+            ConstExprEnum::ExternalValue { party, identifier } => {
+                f.write_fmt(format_args!("{party}::{identifier}"))
+            }
+            ConstExprEnum::Max(args) | ConstExprEnum::Min(args) => {
+                let name = if let ConstExprEnum::Max(_) = expr {
+                    "max"
+                } else {
+                    "min"
+                };
+                let args: Vec<_> = args.iter().map(|arg| arg.to_string()).collect();
+                f.write_fmt(format_args!("{name}({})", args.join(", ")))
+            }
+            ConstExprEnum::Const(name) => f.write_str(name),
+            ConstExprEnum::Op(op, x, y) => f.write_fmt(format_args!("({x} {op} {y})")),
+        }
+    }
+}
+
+fn unsigned_suffix(suffix: UnsignedNumType) -> String {
+    match suffix {
+        UnsignedNumType::Unspecified => String::new(),
+        suffix => suffix.to_string(),
+    }
+}
+
+fn signed_suffix(suffix: SignedNumType) -> String {
+    match suffix {
+        SignedNumType::Unspecified => String::new(),
+        suffix => suffix.to_string(),
+    }
+}
+
+// Unsuffixed literals keep their unspecified suffix after type checking, so the suffix is taken
+// from the inferred type instead:
+fn literal_suffix(ty: &Type, suffix: String) -> String {
+    match ty {
+        Type::Unsigned(ty) => unsigned_suffix(*ty),
+        Type::Signed(ty) => signed_suffix(*ty),
+        _ => suffix,
+    }
+}
+
+fn write_fn_def(out: &mut String, fn_def: &TypedFnDef) {
+    if let Some((party, _)) = &fn_def.reveal_to {
+        let _ = writeln!(out, "#[reveal({party})]");
+    }
+    if fn_def.is_pub {
+        out.push_str("pub ");
+    }
+    let params: Vec<_> = fn_def
+        .params
+        .iter()
+        .map(|param| match param.mutability {
+            Mutability::Mutable => format!("mut {}: {}", param.name, param.ty),
+            Mutability::Immutable => format!("{}: {}", param.name, param.ty),
+        })
+        .collect();
+    let _ = write!(
+        out,
+        "fn {}({}) -> {} ",
+        fn_def.identifier,
+        params.join(", "),
+        fn_def.ty
+    );
+    write_block(out, &fn_def.body, 0);
+    out.push('\n');
+}
+
+fn write_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str(INDENT);
+    }
+}
+
+fn write_block(out: &mut String, stmts: &[TypedStmt], indent: usize) {
+    out.push_str("{\n");
+    for (i, stmt) in stmts.iter().enumerate() {
+        write_indent(out, indent + 1);
+        write_stmt(out, stmt, indent + 1, i == stmts.len() - 1);
+        out.push('\n');
+    }
+    write_indent(out, indent);
+    out.push('}');
+}
+
+fn write_stmt(out: &mut String, stmt: &TypedStmt, indent: usize, is_last: bool) {
+    match &stmt.inner {
+        StmtEnum::Let(pattern, binding) => {
+            let _ = write!(out, "let {pattern} = ");
+            write_expr(out, binding, indent, 0);
+            let _ = write!(out, "; // {}", pattern.2);
+        }
+        StmtEnum::LetMut(name, binding) => {
+            let _ = write!(out, "let mut {name} = ");
+            write_expr(out, binding, indent, 0);
+            let _ = write!(out, "; // {}", binding.ty);
+        }
+        StmtEnum::VarAssign(name, value) => {
+            let _ = write!(out, "{name} = ");
+            write_expr(out, value, indent, 0);
+            out.push(';');
+        }
+        StmtEnum::ArrayAssign(name, index, value) => {
+            let _ = write!(out, "{name}[");
+            write_expr(out, index, indent, 0);
+            out.push_str("] = ");
+            write_expr(out, value, indent, 0);
+            out.push(';');
+        }
+        StmtEnum::ForEachLoop(pattern, array, body) => {
+            let _ = write!(out, "for {pattern} in ");
+            write_expr(out, array, indent, 0);
+            let _ = write!(out, " /* {} */ ", pattern.2);
+            write_block(out, body, indent);
+        }
+        StmtEnum::JoinLoop(pattern, join_ty, (a, b), body) => {
+            let _ = write!(out, "for {pattern} in join(");
+            write_expr(out, a, indent, 0);
+            out.push_str(", ");
+            write_expr(out, b, indent, 0);
+            let _ = write!(out, ") /* {join_ty} */ ");
+            write_block(out, body, indent);
+        }
+        StmtEnum::Expr(expr) => {
+            write_expr(out, expr, indent, 0);
+            if !is_last {
+                out.push(';');
+            }
+        }
+    }
+}
+
+// Binary operators bind more tightly the higher their precedence, mirroring the parser:
+fn op_precedence(op: &Op) -> u8 {
+    match op {
+        Op::ShortCircuitOr => 1,
+        Op::ShortCircuitAnd => 2,
+        Op::Eq | Op::NotEq => 3,
+        Op::GreaterThan | Op::LessThan => 4,
+        Op::BitOr => 5,
+        Op::BitXor => 6,
+        Op::BitAnd => 7,
+        Op::ShiftLeft | Op::ShiftRight | Op::RotateLeft | Op::RotateRight => 8,
+        Op::Add | Op::Sub | Op::SaturatingAdd | Op::SaturatingSub => 9,
+        Op::Mul | Op::Div | Op::Mod | Op::SaturatingMul => 10,
+    }
+}
+
+const CAST_PRECEDENCE: u8 = 11;
+const UNARY_PRECEDENCE: u8 = 12;
+const POSTFIX_PRECEDENCE: u8 = 13;
+
+fn expr_precedence(expr: &TypedExpr) -> u8 {
+    match &expr.inner {
+        // parenthesized whenever they are used as an operand, to avoid any ambiguity:
+        ExprEnum::If(_, _, _) | ExprEnum::Match(_, _) | ExprEnum::Block(_) => 0,
+        ExprEnum::Range(_, _) => 0,
+        ExprEnum::Op(op, _, _) => op_precedence(op),
+        ExprEnum::Cast(_, _) => CAST_PRECEDENCE,
+        ExprEnum::UnaryOp(_, _) => UNARY_PRECEDENCE,
+        ExprEnum::NumSigned(n, _) | ExprEnum::NumFixed(n, _, _) if *n < 0 => UNARY_PRECEDENCE,
+        _ => POSTFIX_PRECEDENCE,
+    }
+}
+
+fn write_exprs(out: &mut String, exprs: &[TypedExpr], indent: usize) {
+    for (i, expr) in exprs.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write_expr(out, expr, indent, 0);
+    }
+}
+
+fn write_fields(out: &mut String, fields: &[(String, TypedExpr)], indent: usize) {
+    for (i, (name, value)) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        let _ = write!(out, "{name}: ");
+        write_expr(out, value, indent, 0);
+    }
+}
+
+// Writes the expr, wrapped in parentheses if it binds less tightly than `min_precedence`:
+fn write_expr(out: &mut String, expr: &TypedExpr, indent: usize, min_precedence: u8) {
+    let needs_parens = expr_precedence(expr) < min_precedence;
+    if needs_parens {
+        out.push('(');
+    }
+    match &expr.inner {
+        ExprEnum::True => out.push_str("true"),
+        ExprEnum::False => out.push_str("false"),
+        ExprEnum::NumUnsigned(n, suffix) => {
+            let _ = write!(
+                out,
+                "{n}{}",
+                literal_suffix(&expr.ty, unsigned_suffix(*suffix))
+            );
+        }
+        ExprEnum::NumUnsignedBytes(bytes, suffix) => {
+            let _ = write!(
+                out,
+                "{}{}",
+                be_bytes_as_decimal(bytes),
+                literal_suffix(&expr.ty, unsigned_suffix(*suffix))
+            );
+        }
+        ExprEnum::NumSigned(n, suffix) => {
+            let _ = write!(
+                out,
+                "{n}{}",
+                literal_suffix(&expr.ty, signed_suffix(*suffix))
+            );
+        }
+        ExprEnum::NumFixed(n, bits, frac) => {
+            let _ = write!(out, "{}fx{bits}_{frac}", format_fixed(*n, *frac));
+        }
+        ExprEnum::Char(c) => {
+            let _ = write!(out, "{c:?}");
+        }
+        ExprEnum::StringLiteral(s) => {
+            let _ = write!(out, "{s:?}");
+        }
+        ExprEnum::Identifier(name) => out.push_str(name),
+        ExprEnum::ArrayLiteral(elems) => {
+            out.push('[');
+            write_exprs(out, elems, indent);
+            out.push(']');
+        }
+        ExprEnum::ArrayRepeatLiteral(elem, size) => {
+            out.push('[');
+            write_expr(out, elem, indent, 0);
+            let _ = write!(out, "; {size}]");
+        }
+        ExprEnum::ArrayRepeatLiteralConst(elem, size) => {
+            out.push('[');
+            write_expr(out, elem, indent, 0);
+            let _ = write!(out, "; {size}]");
+        }
+        ExprEnum::ArrayAccess(array, index) => {
+            write_expr(out, array, indent, POSTFIX_PRECEDENCE);
+            out.push('[');
+            write_expr(out, index, indent, 0);
+            out.push(']');
+        }
+        ExprEnum::ArraySlice(array, from, to) => {
+            write_expr(out, array, indent, POSTFIX_PRECEDENCE);
+            let _ = write!(out, "[{from}..{to}]");
+        }
+        ExprEnum::TupleLiteral(fields) => {
+            out.push('(');
+            write_exprs(out, fields, indent);
+            out.push(')');
+        }
+        ExprEnum::TupleAccess(tuple, index) => {
+            write_expr(out, tuple, indent, POSTFIX_PRECEDENCE);
+            let _ = write!(out, ".{index}");
+        }
+        ExprEnum::StructAccess(struct_expr, field) => {
+            write_expr(out, struct_expr, indent, POSTFIX_PRECEDENCE);
+            let _ = write!(out, ".{field}");
+        }
+        ExprEnum::StructLiteral(name, fields) => {
+            let _ = write!(out, "{name} {{ ");
+            write_fields(out, fields, indent);
+            out.push_str(" }");
+        }
+        ExprEnum::StructUpdate(name, fields, base) => {
+            let _ = write!(out, "{name} {{ ");
+            write_fields(out, fields, indent);
+            if !fields.is_empty() {
+                out.push_str(", ");
+            }
+            out.push_str("..");
+            write_expr(out, base, indent, POSTFIX_PRECEDENCE);
+            out.push_str(" }");
+        }
+        ExprEnum::EnumLiteral(enum_name, variant_name, variant) => {
+            let _ = write!(out, "{enum_name}::{variant_name}");
+            if let VariantExprEnum::Tuple(fields) = variant {
+                out.push('(');
+                write_exprs(out, fields, indent);
+                out.push(')');
+            }
+        }
+        ExprEnum::Match(expr, clauses) => {
+            out.push_str("match ");
+            write_expr(out, expr, indent, 1);
+            out.push_str(" {\n");
+            for (pattern, clause) in clauses {
+                write_indent(out, indent + 1);
+                let _ = write!(out, "{pattern} => ");
+                // the parser wraps each clause in a block, which is omitted for single exprs:
+                match &clause.inner {
+                    ExprEnum::Block(stmts) => match stmts.as_slice() {
+                        [TypedStmt {
+                            inner: StmtEnum::Expr(expr),
+                            ..
+                        }] => write_expr(out, expr, indent + 1, 0),
+                        _ => write_expr(out, clause, indent + 1, 0),
+                    },
+                    _ => write_expr(out, clause, indent + 1, 0),
+                }
+                let _ = writeln!(out, ", // {}", clause.ty);
+            }
+            write_indent(out, indent);
+            out.push('}');
+        }
+        ExprEnum::UnaryOp(op, x) => {
+            out.push(match op {
+                UnaryOp::Not => '!',
+                UnaryOp::Neg => '-',
+            });
+            write_expr(out, x, indent, UNARY_PRECEDENCE);
+        }
+        ExprEnum::Op(op, x, y) => {
+            // all binary operators are left-associative:
+            let precedence = op_precedence(op);
+            write_expr(out, x, indent, precedence);
+            let _ = write!(out, " {op} ");
+            write_expr(out, y, indent, precedence + 1);
+        }
+        ExprEnum::Block(stmts) => write_block(out, stmts, indent),
+        ExprEnum::FnCall(name, args) | ExprEnum::BuiltinFnCall(name, args) => {
+            let _ = write!(out, "{name}(");
+            write_exprs(out, args, indent);
+            out.push(')');
+        }
+        ExprEnum::If(condition, case_true, case_false) => {
+            out.push_str("if ");
+            write_expr(out, condition, indent, 1);
+            out.push(' ');
+            write_branch(out, case_true, indent);
+            out.push_str(" else ");
+            match &case_false.inner {
+                ExprEnum::If(_, _, _) => write_expr(out, case_false, indent, 0),
+                _ => write_branch(out, case_false, indent),
+            }
+        }
+        ExprEnum::Cast(ty, x) => {
+            write_expr(out, x, indent, CAST_PRECEDENCE);
+            let _ = write!(out, " as {ty}");
+        }
+        ExprEnum::Range((min, min_suffix), (max, max_suffix)) => {
+            let _ = write!(
+                out,
+                "{min}{}..{max}{}",
+                unsigned_suffix(*min_suffix),
+                unsigned_suffix(*max_suffix)
+            );
+        }
+    }
+    if needs_parens {
+        out.push(')');
+    }
+}
+
+fn write_branch(out: &mut String, branch: &TypedExpr, indent: usize) {
+    match &branch.inner {
+        ExprEnum::Block(stmts) => write_block(out, stmts, indent),
+        _ => {
+            out.push_str("{\n");
+            write_indent(out, indent + 1);
+            write_expr(out, branch, indent + 1, 0);
+            out.push('\n');
+            write_indent(out, indent);
+            out.push('}');
+        }
+    }
+}
@@ -2,7 +2,10 @@
 
 use std::{iter::Peekable, str::Chars};
 
-use crate::token::{MetaInfo, SignedNumType, Token, TokenEnum, UnsignedNumType};
+use crate::{
+    literal::{digits_as_be_bytes, Literal},
+    token::{parse_fixed_suffix, MetaInfo, SignedNumType, Token, TokenEnum, UnsignedNumType},
+};
 
 /// An error found during scanning, with its location in the source code.
 #[derive(Debug, Clone)]
@@ -17,6 +20,16 @@ pub enum ScanErrorEnum {
     InvalidUnsignedNum,
     /// The scanned token is not a valid signed number.
     InvalidSignedNum,
+    /// The number literal does not fit into the type given by its suffix.
+    NumOutOfRange(String),
+    /// The suffix of the fixed-point literal is not a valid `fx<bits>_<frac>` type.
+    InvalidFixedNum,
+    /// The string literal is not closed before the end of the line.
+    UnterminatedStr,
+    /// The escape sequence in a string or char literal is not supported.
+    InvalidEscapeSequence,
+    /// The char literal does not consist of exactly one (possibly escaped) character.
+    InvalidChar,
 }
 
 impl std::fmt::Display for ScanErrorEnum {
@@ -25,6 +38,11 @@ impl std::fmt::Display for ScanErrorEnum {
             ScanErrorEnum::UnexpectedCharacter => f.write_str("Unexpected character"),
             ScanErrorEnum::InvalidUnsignedNum => f.write_str("Invalid unsigned number"),
             ScanErrorEnum::InvalidSignedNum => f.write_str("Invalid signed number"),
+            ScanErrorEnum::NumOutOfRange(ty) => write!(f, "Number literal out of range for {ty}"),
+            ScanErrorEnum::InvalidFixedNum => f.write_str("Invalid fixed-point number"),
+            ScanErrorEnum::UnterminatedStr => f.write_str("Unterminated string literal"),
+            ScanErrorEnum::InvalidEscapeSequence => f.write_str("Invalid escape sequence"),
+            ScanErrorEnum::InvalidChar => f.write_str("Invalid char literal"),
         }
     }
 }
@@ -76,8 +94,55 @@ impl<'a> Scanner<'a> {
                 '}' => self.push_token(TokenEnum::RightBrace),
                 '[' => self.push_token(TokenEnum::LeftBracket),
                 ']' => self.push_token(TokenEnum::RightBracket),
+                '#' => self.push_token(TokenEnum::Hash),
                 ',' => self.push_token(TokenEnum::Comma),
                 ';' => self.push_token(TokenEnum::Semicolon),
+                '"' => {
+                    let mut s = String::new();
+                    let mut is_terminated = false;
+                    while let Some(c) = self.chars.peek().copied() {
+                        if c == '\n' {
+                            break;
+                        }
+                        self.advance();
+                        match c {
+                            '"' => {
+                                is_terminated = true;
+                                break;
+                            }
+                            '\\' => {
+                                if let Some(escaped) = self.next_escaped_char() {
+                                    s.push(escaped);
+                                }
+                            }
+                            c => s.push(c),
+                        }
+                    }
+                    if is_terminated {
+                        self.push_token(TokenEnum::Str(s));
+                    } else {
+                        self.push_error(ScanErrorEnum::UnterminatedStr);
+                    }
+                }
+                '\'' => {
+                    let c = match self.chars.peek().copied() {
+                        Some('\\') => {
+                            self.advance();
+                            self.next_escaped_char()
+                        }
+                        Some('\'' | '\n') | None => None,
+                        Some(c) => {
+                            self.advance();
+                            Some(c)
+                        }
+                    };
+                    match c {
+                        Some(c) if self.next_matches('\'') => {
+                            self.push_token(TokenEnum::Char(c));
+                        }
+                        _ => self.push_error(ScanErrorEnum::InvalidChar),
+                    }
+                }
                 '.' => {
                     if self.next_matches('.') {
                         if self.next_matches('=') {
@@ -141,6 +206,8 @@ impl<'a> Scanner<'a> {
                     if self.next_matches('>') {
                         if self.next_matches('=') {
                             self.push_token(TokenEnum::ShrAssign);
+                        } else if self.next_matches('>') {
+                            self.push_token(TokenEnum::TripleGreaterThan);
                         } else {
                             self.push_token(TokenEnum::DoubleGreaterThan);
                         }
@@ -154,6 +221,8 @@ impl<'a> Scanner<'a> {
                     if self.next_matches('<') {
                         if self.next_matches('=') {
                             self.push_token(TokenEnum::ShlAssign);
+                        } else if self.next_matches('<') {
+                            self.push_token(TokenEnum::TripleLessThan);
                         } else {
                             self.push_token(TokenEnum::DoubleLessThan);
                         }
@@ -173,6 +242,8 @@ impl<'a> Scanner<'a> {
                 '*' => {
                     if self.next_matches('=') {
                         self.push_token(TokenEnum::MulAssign);
+                    } else if self.next_matches('|') {
+                        self.push_token(TokenEnum::StarBar);
                     } else {
                         self.push_token(TokenEnum::Star);
                     }
@@ -180,6 +251,8 @@ impl<'a> Scanner<'a> {
                 '+' => {
                     if self.next_matches('=') {
                         self.push_token(TokenEnum::AddAssign);
+                    } else if self.next_matches('|') {
+                        self.push_token(TokenEnum::PlusBar);
                     } else {
                         self.push_token(TokenEnum::Plus);
                     }
@@ -217,94 +290,132 @@ impl<'a> Scanner<'a> {
                         self.push_token(TokenEnum::SubAssign);
                     } else if self.next_matches('>') {
                         self.push_token(TokenEnum::Arrow);
+                    } else if self.next_matches('|') {
+                        self.push_token(TokenEnum::MinusBar);
                     } else {
-                        let mut digits = vec!['-'];
-                        while let Some(digit) = self.next_matches_digit() {
-                            digits.push(digit);
-                        }
-                        if digits.len() == 1 {
-                            self.push_token(TokenEnum::Minus);
-                        } else {
-                            let n: String = digits.into_iter().collect();
-                            if let Ok(n) = n.parse::<i64>() {
-                                let mut literal_suffix = String::new();
-                                while let Some(char) = self.next_matches_alphanumeric() {
-                                    literal_suffix.push(char);
-                                }
-                                let literal_suffix = match literal_suffix.as_str() {
-                                    "i8" if n >= i8::MIN as i64 && n <= i8::MAX as i64 => {
-                                        SignedNumType::I8
-                                    }
-                                    "i16" if n >= i16::MIN as i64 && n <= i16::MAX as i64 => {
-                                        SignedNumType::I16
-                                    }
-                                    "i32" if n >= i32::MIN as i64 && n <= i32::MAX as i64 => {
-                                        SignedNumType::I32
-                                    }
-                                    "i64" if (i64::MIN..=i64::MAX).contains(&n) => {
-                                        SignedNumType::I64
-                                    }
-                                    "" => SignedNumType::Unspecified,
-                                    _ => {
-                                        self.push_error(ScanErrorEnum::InvalidUnsignedNum);
-                                        SignedNumType::I64
-                                    }
-                                };
-                                self.push_token(TokenEnum::SignedNum(n, literal_suffix));
-                            } else {
-                                self.push_error(ScanErrorEnum::InvalidSignedNum);
-                            }
-                        }
-                    }
-                }
-                c => {
-                    if is_digit(c) {
-                        let mut digits = vec![c];
-                        while let Some(digit) = self.next_matches_digit() {
-                            digits.push(digit);
-                        }
-                        let n: String = digits.into_iter().collect();
-                        if let Ok(n) = n.parse::<u64>() {
+                        if let Some(first) = self.next_matches_digit() {
+                            let n = self
+                                .next_num_digits(first)
+                                .and_then(|n| be_bytes_as_u64(&n));
+                            let fraction = self.next_fixed_fraction();
                             let mut literal_suffix = String::new();
                             while let Some(char) = self.next_matches_alphanumeric() {
                                 literal_suffix.push(char);
                             }
-                            let token = match literal_suffix.as_str() {
-                                "i8" if n <= i8::MAX as u64 => {
-                                    TokenEnum::SignedNum(n as i64, SignedNumType::I8)
-                                }
-                                "i16" if n <= i16::MAX as u64 => {
-                                    TokenEnum::SignedNum(n as i64, SignedNumType::I16)
-                                }
-                                "i32" if n <= i32::MAX as u64 => {
-                                    TokenEnum::SignedNum(n as i64, SignedNumType::I32)
-                                }
-                                "i64" if n <= i64::MAX as u64 => {
-                                    TokenEnum::SignedNum(n as i64, SignedNumType::I64)
+                            if literal_suffix.starts_with("fx") {
+                                self.push_fixed_num(n, &fraction, true, literal_suffix);
+                                continue;
+                            }
+                            let n = n.and_then(|n| i64::try_from(-(n as i128)).ok());
+                            let Some(n) = n else {
+                                if literal_suffix.is_empty() {
+                                    self.push_token_error(ScanErrorEnum::InvalidSignedNum);
+                                } else {
+                                    let e = ScanErrorEnum::NumOutOfRange(literal_suffix);
+                                    self.push_token_error(e);
                                 }
-                                "usize" if n <= usize::MAX as u64 => {
-                                    TokenEnum::UnsignedNum(n, UnsignedNumType::Usize)
+                                continue;
+                            };
+                            let literal_suffix = match literal_suffix.as_str() {
+                                "i8" if n >= i8::MIN as i64 && n <= i8::MAX as i64 => {
+                                    SignedNumType::I8
                                 }
-                                "u8" if n <= u8::MAX as u64 => {
-                                    TokenEnum::UnsignedNum(n, UnsignedNumType::U8)
+                                "i16" if n >= i16::MIN as i64 && n <= i16::MAX as i64 => {
+                                    SignedNumType::I16
                                 }
-                                "u16" if n <= u16::MAX as u64 => {
-                                    TokenEnum::UnsignedNum(n, UnsignedNumType::U16)
+                                "i32" if n >= i32::MIN as i64 && n <= i32::MAX as i64 => {
+                                    SignedNumType::I32
                                 }
-                                "u32" if n <= u32::MAX as u64 => {
-                                    TokenEnum::UnsignedNum(n, UnsignedNumType::U32)
+                                "i64" => SignedNumType::I64,
+                                "" => SignedNumType::Unspecified,
+                                "i8" | "i16" | "i32" => {
+                                    let e = ScanErrorEnum::NumOutOfRange(literal_suffix);
+                                    self.push_token_error(e);
+                                    continue;
                                 }
-                                "u64" => TokenEnum::UnsignedNum(n, UnsignedNumType::U64),
-                                "" => TokenEnum::UnsignedNum(n, UnsignedNumType::Unspecified),
                                 _ => {
                                     self.push_error(ScanErrorEnum::InvalidUnsignedNum);
-                                    TokenEnum::UnsignedNum(n, UnsignedNumType::U64)
+                                    SignedNumType::I64
                                 }
                             };
-                            self.push_token(token);
+                            self.push_token(TokenEnum::SignedNum(n, literal_suffix));
                         } else {
-                            self.push_error(ScanErrorEnum::InvalidUnsignedNum);
+                            self.push_token(TokenEnum::Minus);
                         }
+                    }
+                }
+                c => {
+                    if is_digit(c) {
+                        let bytes = self.next_num_digits(c);
+                        let n = bytes.as_deref().and_then(be_bytes_as_u64);
+                        let fraction = self.next_fixed_fraction();
+                        let mut literal_suffix = String::new();
+                        while let Some(char) = self.next_matches_alphanumeric() {
+                            literal_suffix.push(char);
+                        }
+                        if literal_suffix.starts_with("fx") {
+                            self.push_fixed_num(n, &fraction, false, literal_suffix);
+                            continue;
+                        }
+                        let Some(n) = n else {
+                            let suffix = match literal_suffix.as_str() {
+                                "u256" => UnsignedNumType::U256,
+                                _ => UnsignedNumType::Unspecified,
+                            };
+                            let big_num = bytes
+                                .filter(|_| matches!(literal_suffix.as_str(), "u256" | ""))
+                                .filter(|n| {
+                                    Literal::from_be_bytes(n, UnsignedNumType::U256).is_some()
+                                });
+                            if let Some(n) = big_num {
+                                self.push_token(TokenEnum::UnsignedBigNum(n, suffix));
+                                continue;
+                            }
+                            if literal_suffix.is_empty() {
+                                self.push_token_error(ScanErrorEnum::InvalidUnsignedNum);
+                            } else {
+                                self.push_token_error(ScanErrorEnum::NumOutOfRange(literal_suffix));
+                            }
+                            continue;
+                        };
+                        let token = match literal_suffix.as_str() {
+                            "i8" if n <= i8::MAX as u64 => {
+                                TokenEnum::SignedNum(n as i64, SignedNumType::I8)
+                            }
+                            "i16" if n <= i16::MAX as u64 => {
+                                TokenEnum::SignedNum(n as i64, SignedNumType::I16)
+                            }
+                            "i32" if n <= i32::MAX as u64 => {
+                                TokenEnum::SignedNum(n as i64, SignedNumType::I32)
+                            }
+                            "i64" if n <= i64::MAX as u64 => {
+                                TokenEnum::SignedNum(n as i64, SignedNumType::I64)
+                            }
+                            "usize" if n <= usize::MAX as u64 => {
+                                TokenEnum::UnsignedNum(n, UnsignedNumType::Usize)
+                            }
+                            "u8" if n <= u8::MAX as u64 => {
+                                TokenEnum::UnsignedNum(n, UnsignedNumType::U8)
+                            }
+                            "u16" if n <= u16::MAX as u64 => {
+                                TokenEnum::UnsignedNum(n, UnsignedNumType::U16)
+                            }
+                            "u32" if n <= u32::MAX as u64 => {
+                                TokenEnum::UnsignedNum(n, UnsignedNumType::U32)
+                            }
+                            "u64" => TokenEnum::UnsignedNum(n, UnsignedNumType::U64),
+                            "u256" => TokenEnum::UnsignedNum(n, UnsignedNumType::U256),
+                            "" => TokenEnum::UnsignedNum(n, UnsignedNumType::Unspecified),
+                            "i8" | "i16" | "i32" | "i64" | "usize" | "u8" | "u16" | "u32" => {
+                                self.push_token_error(ScanErrorEnum::NumOutOfRange(literal_suffix));
+                                continue;
+                            }
+                            _ => {
+                                self.push_error(ScanErrorEnum::InvalidUnsignedNum);
+                                TokenEnum::UnsignedNum(n, UnsignedNumType::U64)
+                            }
+                        };
+                        self.push_token(token);
                     } else if is_alphanumeric(c) {
                         let mut chars = vec![c];
                         while let Some(char) = self.next_matches_alphanumeric() {
@@ -342,6 +453,25 @@ impl<'a> Scanner<'a> {
         }
     }
 
+    // Scans the char following a `\` in a string or char literal.
+    fn next_escaped_char(&mut self) -> Option<char> {
+        let escaped = match self.chars.peek().copied() {
+            Some('"') => '"',
+            Some('\'') => '\'',
+            Some('\\') => '\\',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            _ => {
+                self.push_error(ScanErrorEnum::InvalidEscapeSequence);
+                return None;
+            }
+        };
+        self.advance();
+        Some(escaped)
+    }
+
     fn next_matches_alphanumeric(&mut self) -> Option<char> {
         if let Some(c) = self.chars.peek().copied() {
             if is_alphanumeric(c) {
@@ -352,6 +482,64 @@ impl<'a> Scanner<'a> {
         None
     }
 
+    // Scans the rest of a number literal starting with `first`, which may use a `0x`, `0o` or `0b`
+    // prefix and `_` as a separator between digits. Returns the number as big-endian bytes without
+    // leading zeros, or `None` if there are no digits.
+    fn next_num_digits(&mut self, first: char) -> Option<Vec<u8>> {
+        let mut radix = 10;
+        let mut digits = String::new();
+        if first == '0' && self.next_matches('x') {
+            radix = 16;
+        } else if first == '0' && self.next_matches('o') {
+            radix = 8;
+        } else if first == '0' && self.next_matches('b') {
+            radix = 2;
+        } else {
+            digits.push(first);
+        }
+        while let Some(c) = self.chars.peek().copied() {
+            if c.is_digit(radix) {
+                digits.push(c);
+            } else if c != '_' {
+                break;
+            }
+            self.advance();
+        }
+        digits_as_be_bytes(&digits, radix)
+    }
+
+    // Scans the fractional digits of a fixed-point literal, but only if the `.` is followed by
+    // digits and an `fx` suffix, so that tuple accesses like `t.0.1` are still scanned as such:
+    fn next_fixed_fraction(&mut self) -> String {
+        let mut lookahead = self.chars.clone();
+        let mut fraction = String::new();
+        if lookahead.next() != Some('.') {
+            return fraction;
+        }
+        loop {
+            match lookahead.next() {
+                Some(c) if is_digit(c) => fraction.push(c),
+                Some('f') if !fraction.is_empty() && lookahead.next() == Some('x') => break,
+                _ => return String::new(),
+            }
+        }
+        for _ in 0..=fraction.len() {
+            self.advance();
+        }
+        fraction
+    }
+
+    fn push_fixed_num(&mut self, n: Option<u64>, fraction: &str, negative: bool, suffix: String) {
+        let Some((bits, frac)) = parse_fixed_suffix(&suffix) else {
+            self.push_token_error(ScanErrorEnum::InvalidFixedNum);
+            return;
+        };
+        match n.and_then(|n| fixed_from_decimal(n, fraction, negative, bits, frac)) {
+            Some(n) => self.push_token(TokenEnum::FixedNum(n, bits, frac)),
+            None => self.push_token_error(ScanErrorEnum::NumOutOfRange(suffix)),
+        }
+    }
+
     fn next_matches_digit(&mut self) -> Option<char> {
         if let Some(c) = self.chars.peek().copied() {
             if is_digit(c) {
@@ -388,6 +576,17 @@ impl<'a> Scanner<'a> {
     }
 
     fn push_token(&mut self, t: TokenEnum) {
+        let meta = self.next_token_meta();
+        self.tokens.push(Token(t, meta));
+    }
+
+    // Reports an error for the whole token scanned so far (instead of just the current position):
+    fn push_token_error(&mut self, err: ScanErrorEnum) {
+        let meta = self.next_token_meta();
+        self.errors.push(ScanError(err, meta));
+    }
+
+    fn next_token_meta(&mut self) -> MetaInfo {
         if self.current_token_start == (self.line, self.column) {
             self.column += 1;
         }
@@ -397,7 +596,7 @@ impl<'a> Scanner<'a> {
             end: current_token_end,
         };
         self.current_token_start = current_token_end;
-        self.tokens.push(Token(t, meta));
+        meta
     }
 
     fn push_error(&mut self, err: ScanErrorEnum) {
@@ -409,6 +608,43 @@ impl<'a> Scanner<'a> {
     }
 }
 
+// Converts the decimal number `int.fraction` to a fixed-point number scaled by 2^frac, rounded to
+// the nearest representable number (and away from zero if it lies exactly in between):
+fn fixed_from_decimal(int: u64, fraction: &str, negative: bool, bits: u8, frac: u8) -> Option<i64> {
+    let mut digits: Vec<u8> = fraction.bytes().map(|b| b - b'0').collect();
+    // doubles the decimal fraction and returns the integer part that is carried out of it:
+    let double = |digits: &mut Vec<u8>| {
+        let mut carry = 0;
+        for d in digits.iter_mut().rev() {
+            let doubled = *d * 2 + carry;
+            *d = doubled % 10;
+            carry = doubled / 10;
+        }
+        carry as u128
+    };
+    let mut n = int as u128;
+    for _ in 0..frac {
+        n = (n << 1) | double(&mut digits);
+    }
+    // the remaining fraction is at least 0.5 iff doubling it once more carries out a 1:
+    let n = n.checked_add(double(&mut digits))?;
+    let max = 1u128 << (bits - 1);
+    if negative && n <= max {
+        Some((-(n as i128)) as i64)
+    } else if !negative && n < max {
+        Some(n as i64)
+    } else {
+        None
+    }
+}
+
+fn be_bytes_as_u64(bytes: &[u8]) -> Option<u64> {
+    if bytes.len() > 8 {
+        return None;
+    }
+    Some(bytes.iter().fold(0, |n, &b| (n << 8) | b as u64))
+}
+
 fn is_digit(c: char) -> bool {
     c.is_ascii_digit()
 }
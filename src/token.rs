@@ -3,6 +3,8 @@
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use crate::literal::be_bytes_as_decimal;
+
 /// Tokens produced by [`crate::scan::scan`].
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Token(pub TokenEnum, pub MetaInfo);
@@ -14,8 +16,16 @@ pub enum TokenEnum {
     Identifier(String),
     /// Unsigned number.
     UnsignedNum(u64, UnsignedNumType),
+    /// Unsigned number that does not fit into a `u64`, as big-endian bytes without leading zeros.
+    UnsignedBigNum(Vec<u8>, UnsignedNumType),
     /// Signed number.
     SignedNum(i64, SignedNumType),
+    /// Fixed-point number (scaled by 2^frac) with its total and fractional bits.
+    FixedNum(i64, u8, u8),
+    /// String literal (without quotes and with escape sequences already resolved).
+    Str(String),
+    /// Char literal (without quotes and with escape sequences already resolved).
+    Char(char),
     /// `const` keyword.
     KeywordConst,
     /// `struct` keyword.
@@ -72,6 +82,8 @@ pub enum TokenEnum {
     LeftBracket,
     /// `]`.
     RightBracket,
+    /// `#`.
+    Hash,
     /// `+`.
     Plus,
     /// `-`.
@@ -80,6 +92,12 @@ pub enum TokenEnum {
     Slash,
     /// `*`.
     Star,
+    /// `+|`.
+    PlusBar,
+    /// `-|`.
+    MinusBar,
+    /// `*|`.
+    StarBar,
     /// `%`.
     Percent,
     /// `&`.
@@ -110,8 +128,12 @@ pub enum TokenEnum {
     LessThanEquals,
     /// `>>`.
     DoubleGreaterThan,
+    /// `>>>`.
+    TripleGreaterThan,
     /// `<<`.
     DoubleLessThan,
+    /// `<<<`.
+    TripleLessThan,
     /// `+=`.
     AddAssign,
     /// `-=`.
@@ -139,7 +161,16 @@ impl std::fmt::Display for TokenEnum {
         match self {
             TokenEnum::Identifier(s) => f.write_str(s),
             TokenEnum::UnsignedNum(num, suffix) => f.write_fmt(format_args!("{num}{suffix}")),
+            TokenEnum::UnsignedBigNum(bytes, suffix) => {
+                f.write_fmt(format_args!("{}{suffix}", be_bytes_as_decimal(bytes)))
+            }
             TokenEnum::SignedNum(num, suffix) => f.write_fmt(format_args!("{num}{suffix}")),
+            TokenEnum::FixedNum(num, bits, frac) => {
+                let num = format_fixed(*num, *frac);
+                f.write_fmt(format_args!("{num}fx{bits}_{frac}"))
+            }
+            TokenEnum::Str(s) => f.write_fmt(format_args!("{s:?}")),
+            TokenEnum::Char(c) => f.write_fmt(format_args!("{c:?}")),
             TokenEnum::KeywordConst => f.write_str("const"),
             TokenEnum::KeywordStruct => f.write_str("struct"),
             TokenEnum::KeywordEnum => f.write_str("enum"),
@@ -168,10 +199,14 @@ impl std::fmt::Display for TokenEnum {
             TokenEnum::RightBrace => f.write_str("}"),
             TokenEnum::LeftBracket => f.write_str("["),
             TokenEnum::RightBracket => f.write_str("]"),
+            TokenEnum::Hash => f.write_str("#"),
             TokenEnum::Plus => f.write_str("+"),
             TokenEnum::Minus => f.write_str("-"),
             TokenEnum::Slash => f.write_str("/"),
             TokenEnum::Star => f.write_str("*"),
+            TokenEnum::PlusBar => f.write_str("+|"),
+            TokenEnum::MinusBar => f.write_str("-|"),
+            TokenEnum::StarBar => f.write_str("*|"),
             TokenEnum::Percent => f.write_str("%"),
             TokenEnum::Ampersand => f.write_str("&"),
             TokenEnum::DoubleAmpersand => f.write_str("&&"),
@@ -187,7 +222,9 @@ impl std::fmt::Display for TokenEnum {
             TokenEnum::GreaterThanEquals => f.write_str(">="),
             TokenEnum::LessThanEquals => f.write_str("<="),
             TokenEnum::DoubleGreaterThan => f.write_str(">>"),
+            TokenEnum::TripleGreaterThan => f.write_str(">>>"),
             TokenEnum::DoubleLessThan => f.write_str("<<"),
+            TokenEnum::TripleLessThan => f.write_str("<<<"),
             TokenEnum::AddAssign => f.write_str("+="),
             TokenEnum::SubAssign => f.write_str("-="),
             TokenEnum::MulAssign => f.write_str("*="),
@@ -216,12 +253,15 @@ pub enum UnsignedNumType {
     U32,
     /// 64-bit unsigned integer type.
     U64,
+    /// 256-bit unsigned integer type.
+    U256,
     /// No type suffix has been specified, could be any from i8 to i64.
     Unspecified,
 }
 
 impl UnsignedNumType {
-    /// Returns the max value representable by this type.
+    /// Returns the max value representable by this type, or `None` if the type is unspecified or
+    /// its max value does not fit into a `u64`.
     pub fn max(&self) -> Option<u64> {
         match self {
             UnsignedNumType::Usize => Some(u32::MAX as u64),
@@ -229,7 +269,7 @@ impl UnsignedNumType {
             UnsignedNumType::U16 => Some(u16::MAX as u64),
             UnsignedNumType::U32 => Some(u32::MAX as u64),
             UnsignedNumType::U64 => Some(u64::MAX),
-            UnsignedNumType::Unspecified => None,
+            UnsignedNumType::U256 | UnsignedNumType::Unspecified => None,
         }
     }
 }
@@ -242,6 +282,7 @@ impl std::fmt::Display for UnsignedNumType {
             UnsignedNumType::U16 => "u16",
             UnsignedNumType::U32 => "u32",
             UnsignedNumType::U64 => "u64",
+            UnsignedNumType::U256 => "u256",
             UnsignedNumType::Unspecified => "unspecified unsigned int",
         })
     }
@@ -299,6 +340,40 @@ impl std::fmt::Display for SignedNumType {
     }
 }
 
+/// Parses the name `fx<bits>_<frac>` of a fixed-point type with at most 64 bits.
+pub(crate) fn parse_fixed_suffix(suffix: &str) -> Option<(u8, u8)> {
+    let (bits, frac) = suffix.strip_prefix("fx")?.split_once('_')?;
+    let is_num = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !is_num(bits) || !is_num(frac) {
+        return None;
+    }
+    let bits: u8 = bits.parse().ok()?;
+    let frac: u8 = frac.parse().ok()?;
+    if bits == 0 || bits > 64 || frac > bits {
+        return None;
+    }
+    Some((bits, frac))
+}
+
+/// Formats a fixed-point number (scaled by 2^frac) as an exact decimal number.
+pub(crate) fn format_fixed(n: i64, frac: u8) -> String {
+    let sign = if n < 0 { "-" } else { "" };
+    let n = (n as i128).unsigned_abs();
+    let mask = (1u128 << frac) - 1;
+    let mut fraction = n & mask;
+    let mut s = format!("{sign}{}.", n >> frac);
+    if fraction == 0 {
+        s.push('0');
+    }
+    // every binary fraction has a finite decimal representation of at most `frac` digits:
+    while fraction != 0 {
+        fraction *= 10;
+        s.push(char::from(b'0' + (fraction >> frac) as u8));
+        fraction &= mask;
+    }
+    s
+}
+
 /// The location of a token in the source code, from start `(line, column)` to end `(line, column)`.
 #[derive(Clone, Copy, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
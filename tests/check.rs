@@ -1,9 +1,13 @@
 use garble_lang::{
-    ast::{Pattern, PatternEnum, Type},
+    analyze,
+    ast::{Op, Pattern, PatternEnum, StmtEnum, Type},
+    check,
     check::{TypeError, TypeErrorEnum},
+    compile,
+    parse::ParseErrorEnum,
     scan::scan,
-    token::{MetaInfo, UnsignedNumType},
-    Error, TypedProgram,
+    token::{MetaInfo, SignedNumType, UnsignedNumType},
+    CompileTimeError, Error, TypedProgram,
 };
 
 #[test]
@@ -74,6 +78,40 @@ fn reject_unused_fn() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn reject_unused_let_bindings() -> Result<(), Error> {
+    let prg = "
+  pub fn main(x: u8) -> u8 {
+    let y = x + 1u8;
+    x
+  }
+  ";
+    let e = scan(prg).unwrap().parse().unwrap().type_check();
+    let e = assert_single_type_error(e);
+    assert!(matches!(e, TypeErrorEnum::UnusedVariable(name) if name == "y"));
+
+    let prg = "
+  pub fn main(x: (u8, u8)) -> u8 {
+    let (a, b) = x;
+    a
+  }
+  ";
+    let e = scan(prg).unwrap().parse().unwrap().type_check();
+    let e = assert_single_type_error(e);
+    assert!(matches!(e, TypeErrorEnum::UnusedVariable(name) if name == "b"));
+
+    let prg = "
+  pub fn main(x: (u8, u8)) -> u8 {
+    let _y = x.0 + 1u8;
+    let (a, _b) = x;
+    let mut _z = a;
+    a
+  }
+  ";
+    scan(prg).unwrap().parse().unwrap().type_check()?;
+    Ok(())
+}
+
 #[test]
 fn reject_recursive_fn() -> Result<(), Error> {
     let prg = "
@@ -95,6 +133,60 @@ fn reject_recursive_fn() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn reject_recursive_const() -> Result<(), Error> {
+    let prg = "
+const A: u8 = B + 1;
+const B: u8 = A;
+pub fn main(x: u8) -> u8 {
+  x + A
+}
+";
+    // the cycle is reported at the first const in the source, regardless of the hash order:
+    for _ in 0..10 {
+        let e = scan(prg)?.parse()?.type_check();
+        let e = assert_single_type_error(e);
+        assert_eq!(e, TypeErrorEnum::RecursiveConstDef("A".to_string()));
+    }
+    Ok(())
+}
+
+#[test]
+fn reject_overflowing_const() -> Result<(), Error> {
+    let prg = "
+const A: u8 = 200;
+const B: u8 = A * 2;
+pub fn main(x: u8) -> u8 {
+  x + B
+}
+";
+    let e = scan(prg).unwrap().parse().unwrap().type_check();
+    let e = assert_single_type_error(e);
+    assert!(matches!(e, TypeErrorEnum::InvalidConstOp(Op::Mul)));
+    Ok(())
+}
+
+#[test]
+fn reject_non_usize_const_as_array_size() -> Result<(), Error> {
+    let prg = "
+const LEN: u8 = 3;
+pub fn main(x: u8) -> [u8; LEN] {
+  [x; LEN]
+}
+";
+    let e = scan(prg).unwrap().parse().unwrap().type_check();
+    let Err(errors) = e else {
+        panic!("expected type errors");
+    };
+    for e in errors {
+        assert!(matches!(
+            e,
+            TypeError(TypeErrorEnum::ArraySizeNotUsize(_, _), _)
+        ));
+    }
+    Ok(())
+}
+
 #[test]
 fn reject_main_without_params() -> Result<(), Error> {
     let prg = "
@@ -108,6 +200,86 @@ pub fn main() -> u8 {
     Ok(())
 }
 
+#[test]
+fn reject_missing_main() -> Result<(), Error> {
+    let prg = "
+pub fn add(x: u8, y: u8) -> u8 {
+  x + y
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let Err(mut e) = e else {
+        panic!("Expected a type error, but found {e:?}");
+    };
+    assert_eq!(e.len(), 1);
+    let TypeError(e, meta) = e.pop().unwrap();
+    assert!(matches!(e, TypeErrorEnum::MissingMain));
+    assert_eq!(meta.end, (3, 1));
+    let e = check(prg);
+    assert!(matches!(
+        e,
+        Err(Error::CompileTimeError(CompileTimeError::TypeError(_)))
+    ));
+    Ok(())
+}
+
+#[test]
+fn reject_duplicate_main() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8) -> u8 {
+  x
+}
+
+pub fn main(x: u8) -> u8 {
+  x + 1u8
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let Err(mut e) = e else {
+        panic!("Expected a type error, but found {e:?}");
+    };
+    assert_eq!(e.len(), 1);
+    let TypeError(e, meta) = e.pop().unwrap();
+    assert!(matches!(e, TypeErrorEnum::DuplicateMain));
+    assert_eq!(meta.end, (7, 1));
+
+    let prg = "
+fn inc(x: u8) -> u8 {
+  x + 1u8
+}
+
+fn inc(x: u8) -> u8 {
+  x + 2u8
+}
+
+pub fn main(x: u8) -> u8 {
+  inc(x)
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let Err(mut e) = e else {
+        panic!("Expected a type error, but found {e:?}");
+    };
+    assert_eq!(e.len(), 1);
+    let TypeError(e, meta) = e.pop().unwrap();
+    assert!(matches!(e, TypeErrorEnum::DuplicateFnDef(name) if name == "inc"));
+    assert_eq!(meta.end, (7, 1));
+    Ok(())
+}
+
+#[test]
+fn reject_heterogeneous_tuple_to_array() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8) -> [u8; 2] {
+  tuple_to_array((x, true))
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert!(matches!(e, TypeErrorEnum::ExpectedHomogeneousTupleType(_)));
+    Ok(())
+}
+
 #[test]
 fn reject_non_exhaustive_range_pattern() -> Result<(), Error> {
     let prg = "
@@ -230,6 +402,111 @@ pub fn main(x: i32) -> i32 {
     Ok(())
 }
 
+#[test]
+fn reject_assignment_to_immutable_variables() -> Result<(), Error> {
+    let immutable = [
+        "
+pub fn main(x: u8) -> u8 {
+  let y = x;
+  y = 1u8;
+  y
+}
+",
+        "
+pub fn main(x: u8) -> u8 {
+  x += 1u8;
+  x
+}
+",
+        "
+pub fn main(arr: [u8; 4]) -> [u8; 4] {
+  let a = arr;
+  a[0] = 1u8;
+  a
+}
+",
+        "
+pub fn main(arr: [u8; 4]) -> u8 {
+  let mut sum = 0u8;
+  for x in arr {
+    x = 1u8;
+    sum = sum + x;
+  }
+  sum
+}
+",
+    ];
+    for prg in immutable {
+        let e = scan(prg)?.parse()?.type_check();
+        let e = assert_single_type_error(e);
+        assert!(matches!(
+            e,
+            TypeErrorEnum::IdentifierNotDeclaredAsMutable(_)
+        ));
+    }
+
+    let undeclared = [
+        "
+pub fn main(x: u8) -> u8 {
+  y = x;
+  x
+}
+",
+        "
+pub fn main(x: u8) -> u8 {
+  a[0] = x;
+  x
+}
+",
+    ];
+    for prg in undeclared {
+        let e = scan(prg)?.parse()?.type_check();
+        let e = assert_single_type_error(e);
+        assert!(matches!(e, TypeErrorEnum::UnknownIdentifier(_)));
+    }
+
+    let prg = "
+pub fn main(x: u8, arr: [u8; 4]) -> [u8; 4] {
+  let mut y = x;
+  y += 1u8;
+  let mut a = arr;
+  a[0] = y;
+  a
+}
+";
+    scan(prg)?.parse()?.type_check()?;
+    Ok(())
+}
+
+#[test]
+fn prettify_parse_and_type_errors() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8) -> u8 {
+    x +
+}
+";
+    let e = scan(prg)?.parse().unwrap_err();
+    let pretty = Error::from(e.clone()).prettify(prg);
+    assert_eq!(e[0].prettify(prg), pretty);
+    assert!(pretty.contains("Parse error on line 3:8."), "{pretty}");
+    assert!(pretty.contains("   4 > | }\n     > | ^\n"), "{pretty}");
+
+    let prg = "
+pub fn main(x: u8) -> u8 {
+    x + true
+}
+";
+    let e = scan(prg)?.parse()?.type_check().unwrap_err();
+    let pretty = Error::from(e.clone()).prettify(prg);
+    assert_eq!(e[0].prettify(prg), pretty);
+    assert!(pretty.contains("Type error on line 3:5."), "{pretty}");
+    assert!(
+        pretty.contains("   3 > |     x + true\n     > |     ^^^^^^^^\n"),
+        "{pretty}"
+    );
+    Ok(())
+}
+
 fn assert_single_type_error(e: Result<TypedProgram, Vec<TypeError>>) -> TypeErrorEnum {
     if let Err(mut e) = e {
         if e.len() == 1 {
@@ -241,3 +518,765 @@ fn assert_single_type_error(e: Result<TypedProgram, Vec<TypeError>>) -> TypeErro
         panic!("Expected an error, but found {e:?}");
     }
 }
+
+#[test]
+fn reject_constant_condition_in_strict_mode() -> Result<(), Error> {
+    let prg = "
+pub fn main(a: u8, b: u8) -> u8 {
+  if true { a } else { b }
+}
+";
+    let e = scan(prg)?.parse()?.type_check_strict();
+    let e = assert_single_type_error(e);
+    assert_eq!(e, TypeErrorEnum::ConstantCondition(true));
+
+    let compiled = compile(prg)?;
+    let mut eval = compiled.evaluator();
+    eval.set_u8(1);
+    eval.set_u8(2);
+    let output = eval.run()?;
+    assert_eq!(u8::try_from(output)?, 1);
+    Ok(())
+}
+
+#[test]
+fn reject_match_with_missing_enum_variant() -> Result<(), Error> {
+    let prg = "
+enum Op {
+  Add(u8, u8),
+  Sub(u8, u8),
+  Zero,
+}
+
+pub fn main(op: Op) -> u8 {
+  match op {
+    Op::Add(x, y) => x + y,
+    Op::Zero => 0u8,
+  }
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    let TypeErrorEnum::PatternsAreNotExhaustive(missing) = e else {
+        panic!("Expected non-exhaustive patterns, but found {e:?}");
+    };
+    assert_eq!(missing.len(), 1);
+    assert!(matches!(
+        &missing[0][0],
+        Pattern(PatternEnum::EnumTuple(_, variant, _), _, _) if variant == "Sub"
+    ));
+    Ok(())
+}
+
+#[test]
+fn reject_unreachable_match_clause_in_strict_mode() -> Result<(), Error> {
+    let prg = "
+enum Op {
+  Add(u8, u8),
+  Sub(u8, u8),
+  Zero,
+}
+
+pub fn main(op: Op) -> u8 {
+  match op {
+    Op::Add(x, y) => x + y,
+    _ => 0u8,
+    Op::Zero => 1u8,
+  }
+}
+";
+    assert!(scan(prg)?.parse()?.type_check().is_ok());
+    let e = scan(prg)?.parse()?.type_check_strict();
+    let Err(mut e) = e else {
+        panic!("Expected a type error, but found {e:?}");
+    };
+    assert_eq!(e.len(), 1);
+    let TypeError(e, meta) = e.pop().unwrap();
+    assert_eq!(e, TypeErrorEnum::UnreachablePattern);
+    assert_eq!(meta.start, (11, 4));
+
+    let prg = "
+pub fn main(x: u8) -> u8 {
+  match x {
+    0u8..10u8 => 0u8,
+    5u8 => 1u8,
+    10u8..=255u8 => 2u8,
+  }
+}
+";
+    let e = scan(prg)?.parse()?.type_check_strict();
+    let e = assert_single_type_error(e);
+    assert_eq!(e, TypeErrorEnum::UnreachablePattern);
+
+    let prg = "
+pub fn main(x: (bool, bool)) -> u8 {
+  match x {
+    (true, _) => 0u8,
+    (false, true) => 1u8,
+    (_, false) => 2u8,
+  }
+}
+";
+    assert!(scan(prg)?.parse()?.type_check_strict().is_ok());
+    Ok(())
+}
+
+#[test]
+fn reject_array_of_fns() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8, fs: [fn(u8) -> u8; 2]) -> u8 {
+  x
+}
+";
+    let e = scan(prg)?.parse().unwrap_err();
+    assert_eq!(e.len(), 1);
+    assert!(matches!(e[0].0, ParseErrorEnum::InvalidFnType));
+    assert_eq!(
+        e[0].1,
+        MetaInfo {
+            start: (1, 24),
+            end: (1, 36),
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn reject_gather_bits_with_non_literal_mask() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8, mask: u8) -> u8 {
+  gather_bits(x, mask)
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(e, TypeErrorEnum::ExpectedNumberLiteral);
+    Ok(())
+}
+
+#[test]
+fn reject_struct_update_with_wrong_base_type() -> Result<(), Error> {
+    let prg = "
+struct Point {
+  x: u8,
+  y: u8,
+}
+
+pub fn main(x: u8) -> Point {
+  Point { x, ..(x, x) }
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert!(matches!(e, TypeErrorEnum::UnexpectedType { .. }));
+    Ok(())
+}
+
+#[test]
+fn compile_reports_type_errors_as_unified_error() -> Result<(), Error> {
+    let valid = "
+pub fn main(x: u8) -> u8 {
+  x + 1
+}
+";
+    let compiled = compile(valid)?;
+    assert_eq!(compiled.main.identifier, "main");
+    assert_eq!(compiled.main.ty, Type::Unsigned(UnsignedNumType::U8));
+
+    let invalid = "
+pub fn main(x: u8) -> u8 {
+  x + true
+}
+";
+    let e = compile(invalid);
+    assert!(matches!(
+        e,
+        Err(Error::CompileTimeError(CompileTimeError::TypeError(_)))
+    ));
+    Ok(())
+}
+
+#[test]
+fn accept_trailing_commas() -> Result<(), Error> {
+    let with_trailing_commas = "
+struct Point { x: u8, y: u8, }
+fn add(a: u8, b: u8,) -> u8 { a + b }
+pub fn main(x: u8,) -> u8 {
+    let arr = [1u8, 2u8, 3u8,];
+    let pair = (x, x,);
+    let p = Point { x: arr[0], y: pair.1, };
+    match add(p.x, p.y,) {
+        0u8 => 0u8,
+        n => n,
+    }
+}
+";
+    let without_trailing_commas = with_trailing_commas
+        .replace(",}", " }")
+        .replace(",)", " )")
+        .replace(",]", " ]")
+        .replace(", }", "  }")
+        .replace(",\n    }", "\n    }");
+    assert_ne!(with_trailing_commas, without_trailing_commas);
+    assert_eq!(
+        scan(with_trailing_commas)?.parse()?,
+        scan(&without_trailing_commas)?.parse()?
+    );
+    Ok(())
+}
+
+#[test]
+fn reject_doubled_commas() -> Result<(), Error> {
+    for expr in ["[1u8,,2u8]", "[1u8, 2u8,,]", "(1u8,,2u8)", "f(1u8,,2u8)"] {
+        let prg =
+            format!("fn f(a: u8, b: u8) -> u8 {{ a }}\npub fn main() -> u8 {{ {expr}; 0u8 }}");
+        let tokens = scan(&prg)?;
+        assert!(tokens.parse().is_err(), "{expr} should be rejected");
+    }
+    Ok(())
+}
+
+#[test]
+fn reject_histogram_with_invalid_args() -> Result<(), Error> {
+    let prg = "
+pub fn main(values: [u8; 4], buckets: usize) -> [usize; 3] {
+  histogram(values, buckets)
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(e, TypeErrorEnum::ExpectedNumberLiteral);
+
+    let prg = "
+pub fn main(values: [i8; 4]) -> [usize; 3] {
+  histogram(values, 3)
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(
+        e,
+        TypeErrorEnum::ExpectedUnsignedNumberType(Type::Signed(SignedNumType::I8))
+    );
+    Ok(())
+}
+
+#[test]
+fn reject_invalid_generic_fns() -> Result<(), Error> {
+    let prg = "
+pub fn main(_x: u8) -> u8 {
+  default()
+}
+
+fn default<T>() -> T {
+  0
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(e, TypeErrorEnum::CannotInferTypeParam("T".to_string()));
+
+    let prg = "
+pub fn main<T>(x: T) -> T {
+  x
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(e, TypeErrorEnum::PubFnWithTypeParam("main".to_string()));
+
+    let prg = "
+pub fn main(a: u8, b: u32) -> u32 {
+  sum(a, b)
+}
+
+fn sum<N>(a: N, b: N) -> N {
+  a + b
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(
+        e,
+        TypeErrorEnum::ConflictingTypeParam(
+            "N".to_string(),
+            Box::new(Type::Unsigned(UnsignedNumType::U8)),
+            Box::new(Type::Unsigned(UnsignedNumType::U32))
+        )
+    );
+
+    let prg = "
+pub fn main(a: bool) -> bool {
+  id(a)
+}
+
+fn id<T>(x: T) -> T {
+  x
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(
+        e,
+        TypeErrorEnum::NonIntegerTypeParam("T".to_string(), Type::Bool)
+    );
+    Ok(())
+}
+
+#[test]
+fn reject_invalid_map2_and_filter_mask() -> Result<(), Error> {
+    let prg = "
+pub fn main(a: [u8; 4], b: [u8; 3]) -> [u8; 4] {
+  map2(a, b, xor)
+}
+
+fn xor(x: u8, y: u8) -> u8 {
+  x ^ y
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(
+        e,
+        TypeErrorEnum::UnexpectedType {
+            expected: Type::Array(Box::new(Type::Unsigned(UnsignedNumType::U8)), 4),
+            actual: Type::Array(Box::new(Type::Unsigned(UnsignedNumType::U8)), 3),
+        }
+    );
+
+    let prg = "
+pub fn main(a: [u8; 4], b: [bool; 4]) -> [u8; 4] {
+  map2(a, b, xor)
+}
+
+fn xor(x: u8, y: u8) -> u8 {
+  x ^ y
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(
+        e,
+        TypeErrorEnum::UnexpectedType {
+            expected: Type::Unsigned(UnsignedNumType::U8),
+            actual: Type::Bool,
+        }
+    );
+
+    let prg = "
+pub fn main(a: [u8; 4], b: [u8; 4]) -> [u8; 4] {
+  map2(a, b, 1)
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(e, TypeErrorEnum::ExpectedFnName);
+
+    let prg = "
+pub fn main(a: [u8; 4]) -> [(u8, bool); 4] {
+  filter_mask(a, double)
+}
+
+fn double(x: u8) -> u8 {
+  x * 2
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(
+        e,
+        TypeErrorEnum::UnexpectedType {
+            expected: Type::Bool,
+            actual: Type::Unsigned(UnsignedNumType::U8),
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn reject_get_with_invalid_index_path() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: (u8, (u8, u8)), i: usize) -> u8 {
+  get(x, [1, i])
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(e, TypeErrorEnum::ExpectedIndexPath);
+
+    let prg = "
+pub fn main(x: (u8, (u8, u8))) -> u8 {
+  get(x, [1, 2])
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    let ty = Type::Tuple(vec![
+        Type::Unsigned(UnsignedNumType::U8),
+        Type::Unsigned(UnsignedNumType::U8),
+    ]);
+    assert_eq!(e, TypeErrorEnum::InvalidIndexPath(ty, 2));
+    Ok(())
+}
+
+#[test]
+fn analyze_program_with_inferred_types_and_diagnostics() -> Result<(), Error> {
+    let prg = "
+pub fn main(a: u8, b: u8) -> u8 {
+  let sum = a + 1;
+  if true { sum } else { b }
+}
+";
+    let analyzed = analyze(prg)?;
+    let main = analyzed.program.fn_defs.get("main").unwrap();
+    let StmtEnum::Let(Pattern(PatternEnum::Identifier(name), _, ty), _) = &main.body[0].inner
+    else {
+        panic!("Expected a let binding, but found {:?}", main.body[0]);
+    };
+    assert_eq!(name, "sum");
+    assert_eq!(ty, &Type::Unsigned(UnsignedNumType::U8));
+    let diagnostics: Vec<TypeErrorEnum> = analyzed
+        .diagnostics
+        .into_iter()
+        .map(|TypeError(e, _)| e)
+        .collect();
+    assert_eq!(diagnostics, vec![TypeErrorEnum::ConstantCondition(true)]);
+    Ok(())
+}
+
+#[test]
+fn reject_types_that_are_too_large() -> Result<(), Error> {
+    let prg = "
+pub fn main(s: String<18446744073709551615>) -> bool {
+  true
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(e, TypeErrorEnum::TypeTooLarge(Type::String(usize::MAX)));
+
+    let prg = "
+pub fn main(_x: u8) -> u8 {
+  let a = [0u64; 18446744073709551615];
+  a[0] as u8
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    let elem = Type::Unsigned(UnsignedNumType::U64);
+    assert_eq!(
+        e,
+        TypeErrorEnum::TypeTooLarge(Type::Array(Box::new(elem), usize::MAX))
+    );
+
+    let prg = "
+pub fn main(a: [[u8; 4294967296]; 4294967296]) -> u8 {
+  a[0][0]
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    let inner = Type::Array(Box::new(Type::Unsigned(UnsignedNumType::U8)), 4294967296);
+    assert_eq!(
+        e,
+        TypeErrorEnum::TypeTooLarge(Type::Array(Box::new(inner), 4294967296))
+    );
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_roundtrip_of_analyzed_program() -> Result<(), String> {
+    let prg = "
+pub fn main(a: u8, b: u8) -> u8 {
+  let sum = a + 1;
+  if true { sum } else { b }
+}
+";
+    let analyzed = analyze(prg).map_err(|e| e.prettify(prg))?;
+    let json = serde_json::to_string(&analyzed).map_err(|e| e.to_string())?;
+    let deserialized: garble_lang::AnalyzedProgram =
+        serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    let main = deserialized.program.fn_defs.get("main").unwrap();
+    let StmtEnum::Let(Pattern(PatternEnum::Identifier(name), _, ty), _) = &main.body[0].inner
+    else {
+        panic!("Expected a let binding, but found {:?}", main.body[0]);
+    };
+    assert_eq!(name, "sum");
+    assert_eq!(ty, &Type::Unsigned(UnsignedNumType::U8));
+    assert_eq!(deserialized.diagnostics, analyzed.diagnostics);
+    Ok(())
+}
+
+#[test]
+fn reject_array_literal_with_inconsistent_elements() -> Result<(), Error> {
+    let prg = "
+pub fn main(_x: u8) -> [u8; 2] {
+  [1u8, 2u16]
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(
+        e,
+        TypeErrorEnum::InconsistentArrayElements {
+            expected: Type::Unsigned(UnsignedNumType::U8),
+            found: Type::Unsigned(UnsignedNumType::U16),
+            index: 1,
+        }
+    );
+
+    let prg = "
+pub fn main(_x: u8) -> [u8; 3] {
+  [1, 2, 3]
+}
+";
+    scan(prg)?.parse()?.type_check()?;
+    Ok(())
+}
+
+#[test]
+fn reject_literals_of_different_widths() -> Result<(), Error> {
+    let prg = "
+pub fn main(_x: u8) -> u16 {
+  5u8 + 5u16
+}
+";
+    for e in [
+        scan(prg)?.parse()?.type_check(),
+        scan(prg)?.parse()?.type_check_strict(),
+    ] {
+        let e = assert_single_type_error(e);
+        assert_eq!(
+            e,
+            TypeErrorEnum::TypeMismatch(
+                Type::Unsigned(UnsignedNumType::U8),
+                Type::Unsigned(UnsignedNumType::U16)
+            )
+        );
+    }
+
+    let prg = "
+pub fn main(x: u8) -> u16 {
+  (x + 5) as u16 + 5
+}
+";
+    scan(prg)?.parse()?.type_check()?;
+    Ok(())
+}
+
+#[test]
+fn reject_comparison_with_out_of_range_literal_in_strict_mode() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8) -> bool {
+  x == 300
+}
+";
+    let e = scan(prg)?.parse()?.type_check_strict();
+    let e = assert_single_type_error(e);
+    assert_eq!(
+        e,
+        TypeErrorEnum::ComparisonAlwaysFalse(Type::Unsigned(UnsignedNumType::U8))
+    );
+
+    let prg = "
+pub fn main(x: i8) -> bool {
+  -200 == x
+}
+";
+    let e = scan(prg)?.parse()?.type_check_strict();
+    let e = assert_single_type_error(e);
+    assert_eq!(
+        e,
+        TypeErrorEnum::ComparisonAlwaysFalse(Type::Signed(SignedNumType::I8))
+    );
+
+    let prg = "
+pub fn main(x: u8) -> bool {
+  x == 255
+}
+";
+    scan(prg)?.parse()?.type_check_strict()?;
+    Ok(())
+}
+
+#[test]
+fn reject_arithmetic_on_chars() -> Result<(), Error> {
+    let prg = "
+pub fn main(c: char) -> char {
+  c + 'a'
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(e, TypeErrorEnum::ExpectedNumberType(Type::Char));
+
+    let prg = "
+pub fn main(c: char) -> u8 {
+  c as u8
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let e = assert_single_type_error(e);
+    assert_eq!(
+        e,
+        TypeErrorEnum::UnexpectedType {
+            expected: Type::Unsigned(UnsignedNumType::U32),
+            actual: Type::Unsigned(UnsignedNumType::U8),
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn reject_non_exhaustive_negative_range_pattern() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: i8) -> u8 {
+  match x {
+    -128i8..=-2i8 => 1u8,
+    0i8..=127i8 => 2u8,
+  }
+}
+  ";
+    let e = scan(prg).unwrap().parse().unwrap().type_check();
+    let e = assert_single_type_error(e);
+    if let TypeErrorEnum::PatternsAreNotExhaustive(missing) = e {
+        let meta = MetaInfo {
+            start: (0, 0),
+            end: (0, 0),
+        };
+        assert_eq!(
+            missing,
+            vec![[Pattern::typed(
+                PatternEnum::SignedInclusiveRange(-1, -1, SignedNumType::I8),
+                Type::Signed(SignedNumType::I8),
+                meta
+            )]]
+        );
+    } else {
+        panic!("Expected patterns to be non-exhaustive, but found {e:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn report_type_errors_at_source_spans_of_sugar() -> Result<(), Error> {
+    let prg = "
+pub fn main(n: u8) -> u8 {
+    let mut acc = 0u8;
+    for i in 0u8..4u8 {
+        acc = acc + (i == n);
+    }
+    acc
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let Err(errs) = e else {
+        panic!("Expected a type error, but found {e:?}");
+    };
+    let spans: Vec<MetaInfo> = errs.into_iter().map(|TypeError(_, meta)| meta).collect();
+    assert_eq!(
+        spans,
+        vec![MetaInfo {
+            start: (4, 14),
+            end: (4, 27),
+        }]
+    );
+
+    let prg = "
+pub fn main(n: u8) -> u8 {
+    let mut acc = 0u8;
+    acc += n == 0u8;
+    acc
+}
+";
+    let e = scan(prg)?.parse()?.type_check();
+    let Err(errs) = e else {
+        panic!("Expected a type error, but found {e:?}");
+    };
+    let spans: Vec<MetaInfo> = errs.into_iter().map(|TypeError(_, meta)| meta).collect();
+    assert_eq!(
+        spans,
+        vec![MetaInfo {
+            start: (3, 4),
+            end: (3, 19),
+        }]
+    );
+    Ok(())
+}
+
+#[test]
+fn reject_empty_or_out_of_bounds_array_slices() -> Result<(), Error> {
+    for (slice, from, to) in [("x[2..2]", 2, 2), ("x[3..1]", 3, 1), ("x[2..5]", 2, 5)] {
+        let prg = format!(
+            "
+pub fn main(x: [u8; 4]) -> [u8; 2] {{
+    {slice}
+}}
+"
+        );
+        let e = scan(&prg)?.parse()?.type_check();
+        let Err(mut e) = e else {
+            panic!("Expected a type error for {slice}, but found {e:?}");
+        };
+        assert_eq!(e.len(), 1);
+        let TypeError(e, _) = e.pop().unwrap();
+        let ty = Type::Array(Box::new(Type::Unsigned(UnsignedNumType::U8)), 4);
+        assert_eq!(e, TypeErrorEnum::InvalidArraySlice { from, to, ty });
+    }
+    Ok(())
+}
+
+#[test]
+fn pretty_print_typed_program() -> Result<(), Error> {
+    let prg = "
+const LEN: usize = 2;
+struct Point { x: i16, y: i16 }
+enum Shape { Dot(Point), Empty }
+pub fn main(shapes: [Shape; LEN], offset: i16) -> (i16, bool) {
+    let mut sum = 0i16;
+    for shape in shapes {
+        sum = sum + match shape {
+            Shape::Dot(Point { x, y }) => (x + offset) * (y - (1 - offset)),
+            Shape::Empty => 0,
+        };
+    }
+    let is_big = if sum > 100 { true } else { -sum > 100 && !(sum == 0) };
+    (sum, is_big)
+}
+";
+    let expected = "const LEN: usize = 2usize;
+
+struct Point {
+    x: i16,
+    y: i16,
+}
+
+enum Shape {
+    Dot(Point),
+    Empty,
+}
+
+pub fn main(shapes: [Shape; 2], offset: i16) -> (i16, bool) {
+    let mut sum = 0i16; // i16
+    for shape in shapes /* Shape */ {
+        sum = sum + (match shape {
+            Shape::Dot(Point { x: x, y: y}) => (x + offset) * (y - (1i16 - offset)), // i16
+            Shape::Empty => 0i16, // i16
+        });
+    }
+    let is_big = if sum > 100i16 {
+        true
+    } else {
+        -sum > 100i16 && !(sum == 0i16)
+    }; // bool
+    (sum, is_big)
+}
+";
+    let pretty = scan(prg)?.parse()?.type_check()?.to_string();
+    assert_eq!(pretty, expected);
+    // the pretty-printed program is valid source code and prints to the same text:
+    let reprinted = scan(&pretty)?.parse()?.type_check()?.to_string();
+    assert_eq!(pretty, reprinted);
+    Ok(())
+}
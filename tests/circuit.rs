@@ -1,4 +1,17 @@
-use garble_lang::compile;
+use std::collections::HashMap;
+
+use garble_lang::{
+    check,
+    circuit::{
+        Circuit, CircuitBuilderConfig, CircuitError, CircuitParseError, EquivalenceConfig,
+        EquivalenceResult, Gate, OutputLayout,
+    },
+    compile,
+    compile::{CompileOptions, CompilerError},
+    literal::{BitOrder, Literal},
+    token::MetaInfo,
+    CompileTimeError, Error,
+};
 
 #[test]
 fn optimize_or() -> Result<(), String> {
@@ -66,6 +79,34 @@ pub fn main(b: bool, x: i32) -> bool {
     Ok(())
 }
 
+#[test]
+fn optimize_div_and_mod_with_same_operands() -> Result<(), String> {
+    let separate = "
+pub fn main(a: u32, b: u32) -> (u32, u32) {
+    (a / b, a % b)
+}
+";
+    let combined = "
+pub fn main(a: u32, b: u32) -> (u32, u32) {
+    divmod(a, b)
+}
+";
+    let div_only = "
+pub fn main(a: u32, b: u32) -> (u32, u32) {
+    (a / b, 0u32)
+}
+";
+    let separate = compile(separate).map_err(|e| e.prettify(separate))?;
+    let combined = compile(combined).map_err(|e| e.prettify(combined))?;
+    let div_only = compile(div_only).map_err(|e| e.prettify(div_only))?;
+    // the gates of the second division circuit are all cached, only the panic checks differ:
+    let separate_gates = separate.circuit.gates.len();
+    let combined_gates = combined.circuit.gates.len();
+    assert!(separate_gates.abs_diff(combined_gates) < 10);
+    assert!(separate_gates < div_only.circuit.gates.len() * 11 / 10);
+    Ok(())
+}
+
 #[test]
 fn optimize_not_equivalence() -> Result<(), String> {
     let unoptimized = "
@@ -300,3 +341,1278 @@ pub fn main(rows0: [([u8; 8], u32); ROWS_0], rows1: [([u8; 8], u32); ROWS_1]) ->
     root.present().unwrap();
     Ok(())
 }
+
+#[test]
+fn dot_product_is_shallower_than_a_fold() -> Result<(), String> {
+    let dot = "
+pub fn main(a: [u16; 8], b: [u16; 8]) -> u16 {
+    dot(a, b)
+}
+";
+    let fold = "
+pub fn main(a: [u16; 8], b: [u16; 8]) -> u16 {
+    let mut sum = 0u16;
+    for i in 0usize..8usize {
+        sum = sum + a[i] * b[i];
+    }
+    sum
+}
+";
+    let dot_depth = check(dot)
+        .map_err(|e| e.prettify(dot))?
+        .critical_path("main", HashMap::new())
+        .map_err(|e| format!("{e:?}"))?
+        .and_depth;
+    let fold_depth = check(fold)
+        .map_err(|e| e.prettify(fold))?
+        .critical_path("main", HashMap::new())
+        .map_err(|e| format!("{e:?}"))?
+        .and_depth;
+    assert!(dot_depth < fold_depth, "{dot_depth} vs {fold_depth}");
+    for ty in ["u16", "i16", "i8"] {
+        let dot = dot.replace("u16", ty);
+        let fold = fold.replace("u16", ty);
+        let dot_gates = compile(&dot).map_err(|e| e.prettify(&dot))?;
+        let fold_gates = compile(&fold).map_err(|e| e.prettify(&fold))?;
+        let (dot_gates, fold_gates) = (
+            dot_gates.circuit.and_gates(),
+            fold_gates.circuit.and_gates(),
+        );
+        assert!(dot_gates < fold_gates, "{ty}: {dot_gates} vs {fold_gates}");
+    }
+
+    let public = "
+pub fn main(a: [u16; 8]) -> u16 {
+    dot(a, [1u16, 2, 3, 4, 5, 6, 7, 8])
+}
+";
+    let secret = compile(dot).map_err(|e| e.prettify(dot))?;
+    let public = compile(public).map_err(|e| e.prettify(public))?;
+    assert!(public.circuit.and_gates() * 4 < secret.circuit.and_gates());
+    Ok(())
+}
+
+#[test]
+fn sum_and_product_are_shallower_than_a_fold() -> Result<(), String> {
+    for (builtin, op) in [("sum", "+"), ("product", "*")] {
+        let reduction = format!(
+            "
+pub fn main(a: [u16; 32]) -> u16 {{
+    {builtin}(a)
+}}
+"
+        );
+        let fold = format!(
+            "
+pub fn main(a: [u16; 32]) -> u16 {{
+    let mut acc = a[0];
+    for i in 1usize..32usize {{
+        acc = acc {op} a[i];
+    }}
+    acc
+}}
+"
+        );
+        let reduction_depth = check(&reduction)
+            .map_err(|e| e.prettify(&reduction))?
+            .critical_path("main", HashMap::new())
+            .map_err(|e| format!("{e:?}"))?
+            .and_depth;
+        let fold_depth = check(&fold)
+            .map_err(|e| e.prettify(&fold))?
+            .critical_path("main", HashMap::new())
+            .map_err(|e| format!("{e:?}"))?
+            .and_depth;
+        // a chain of multiplications is already pipelined quite well, so the product is only
+        // slightly shallower, whereas the sum avoids all but one carry chain:
+        if builtin == "sum" {
+            assert!(
+                reduction_depth * 4 < fold_depth * 3,
+                "{builtin}: {reduction_depth} vs {fold_depth}"
+            );
+        } else {
+            assert!(
+                reduction_depth < fold_depth,
+                "{builtin}: {reduction_depth} vs {fold_depth}"
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn balance_associative_chains() -> Result<(), String> {
+    let chain = "
+pub fn main(a: u16, b: u16, c: u16, d: u16) -> (u16, u16, u16) {
+    (a & b & c & d, a | (b | c) | d, a ^ b ^ c ^ d)
+}
+";
+    let sequential = "
+pub fn main(a: u16, b: u16, c: u16, d: u16) -> (u16, u16, u16) {
+    let and = a & b;
+    let and = and & c;
+    let or = a | b;
+    let or = or | c;
+    let xor = a ^ b;
+    let xor = xor ^ c;
+    (and & d, or | d, xor ^ d)
+}
+";
+    let chain_depth = check(chain)
+        .map_err(|e| e.prettify(chain))?
+        .critical_path("main", HashMap::new())
+        .map_err(|e| format!("{e:?}"))?
+        .and_depth;
+    let sequential_depth = check(sequential)
+        .map_err(|e| e.prettify(sequential))?
+        .critical_path("main", HashMap::new())
+        .map_err(|e| format!("{e:?}"))?
+        .and_depth;
+    assert_eq!((chain_depth, sequential_depth), (2, 3));
+
+    let chain = compile(chain).map_err(|e| e.prettify(chain))?;
+    let sequential = compile(sequential).map_err(|e| e.prettify(sequential))?;
+    assert!(chain.circuit.and_gates() <= sequential.circuit.and_gates());
+    for args in [
+        [0u16, 0, 0, 0],
+        [1, 2, 3, 4],
+        [0xffff, 0xff0f, 0xf0ff, 0x0fff],
+        [0x1234, 0xabcd, 0xffff, 0x8001],
+    ] {
+        let mut outputs = vec![];
+        for compiled in [&chain, &sequential] {
+            let mut eval = compiled.evaluator();
+            for arg in args {
+                eval.set_u16(arg);
+            }
+            let output = eval.run().map_err(|e| format!("{e:?}"))?;
+            outputs.push(output.into_literal().map_err(|e| format!("{e:?}"))?);
+        }
+        assert_eq!(outputs[0], outputs[1]);
+    }
+
+    let chain = "
+pub fn main(a: u16, b: u16, c: u16, d: u16) -> u16 {
+    a + b + c + d
+}
+";
+    let sequential = "
+pub fn main(a: u16, b: u16, c: u16, d: u16) -> u16 {
+    let sum = a + b;
+    let sum = sum + c;
+    sum + d
+}
+";
+    let chain_depth = check(chain)
+        .map_err(|e| e.prettify(chain))?
+        .critical_path("main", HashMap::new())
+        .map_err(|e| format!("{e:?}"))?
+        .and_depth;
+    let sequential_depth = check(sequential)
+        .map_err(|e| e.prettify(sequential))?
+        .critical_path("main", HashMap::new())
+        .map_err(|e| format!("{e:?}"))?
+        .and_depth;
+    assert!(chain_depth < sequential_depth);
+
+    let chain = compile(chain).map_err(|e| e.prettify(chain))?;
+    let sequential = compile(sequential).map_err(|e| e.prettify(sequential))?;
+    for args in [
+        [0u16, 0, 0, 0],
+        [1, 2, 3, 4],
+        [0x7fff, 0x7fff, 1, 0],
+        [0xffff, 0, 0, 0],
+        [0xffff, 1, 0, 0],
+        [0x4000, 0x4000, 0x4000, 0x4000],
+        [0x1234, 0xabcd, 0x0fff, 0x2001],
+    ] {
+        let mut outputs = vec![];
+        for compiled in [&chain, &sequential] {
+            let mut eval = compiled.evaluator();
+            for arg in args {
+                eval.set_u16(arg);
+            }
+            let output = eval.run().map_err(|e| format!("{e:?}"))?;
+            outputs.push(output.into_literal().ok());
+        }
+        assert_eq!(outputs[0], outputs[1]);
+    }
+    Ok(())
+}
+
+#[test]
+fn critical_path_of_multiplications() -> Result<(), String> {
+    let prg = "
+pub fn main(a: u8, b: u8, c: u8, d: u8) -> u8 {
+    a * b + c * d
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let critical_path = checked
+        .critical_path("main", HashMap::new())
+        .map_err(|e| format!("{e:?}"))?;
+    let mul_a_b = MetaInfo {
+        start: (2, 4),
+        end: (2, 9),
+    };
+    let mul_c_d = MetaInfo {
+        start: (2, 12),
+        end: (2, 17),
+    };
+    assert!(critical_path.and_depth > 0);
+    assert!(
+        critical_path.locations.contains(&mul_a_b) || critical_path.locations.contains(&mul_c_d),
+        "{:?}",
+        critical_path.locations
+    );
+    Ok(())
+}
+
+#[test]
+fn estimate_gates_of_multiplication() -> Result<(), String> {
+    let prg = "
+pub fn main(a: u32, b: u32) -> u32 {
+    a * b
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let estimate = checked
+        .estimate_gates("main")
+        .map_err(|e| format!("{e:?}"))?;
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    let gates = compiled.circuit.gates.len();
+    let and_gates = compiled.circuit.and_gates();
+    assert!(
+        estimate.gates >= gates / 2 && estimate.gates <= gates * 2,
+        "estimated {} gates, but the circuit has {gates}",
+        estimate.gates
+    );
+    assert!(
+        estimate.and_gates >= and_gates / 2 && estimate.and_gates <= and_gates * 2,
+        "estimated {} AND gates, but the circuit has {and_gates}",
+        estimate.and_gates
+    );
+    Ok(())
+}
+
+#[test]
+fn estimate_gates_of_builtins_and_shifts() -> Result<(), String> {
+    let prgs = [
+        "pub fn main(a: [u16; 8], b: [u16; 8]) -> u16 { dot(a, b) }",
+        "pub fn main(a: [i16; 8]) -> i16 { mean(a) }",
+        "pub fn main(a: [u16; 8], p: [usize; 8]) -> [u16; 8] { permute(a, p) }",
+        "pub fn main(a: [u8; 8]) -> [usize; 4] { histogram(a, 4) }",
+        "pub fn main(a: [u8; 8], i: [usize; 3]) -> [u8; 3] { lookup_table(a, i) }",
+        "pub fn main(a: u64) -> u8 { leading_zeros(a) }",
+        "pub fn main(a: [bool; 64]) -> bool { any(a) }",
+        "pub fn main(a: [u32; 8]) -> u32 { sum(a) }",
+        "pub fn main(a: [u8; 4]) -> u8 { product(a) }",
+        "pub fn main(a: [u16; 8]) -> [u16; 8] { prefix_sum(a) }",
+        "pub fn main(a: [u8; 8], i: usize) -> u8 { get_or(a, i, 0u8) }",
+        "pub fn main(a: u32, b: u32) -> (u32, u32) { divmod(a, b) }",
+        "pub fn main(a: i32, b: i32) -> i32 { max(a, b) }",
+        "pub fn main(a: u32, b: u32) -> u32 { wrapping_mul(a, b) }",
+        "pub fn main(a: u64) -> u8 { count_ones(a) }",
+        "pub fn main(a: u32, b: u8) -> u32 { a << b }",
+        "pub fn main(a: u32, b: u8) -> u32 { a <<< b }",
+    ];
+    for prg in prgs {
+        let checked = check(prg).map_err(|e| e.prettify(prg))?;
+        let estimate = checked
+            .estimate_gates("main")
+            .map_err(|e| format!("{e:?}"))?;
+        let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+        let gates = compiled.circuit.gates.len();
+        let and_gates = compiled.circuit.and_gates();
+        assert!(
+            estimate.gates >= gates / 2 && estimate.gates <= gates * 2,
+            "estimated {} gates, but the circuit of '{prg}' has {gates}",
+            estimate.gates
+        );
+        assert!(
+            estimate.and_gates >= and_gates / 2 && estimate.and_gates <= and_gates * 2,
+            "estimated {} AND gates, but the circuit of '{prg}' has {and_gates}",
+            estimate.and_gates
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn gate_stats_of_small_circuits() -> Result<(), String> {
+    let prg = "
+pub fn main(a: bool, b: bool, c: bool) -> bool {
+    (a & b) & !c
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let (circuit, _) = checked.compile("main").map_err(|e| format!("{e:?}"))?;
+    let stats = circuit.gate_stats();
+    assert_eq!(stats.and_gates, 2);
+    assert_eq!(stats.and_depth, 2);
+    assert_eq!(stats.input_bits, 3);
+    assert_eq!(stats.output_bits, circuit.output_gates.len());
+
+    // a full adder with an AND of the carry and the first input, plus an unused AND of depth 3:
+    let circuit = Circuit {
+        input_gates: vec![1, 1, 1],
+        gates: vec![
+            Gate::Xor(0, 1),
+            Gate::And(0, 1),
+            Gate::Xor(3, 2),
+            Gate::And(3, 2),
+            Gate::Xor(4, 6),
+            Gate::Not(7),
+            Gate::And(7, 0),
+            Gate::And(9, 1),
+        ],
+        output_gates: vec![5, 8, 9],
+    };
+    let stats = circuit.gate_stats();
+    assert_eq!(stats.and_gates, 4);
+    assert_eq!(stats.xor_gates, 3);
+    assert_eq!(stats.not_gates, 1);
+    assert_eq!(stats.input_bits, 3);
+    assert_eq!(stats.output_bits, 3);
+    assert_eq!(stats.and_depth, 2);
+
+    let prg = "
+pub fn main(a: u8, b: u8) -> u8 {
+    a * b
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let (circuit, _) = checked.compile("main").map_err(|e| format!("{e:?}"))?;
+    let critical_path = checked
+        .critical_path("main", HashMap::new())
+        .map_err(|e| format!("{e:?}"))?;
+    assert_eq!(circuit.gate_stats().and_depth, critical_path.and_depth);
+    Ok(())
+}
+
+#[test]
+fn iterate_and_gates_in_topological_order() -> Result<(), String> {
+    let prg = "
+pub fn main(a: u8, b: u8) -> (u8, bool) {
+    (a * b, a < b)
+}
+";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    let circuit = &compiled.circuit;
+    let input_len: usize = circuit.input_gates.iter().sum();
+    let and_gates: Vec<_> = circuit.and_gate_indices().collect();
+    assert_eq!(and_gates.len(), circuit.and_gates());
+    let mut prev = None;
+    for (w, x, y) in and_gates {
+        assert_eq!(circuit.gates[w - input_len], Gate::And(x, y));
+        assert!(x < w && y < w);
+        assert!(prev < Some(w));
+        prev = Some(w);
+    }
+    Ok(())
+}
+
+#[test]
+fn minimize_and_gates_of_comparisons() -> Result<(), String> {
+    let prg = "
+pub fn main(values: [u8; 8], threshold: u8) -> (u8, u8) {
+    let mut max = 0u8;
+    let mut above = 0u8;
+    for x in values {
+        max = if x > max { x } else { max };
+        above = if x > threshold { above + 1u8 } else { above };
+    }
+    (max, above)
+}
+";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    let circuit = compiled.circuit.clone();
+    let (minimized, removed) = circuit.clone().minimize_and_gates();
+    assert!(removed > 0);
+    assert_eq!(minimized.and_gates() + removed, circuit.and_gates());
+    minimized.validate().map_err(|e| format!("{e:?}"))?;
+    for (values, threshold) in [
+        ([1u8, 5, 3, 200, 7, 0, 9, 9], 6u8),
+        ([255, 0, 255, 0, 1, 2, 3, 4], 0),
+        ([0; 8], 255),
+    ] {
+        let values = Literal::Array(values.iter().map(|&v| v.into()).collect());
+        let inputs = vec![
+            compiled
+                .literal_arg(0, values)
+                .map_err(|e| e.prettify(prg))?
+                .as_bits(),
+            compiled
+                .literal_arg(1, threshold.into())
+                .map_err(|e| e.prettify(prg))?
+                .as_bits(),
+        ];
+        assert_eq!(minimized.eval(&inputs), circuit.eval(&inputs));
+    }
+    Ok(())
+}
+
+#[test]
+fn reject_circuit_exceeding_gate_limit() -> Result<(), String> {
+    let prg = "
+pub fn main(a: u32, b: u32) -> u32 {
+    a * b
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    assert!(checked
+        .compile_with_gate_limit("main", HashMap::new(), 1_000_000)
+        .is_ok());
+    let e: Error = checked
+        .compile_with_gate_limit("main", HashMap::new(), 100)
+        .unwrap_err()
+        .into();
+    match &e {
+        Error::CompileTimeError(CompileTimeError::CompilerError(errs)) => {
+            assert!(matches!(
+                errs.as_slice(),
+                [CompilerError::GateLimitExceeded { limit: 100, .. }]
+            ));
+        }
+        e => panic!("Expected a compiler error, but found {e:?}"),
+    }
+    assert!(e.prettify(prg).contains("exceeds the limit of 100 gates"));
+    Ok(())
+}
+
+#[test]
+fn reject_circuit_exceeding_and_budget() -> Result<(), String> {
+    let budget = |max_and_gates: usize| CompileOptions {
+        max_and_gates: Some(max_and_gates),
+        ..Default::default()
+    };
+    let prg = "
+pub fn main(a: u8, b: u8) -> u8 {
+    a & b
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let ((circuit, _, _), _) = checked
+        .compile_with_options("main", HashMap::new(), &budget(8))
+        .map_err(|e| Error::from(e).prettify(prg))?;
+    assert_eq!(circuit.and_gates(), 8);
+    assert!(checked
+        .compile_with_options("main", HashMap::new(), &budget(7))
+        .is_err());
+
+    // gates that are pruned because they do not contribute to the output are not counted:
+    let prg = "
+pub fn main(a: u8, b: u8) -> u8 {
+    let _m = wrapping_mul(a, b);
+    a & b
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let ((circuit, _, _), _) = checked
+        .compile_with_options("main", HashMap::new(), &budget(8))
+        .map_err(|e| Error::from(e).prettify(prg))?;
+    assert_eq!(circuit.and_gates(), 8);
+
+    let prg = "
+pub fn main(a: u32, b: u32) -> u32 {
+    let c = a ^ b;
+    c * b
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let e: Error = checked
+        .compile_with_options("main", HashMap::new(), &budget(100))
+        .unwrap_err()
+        .into();
+    match &e {
+        Error::CompileTimeError(CompileTimeError::CompilerError(errs)) => {
+            let [CompilerError::AndBudgetExceeded { limit, reached_at }] = errs.as_slice() else {
+                panic!("Expected an exceeded AND budget, but found {errs:?}");
+            };
+            assert_eq!(*limit, 100);
+            assert_eq!(
+                *reached_at,
+                MetaInfo {
+                    start: (3, 4),
+                    end: (3, 9)
+                }
+            );
+        }
+        e => panic!("Expected a compiler error, but found {e:?}"),
+    }
+    assert!(e
+        .prettify(prg)
+        .contains("exceeds the budget of 100 AND gates"));
+
+    // compile_with_budget is a shorthand for compiling with only an AND budget:
+    let prg = "
+pub fn main(a: u32, b: u32) -> u32 {
+    a * b
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    assert!(checked
+        .compile_with_budget("main", HashMap::new(), 10)
+        .is_err());
+    let prg = "
+pub fn main(a: u8, b: u8) -> u8 {
+    a & b
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let (circuit, _, _) = checked
+        .compile_with_budget("main", HashMap::new(), 10)
+        .map_err(|e| Error::from(e).prettify(prg))?;
+    assert_eq!(circuit.and_gates(), 8);
+    Ok(())
+}
+
+#[test]
+fn gate_fanout_of_input_feeding_three_gates() {
+    let circuit = Circuit {
+        input_gates: vec![1, 1],
+        gates: vec![
+            Gate::Xor(0, 1),
+            Gate::And(0, 1),
+            Gate::Not(0),
+            Gate::Xor(2, 3),
+        ],
+        output_gates: vec![5, 4, 5],
+    };
+    assert_eq!(circuit.validate(), Ok(()));
+    assert_eq!(circuit.gate_fanout(), vec![3, 2, 1, 1, 1, 2]);
+}
+
+fn eval_bristol(bristol: &str, inputs: &[bool]) -> Vec<Vec<bool>> {
+    let mut lines = bristol.lines();
+    let header: Vec<usize> = lines
+        .next()
+        .unwrap()
+        .split(' ')
+        .map(|n| n.parse().unwrap())
+        .collect();
+    let (num_gates, num_wires) = (header[0], header[1]);
+    lines.next();
+    let outputs: Vec<usize> = lines
+        .next()
+        .unwrap()
+        .split(' ')
+        .map(|n| n.parse().unwrap())
+        .collect();
+    lines.next();
+    let mut wires = vec![None; num_wires];
+    for (w, &bit) in inputs.iter().enumerate() {
+        wires[w] = Some(bit);
+    }
+    let mut gates = 0;
+    for line in lines {
+        let parts: Vec<&str> = line.split(' ').collect();
+        let w = |i: usize| parts[i].parse::<usize>().unwrap();
+        let (out, bit) = match *parts.last().unwrap() {
+            "XOR" => (w(4), wires[w(2)].unwrap() ^ wires[w(3)].unwrap()),
+            "AND" => (w(4), wires[w(2)].unwrap() & wires[w(3)].unwrap()),
+            "INV" => (w(3), !wires[w(2)].unwrap()),
+            "EQW" => (w(3), wires[w(2)].unwrap()),
+            gate => panic!("unexpected gate {gate}"),
+        };
+        wires[out] = Some(bit);
+        gates += 1;
+    }
+    assert_eq!(gates, num_gates);
+    assert_eq!(outputs[0], outputs.len() - 1);
+    let mut w = num_wires - outputs[1..].iter().sum::<usize>();
+    let mut values = vec![];
+    for &len in outputs[1..].iter() {
+        values.push(wires[w..w + len].iter().map(|bit| bit.unwrap()).collect());
+        w += len;
+    }
+    values
+}
+
+#[test]
+fn export_bristol_with_bit_order() -> Result<(), String> {
+    let prg = "
+pub fn main(x: u8, y: u8) -> u8 {
+    (x & y) ^ (x >> 1u8)
+}
+";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    let circuit = &compiled.circuit;
+    let msb_first = circuit.to_bristol(BitOrder::MsbFirst, OutputLayout::WithPanic);
+    let lsb_first = circuit.to_bristol(BitOrder::LsbFirst, OutputLayout::WithPanic);
+    let msb_lines: Vec<&str> = msb_first.lines().collect();
+    let lsb_lines: Vec<&str> = lsb_first.lines().collect();
+    assert_eq!(msb_lines[..4], lsb_lines[..4]);
+    assert_eq!(msb_lines[1], "2 8 8");
+    assert_ne!(msb_first, lsb_first);
+
+    // the input wires of each party are reversed, all other wires stay the same:
+    let reversed = |w: &str| match w.parse::<usize>() {
+        Ok(w) if w < 16 => (w / 8 * 8 + 7 - w % 8).to_string(),
+        _ => w.to_string(),
+    };
+    let gates = circuit.gates.len();
+    for (msb, lsb) in msb_lines[4..4 + gates].iter().zip(&lsb_lines[4..4 + gates]) {
+        let mut msb: Vec<String> = msb.split(' ').map(String::from).collect();
+        let num_wires = msb.len() - 1;
+        for w in msb[2..num_wires].iter_mut() {
+            *w = reversed(w);
+        }
+        assert_eq!(msb.join(" "), *lsb);
+    }
+
+    for (x, y) in [(0u8, 0u8), (5, 3), (200, 77), (255, 255)] {
+        let expected = circuit.eval(&[
+            Literal::from(x).as_bits(&compiled.program, &HashMap::new()),
+            Literal::from(y).as_bits(&compiled.program, &HashMap::new()),
+        ]);
+        for (order, bristol) in [
+            (BitOrder::MsbFirst, &msb_first),
+            (BitOrder::LsbFirst, &lsb_first),
+        ] {
+            let mut inputs = vec![];
+            for n in [x, y] {
+                let n = Literal::from(n);
+                inputs.extend(n.as_bits_with_order(&compiled.program, &HashMap::new(), order));
+            }
+            let [mut output, panic]: [Vec<bool>; 2] =
+                eval_bristol(bristol, &inputs).try_into().unwrap();
+            if order == BitOrder::LsbFirst {
+                output.reverse();
+            }
+            let output = [panic, output].concat();
+            assert_eq!(output, expected);
+            let result = compiled
+                .parse_output(&output)
+                .map_err(|e| format!("{e:?}"))?;
+            assert_eq!(result, Literal::from((x & y) ^ (x >> 1)));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn report_large_inlined_fn_calls() -> Result<(), String> {
+    let prg = "
+fn mul(a: u32, b: u32) -> u32 {
+    a * b
+}
+
+fn inc(a: u32) -> u32 {
+    a + 1u32
+}
+
+pub fn main(xs: [u32; 4], y: u32) -> [u32; 4] {
+    let mut result = [0u32; 4];
+    let mut i = 0usize;
+    for x in xs {
+        result[i] = inc(mul(x, y));
+        i = i + 1usize;
+    }
+    result
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let options = CompileOptions {
+        warn_on_large_inline: 1_000,
+        ..Default::default()
+    };
+    let ((circuit, _, _), large_inlines) = checked
+        .compile_with_options("main", HashMap::new(), &options)
+        .map_err(|e| Error::from(e).prettify(prg))?;
+    let (unreported, _) = checked
+        .compile("main")
+        .map_err(|e| Error::from(e).prettify(prg))?;
+    assert_eq!(circuit.gates, unreported.gates);
+    assert_eq!(large_inlines.len(), 1);
+    let report = &large_inlines[0];
+    assert_eq!(report.fn_name, "mul");
+    assert_eq!(report.calls, 4);
+    assert!(report.gates > 4 * 1_000);
+    assert!(report.to_string().contains("'mul'"));
+
+    let (_, large_inlines) = checked
+        .compile_with_options("main", HashMap::new(), &CompileOptions::default())
+        .map_err(|e| Error::from(e).prettify(prg))?;
+    assert!(large_inlines.is_empty());
+    Ok(())
+}
+
+#[test]
+fn report_shared_sub_expressions() -> Result<(), String> {
+    let prg = "
+pub fn main(a: bool, b: bool) -> bool {
+    (a & b) ^ (a & b)
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let report = checked
+        .optimization_report("main", HashMap::new())
+        .map_err(|e| Error::from(e).prettify(prg))?;
+    assert!(report.gates_shared >= 1);
+    assert!(report.gates_folded >= 1);
+    let (circuit, _) = checked
+        .compile("main")
+        .map_err(|e| Error::from(e).prettify(prg))?;
+    assert_eq!(report.gates, circuit.gates.len());
+    Ok(())
+}
+
+#[test]
+fn compile_with_optimization_depth() -> Result<(), String> {
+    let prg = "
+pub fn main(x: u8, y: u8, z: u8) -> u8 {
+    ((x ^ y) ^ (z ^ x)) ^ (y & 255u8)
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let compile_with_depth = |max_optimization_depth| {
+        let config = CircuitBuilderConfig {
+            max_optimization_depth,
+            ..Default::default()
+        };
+        checked
+            .compile_with_config("main", HashMap::new(), config)
+            .map(|((circuit, _, _), report)| (circuit, report))
+            .map_err(|e| Error::from(e).prettify(prg))
+    };
+    let (unoptimized, report_0) = compile_with_depth(0)?;
+    let (default, report_2) = compile_with_depth(2)?;
+    let (optimized, report_8) = compile_with_depth(8)?;
+    assert_eq!(report_0.gates_folded, 0);
+    assert!(optimized.gates.len() < default.gates.len());
+    assert!(default.gates.len() < unoptimized.gates.len());
+    assert!(report_8.gates_folded > report_2.gates_folded);
+    let (compiled, _) = checked
+        .compile("main")
+        .map_err(|e| Error::from(e).prettify(prg))?;
+    assert_eq!(compiled.gates, default.gates);
+    for inputs in [[0u8, 0, 0], [1, 2, 3], [255, 170, 85], [19, 255, 0]] {
+        let bits: Vec<Vec<bool>> = inputs
+            .iter()
+            .map(|x| (0..8).rev().map(|i| (x >> i) & 1 == 1).collect())
+            .collect();
+        let expected = default.eval(&bits);
+        assert_eq!(unoptimized.eval(&bits), expected);
+        assert_eq!(optimized.eval(&bits), expected);
+    }
+
+    let prg = "
+pub fn main(x: u8, y: u8) -> u8 {
+    let _unused = wrapping_mul(x, y);
+    wrapping_add(x, y) ^ wrapping_add(x, y)
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let ((default, _, _), report) = checked
+        .compile_with_config("main", HashMap::new(), CircuitBuilderConfig::default())
+        .map_err(|e| Error::from(e).prettify(prg))?;
+    assert!(report.gates_shared > 0);
+    assert!(report.gates_pruned > 0);
+    let config = CircuitBuilderConfig {
+        dedup: false,
+        prune: false,
+        ..Default::default()
+    };
+    let ((circuit, _, _), report) = checked
+        .compile_with_config("main", HashMap::new(), config)
+        .map_err(|e| Error::from(e).prettify(prg))?;
+    assert_eq!(report.gates_shared, 0);
+    assert_eq!(report.gates_pruned, 0);
+    assert!(circuit.gates.len() > default.gates.len());
+    Ok(())
+}
+
+#[test]
+fn export_bristol_and_evaluate_random_inputs() -> Result<(), String> {
+    let prg = "
+pub fn main(x: u16, y: u16, z: [bool; 3]) -> (u16, bool) {
+    let sum = x + y;
+    (sum / (y | 1u16), z[0usize] ^ (z[1usize] & z[2usize]))
+}
+";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    let circuit = &compiled.circuit;
+    let mut bristol = vec![];
+    circuit
+        .write_bristol(BitOrder::MsbFirst, OutputLayout::WithPanic, &mut bristol)
+        .map_err(|e| e.to_string())?;
+    let bristol = String::from_utf8(bristol).map_err(|e| e.to_string())?;
+    assert_eq!(
+        bristol,
+        circuit.to_bristol(BitOrder::MsbFirst, OutputLayout::WithPanic)
+    );
+    let lines: Vec<&str> = bristol.lines().collect();
+    assert_eq!(lines[1], "3 16 16 3");
+    assert_eq!(lines[2], "2 17 193");
+
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for _ in 0..100 {
+        let mut inputs = vec![];
+        for &len in circuit.input_gates.iter() {
+            let mut party = vec![];
+            for _ in 0..len {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                party.push(state & 1 == 1);
+            }
+            inputs.push(party);
+        }
+        let expected = circuit.eval(&inputs);
+        let [output, panic]: [Vec<bool>; 2] =
+            eval_bristol(&bristol, &inputs.concat()).try_into().unwrap();
+        assert_eq!([panic, output].concat(), expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn export_hand_built_circuit_as_plain_bristol() {
+    // enough output bits to be mistaken for a circuit with panic bits:
+    let circuit = Circuit {
+        input_gates: vec![1, 1],
+        gates: vec![Gate::Xor(0, 1), Gate::And(0, 1)],
+        output_gates: [2, 3].repeat(100),
+    };
+    let bristol = circuit.to_bristol(BitOrder::MsbFirst, OutputLayout::Plain);
+    let lines: Vec<&str> = bristol.lines().collect();
+    assert_eq!(lines[2], "1 200");
+    for (x, y) in [(false, false), (false, true), (true, false), (true, true)] {
+        let expected = circuit.eval(&[vec![x], vec![y]]);
+        let [output]: [Vec<bool>; 1] = eval_bristol(&bristol, &[x, y]).try_into().unwrap();
+        assert_eq!(output, expected);
+    }
+}
+
+#[test]
+fn import_bristol_adder() -> Result<(), String> {
+    // a 2-bit adder of x = (x0, x1) and y = (y0, y1), with a 3-bit sum, all LSB first:
+    let bristol = "
+7 11
+2 2 2
+1 3
+
+2 1 0 2 4 AND
+2 1 1 3 5 XOR
+2 1 1 3 6 AND
+2 1 5 4 7 AND
+2 1 0 2 8 XOR
+2 1 5 4 9 XOR
+2 1 6 7 10 XOR
+";
+    let circuit = Circuit::from_bristol(bristol, OutputLayout::Plain).map_err(|e| e.to_string())?;
+    circuit.validate().map_err(|e| format!("{e:?}"))?;
+    assert_eq!(circuit.input_gates, vec![2, 2]);
+    assert_eq!(circuit.output_gates.len(), 3);
+    for x in 0..4u8 {
+        for y in 0..4u8 {
+            let bits = |n: u8| vec![n & 1 == 1, n & 2 == 2];
+            let output = circuit.eval(&[bits(x), bits(y)]);
+            let sum = output
+                .iter()
+                .enumerate()
+                .map(|(i, &b)| (b as u8) << i)
+                .sum::<u8>();
+            assert_eq!(sum, x + y);
+        }
+    }
+
+    let import = |bristol: &str| Circuit::from_bristol(bristol, OutputLayout::Plain).unwrap_err();
+    assert_eq!(
+        import("1 3\n1 2\n1 1\n\n2 1 0 1 2 MAND\n"),
+        CircuitParseError::UnsupportedGate(5, "MAND".to_string())
+    );
+    assert_eq!(
+        import("1 3\n1 2\n1 1\n\n2 1 0 7 2 AND\n"),
+        CircuitParseError::InvalidWire(5, 7)
+    );
+    assert_eq!(
+        import("1 3\n2 2\n1 1\n\n2 1 0 1 2 AND\n"),
+        CircuitParseError::MalformedHeader(2)
+    );
+    assert_eq!(
+        import("2 3\n1 2\n1 1\n\n2 1 0 1 2 AND\n"),
+        CircuitParseError::UnexpectedNumberOfGates {
+            expected: 2,
+            actual: 1
+        }
+    );
+
+    // huge numbers must not overflow:
+    let max = usize::MAX;
+    assert_eq!(
+        import(&format!("1 3\n2 1 {max}\n1 1\n\n2 1 0 1 2 AND\n")),
+        CircuitParseError::MalformedHeader(2)
+    );
+    assert_eq!(
+        import(&format!("1 3\n1 2\n2 1 {max}\n\n2 1 0 1 2 AND\n")),
+        CircuitParseError::MalformedHeader(3)
+    );
+    assert_eq!(
+        import(&format!("1 3\n1 2\n1 {max}\n\n2 1 0 1 2 AND\n")),
+        CircuitParseError::MalformedHeader(1)
+    );
+    assert_eq!(
+        import(&format!("1 3\n1 2\n1 1\n\n{max} 1 0 1 2 AND\n")),
+        CircuitParseError::MalformedGate(5)
+    );
+    assert_eq!(
+        import(&format!("1 3\n1 2\n1 1\n\n2 {max} 0 1 2 AND\n")),
+        CircuitParseError::MalformedGate(5)
+    );
+    // the panic bits must be the last output value:
+    assert_eq!(
+        Circuit::from_bristol(bristol, OutputLayout::WithPanic).unwrap_err(),
+        CircuitParseError::MalformedHeader(4)
+    );
+    Ok(())
+}
+
+#[test]
+fn import_exported_bristol() -> Result<(), String> {
+    let prg = "
+pub fn main(x: u8, y: u8) -> u8 {
+    x * y + 1u8
+}
+";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    let circuit = &compiled.circuit;
+    for layout in [OutputLayout::Plain, OutputLayout::WithPanic] {
+        let bristol = circuit.to_bristol(BitOrder::MsbFirst, layout);
+        let imported = Circuit::from_bristol(&bristol, layout).map_err(|e| e.to_string())?;
+        assert_eq!(imported.input_gates, circuit.input_gates);
+        assert_eq!(imported.output_gates.len(), circuit.output_gates.len());
+        for (x, y) in [(0u8, 0u8), (3, 5), (16, 16), (255, 1)] {
+            let inputs = [
+                Literal::from(x).as_bits(&compiled.program, &HashMap::new()),
+                Literal::from(y).as_bits(&compiled.program, &HashMap::new()),
+            ];
+            assert_eq!(imported.eval(&inputs), circuit.eval(&inputs));
+        }
+        let result = compiled
+            .parse_output(&imported.eval(&[
+                Literal::from(3u8).as_bits(&compiled.program, &HashMap::new()),
+                Literal::from(5u8).as_bits(&compiled.program, &HashMap::new()),
+            ]))
+            .map_err(|e| format!("{e:?}"))?;
+        assert_eq!(result, Literal::from(16u8));
+        assert!(imported.is_equivalent(circuit).is_equivalent());
+    }
+    Ok(())
+}
+
+#[test]
+fn trace_all_wires_of_an_evaluation() -> Result<(), String> {
+    let prg = "
+pub fn main(a: u8, b: u8) -> u8 {
+    (a + b) ^ (a & b)
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let (circuit, _) = checked.compile("main").map_err(|e| format!("{e:?}"))?;
+    let inputs = vec![
+        Literal::from(13u8).as_bits(&checked, &HashMap::new()),
+        Literal::from(200u8).as_bits(&checked, &HashMap::new()),
+    ];
+    let trace = circuit.eval_trace(&inputs);
+    assert_eq!(trace.outputs(), circuit.eval(&inputs));
+    for (i, &output_gate) in circuit.output_gates.iter().enumerate() {
+        assert_eq!(trace.value(output_gate), Some(circuit.eval(&inputs)[i]));
+    }
+    let input_len: usize = circuit.input_gates.iter().sum();
+    for (i, bit) in inputs.concat().into_iter().enumerate() {
+        assert_eq!(trace.value(i), Some(bit));
+    }
+    let mut gates = 0;
+    for traced in trace.gates() {
+        assert_eq!(traced.wire, input_len + gates);
+        assert_eq!(traced.gate, &circuit.gates[gates]);
+        let expected = match (traced.gate, traced.inputs) {
+            (Gate::Xor(_, _), (x, Some(y))) => x ^ y,
+            (Gate::And(_, _), (x, Some(y))) => x & y,
+            (Gate::Not(_), (x, None)) => !x,
+            _ => return Err(format!("unexpected inputs for {:?}", traced.gate)),
+        };
+        assert_eq!(traced.output, expected);
+        assert_eq!(trace.value(traced.wire), Some(traced.output));
+        gates += 1;
+    }
+    assert_eq!(gates, circuit.gates.len());
+    assert_eq!(trace.value(input_len + gates), None);
+    Ok(())
+}
+
+#[test]
+fn reuse_fn_calls_with_identical_args() -> Result<(), String> {
+    let same_args = "
+pub fn main(x: u8, y: u8) -> (u8, u8) {
+    (f(x) ^ y, f(x) | y)
+}
+
+fn f(x: u8) -> u8 {
+    x * x + 3u8
+}
+";
+    let distinct_args = "
+pub fn main(x: u8, y: u8) -> (u8, u8) {
+    (f(x) ^ y, f(y) | y)
+}
+
+fn f(x: u8) -> u8 {
+    x * x + 3u8
+}
+";
+    let called_once = "
+pub fn main(x: u8, y: u8) -> (u8, u8) {
+    let fx = f(x);
+    (fx ^ y, fx | y)
+}
+
+fn f(x: u8) -> u8 {
+    x * x + 3u8
+}
+";
+    let same_args = compile(same_args).map_err(|e| e.prettify(same_args))?;
+    let distinct_args = compile(distinct_args).map_err(|e| e.prettify(distinct_args))?;
+    let called_once = compile(called_once).map_err(|e| e.prettify(called_once))?;
+    assert!(same_args.circuit.gates.len() < distinct_args.circuit.gates.len());
+    // even the panic checks of the second call are not compiled again:
+    assert_eq!(
+        same_args.circuit.gates.len(),
+        called_once.circuit.gates.len()
+    );
+
+    let prg = "
+pub fn main(x: u8, y: u8) -> (u8, u8) {
+    (f(x) ^ y, f(x) | y)
+}
+
+fn f(x: u8) -> u8 {
+    x * x + 3u8
+}
+";
+    let checked = check(prg).map_err(|e| e.prettify(prg))?;
+    let options = CompileOptions {
+        warn_on_large_inline: 10,
+        ..Default::default()
+    };
+    let (_, large_inlines) = checked
+        .compile_with_options("main", HashMap::new(), &options)
+        .map_err(|e| Error::from(e).prettify(prg))?;
+    assert_eq!(large_inlines.len(), 2);
+    assert_eq!(large_inlines[0].gates, large_inlines[1].gates);
+
+    let config = CircuitBuilderConfig {
+        dedup: false,
+        ..Default::default()
+    };
+    let ((without_dedup, _, _), _) = checked
+        .compile_with_config("main", HashMap::new(), config)
+        .map_err(|e| Error::from(e).prettify(prg))?;
+    let ((called_once, _, _), _) = called_once
+        .program
+        .compile_with_config("main", HashMap::new(), config)
+        .map_err(|e| format!("{e:?}"))?;
+    // without dedup, the second call is compiled again instead of reusing the first:
+    assert!(without_dedup.gates.len() > called_once.gates.len());
+    Ok(())
+}
+
+#[test]
+fn export_half_adder_as_dot() -> Result<(), String> {
+    let half_adder = Circuit {
+        input_gates: vec![1, 1],
+        gates: vec![Gate::Xor(0, 1), Gate::And(0, 1)],
+        output_gates: vec![2, 3],
+    };
+    let dot = half_adder
+        .to_dot(OutputLayout::Plain, None)
+        .map_err(|e| format!("{e:?}"))?;
+    assert!(dot.starts_with("digraph circuit {"));
+    let nodes = dot.lines().filter(|l| l.contains("[label=")).count();
+    let edges = dot.lines().filter(|l| l.contains(" -> ")).count();
+    assert_eq!(nodes, 6);
+    assert_eq!(edges, 6);
+    assert!(dot.contains("w0 [label=\"party 0, bit 0\", style=filled, fillcolor=lightblue];"));
+    assert!(dot.contains("w1 [label=\"party 1, bit 0\", style=filled, fillcolor=palegreen];"));
+    assert!(dot.contains("w3 [label=\"AND\", shape=box];"));
+    assert!(dot.contains("w3 -> o1;"));
+
+    assert_eq!(
+        half_adder.to_dot(OutputLayout::Plain, Some(1)),
+        Err(CircuitError::MaxCircuitSizeExceeded)
+    );
+    assert!(half_adder.to_dot(OutputLayout::Plain, Some(2)).is_ok());
+
+    // outputs are only labeled as panic bits if the layout says so, not based on their number:
+    let many_outputs = Circuit {
+        output_gates: [2, 3].repeat(100),
+        ..half_adder
+    };
+    let dot = many_outputs
+        .to_dot(OutputLayout::Plain, None)
+        .map_err(|e| format!("{e:?}"))?;
+    assert!(!dot.contains("panic"));
+    assert!(dot.contains("o199 [label=\"output 199\""));
+    let dot = many_outputs
+        .to_dot(OutputLayout::WithPanic, None)
+        .map_err(|e| format!("{e:?}"))?;
+    assert!(dot.contains("o0 [label=\"panic 0\""));
+    assert!(dot.contains("o199 [label=\"output 6\""));
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_roundtrip_of_compiled_circuit() -> Result<(), String> {
+    let prg = "
+pub fn main(x: u16, y: u16) -> (u16, bool) {
+    (x * y + 3, x < y)
+}
+";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    let json = serde_json::to_string(&compiled.circuit).map_err(|e| e.to_string())?;
+    let deserialized: Circuit = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    assert_eq!(deserialized.input_gates, compiled.circuit.input_gates);
+    assert_eq!(deserialized.gates, compiled.circuit.gates);
+    assert_eq!(deserialized.output_gates, compiled.circuit.output_gates);
+
+    // a simple xorshift generator is enough to get varied inputs:
+    let mut state = 0x2545_f491_u32;
+    for _ in 0..100 {
+        let inputs: Vec<Vec<bool>> = (0..2)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (0..16).map(|i| state & (1 << i) != 0).collect()
+            })
+            .collect();
+        assert_eq!(deserialized.eval(&inputs), compiled.circuit.eval(&inputs));
+    }
+    Ok(())
+}
+
+#[test]
+fn concat_circuits() -> Result<(), String> {
+    let add = "
+pub fn main(x: u8, y: u8) -> u8 {
+    x + y
+}
+";
+    let div = "
+pub fn main(x: u16, y: u8) -> (u16, bool) {
+    (x / (y as u16), x > 1000u16)
+}
+";
+    let add = compile(add).map_err(|e| e.prettify(add))?;
+    let div = compile(div).map_err(|e| e.prettify(div))?;
+    let concatenated = Circuit::concat(&[add.circuit.clone(), div.circuit.clone()]);
+    assert_eq!(concatenated.input_gates, vec![8 + 16, 8 + 8]);
+    concatenated.validate().map_err(|e| format!("{e:?}"))?;
+    let panic_bits = add.circuit.output_gates.len() - 8;
+    let empty = HashMap::new();
+    for (a, b, x, y) in [
+        (5u8, 6u8, 2000u16, 3u8),
+        (200, 100, 7, 2),
+        (1, 2, 3, 0),
+        (255, 1, 3, 0),
+    ] {
+        let add_inputs = vec![
+            Literal::from(a).as_bits(&add.program, &empty),
+            Literal::from(b).as_bits(&add.program, &empty),
+        ];
+        let div_inputs = vec![
+            Literal::from(x).as_bits(&div.program, &empty),
+            Literal::from(y).as_bits(&div.program, &empty),
+        ];
+        let add_output = add.circuit.eval(&add_inputs);
+        let div_output = div.circuit.eval(&div_inputs);
+        let inputs = vec![
+            [add_inputs[0].clone(), div_inputs[0].clone()].concat(),
+            [add_inputs[1].clone(), div_inputs[1].clone()].concat(),
+        ];
+        let output = concatenated.eval(&inputs);
+        let expected_panic = if add_output[0] {
+            &add_output[..panic_bits]
+        } else {
+            &div_output[..panic_bits]
+        };
+        assert_eq!(&output[..panic_bits], expected_panic);
+        let expected = [&add_output[panic_bits..], &div_output[panic_bits..]].concat();
+        assert_eq!(&output[panic_bits..], expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn check_equivalence_of_max_implementations() -> Result<(), String> {
+    let max = "
+pub fn main(x: u8, y: u8) -> u8 {
+    if x > y { x } else { y }
+}
+";
+    let max_swapped = "
+pub fn main(x: u8, y: u8) -> u8 {
+    if y >= x { y } else { x }
+}
+";
+    let max_off_by_one = "
+pub fn main(x: u8, y: u8) -> u8 {
+    if x > y + 1u8 { x } else { y }
+}
+";
+    let max = compile(max).map_err(|e| e.prettify(max))?.circuit;
+    let max_swapped = compile(max_swapped)
+        .map_err(|e| e.prettify(max_swapped))?
+        .circuit;
+    let max_off_by_one = compile(max_off_by_one)
+        .map_err(|e| e.prettify(max_off_by_one))?
+        .circuit
+        .remove_panic_tracking();
+
+    assert_eq!(
+        max.is_equivalent(&max_swapped),
+        EquivalenceResult::Equivalent {
+            inputs_checked: 1 << 16,
+            exhaustive: true
+        }
+    );
+    match max.is_equivalent(&max_off_by_one) {
+        EquivalenceResult::Counterexample {
+            inputs,
+            left,
+            right,
+        } => {
+            assert_ne!(left, right);
+            assert_eq!(max.eval(&inputs), left);
+            assert_eq!(max_off_by_one.eval(&inputs), right);
+        }
+        result => panic!("Expected a counterexample, but found {result:?}"),
+    }
+
+    let config = EquivalenceConfig {
+        max_exhaustive_bits: 0,
+        random_inputs: 100,
+        seed: 42,
+    };
+    assert_eq!(
+        max.is_equivalent_with(&max_swapped, config),
+        EquivalenceResult::Equivalent {
+            inputs_checked: 100,
+            exhaustive: false
+        }
+    );
+    let max_u16 = "
+pub fn main(x: u16, y: u8) -> u16 {
+    if x > y as u16 { x } else { y as u16 }
+}
+";
+    let max_u16 = compile(max_u16).map_err(|e| e.prettify(max_u16))?.circuit;
+    assert_eq!(
+        max.is_equivalent(&max_u16),
+        EquivalenceResult::IncompatibleWidths
+    );
+    Ok(())
+}
@@ -1,7 +1,14 @@
 use std::collections::HashMap;
 
 use garble_lang::{
-    compile, compile_with_constants, literal::Literal, token::UnsignedNumType, Error,
+    ast::{ExprEnum, Op, StmtEnum, Type},
+    check,
+    check::TypeErrorEnum,
+    compile, compile_with_constants,
+    eval::EvalError,
+    literal::{BitOrder, Literal},
+    token::{SignedNumType, UnsignedNumType},
+    CompileTimeError, Error,
 };
 
 fn pretty_print<E: Into<Error>>(e: E, prg: &str) -> Error {
@@ -315,6 +322,373 @@ pub fn main(mode: bool, x: u16, y: u8) -> u16 {
     Ok(())
 }
 
+#[test]
+fn compile_rotate_right() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u16, y: u8) -> u16 {
+    x >>> y
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for x in [0, 1, 0b1011, 0x8001, u16::MAX] {
+        for y in [0, 1, 3, 15, 16, 17, 200, 255] {
+            let mut eval = compiled.evaluator();
+            eval.set_u16(x);
+            eval.set_u8(y);
+            let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+            assert_eq!(
+                u16::try_from(output).map_err(|e| pretty_print(e, prg))?,
+                x.rotate_right(y as u32),
+                "{x} >>> {y}"
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_rotate_left() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u32, y: u8) -> (u32, u32, u32) {
+    (rotate_left(x, y), x <<< y, rotate_right(rotate_left(x, y), y))
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for x in [0, 1, 0b1011, 0x8000_0001, 0xdead_beef, u32::MAX] {
+        for y in [0, 1, 7, 16, 31, 32, 33, 200, 255] {
+            let mut eval = compiled.evaluator();
+            eval.set_u32(x);
+            eval.set_u8(y);
+            let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+            let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+            let rotated = x.rotate_left(y as u32);
+            let expected = Literal::Tuple(vec![rotated.into(), rotated.into(), x.into()]);
+            assert_eq!(r, expected, "rotate_left({x}, {y})");
+        }
+    }
+
+    let prg = "
+pub fn main(x: u32) -> u32 {
+    rotate_left(x, 300u16)
+}
+";
+    assert!(compile(prg).is_err());
+    let prg = "
+pub fn main(x: bool) -> bool {
+    rotate_left(x, 1)
+}
+";
+    assert!(compile(prg).is_err());
+    Ok(())
+}
+
+#[test]
+fn compile_count_ones() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u16, b: bool) -> (usize, u8, usize) {
+    (count_ones(x), count_ones_u8(x), count_ones(b))
+}
+
+fn count_ones_u8(x: u16) -> u8 {
+    count_ones(x)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for x in [0, 1, 0b1011, 0x8000, 0x00ff, 0xaaaa, 0x7fff, u16::MAX] {
+        for b in [false, true] {
+            let mut eval = compiled.evaluator();
+            eval.set_u16(x);
+            eval.set_bool(b);
+            let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+            let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+            let n = x.count_ones();
+            let expected = Literal::Tuple(vec![
+                Literal::NumUnsigned(n as u64, UnsignedNumType::Usize),
+                (n as u8).into(),
+                Literal::NumUnsigned(b as u64, UnsignedNumType::Usize),
+            ]);
+            assert_eq!(r, expected, "count_ones({x}), count_ones({b})");
+        }
+    }
+
+    let prg = "
+pub fn main(x: u8) -> (u8, usize) {
+    (x, count_ones(61455u16))
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(compiled.circuit.and_gates(), 0);
+    let mut eval = compiled.evaluator();
+    eval.set_u8(3);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    let expected = Literal::Tuple(vec![
+        3u8.into(),
+        Literal::NumUnsigned(8, UnsignedNumType::Usize),
+    ]);
+    assert_eq!(r, expected);
+
+    let prg = "
+pub fn main(x: [u8; 2]) -> usize {
+    count_ones(x)
+}
+";
+    assert!(compile(prg).is_err());
+
+    // the count of 256 bits does not fit into a u8, but into a u16:
+    let prg = "
+pub fn main(x: u256) -> u8 {
+    count_ones(x)
+}
+";
+    assert!(compile(prg).is_err());
+    let prg = "
+pub fn main(x: u256) -> u16 {
+    count_ones(x)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    let u256_max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+    eval.parse_literal(&format!("{u256_max}u256"))
+        .map_err(|e| pretty_print(e, prg))?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(r, 256u16.into());
+    Ok(())
+}
+
+#[test]
+fn compile_leading_and_trailing_zeros() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8, y: u32) -> (u8, u8, u8, u8) {
+    (leading_zeros(x), trailing_zeros(x), leading_zeros(y), trailing_zeros(y))
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let xs = [0, 1, 2, 0b0001_0100, 0x7f, 0x80, 0x81, u8::MAX];
+    let ys = [
+        0,
+        1,
+        0b1000,
+        0x0001_0000,
+        0x7fff_ffff,
+        0x8000_0000,
+        u32::MAX,
+    ];
+    for x in xs {
+        for y in ys {
+            let mut eval = compiled.evaluator();
+            eval.set_u8(x);
+            eval.set_u32(y);
+            let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+            let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+            let expected = Literal::Tuple(vec![
+                (x.leading_zeros() as u8).into(),
+                (x.trailing_zeros() as u8).into(),
+                (y.leading_zeros() as u8).into(),
+                (y.trailing_zeros() as u8).into(),
+            ]);
+            assert_eq!(r, expected, "x = {x}, y = {y}");
+        }
+    }
+
+    let prg = "
+pub fn main(x: i16) -> (u8, u8) {
+    (leading_zeros(x), trailing_zeros(x))
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for x in [i16::MIN, -1, 0, 1, 256, i16::MAX] {
+        let mut eval = compiled.evaluator();
+        eval.set_i16(x);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected = Literal::Tuple(vec![
+            (x.leading_zeros() as u8).into(),
+            (x.trailing_zeros() as u8).into(),
+        ]);
+        assert_eq!(r, expected, "x = {x}");
+    }
+
+    let prg = "
+pub fn main(x: u256) -> (u16, u16) {
+    (leading_zeros(x), trailing_zeros(x))
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for (x, leading, trailing) in [(0, 256, 256), (1, 255, 0), (1 << 63, 192, 63)] {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::NumUnsigned(x, UnsignedNumType::U256))
+            .map_err(|e| pretty_print(e, prg))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected = Literal::Tuple(vec![(leading as u16).into(), (trailing as u16).into()]);
+        assert_eq!(r, expected, "x = {x}");
+    }
+
+    let prg = "
+pub fn main(x: bool) -> u8 {
+    leading_zeros(x)
+}
+";
+    assert!(compile(prg).is_err());
+    Ok(())
+}
+
+#[test]
+fn compile_fns_that_shadow_builtins() -> Result<(), Error> {
+    let prg = "
+fn min(a: u8, b: u16) -> u8 {
+    a + (b as u8)
+}
+
+fn max(a: u8, b: u16) -> u8 {
+    a + (b as u8)
+}
+
+fn wrapping_add(a: u16) -> u8 {
+    a as u8
+}
+
+fn sum(a: u8, b: u16) -> u8 {
+    a + (b as u8)
+}
+
+fn product(a: u16) -> u8 {
+    a as u8
+}
+
+fn get_or(a: u8, _i: usize, b: u16) -> u8 {
+    a + (b as u8)
+}
+
+fn reverse(a: u16) -> u8 {
+    a as u8
+}
+
+fn rotate(a: u16, b: u8) -> u8 {
+    (a as u8) + b
+}
+
+pub fn main(x: u8) -> [u8; 6] {
+    [min(1, 2), max(1, 2) + wrapping_add(4), sum(1, 2) * product(2), get_or(1, 0, 2), reverse(2) + rotate(2, 1), x]
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_u8(5);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    let expected = [3u8, 7, 6, 3, 5, 5]
+        .into_iter()
+        .map(Literal::from)
+        .collect();
+    assert_eq!(r, Literal::Array(expected));
+    Ok(())
+}
+
+#[test]
+fn compile_min_and_max() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: i8, y: i8) -> (i8, i8, i8) {
+    (min(x, y), max(x, y), min(-128i8, 1i8))
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let values = [i8::MIN, i8::MIN + 1, -1, 0, 1, i8::MAX - 1, i8::MAX];
+    for x in values {
+        for y in values {
+            let mut eval = compiled.evaluator();
+            eval.set_i8(x);
+            eval.set_i8(y);
+            let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+            let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+            let expected = Literal::Tuple(vec![x.min(y).into(), x.max(y).into(), (-128i8).into()]);
+            assert_eq!(r, expected, "min/max({x}, {y})");
+        }
+    }
+
+    let prg = "
+pub fn main(x: u8, y: u8) -> (u8, u8) {
+    (min(x, y), max(x, 200))
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for x in [0, 1, 127, 128, 199, 200, 201, u8::MAX] {
+        for y in [0, 127, 128, u8::MAX] {
+            let mut eval = compiled.evaluator();
+            eval.set_u8(x);
+            eval.set_u8(y);
+            let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+            let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+            let expected = Literal::Tuple(vec![x.min(y).into(), x.max(200).into()]);
+            assert_eq!(r, expected, "min/max({x}, {y})");
+        }
+    }
+
+    let prg = "
+pub fn main(x: i8, y: u8) -> i8 {
+    min(x, y)
+}
+";
+    assert!(compile(prg).is_err());
+    let prg = "
+pub fn main(x: bool, y: bool) -> bool {
+    max(x, y)
+}
+";
+    assert!(compile(prg).is_err());
+    Ok(())
+}
+
+#[test]
+fn compile_array_slices() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: [u16; 4]) -> ([u16; 4], [u16; 2], u16) {
+    (x[0..4], x[1..3], x[2..4][1])
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(compiled.circuit.and_gates(), 0);
+    let mut eval = compiled.evaluator();
+    let x = Literal::Array(vec![1u16.into(), 2u16.into(), 3u16.into(), 4u16.into()]);
+    eval.set_literal(x.clone())
+        .map_err(|e| pretty_print(e, prg))?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    let expected = Literal::Tuple(vec![
+        x,
+        Literal::Array(vec![2u16.into(), 3u16.into()]),
+        4u16.into(),
+    ]);
+    assert_eq!(r, expected);
+    Ok(())
+}
+
+#[test]
+fn compile_arithmetic_and_logical_shift_right() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: i8, y: u8) -> (i8, u8) {
+    (x >> y, (x as u8) >> y)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for x in [-128, -5, -1, 0, 5, 127] {
+        for y in 0..8 {
+            let mut eval = compiled.evaluator();
+            eval.set_i8(x);
+            eval.set_u8(y);
+            let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+            let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+            let expected =
+                Literal::Tuple(vec![Literal::from(x >> y), Literal::from((x as u8) >> y)]);
+            assert_eq!(r, expected, "{x} >> {y}");
+        }
+    }
+    Ok(())
+}
+
 #[test]
 fn compile_bit_shifts_u16() -> Result<(), Error> {
     let prg = "
@@ -659,6 +1033,100 @@ pub fn main(x: i8, y: i8) -> i8 {
             }
         }
     }
+    for (x, y) in [(16, 8), (-16, -8), (-128, -1), (64, 2), (-2, 64), (127, -1)] {
+        let mut eval = compiled.evaluator();
+        eval.set_i8(x);
+        eval.set_i8(y);
+        let result = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let output = i8::try_from(result);
+        match x.checked_mul(y) {
+            Some(expected) => assert_eq!(output.ok(), Some(expected), "{x} * {y}"),
+            None => assert!(output.is_err(), "{x} * {y}"),
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_saturating_ops() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8, y: u8) -> (u8, u8, u8) {
+    (x +| y, x -| y, x *| y)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let samples: Vec<u8> = [0, 1, 2, 15, 16, 17, 127, 128, 129, 254, 255]
+        .into_iter()
+        .chain((0..=255).step_by(7))
+        .collect();
+    for &x in samples.iter() {
+        for &y in samples.iter() {
+            let mut eval = compiled.evaluator();
+            eval.set_u8(x);
+            eval.set_u8(y);
+            let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+            let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+            let expected = Literal::Tuple(vec![
+                x.saturating_add(y).into(),
+                x.saturating_sub(y).into(),
+                x.saturating_mul(y).into(),
+            ]);
+            assert_eq!(r, expected, "{x}, {y}");
+        }
+    }
+
+    let prg = "
+pub fn main(x: i8, y: i8) -> (i8, i8, i8) {
+    (x +| y, x -| y, x *| y)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let samples: Vec<i8> = [
+        -128, -127, -64, -16, -8, -2, -1, 0, 1, 2, 8, 16, 64, 126, 127,
+    ]
+    .into_iter()
+    .chain((-128..=127).step_by(7))
+    .collect();
+    for &x in samples.iter() {
+        for &y in samples.iter() {
+            let mut eval = compiled.evaluator();
+            eval.set_i8(x);
+            eval.set_i8(y);
+            let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+            let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+            let expected = Literal::Tuple(vec![
+                x.saturating_add(y).into(),
+                x.saturating_sub(y).into(),
+                x.saturating_mul(y).into(),
+            ]);
+            assert_eq!(r, expected, "{x}, {y}");
+        }
+    }
+
+    let prg = "
+pub fn main(x: u8) -> (u8, i8, i8, u8) {
+    (x +| 250u8 +| 10u8, 100i8 *| -2i8, -100i8 -| 100i8, 3u8 -| 5u8)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_u8(1);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    let expected = Literal::Tuple(vec![
+        255u8.into(),
+        (-128i8).into(),
+        (-128i8).into(),
+        0u8.into(),
+    ]);
+    assert_eq!(r, expected);
+
+    let prg = "
+pub fn main(x: bool, y: bool) -> bool {
+    x +| y
+}
+";
+    assert!(compile(prg).is_err());
     Ok(())
 }
 
@@ -1116,19 +1584,70 @@ pub fn main(b: bool) -> u8 {
 }
 
 #[test]
-fn compile_exhaustive_enum_pattern_with_literals() -> Result<(), Error> {
+fn compile_enum_pattern_with_wildcard() -> Result<(), Error> {
     let prg = "
-enum Ops {
-    Mul(u8, u8),
-    Div(u8, u8),
+enum Op {
+    Add(u8, u8),
+    Neg(u8),
+    Zero,
+    One,
 }
 
-pub fn main(choice: u8, x: u8, y: u8) -> u8 {
-    let op = if choice == 0 {
-        Ops::Mul(x, y)
-    } else {
-        Ops::Div(x, y)
-    };
+pub fn main(op: Op) -> u8 {
+    match op {
+        Op::Add(x, _) => x,
+        Op::One => 1,
+        _ => 0,
+    }
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for (op, expected) in [
+        ("Op::Add(3, 4)", 3),
+        ("Op::Neg(5)", 0),
+        ("Op::Zero", 0),
+        ("Op::One", 1),
+    ] {
+        let mut eval = compiled.evaluator();
+        eval.parse_literal(op).map_err(|e| pretty_print(e, prg))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        assert_eq!(
+            u8::try_from(output).map_err(|e| pretty_print(e, prg))?,
+            expected,
+            "{op}"
+        );
+    }
+
+    // unlike an identifier, the wildcard does not bind the matched value:
+    let prg = "
+pub fn main(x: u8) -> u8 {
+    match x {
+        0 => 1,
+        _ => _,
+    }
+}
+";
+    let Err(Error::CompileTimeError(CompileTimeError::TypeError(e))) = check(prg) else {
+        panic!("expected a type error for using the wildcard as a variable");
+    };
+    assert_eq!(e[0].0, TypeErrorEnum::UnknownIdentifier("_".to_string()));
+    Ok(())
+}
+
+#[test]
+fn compile_exhaustive_enum_pattern_with_literals() -> Result<(), Error> {
+    let prg = "
+enum Ops {
+    Mul(u8, u8),
+    Div(u8, u8),
+}
+
+pub fn main(choice: u8, x: u8, y: u8) -> u8 {
+    let op = if choice == 0 {
+        Ops::Mul(x, y)
+    } else {
+        Ops::Div(x, y)
+    };
 
     match op {
         Ops::Div(x, 0) => 42,
@@ -1418,7 +1937,7 @@ fn compile_lexically_scoped_block() -> Result<(), Error> {
     let prg = "
 pub fn main(x: i32) -> i32 {
     let y = x + 1;
-    let z = {
+    let _z = {
         let y = x + 10;
         y
     };
@@ -1520,12 +2039,12 @@ struct FooBar {
 }
 
 pub fn main(x: (i32, i32)) -> i32 {
-    let (a, b) = x;
+    let (a, _b) = x;
 
     let bar = (0, 0);
     let foobar = FooBar { foo: 0, bar };
     let FooBar { bar, .. } = foobar;
-    let (y, z) = bar;
+    let (y, _z) = bar;
     a + y
 }
 ";
@@ -1584,12 +2103,12 @@ pub fn main(x: i32) -> i32 {
         },
         1 => {
             let FooBar { bar, .. } = foobar;
-            let (x, y) = bar;
+            let (_x, y) = bar;
             y
         },
         _ => {
             let FooBar { baz, .. } = foobar;
-            let (x, y, z) = baz;
+            let (_x, _y, z) = baz;
             z
         }
     }
@@ -1772,11 +2291,11 @@ fn compile_arrays_copied_by_value() -> Result<(), Error> {
     let prg = "
 pub fn main(replacement: i32) -> [i32; 4] {
     let mut array1 = [10, 20, 30, 40];
-    let second_val = array1[1]; // will be `20`
+    let _second_val = array1[1]; // will be `20`
     let mut array2 = array1;
     array2[1] = replacement;
-    let second_val1 = array1[1]; // will still be `20`
-    let second_val2 = array2[1]; // will be equal to the value of `replacement`
+    let _second_val1 = array1[1]; // will still be `20`
+    let _second_val2 = array2[1]; // will be equal to the value of `replacement`
     array2
 }
 ";
@@ -1795,32 +2314,32 @@ pub fn main(replacement: i32) -> [i32; 4] {
 fn compile_operator_examples() -> Result<(), Error> {
     let prg = "
 pub fn main(_a: i32, _b: i32) -> () {
-    let add = 0 + 1;
-    let sub = 1 - 1;
-    let mul = 2 * 1;
-    let div = 2 / 1;
-    let rem = 5 % 2;
+    let _add = 0 + 1;
+    let _sub = 1 - 1;
+    let _mul = 2 * 1;
+    let _div = 2 / 1;
+    let _rem = 5 % 2;
 
-    let bit_xor = 4 ^ 6;
-    let bit_and = 4 & 6;
-    let bit_or = 4 | 6;
-    let bit_shiftl = 4 << 1;
-    let bit_shiftr = 4 >> 1;
+    let _bit_xor = 4 ^ 6;
+    let _bit_and = 4 & 6;
+    let _bit_or = 4 | 6;
+    let _bit_shiftl = 4 << 1;
+    let _bit_shiftr = 4 >> 1;
 
-    let and = true & false;
-    let or = true | false;
+    let _and = true & false;
+    let _or = true | false;
 
-    let eq = true == false;
-    let neq = true != false;
+    let _eq = true == false;
+    let _neq = true != false;
 
-    let gt = 5 > 4;
-    let lt = 4 < 5;
-    let gte = 5 >= 4;
-    let lte = 4 <= 5;
+    let _gt = 5 > 4;
+    let _lt = 4 < 5;
+    let _gte = 5 >= 4;
+    let _lte = 4 <= 5;
 
-    let unary_not = !true;
-    let unary_minus = -5;
-    let unary_bitflip = !5;
+    let _unary_not = !true;
+    let _unary_minus = -5;
+    let _unary_bitflip = !5;
 }
 ";
     let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
@@ -2010,6 +2529,57 @@ pub fn main(array: [u16; MY_CONST]) -> u16 {
     Ok(())
 }
 
+#[test]
+fn input_schema_of_mixed_params() -> Result<(), Error> {
+    let prg = "
+const LEN: usize = PARTY_1::LEN;
+struct Point {
+    x: i16,
+    y: i16,
+}
+pub fn main(a: u8, b: (bool, [i32; LEN]), c: Point) -> i32 {
+    let (flag, values) = b;
+    if flag {
+        values[0] + (a as i32)
+    } else {
+        (c.x as i32) + (c.y as i32)
+    }
+}
+";
+    let consts = HashMap::from_iter(vec![(
+        "PARTY_1".to_string(),
+        HashMap::from_iter(vec![(
+            "LEN".to_string(),
+            Literal::NumUnsigned(3, UnsignedNumType::Usize),
+        )]),
+    )]);
+    let checked = check(prg).map_err(|e| pretty_print(e, prg))?;
+    let schema = checked
+        .input_schema("main", consts.clone())
+        .map_err(|e| pretty_print(e, prg))?;
+    let i32_array = Type::Array(Box::new(Type::Signed(SignedNumType::I32)), 3);
+    assert_eq!(
+        schema,
+        vec![
+            (0, "a".to_string(), Type::Unsigned(UnsignedNumType::U8), 8),
+            (
+                1,
+                "b".to_string(),
+                Type::Tuple(vec![Type::Bool, i32_array]),
+                1 + 3 * 32
+            ),
+            (2, "c".to_string(), Type::Struct("Point".to_string()), 32),
+        ]
+    );
+    let (circuit, _, _) = checked
+        .compile_with_constants("main", consts)
+        .map_err(|e| pretty_print(e, prg))?;
+    let sizes: Vec<usize> = schema.iter().map(|(_, _, _, size)| *size).collect();
+    assert_eq!(sizes, circuit.input_gates);
+    assert!(checked.input_schema("main", HashMap::new()).is_err());
+    Ok(())
+}
+
 #[test]
 fn compile_const_size_for_each_loop() -> Result<(), Error> {
     let prg = "
@@ -2046,6 +2616,72 @@ pub fn main(array: [u16; MY_CONST]) -> u16 {
     Ok(())
 }
 
+#[test]
+fn compile_const_shared_across_fns() -> Result<(), Error> {
+    let prg = "
+const LEN: usize = 4;
+const OFFSET: u8 = 3;
+const DOUBLE_OFFSET: u8 = OFFSET * 2;
+pub fn main(x: [u8; LEN]) -> (u8, u8) {
+    (first(x) + DOUBLE_OFFSET, last(x) * OFFSET)
+}
+fn first(x: [u8; LEN]) -> u8 {
+    x[0]
+}
+fn last(x: [u8; LEN]) -> u8 {
+    x[LEN - 1]
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.parse_literal("[1, 2, 3, 4]").unwrap();
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(
+        r,
+        Literal::Tuple(vec![
+            Literal::NumUnsigned(7, UnsignedNumType::U8),
+            Literal::NumUnsigned(12, UnsignedNumType::U8),
+        ])
+    );
+    Ok(())
+}
+
+#[test]
+fn compile_arrays_sized_by_shared_const() -> Result<(), Error> {
+    let prg = "
+const LEN: usize = 3;
+const TOTAL: usize = LEN + LEN;
+pub fn main(a: [u8; LEN], b: [u8; LEN]) -> [u8; TOTAL] {
+    let mut result = [0u8; TOTAL];
+    let mut i = 0usize;
+    for elem in a {
+        result[i] = elem;
+        i += 1;
+    }
+    for elem in b {
+        result[i] = elem;
+        i += 1;
+    }
+    result
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let u8_ty = Box::new(Type::Unsigned(UnsignedNumType::U8));
+    assert_eq!(compiled.main.params[0].ty, Type::Array(u8_ty.clone(), 3));
+    assert_eq!(compiled.main.ty, Type::Array(u8_ty, 6));
+    let mut eval = compiled.evaluator();
+    eval.parse_literal("[1, 2, 3]").unwrap();
+    eval.parse_literal("[4, 5, 6]").unwrap();
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    let expected = (1..=6)
+        .map(|n| Literal::NumUnsigned(n, UnsignedNumType::U8))
+        .collect();
+    assert_eq!(r, Literal::Array(expected));
+    Ok(())
+}
+
 #[test]
 fn compile_join_loop() -> Result<(), Error> {
     let prg = "
@@ -2318,3 +2954,1697 @@ pub fn main(_x: i32) -> i32 {
     }
     Ok(())
 }
+
+#[test]
+fn compile_tuple_to_array_and_back() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8) -> (bool, (u8, u8, u8)) {
+    let arr = tuple_to_array((1u8, 2u8, x));
+    let unchanged = arr == [1u8, 2u8, 3u8];
+    let tuple = array_to_tuple([x, 5, 6]);
+    (unchanged, tuple)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for x in [3, 4] {
+        let mut eval = compiled.evaluator();
+        eval.set_u8(x);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected = Literal::Tuple(vec![
+            Literal::from(x == 3),
+            Literal::Tuple(vec![x.into(), 5u8.into(), 6u8.into()]),
+        ]);
+        assert_eq!(r, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_fn_body_with_multiple_statements() -> Result<(), Error> {
+    let prg = "
+fn add_and_double(x: u8, y: u8) -> u8 {
+    let sum = x + y;
+    let doubled = sum + sum;
+    doubled
+}
+
+pub fn main(x: u8) -> u8 {
+    add_and_double(x, 3u8)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for x in 0..10 {
+        let mut eval = compiled.evaluator();
+        eval.set_u8(x);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        assert_eq!(
+            u8::try_from(output).map_err(|e| pretty_print(e, prg))?,
+            (x + 3) * 2
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn literal_as_bits_with_order() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8) -> u8 {
+    x
+}
+";
+    let checked = check(prg).map_err(|e| pretty_print(e, prg))?;
+    let consts = HashMap::new();
+    let five = Literal::from(5u8);
+    let msb_first = five.as_bits_with_order(&checked, &consts, BitOrder::MsbFirst);
+    assert_eq!(
+        msb_first,
+        vec![false, false, false, false, false, true, false, true]
+    );
+    assert_eq!(msb_first, five.as_bits(&checked, &consts));
+    let lsb_first = five.as_bits_with_order(&checked, &consts, BitOrder::LsbFirst);
+    assert_eq!(
+        lsb_first,
+        vec![true, false, true, false, false, false, false, false]
+    );
+    let ty = Type::Tuple(vec![Type::Unsigned(UnsignedNumType::U8), Type::Bool]);
+    let literal = Literal::Tuple(vec![five, Literal::True]);
+    for order in [BitOrder::MsbFirst, BitOrder::LsbFirst] {
+        let bits = literal.as_bits_with_order(&checked, &consts, order);
+        let decoded = Literal::from_unwrapped_bits_with_order(&checked, &ty, &bits, &consts, order)
+            .map_err(|e| pretty_print(e, prg))?;
+        assert_eq!(decoded, literal);
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_gather_bits() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8) -> (u8, u8) {
+    (gather_bits(x, 12), gather_bits(x, 255u8))
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for x in [0b1010u8, 0b0110_0111] {
+        let mut eval = compiled.evaluator();
+        eval.set_u8(x);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let gathered = (x & 0b1100) >> 2;
+        assert_eq!(r, Literal::Tuple(vec![gathered.into(), x.into()]));
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_64_bit_boundaries() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u64, y: i64) -> (u64, u64, i64, i64) {
+    (18446744073709551615u64, x + 1u64, y, -9223372036854775807i64 - 1i64)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_u64(u64::MAX - 1);
+    eval.set_i64(i64::MAX);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    let expected = Literal::Tuple(vec![
+        u64::MAX.into(),
+        u64::MAX.into(),
+        i64::MAX.into(),
+        i64::MIN.into(),
+    ]);
+    assert_eq!(r, expected);
+    Ok(())
+}
+
+#[test]
+fn compile_256_bit_addition() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u256, y: u256) -> (u256, u256, bool) {
+    (wrapping_add(x, y), x >> 200u8, x > y)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let max = Literal::from_be_bytes(&[0xff; 32], UnsignedNumType::U256).unwrap();
+    let cases = [
+        (max.clone(), 1u64, "0", "72057594037927935", "true"),
+        (max.clone(), 2, "1", "72057594037927935", "true"),
+        (
+            Literal::NumUnsigned(u64::MAX, UnsignedNumType::U256),
+            1,
+            "18446744073709551616",
+            "0",
+            "true",
+        ),
+    ];
+    for (x, y, sum, shifted, greater) in cases {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(x).map_err(|e| pretty_print(e, prg))?;
+        eval.set_literal(Literal::NumUnsigned(y, UnsignedNumType::U256))
+            .map_err(|e| pretty_print(e, prg))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        assert_eq!(r.to_string(), format!("({sum}, {shifted}, {greater})"));
+    }
+
+    let prg = "
+pub fn main(x: u256) -> u256 {
+    x + 1u256
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.parse_literal("340282366920938463463374607431768211455")
+        .map_err(|e| pretty_print(e, prg))?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let mut expected = vec![0; 32];
+    expected[15] = 1;
+    assert_eq!(
+        output.get_biguint().map_err(|e| pretty_print(e, prg))?,
+        expected
+    );
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(r.to_string(), "340282366920938463463374607431768211456");
+
+    let mut eval = compiled.evaluator();
+    eval.set_literal(max).map_err(|e| pretty_print(e, prg))?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    assert!(matches!(output.get_biguint(), Err(EvalError::Panic(_))));
+    Ok(())
+}
+
+#[test]
+fn compile_256_bit_literals() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u256) -> (u256, u256) {
+    let max = 0xFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFFu256;
+    (x + 18446744073709551616u256, max - x)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.parse_literal("1u256")
+        .map_err(|e| pretty_print(e, prg))?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    let u256_max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+    let max_minus_1 =
+        "115792089237316195423570985008687907853269984665640564039457584007913129639934";
+    assert_eq!(
+        r.to_string(),
+        format!("(18446744073709551617, {max_minus_1})")
+    );
+
+    let prg = "
+pub fn main(x: [u256; 2], y: (u256, u8)) -> u256 {
+    wrapping_add(x[0], x[1]) | y.0
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.parse_literal(&format!("[{u256_max}, 1u256]"))
+        .map_err(|e| pretty_print(e, prg))?;
+    eval.parse_literal("(0x1_0000_0000_0000_0000_0000_0000_0000_0000u256, 1u8)")
+        .map_err(|e| pretty_print(e, prg))?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(r.to_string(), "340282366920938463463374607431768211456");
+
+    let ty = Type::Tuple(vec![
+        Type::Unsigned(UnsignedNumType::U256),
+        Type::Unsigned(UnsignedNumType::U8),
+    ]);
+    let literal = Literal::parse(&compiled.program, &ty, &format!("({u256_max}, 1u8)"))
+        .map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(literal.to_string(), format!("({u256_max}, 1)"));
+    let too_large =
+        "0x1_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000_0000";
+    assert!(Literal::parse(&compiled.program, &ty, &format!("({too_large}, 1u8)")).is_err());
+    Ok(())
+}
+
+#[test]
+fn compile_struct_update() -> Result<(), Error> {
+    let prg = "
+struct Point {
+    x: u8,
+    y: u8,
+}
+
+pub fn main(base: (u8, u8), x: u8) -> Point {
+    let (base_x, base_y) = base;
+    let base = Point { x: base_x, y: base_y };
+    Point { x, ..base }
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_literal(Literal::Tuple(vec![1u8.into(), 2u8.into()]))?;
+    eval.set_u8(5);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    let expected = Literal::Struct(
+        "Point".to_string(),
+        vec![("x".to_string(), 5u8.into()), ("y".to_string(), 2u8.into())],
+    );
+    assert_eq!(r, expected);
+    Ok(())
+}
+
+#[test]
+fn compile_prefix_sum() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8) -> bool {
+    prefix_sum([1u8, 2, 3, x]) == [1u8, 3, 6, 10]
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for x in [3, 4] {
+        let mut eval = compiled.evaluator();
+        eval.set_u8(x);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        assert_eq!(
+            bool::try_from(output).map_err(|e| pretty_print(e, prg))?,
+            x == 4
+        );
+    }
+    for n in 1..10 {
+        let prg = format!(
+            "
+pub fn main(a: [i8; {n}]) -> [i8; {n}] {{
+    prefix_sum(a)
+}}
+"
+        );
+        let compiled = compile(&prg).map_err(|e| pretty_print(e, &prg))?;
+        // large intermediate sums of non-prefix ranges must not be reported as overflows:
+        let a: Vec<i8> = (0..n).map(|i| [-100, 100, 27][i % 3]).collect();
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::Array(a.iter().map(|&n| n.into()).collect()))?;
+        let output = eval.run().map_err(|e| pretty_print(e, &prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, &prg))?;
+        let mut sum = 0;
+        let expected = a.iter().map(|n| {
+            sum += n;
+            sum.into()
+        });
+        assert_eq!(r, Literal::Array(expected.collect()));
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_permute() -> Result<(), Error> {
+    let prg = "
+pub fn main(a: [u8; 5], perm: [usize; 5]) -> [u8; 5] {
+    permute(a, perm)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let a = [10u8, 20, 30, 40, 50];
+    for perm in [[0usize, 1, 2, 3, 4], [4, 3, 2, 1, 0], [2, 0, 4, 1, 3]] {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::Array(a.iter().map(|&n| n.into()).collect()))?;
+        let perm_literal = perm
+            .iter()
+            .map(|&i| Literal::NumUnsigned(i as u64, UnsignedNumType::Usize))
+            .collect();
+        eval.set_literal(Literal::Array(perm_literal))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let mut expected = [0u8; 5];
+        for (i, &p) in perm.iter().enumerate() {
+            expected[p] = a[i];
+        }
+        let expected = Literal::Array(expected.iter().map(|&n| n.into()).collect());
+        assert_eq!(r, expected);
+    }
+    // not a permutation, but the result must still be deterministic:
+    let mut results = vec![];
+    for _ in 0..2 {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::Array(a.iter().map(|&n| n.into()).collect()))?;
+        let perm_literal = [1usize, 1, 7, 0, 0]
+            .iter()
+            .map(|&i| Literal::NumUnsigned(i as u64, UnsignedNumType::Usize))
+            .collect();
+        eval.set_literal(Literal::Array(perm_literal))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        results.push(output.into_literal().map_err(|e| pretty_print(e, prg))?);
+    }
+    assert_eq!(results[0], results[1]);
+    Ok(())
+}
+
+#[test]
+fn compile_get_or() -> Result<(), Error> {
+    let prg = "
+pub fn main(i: usize) -> u8 {
+    get_or([1u8, 2, 3], i, 99)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    assert!(!compiled.circuit.gates.is_empty());
+    for (i, expected) in [(0, 1), (2, 3), (3, 99), (5, 99)] {
+        let mut eval = compiled.evaluator();
+        eval.set_usize(i);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        assert_eq!(
+            u8::try_from(output).map_err(|e| pretty_print(e, prg))?,
+            expected
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_lookup_table() -> Result<(), Error> {
+    let lookup = "
+pub fn main(table: [u8; 8], i: [usize; 4]) -> [u8; 4] {
+    lookup_table(table, i)
+}
+";
+    let separate = "
+pub fn main(table: [u8; 8], i: [usize; 4]) -> [u8; 4] {
+    [table[i[0]], table[i[1]], table[i[2]], table[i[3]]]
+}
+";
+    let table: Vec<u8> = vec![3, 1, 4, 1, 5, 9, 2, 6];
+    let indexes: Vec<usize> = vec![7, 0, 5, 5];
+    let mut and_gates = vec![];
+    for prg in [lookup, separate] {
+        let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+        and_gates.push(compiled.circuit.and_gates());
+        let mut eval = compiled.evaluator();
+        let table_arg = Literal::Array(table.iter().map(|&x| x.into()).collect());
+        let index_arg = Literal::Array(
+            indexes
+                .iter()
+                .map(|&i| Literal::NumUnsigned(i as u64, UnsignedNumType::Usize))
+                .collect(),
+        );
+        eval.set_literal(table_arg)
+            .map_err(|e| pretty_print(e, prg))?;
+        eval.set_literal(index_arg)
+            .map_err(|e| pretty_print(e, prg))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected = indexes.iter().map(|&i| table[i].into()).collect();
+        assert_eq!(r, Literal::Array(expected));
+    }
+    assert!(and_gates[0] < and_gates[1], "{and_gates:?}");
+    Ok(())
+}
+
+#[test]
+fn compile_lookup_table_out_of_bounds() -> Result<(), Error> {
+    let prg = "
+pub fn main(i: usize) -> [u8; 2] {
+    lookup_table([1, 2, 3], [i, 0])
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_usize(2);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(r, Literal::Array(vec![3u8.into(), 1u8.into()]));
+    let mut eval = compiled.evaluator();
+    eval.set_usize(3);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    assert!(output.into_literal().is_err());
+    Ok(())
+}
+
+#[test]
+fn encode_party_input() -> Result<(), Error> {
+    let prg = "
+pub fn main(pair: (u8, bool), y: u8) -> u8 {
+    let (x, b) = pair;
+    if b { x } else { y }
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let bits = compiled.encode_party_input(0, &[5u8.into(), true.into()])?;
+    assert_eq!(bits.len(), compiled.circuit.input_gates[0]);
+    assert_eq!(
+        bits,
+        vec![false, false, false, false, false, true, false, true, true]
+    );
+    let bits = compiled.encode_party_input(1, &[6u8.into()])?;
+    assert_eq!(bits.len(), compiled.circuit.input_gates[1]);
+    assert!(matches!(
+        compiled.encode_party_input(0, &[5u8.into()]),
+        Err(EvalError::UnexpectedNumberOfPartyValues {
+            party: 0,
+            expected: 2,
+            actual: 1
+        })
+    ));
+    assert!(matches!(
+        compiled.encode_party_input(0, &[true.into(), 5u8.into()]),
+        Err(EvalError::InvalidLiteralType(_, _))
+    ));
+    let pair = Literal::Tuple(vec![5u8.into(), true.into()]);
+    assert!(compiled.encode_party_input(1, &[pair]).is_err());
+    assert!(matches!(
+        compiled.encode_party_input(2, &[5u8.into()]),
+        Err(EvalError::InvalidArgIndex(2))
+    ));
+    Ok(())
+}
+
+#[test]
+fn compile_match_on_tuple_of_enums() -> Result<(), Error> {
+    let prg = "
+enum A {
+    X,
+    Y,
+}
+
+enum B {
+    X,
+    Y,
+}
+
+pub fn main(a_is_x: bool, b_is_x: bool) -> u8 {
+    let a = if a_is_x { A::X } else { A::Y };
+    let b = if b_is_x { B::X } else { B::Y };
+    match (a, b) {
+        (A::X, B::X) => 1,
+        (A::X, B::Y) => 2,
+        (A::Y, B::X) => 3,
+        (A::Y, B::Y) => 4,
+    }
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for (a_is_x, b_is_x, expected) in [
+        (true, true, 1),
+        (true, false, 2),
+        (false, true, 3),
+        (false, false, 4),
+    ] {
+        let mut eval = compiled.evaluator();
+        eval.set_bool(a_is_x);
+        eval.set_bool(b_is_x);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        assert_eq!(
+            u8::try_from(output).map_err(|e| pretty_print(e, prg))?,
+            expected
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_nested_tuple_and_enum_patterns_in_variant_fields() -> Result<(), Error> {
+    let prg = "
+enum Inner {
+    X,
+    Y(u8),
+}
+
+enum Outer {
+    Wrap(Inner),
+    Pair((u8, (bool, u8))),
+}
+
+pub fn main(o: Outer) -> u8 {
+    match o {
+        Outer::Wrap(Inner::X) => 1,
+        Outer::Wrap(Inner::Y(n)) => n,
+        Outer::Pair((a, (true, b))) => a + b,
+        Outer::Pair((a, (false, _))) => a,
+    }
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for (arg, expected) in [
+        ("Outer::Wrap(Inner::X)", 1),
+        ("Outer::Wrap(Inner::Y(7))", 7),
+        ("Outer::Pair((3, (true, 4)))", 7),
+        ("Outer::Pair((3, (false, 4)))", 3),
+    ] {
+        let mut eval = compiled.evaluator();
+        eval.parse_literal(arg).map_err(|e| pretty_print(e, prg))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        assert_eq!(
+            u8::try_from(output).map_err(|e| pretty_print(e, prg))?,
+            expected,
+            "{arg}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_histogram() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8) -> bool {
+    histogram([0u8, 1, 1, 2], 3) == [1usize, 2, 1]
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_u8(0);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    assert!(bool::try_from(output).map_err(|e| pretty_print(e, prg))?);
+
+    let prg = "
+pub fn main(values: [u8; 5]) -> [usize; 3] {
+    histogram(values, 3)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for (values, expected) in [
+        ([0, 1, 1, 2, 0], [2, 2, 1]),
+        ([2, 2, 2, 2, 2], [0, 0, 5]),
+        ([3, 255, 1, 4, 0], [1, 1, 0]),
+    ] {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::Array(
+            values.iter().map(|&n: &u8| n.into()).collect(),
+        ))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected: Vec<Literal> = expected
+            .iter()
+            .map(|&n| Literal::NumUnsigned(n, UnsignedNumType::Usize))
+            .collect();
+        assert_eq!(r, Literal::Array(expected));
+    }
+
+    let prg = "
+pub fn main(values: [u64; 3]) -> [usize; 2] {
+    histogram(values, 2)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.parse_literal(&format!("[1u64, {}u64, 9223372036854775808u64]", u64::MAX))
+        .map_err(|e| pretty_print(e, prg))?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(r.to_string(), "[0, 1]");
+
+    let prg = "
+pub fn main(values: [u256; 4]) -> [usize; 2] {
+    histogram(values, 2)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.parse_literal("[0u256, 1u256, 1u256, 0x1_0000_0000_0000_0000_0000_0000_0000_0001u256]")
+        .map_err(|e| pretty_print(e, prg))?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(r.to_string(), "[1, 2]");
+    Ok(())
+}
+
+#[test]
+fn compile_string_literals_as_byte_arrays() -> Result<(), Error> {
+    let prg = r#"
+pub fn main(x: [u8; 2]) -> (bool, bool, [u8; 2]) {
+    ("AB" == [65u8, 66u8], x == "AB", "AB")
+}
+"#;
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let ab = Literal::Array(vec![65u8.into(), 66u8.into()]);
+    for (x, expected) in [
+        (ab.clone(), true),
+        (Literal::Array(vec![65u8.into(), 67u8.into()]), false),
+    ] {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(x)?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        assert_eq!(
+            r,
+            Literal::Tuple(vec![Literal::True, expected.into(), ab.clone()])
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_char() -> Result<(), Error> {
+    let prg = "
+pub fn main(c: char) -> (bool, bool, bool, u32, char) {
+    ('A' == 'A', 'A' < 'B', 'A' as u32 == 65, c as u32, (c as u32 + 1) as char)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(compiled.main.params[0].ty, Type::Char);
+    for c in ['a', 'ä', '€'] {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::Char(c))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected = Literal::Tuple(vec![
+            Literal::True,
+            Literal::True,
+            Literal::True,
+            Literal::NumUnsigned(c as u64, UnsignedNumType::U32),
+            Literal::Char(char::from_u32(c as u32 + 1).unwrap()),
+        ]);
+        assert_eq!(r, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_generic_fn_at_two_types() -> Result<(), Error> {
+    let prg = "
+pub fn main(a: u8, b: i32) -> (u8, i32, u8) {
+    (max2(a, 5u8), max2(b, -5), max2(a, 200))
+}
+
+fn max2<T>(a: T, b: T) -> T {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut instances: Vec<&String> = compiled.program.fn_defs.keys().collect();
+    instances.sort();
+    assert_eq!(instances, vec!["main", "max2::<i32>", "max2::<u8>"]);
+    for (a, b, expected) in [(3, -7, (5, -5, 200)), (250, 10, (250, 10, 250))] {
+        let mut eval = compiled.evaluator();
+        eval.set_u8(a);
+        eval.set_i32(b);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let (max_a, max_b, max_c) = expected;
+        let expected = Literal::Tuple(vec![
+            Literal::NumUnsigned(max_a, UnsignedNumType::U8),
+            Literal::NumSigned(max_b, SignedNumType::I32),
+            Literal::NumUnsigned(max_c, UnsignedNumType::U8),
+        ]);
+        assert_eq!(r, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_generic_sum_at_u8_and_u32() -> Result<(), Error> {
+    let prg = "
+pub fn main(a: u8, b: u32) -> (u8, u32) {
+    (sum(a, 3u8), sum(b, 70000u32))
+}
+
+fn sum<N>(a: N, b: N) -> N {
+    a + b
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut instances: Vec<&String> = compiled.program.fn_defs.keys().collect();
+    instances.sort();
+    assert_eq!(instances, vec!["main", "sum::<u32>", "sum::<u8>"]);
+    for (a, b) in [(0, 0), (100, 5), (250, 4_000_000_000)] {
+        let mut eval = compiled.evaluator();
+        eval.set_u8(a);
+        eval.set_u32(b);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected = Literal::Tuple(vec![
+            Literal::NumUnsigned((a + 3) as u64, UnsignedNumType::U8),
+            Literal::NumUnsigned((b + 70000) as u64, UnsignedNumType::U32),
+        ]);
+        assert_eq!(r, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_get_with_index_path() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: ((u8, (u8, u8, u8))), arr: [(u8, u8); 2]) -> (u8, u8, (u8, u8, u8), u8) {
+    (get(x, [0, 0]), get(x, [0, 1, 2]), get(x, [0, 1]), get(arr, [1, 0]))
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    let inner = Literal::Tuple(vec![2u8.into(), 3u8.into(), 4u8.into()]);
+    eval.set_literal(Literal::Tuple(vec![Literal::Tuple(vec![
+        1u8.into(),
+        inner.clone(),
+    ])]))?;
+    eval.parse_literal("[(5, 6), (7, 8)]")
+        .map_err(|e| pretty_print(e, prg))?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    let expected = Literal::Tuple(vec![1u8.into(), 4u8.into(), inner, 7u8.into()]);
+    assert_eq!(r, expected);
+    Ok(())
+}
+
+#[test]
+fn compile_divmod() -> Result<(), Error> {
+    let prg = "
+pub fn main(_x: u8) -> (bool, bool) {
+    (divmod(17u8, 5) == (3u8, 2u8), divmod(-17i8, 5) == (-3i8, -2i8))
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_u8(0);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(r, Literal::Tuple(vec![Literal::True, Literal::True]));
+
+    let separate = "
+pub fn main(x: u32, y: u32) -> (u32, u32) {
+    (x / y, x % y)
+}
+";
+    let combined = "
+pub fn main(x: u32, y: u32) -> (u32, u32) {
+    divmod(x, y)
+}
+";
+    let separate = compile(separate).map_err(|e| pretty_print(e, separate))?;
+    let combined = compile(combined).map_err(|e| pretty_print(e, combined))?;
+    assert!(combined.circuit.gates.len() <= separate.circuit.gates.len());
+    for (x, y) in [(17, 5), (u32::MAX, 7), (3, 10)] {
+        let mut eval = combined.evaluator();
+        eval.set_u32(x);
+        eval.set_u32(y);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected = Literal::Tuple(vec![
+            Literal::NumUnsigned((x / y) as u64, UnsignedNumType::U32),
+            Literal::NumUnsigned((x % y) as u64, UnsignedNumType::U32),
+        ]);
+        assert_eq!(r, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_one_hot() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8) -> [bool; 3] {
+    one_hot(x, [0, 1, 2])
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for (x, expected) in [
+        (2, [false, false, true]),
+        (0, [true, false, false]),
+        (7, [false, false, false]),
+    ] {
+        let mut eval = compiled.evaluator();
+        eval.set_u8(x);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected = expected
+            .iter()
+            .map(|&b| if b { Literal::True } else { Literal::False })
+            .collect();
+        assert_eq!(r, Literal::Array(expected));
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_nested_loops_with_scoped_bindings() -> Result<(), Error> {
+    let prg = "
+pub fn main(rows: [[u8; 3]; 2]) -> [u8; 2] {
+    let offset = 1u8;
+    let mut sums = [0u8; 2];
+    let mut i = 0usize;
+    for row in rows {
+        let mut acc = 0u8;
+        for e in row {
+            let offset = offset + 1u8;
+            acc = acc + e + offset - 2u8;
+        }
+        sums[i] = acc;
+        i = i + 1usize;
+    }
+    sums
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_literal(
+        compiled
+            .parse_arg(0, "[[1, 2, 3], [4, 5, 6]]")?
+            .as_literal(),
+    )?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(
+        r,
+        Literal::Array(vec![
+            Literal::NumUnsigned(6, UnsignedNumType::U8),
+            Literal::NumUnsigned(15, UnsignedNumType::U8),
+        ])
+    );
+    Ok(())
+}
+
+#[test]
+fn compile_reverse_and_rotate() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: [u8; 4]) -> ([u8; 4], [u8; 4], [u8; 4], [u8; 4], [u8; 4]) {
+    (reverse(x), rotate(x, 1), rotate(x, 6), reverse(reverse(x)), rotate(x, 4))
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(compiled.circuit.and_gates(), 0);
+    let mut eval = compiled.evaluator();
+    eval.set_literal(compiled.parse_arg(0, "[1, 2, 3, 4]")?.as_literal())?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    let array = |elems: [u8; 4]| Literal::Array(elems.iter().map(|&x| x.into()).collect());
+    assert_eq!(
+        r,
+        Literal::Tuple(vec![
+            array([4, 3, 2, 1]),
+            array([2, 3, 4, 1]),
+            array([3, 4, 1, 2]),
+            array([1, 2, 3, 4]),
+            array([1, 2, 3, 4]),
+        ])
+    );
+    Ok(())
+}
+
+#[test]
+fn compile_map2() -> Result<(), Error> {
+    let prg = "
+pub fn main(a: [u8; 4], b: [u8; 4]) -> [u8; 4] {
+    map2(a, b, xor)
+}
+
+fn xor(x: u8, y: u8) -> u8 {
+    x ^ y
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let samples: [([u8; 4], [u8; 4]); 3] = [
+        ([0, 0, 0, 0], [0, 0, 0, 0]),
+        ([1, 2, 3, 4], [4, 3, 2, 1]),
+        ([255, 170, 15, 0], [255, 85, 240, 255]),
+    ];
+    for (a, b) in samples {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::Array(a.iter().map(|&x| x.into()).collect()))?;
+        eval.set_literal(Literal::Array(b.iter().map(|&x| x.into()).collect()))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected = a.iter().zip(b.iter()).map(|(&x, &y)| (x ^ y).into());
+        assert_eq!(r, Literal::Array(expected.collect()));
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_filter_mask() -> Result<(), Error> {
+    let prg = "
+pub fn main(values: [u8; 5]) -> [(u8, bool); 5] {
+    filter_mask(values, is_even)
+}
+
+fn is_even(x: u8) -> bool {
+    x % 2 == 0
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for values in [[0u8, 1, 2, 3, 4], [255, 254, 7, 100, 33]] {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::Array(values.iter().map(|&x| x.into()).collect()))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected = values
+            .iter()
+            .map(|&x| Literal::Tuple(vec![x.into(), (x % 2 == 0).into()]));
+        assert_eq!(r, Literal::Array(expected.collect()));
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_bool_array_bitmasks() -> Result<(), Error> {
+    let prg = "
+pub fn main(a: [bool; 3], b: [bool; 3]) -> ([bool; 3], bool, bool, bool, bool) {
+    (
+        (a & b) ^ !(a | b),
+        all([true, true, false]) == false,
+        any([false, false, true]) == true,
+        any(a & b),
+        all(a | b),
+    )
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_literal(compiled.parse_arg(0, "[true, true, false]")?.as_literal())?;
+    eval.set_literal(compiled.parse_arg(1, "[true, false, false]")?.as_literal())?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(
+        r,
+        Literal::Tuple(vec![
+            Literal::Array(vec![Literal::True, Literal::False, Literal::True]),
+            Literal::True,
+            Literal::True,
+            Literal::True,
+            Literal::False,
+        ])
+    );
+    Ok(())
+}
+
+#[test]
+fn compile_negative_range_patterns() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: i8) -> u8 {
+    match x {
+        -10..=-1 => 1,
+        0..=10 => 2,
+        _ => 3,
+    }
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for (x, expected) in [
+        (-128, 3),
+        (-11, 3),
+        (-10, 1),
+        (-5, 1),
+        (-1, 1),
+        (0, 2),
+        (10, 2),
+        (11, 3),
+        (127, 3),
+    ] {
+        let mut eval = compiled.evaluator();
+        eval.set_i8(x);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        assert_eq!(
+            u8::try_from(output).map_err(|e| pretty_print(e, prg))?,
+            expected
+        );
+    }
+
+    let exhaustive = "
+pub fn main(x: i8) -> u8 {
+    match x {
+        -128..-10 => 1,
+        -10..=5 => 2,
+        6..=127 => 3,
+    }
+}
+";
+    let compiled = compile(exhaustive).map_err(|e| pretty_print(e, exhaustive))?;
+    for x in -128..=127 {
+        let mut eval = compiled.evaluator();
+        eval.set_i8(x);
+        let output = eval.run().map_err(|e| pretty_print(e, exhaustive))?;
+        let expected = if x < -10 {
+            1
+        } else if x <= 5 {
+            2
+        } else {
+            3
+        };
+        assert_eq!(
+            u8::try_from(output).map_err(|e| pretty_print(e, exhaustive))?,
+            expected
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn cast_literals_like_compiled_casts() -> Result<(), Error> {
+    let x = Literal::NumUnsigned(0x1234, UnsignedNumType::U16);
+    assert_eq!(
+        x.cast(&Type::Unsigned(UnsignedNumType::U8)),
+        Ok(Literal::NumUnsigned(0x34, UnsignedNumType::U8))
+    );
+    assert_eq!(
+        x.cast(&Type::Unsigned(UnsignedNumType::U32)),
+        Ok(Literal::NumUnsigned(0x1234, UnsignedNumType::U32))
+    );
+    let y = Literal::NumUnsigned(0xFFFF, UnsignedNumType::U16);
+    assert_eq!(
+        y.cast(&Type::Unsigned(UnsignedNumType::U32)),
+        Ok(Literal::NumUnsigned(0xFFFF, UnsignedNumType::U32))
+    );
+    assert_eq!(
+        y.cast(&Type::Signed(SignedNumType::I16)),
+        Ok(Literal::NumSigned(-1, SignedNumType::I16))
+    );
+    assert_eq!(
+        Literal::NumSigned(-2, SignedNumType::I8).cast(&Type::Signed(SignedNumType::I64)),
+        Ok(Literal::NumSigned(-2, SignedNumType::I64))
+    );
+    assert!(Literal::Tuple(vec![]).cast(&Type::Bool).is_err());
+
+    let prg = "
+pub fn main(x: u16, y: i8) -> (u8, u32, i16, u64, bool) {
+    (x as u8, x as u32, x as i16, y as u64, x as bool)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for (x, y) in [(0x1234u16, -2i8), (0xFFFF, 127), (0x80, -128)] {
+        let mut eval = compiled.evaluator();
+        eval.set_u16(x);
+        eval.set_i8(y);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let (x, y) = (Literal::from(x), Literal::from(y));
+        let expected = Literal::Tuple(vec![
+            x.cast(&Type::Unsigned(UnsignedNumType::U8)).unwrap(),
+            x.cast(&Type::Unsigned(UnsignedNumType::U32)).unwrap(),
+            x.cast(&Type::Signed(SignedNumType::I16)).unwrap(),
+            y.cast(&Type::Unsigned(UnsignedNumType::U64)).unwrap(),
+            x.cast(&Type::Bool).unwrap(),
+        ]);
+        assert_eq!(r, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_mean() -> Result<(), Error> {
+    let prg = "
+pub fn main(_x: u8) -> (bool, bool, bool) {
+    (
+        mean([2u8, 4u8, 6u8]) == 4u8,
+        mean([1u8, 2u8]) == 1u8,
+        mean([-1i8, -2i8]) == -1i8,
+    )
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_u8(0);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(
+        r,
+        Literal::Tuple(vec![Literal::True, Literal::True, Literal::True])
+    );
+
+    let prg = "
+pub fn main(a: [u8; 3], b: [i8; 5]) -> (u8, i8) {
+    (mean(a), mean(b))
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let samples: [([u8; 3], [i8; 5]); 4] = [
+        ([255, 255, 255], [-128, -128, -128, -128, -127]),
+        ([255, 255, 254], [127, 127, 127, 127, 126]),
+        ([0, 1, 1], [-1, -1, 0, 0, 0]),
+        ([10, 20, 31], [-7, 3, 100, -50, 1]),
+    ];
+    for (a, b) in samples {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::Array(a.iter().map(|&x| x.into()).collect()))?;
+        eval.set_literal(Literal::Array(b.iter().map(|&x| x.into()).collect()))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let mean_a = a.iter().map(|&x| x as u32).sum::<u32>() / 3;
+        let mean_b = b.iter().map(|&x| x as i32).sum::<i32>() / 5;
+        assert_eq!(
+            r,
+            Literal::Tuple(vec![(mean_a as u8).into(), (mean_b as i8).into()])
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_reveal_to_single_party() -> Result<(), Error> {
+    let prg = "
+#[reveal(b)]
+pub fn main(a: u8, b: u8) -> bool {
+    a > b
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(compiled.output_parties(), vec![1]);
+    let bits = compiled.circuit.eval(&[
+        Literal::from(5u8).as_bits(&compiled.program, &HashMap::new()),
+        Literal::from(3u8).as_bits(&compiled.program, &HashMap::new()),
+    ]);
+    assert_eq!(compiled.parse_output_for_party(1, &bits)?, Literal::True);
+    assert!(matches!(
+        compiled.parse_output_for_party(0, &bits),
+        Err(EvalError::OutputNotRevealed(0))
+    ));
+
+    let prg = "
+pub fn main(a: u8, b: u8) -> bool {
+    a > b
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(compiled.output_parties(), vec![0, 1]);
+
+    let prg = "
+#[reveal(c)]
+pub fn main(a: u8, b: u8) -> bool {
+    a > b
+}
+";
+    let Err(Error::CompileTimeError(CompileTimeError::TypeError(e))) = compile(prg) else {
+        panic!("expected a type error for an unknown reveal party");
+    };
+    assert!(matches!(&e[0].0, TypeErrorEnum::UnknownRevealParty(party) if party == "c"));
+    // the error is reported at the attribute, not at the whole fn:
+    assert_eq!(e[0].1.end, (1, 12));
+
+    let prg = "
+#[reveal(a)]
+fn reveal(a: u8, b: u8) -> bool {
+    a > b
+}
+
+pub fn main(a: u8, b: u8) -> bool {
+    reveal(a, b)
+}
+";
+    let Err(Error::CompileTimeError(CompileTimeError::ParseError(e))) = compile(prg) else {
+        panic!("expected a parse error for a reveal attribute on a non-pub fn");
+    };
+    assert_eq!(e[0].1.end, (1, 12));
+    Ok(())
+}
+
+#[test]
+fn compile_sum_and_product() -> Result<(), Error> {
+    let prg = "
+pub fn main(a: [i8; 5], b: [u16; 3]) -> (i8, u16, bool, bool) {
+    (sum(a), product(b), any([a[0] > 0, a[1] > 0]), all([a[0] > 0, a[1] > 0]))
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let samples: [([i8; 5], [u16; 3]); 4] = [
+        ([0, 0, 0, 0, 0], [0, 0, 0]),
+        ([1, 2, 3, 4, 5], [1, 2, 3]),
+        ([-100, 100, 100, -100, 127], [255, 256, 1]),
+        ([-128, 127, -1, 0, 2], [7, 11, 13]),
+    ];
+    for (a, b) in samples {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::Array(a.iter().map(|&x| x.into()).collect()))?;
+        eval.set_literal(Literal::Array(b.iter().map(|&x| x.into()).collect()))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let sum: i8 = a.iter().sum();
+        let product: u16 = b.iter().product();
+        let expected = Literal::Tuple(vec![
+            sum.into(),
+            product.into(),
+            (a[0] > 0 || a[1] > 0).into(),
+            (a[0] > 0 && a[1] > 0).into(),
+        ]);
+        assert_eq!(r, expected);
+    }
+    for (a, b) in [
+        ([100i8, 100, 0, 0, 0], [1u16, 1, 1]),
+        ([0; 5], [256, 256, 1]),
+    ] {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::Array(a.iter().map(|&x| x.into()).collect()))?;
+        eval.set_literal(Literal::Array(b.iter().map(|&x| x.into()).collect()))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        assert!(output.into_literal().is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_dot() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8) -> u8 {
+    dot([1u8, 2, 3], [4u8, 5, 6]) + x
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_u8(0);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(u8::try_from(output).map_err(|e| pretty_print(e, prg))?, 32);
+
+    let prg = "
+pub fn main(a: [i8; 3], b: [i8; 3]) -> i8 {
+    dot(a, b)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let samples: [([i8; 3], [i8; 3]); 5] = [
+        ([1, 2, 3], [4, 5, 6]),
+        ([-1, 2, -3], [4, -5, 6]),
+        ([-128, 0, 0], [1, 0, 0]),
+        ([-128, 127, 0], [1, 1, 0]),
+        ([100, -100, 27], [100, 100, 1]),
+    ];
+    for (a, b) in samples {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::Array(a.iter().map(|&x| x.into()).collect()))?;
+        eval.set_literal(Literal::Array(b.iter().map(|&x| x.into()).collect()))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected: i32 = a
+            .iter()
+            .zip(b.iter())
+            .map(|(&x, &y)| x as i32 * y as i32)
+            .sum();
+        assert_eq!(r, Literal::from(expected as i8));
+    }
+    for (a, b) in [([100i8, 100, 0], [1i8, 1, 0]), ([-128, 0, 0], [-1, 0, 0])] {
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::Array(a.iter().map(|&x| x.into()).collect()))?;
+        eval.set_literal(Literal::Array(b.iter().map(|&x| x.into()).collect()))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        assert!(output.into_literal().is_err());
+    }
+
+    let prg = "
+pub fn main(a: [u8; 3], b: [u16; 3]) -> u8 {
+    dot(a, b)
+}
+";
+    assert!(compile(prg).is_err());
+    let prg = "
+pub fn main(a: [u8; 3], b: [u8; 4]) -> u8 {
+    dot(a, b)
+}
+";
+    assert!(compile(prg).is_err());
+    Ok(())
+}
+
+#[test]
+fn compile_three_parties() -> Result<(), Error> {
+    let prg = "
+pub fn main(a: u8, b: u8, c: u8) -> u8 {
+    a + b + c
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(compiled.circuit.input_gates, vec![8, 8, 8]);
+    let mut eval = compiled.evaluator();
+    eval.set_u8(1);
+    eval.set_u8(20);
+    eval.set_u8(100);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(u8::try_from(output).map_err(|e| pretty_print(e, prg))?, 121);
+
+    let mut eval = compiled.evaluator();
+    eval.set_u8(1);
+    eval.set_u8(20);
+    eval.set_bool(true);
+    assert!(matches!(
+        eval.run(),
+        Err(EvalError::UnexpectedNumberOfInputsFromParty(2))
+    ));
+    Ok(())
+}
+
+#[test]
+fn fold_constants_before_compiling() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8) -> (u8, i16, bool, u8) {
+    let a = (2u8 + 3u8) * 4u8 + (1u8 << 4u8) - 7u8 / 2u8;
+    let b = -(300i16 as i8 as i16) % 7i16;
+    let c = !(31u8 > 30u8) ^ (true & !false);
+    let d = if 3u8 > 2u8 { !a } else { x };
+    (a, b, c, d)
+}
+";
+    let checked = check(prg).map_err(|e| pretty_print(e, prg))?;
+    let folded = checked.clone().fold_constants();
+    let main = folded.fn_defs.get("main").unwrap();
+    for stmt in main.body.iter().take(3) {
+        let StmtEnum::Let(_, expr) = &stmt.inner else {
+            panic!("expected a let statement, found {stmt:?}");
+        };
+        assert!(
+            matches!(
+                expr.inner,
+                ExprEnum::NumUnsigned(_, _)
+                    | ExprEnum::NumSigned(_, _)
+                    | ExprEnum::True
+                    | ExprEnum::False
+            ),
+            "{expr:?}"
+        );
+    }
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(compiled.circuit.and_gates(), 0);
+    let mut eval = compiled.evaluator();
+    eval.set_u8(0);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    let a = (2u8 + 3) * 4 + (1 << 4) - 7 / 2;
+    let b = -(300i16 as i8 as i16) % 7;
+    let c = true;
+    assert_eq!(
+        r,
+        Literal::Tuple(vec![a.into(), b.into(), c.into(), (!a).into()])
+    );
+
+    let prg = "
+pub fn main(x: u8) -> u8 {
+    x + 200u8 + 100u8 * 1u8 + 1u8 / 0u8
+}
+";
+    let folded = check(prg)
+        .map_err(|e| pretty_print(e, prg))?
+        .fold_constants();
+    let main = folded.fn_defs.get("main").unwrap();
+    let StmtEnum::Expr(expr) = &main.body[0].inner else {
+        panic!("expected an expr statement");
+    };
+    let ExprEnum::Op(Op::Add, _, div) = &expr.inner else {
+        panic!("expected an addition, found {expr:?}");
+    };
+    assert!(matches!(div.inner, ExprEnum::Op(Op::Div, _, _)));
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_u8(0);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    assert!(output.into_literal().is_err());
+
+    // constants are folded by every compile method, so the dead branch is never compiled:
+    let prg = "
+pub fn main(x: u8) -> u8 {
+    if 1u8 > 2u8 { x / 0u8 } else { x }
+}
+";
+    let checked = check(prg).map_err(|e| pretty_print(e, prg))?;
+    let (_, panic_sites) = checked.compile_with_panic_sites("main", HashMap::new())?;
+    assert_eq!(panic_sites, vec![]);
+    Ok(())
+}
+
+#[test]
+fn compile_string_equality_between_parties() -> Result<(), Error> {
+    let prg = r#"
+pub fn main(a: String<8>, b: String<8>) -> (bool, bool, String<8>) {
+    (a == b, a != "alice", "bob")
+}
+"#;
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for (a, b) in [
+        ("alice", "alice"),
+        ("alice", "alicia"),
+        ("ab", "abc"),
+        ("", ""),
+        ("", "a"),
+        ("12345678", "12345678"),
+    ] {
+        let mut eval = compiled.evaluator();
+        eval.parse_literal(&format!("{a:?}"))?;
+        eval.parse_literal(&format!("{b:?}"))?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected = Literal::Tuple(vec![
+            (a == b).into(),
+            (a != "alice").into(),
+            Literal::String("bob".to_string(), 8),
+        ]);
+        assert_eq!(r, expected, "{a:?} == {b:?}");
+    }
+
+    // bytes after the length of the string are ignored, even if they are not zero:
+    let ty = compiled.main.params[0].ty.clone();
+    let alice = Literal::String("alice".to_string(), 8);
+    let mut bits = alice.as_bits(&compiled.program, &HashMap::new());
+    let len = bits.len();
+    bits[len - 1] = true;
+    let mut eval = compiled.evaluator();
+    eval.set_bits(&bits)?;
+    eval.set_literal(alice.clone())?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(
+        r,
+        Literal::Tuple(vec![
+            true.into(),
+            false.into(),
+            Literal::String("bob".to_string(), 8)
+        ])
+    );
+    assert_eq!(
+        Literal::from_unwrapped_bits(&compiled.program, &ty, &bits, &HashMap::new())
+            .map_err(|e| pretty_print(e, prg))?,
+        alice
+    );
+
+    let mut eval = compiled.evaluator();
+    assert!(eval.parse_literal("\"too long string\"").is_err());
+
+    let prg = r#"
+pub fn main(a: String<4>) -> bool {
+    a == "alice"
+}
+"#;
+    let Err(Error::CompileTimeError(CompileTimeError::TypeError(e))) = compile(prg) else {
+        panic!("expected a type error for a string literal that is too long");
+    };
+    assert!(matches!(
+        e[0].0,
+        TypeErrorEnum::StringTooLong {
+            max_len: 4,
+            actual_len: 5
+        }
+    ));
+    Ok(())
+}
+
+#[test]
+fn compile_fixed_point_add_and_mul() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: fx16_8, y: fx16_8) -> (fx16_8, fx16_8, fx16_8, bool) {
+    (x + y - 0.5fx16_8, x * y, -x * 1.25fx16_8, x < y)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for (x, y) in [(1.5, 2.25), (-3.75, 0.5), (0.0625, -12.0), (-1.0, -1.0)] {
+        let mut eval = compiled.evaluator();
+        eval.set_f64(x)?;
+        eval.set_f64(y)?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let Literal::Tuple(fields) = r else {
+            panic!("expected a tuple, but found {r}");
+        };
+        assert_eq!(fields[0].as_f64(), Some(x + y - 0.5));
+        assert_eq!(fields[1].as_f64(), Some(x * y));
+        assert_eq!(fields[2].as_f64(), Some(-x * 1.25));
+        assert_eq!(fields[3], Literal::from(x < y));
+    }
+
+    // the product is shifted right by the fractional bits, rounding towards negative infinity:
+    let prg = "
+pub fn main(x: fx8_4, y: fx8_4) -> fx8_4 {
+    x * y
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for (x, y, expected) in [
+        (0.25, 0.25, 0.0625),
+        (0.25, 0.1875, 0.0),
+        (-0.25, 0.1875, -0.0625),
+    ] {
+        let mut eval = compiled.evaluator();
+        eval.parse_literal(&x.to_string())?;
+        eval.parse_literal(&y.to_string())?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        assert_eq!(f64::try_from(output)?, expected, "{x} * {y}");
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_fixed_point_casts() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: fx16_8) -> (fx8_4, fx32_16, i16, fx16_8) {
+    (x as fx8_4, x as fx32_16, x as i16, 3i16 as fx16_8)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.parse_literal("-2.515625fx16_8")?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(
+        r,
+        Literal::Tuple(vec![
+            Literal::NumFixed(-41, 8, 4),
+            Literal::NumFixed(-164864, 32, 16),
+            Literal::NumSigned(-3, SignedNumType::I16),
+            Literal::NumFixed(3 << 8, 16, 8),
+        ])
+    );
+    assert_eq!(
+        r.to_string(),
+        "(-2.5625fx8_4, -2.515625fx32_16, -3, 3.0fx16_8)"
+    );
+    Ok(())
+}
+
+#[test]
+fn reject_mixed_fixed_point_formats() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: fx16_8, y: fx16_4) -> fx16_8 {
+    x + y
+}
+";
+    let Err(Error::CompileTimeError(CompileTimeError::TypeError(e))) = compile(prg) else {
+        panic!("expected a type error for mixed fixed-point formats");
+    };
+    assert!(matches!(
+        e[0].0,
+        TypeErrorEnum::TypeMismatch(Type::Fixed(16, 8), Type::Fixed(16, 4))
+    ));
+
+    let prg = "
+pub fn main(x: fx16_8, y: fx16_4) -> fx16_8 {
+    x + (y as fx16_8)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_f64(1.5)?;
+    eval.set_f64(-0.25)?;
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(f64::try_from(output)?, 1.25);
+    Ok(())
+}
+
+#[test]
+fn compile_prelude_option() -> Result<(), Error> {
+    let prg = "
+fn checked_div(x: u32, y: u32) -> OptionU32 {
+    if y == 0u32 {
+        OptionU32::None
+    } else {
+        OptionU32::Some(x / y)
+    }
+}
+
+pub fn main(x: u32, y: u32) -> (u32, OptionU32) {
+    let quotient = checked_div(x, y);
+    let value = match quotient {
+        OptionU32::Some(q) => q,
+        OptionU32::None => 0u32,
+    };
+    (value, quotient)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for (x, y) in [(10u32, 3u32), (7, 0)] {
+        let mut eval = compiled.evaluator();
+        eval.set_u32(x);
+        eval.set_u32(y);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected = match x.checked_div(y) {
+            Some(q) => format!("({q}, OptionU32::Some({q}))"),
+            None => "(0, OptionU32::None)".to_string(),
+        };
+        assert_eq!(r.to_string(), expected);
+    }
+
+    // a program can declare its own enum with the name of a prelude enum:
+    let prg = "
+enum OptionU8 {
+    Nothing,
+    Just(u8),
+}
+
+pub fn main(x: u8) -> OptionU8 {
+    OptionU8::Just(x)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let mut eval = compiled.evaluator();
+    eval.set_u8(5);
+    let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+    let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+    assert_eq!(r.to_string(), "OptionU8::Just(5)");
+    Ok(())
+}
+
+#[test]
+fn compile_enum_cast_to_discriminant() -> Result<(), Error> {
+    let prg = "
+enum Color {
+    Red,
+    Green,
+    Blue(u8),
+}
+
+pub fn main(c: Color) -> (u8, i32, u8) {
+    (c as u8, c as i32, Color::Green as u8)
+}
+";
+    let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    for (color, discriminant) in [
+        ("Color::Red", 0),
+        ("Color::Green", 1),
+        ("Color::Blue(7)", 2),
+    ] {
+        let mut eval = compiled.evaluator();
+        eval.parse_literal(color)?;
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        let r = output.into_literal().map_err(|e| pretty_print(e, prg))?;
+        let expected = Literal::Tuple(vec![
+            Literal::NumUnsigned(discriminant, UnsignedNumType::U8),
+            Literal::NumSigned(discriminant as i64, SignedNumType::I32),
+            Literal::NumUnsigned(1, UnsignedNumType::U8),
+        ]);
+        assert_eq!(r, expected, "{color}");
+    }
+
+    let variants: Vec<String> = (0..300).map(|i| format!("V{i}")).collect();
+    let prg = format!(
+        "
+enum Big {{
+    {}
+}}
+
+pub fn main(b: Big) -> u8 {{
+    b as u8
+}}
+",
+        variants.join(", ")
+    );
+    let Err(Error::CompileTimeError(CompileTimeError::TypeError(e))) = compile(&prg) else {
+        panic!("expected a type error for an enum that does not fit into a u8");
+    };
+    assert!(matches!(
+        &e[0].0,
+        TypeErrorEnum::EnumCastOutOfRange { variants: 300, .. }
+    ));
+
+    let prg = "
+enum Color {
+    Red,
+    Green,
+}
+
+pub fn main(x: u8) -> Color {
+    x as Color
+}
+";
+    assert!(compile(prg).is_err());
+    Ok(())
+}
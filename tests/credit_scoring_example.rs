@@ -13,7 +13,7 @@ fn credit_scoring_multiple_compilations() -> Result<(), Error> {
     let mut circuit: Option<Circuit> = None;
 
     for _ in 0..4 {
-        let (compute_score_circuit, _) = typed_prg.compile("compute_score")?;
+        let (compute_score_circuit, _) = typed_prg.compile("main")?;
         println!(
             ">> 'compute_score' has {}",
             compute_score_circuit.report_gates()
@@ -127,7 +127,7 @@ fn credit_scoring_single_run() -> Result<(), Error> {
         }",
     )?;
 
-    let (compute_score_circuit, compute_score_fn) = typed_prg.compile("compute_score")?;
+    let (compute_score_circuit, compute_score_fn) = typed_prg.compile("main")?;
     let const_sizes = HashMap::new();
     let mut eval = Evaluator::new(
         &typed_prg,
@@ -2,7 +2,9 @@ use garble_lang::{
     circuit::{EvalPanic, PanicReason},
     compile,
     eval::{EvalError, EvalOutput},
-    Error,
+    literal::Literal,
+    token::MetaInfo,
+    Error, GarbleProgram,
 };
 
 #[test]
@@ -47,6 +49,74 @@ pub fn main(x: i8) -> i8 {
     Ok(())
 }
 
+#[test]
+fn panic_on_mul_with_overflow() -> Result<(), String> {
+    let prg = "
+pub fn main(x: u8, y: u8) -> u8 {
+    x * y
+}";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    for (x, y) in [(200, 2), (16, 16), (128, 2), (255, 255)] {
+        let mut computation = compiled.evaluator();
+        computation.set_u8(x);
+        computation.set_u8(y);
+        expect_panic(computation.run(), PanicReason::Overflow);
+    }
+    for (x, y) in [(127, 2), (255, 1), (15, 17), (0, 255)] {
+        let mut computation = compiled.evaluator();
+        computation.set_u8(x);
+        computation.set_u8(y);
+        let output = computation.run().map_err(|e| e.prettify(prg))?;
+        assert_eq!(u8::try_from(output).ok(), Some(x * y), "{x} * {y}");
+    }
+
+    let prg = "
+pub fn main(x: i8, y: i8) -> i8 {
+    x * y
+}";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    for (x, y) in [(-128, -1), (64, 2), (-64, -2), (-128, 2), (127, 127)] {
+        let mut computation = compiled.evaluator();
+        computation.set_i8(x);
+        computation.set_i8(y);
+        expect_panic(computation.run(), PanicReason::Overflow);
+    }
+    for (x, y) in [(-64, 2), (-128, 1), (127, -1), (-1, -127), (0, -128)] {
+        let mut computation = compiled.evaluator();
+        computation.set_i8(x);
+        computation.set_i8(y);
+        let output = computation.run().map_err(|e| e.prettify(prg))?;
+        assert_eq!(i8::try_from(output).ok(), Some(x * y), "{x} * {y}");
+    }
+    Ok(())
+}
+
+#[test]
+fn wrapping_arithmetic_never_panics() -> Result<(), String> {
+    let prg = "
+pub fn main(x: u8) -> (u8, u8, u8) {
+    (wrapping_add(x, 1u8), wrapping_sub(0u8, x), wrapping_mul(x, 2u8))
+}";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    assert_eq!(compiled.panic_sites(), vec![]);
+    let mut computation = compiled.evaluator();
+    computation.set_u8(255);
+    let output = computation.run().map_err(|e| e.prettify(prg))?;
+    let r = output.into_literal().map_err(|e| e.prettify(prg))?;
+    assert_eq!(r.to_string(), "(0, 1, 254)");
+
+    let prg = "
+pub fn main(x: u8) -> u8 {
+    x + 1u8
+}";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    assert_eq!(compiled.panic_sites().len(), 1);
+    let mut computation = compiled.evaluator();
+    computation.set_u8(255);
+    expect_panic(computation.run(), PanicReason::Overflow);
+    Ok(())
+}
+
 #[test]
 fn panic_on_div_by_zero() -> Result<(), String> {
     let prg = "
@@ -61,6 +131,48 @@ pub fn main(x: u8) -> u8 {
     Ok(())
 }
 
+#[test]
+fn panic_on_div_with_overflow() -> Result<(), String> {
+    for op in ["x / y", "x % y", "divmod(x, y).0"] {
+        let prg = format!(
+            "
+pub fn main(x: i8, y: i8) -> i8 {{
+    {op}
+}}"
+        );
+        let compiled = compile(&prg).map_err(|e| e.prettify(&prg))?;
+        let mut computation = compiled.evaluator();
+        computation.set_i8(i8::MIN);
+        computation.set_i8(-1);
+        expect_panic(computation.run(), PanicReason::Overflow);
+        let mut computation = compiled.evaluator();
+        computation.set_i8(i8::MIN);
+        computation.set_i8(0);
+        expect_panic(computation.run(), PanicReason::DivByZero);
+        for (x, y) in [(i8::MIN, 1), (i8::MIN + 1, -1), (i8::MIN, -2), (-1, -1)] {
+            let mut computation = compiled.evaluator();
+            computation.set_i8(x);
+            computation.set_i8(y);
+            assert!(computation.run().is_ok(), "{x}, {y}: {op}");
+        }
+    }
+
+    let prg = "
+pub fn main(x: u8, y: u8) -> (u8, u8) {
+    (x / y, x % y)
+}";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    let mut computation = compiled.evaluator();
+    computation.set_u8(5);
+    computation.set_u8(0);
+    expect_panic(computation.run(), PanicReason::DivByZero);
+    let mut computation = compiled.evaluator();
+    computation.set_u8(128);
+    computation.set_u8(255);
+    assert!(computation.run().is_ok());
+    Ok(())
+}
+
 #[test]
 fn panic_in_branch_of_if_expr() -> Result<(), String> {
     let prg = "
@@ -328,6 +440,120 @@ pub fn main(b: bool) -> bool {
     Ok(())
 }
 
+#[test]
+fn no_panic_in_skipped_right_operand() -> Result<(), Error> {
+    for (prg, is_zero_result) in [
+        (
+            "
+pub fn main(x: u8) -> bool {
+    x != 0u8 && 10u8 / x > 1u8
+}
+",
+            false,
+        ),
+        (
+            "
+pub fn main(x: u8) -> bool {
+    x == 0u8 || 10u8 / x > 1u8
+}
+",
+            true,
+        ),
+    ] {
+        let compiled = compile(prg).map_err(|e| pretty_print(e, prg))?;
+        let mut eval = compiled.evaluator();
+        eval.set_u8(0);
+        let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+        assert_eq!(bool::try_from(output).ok(), Some(is_zero_result));
+        for (x, expected) in [(5, true), (6, false)] {
+            let mut eval = compiled.evaluator();
+            eval.set_u8(x);
+            let output = eval.run().map_err(|e| pretty_print(e, prg))?;
+            assert_eq!(bool::try_from(output).ok(), Some(expected));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn panic_on_prefix_sum_with_overflow() -> Result<(), String> {
+    let prg = "
+pub fn main(x: u8) -> [u8; 3] {
+    prefix_sum([x, 100u8, 1u8])
+}";
+    let prg = compile(prg).map_err(|e| e.prettify(prg))?;
+    let mut computation = prg.evaluator();
+    computation.set_u8(155);
+    let res = computation.run();
+    expect_panic(res, PanicReason::Overflow);
+    Ok(())
+}
+
+#[test]
+fn remove_panic_tracking_from_div() -> Result<(), Error> {
+    let prg = "
+pub fn main(x: u8, y: u8) -> u8 {
+    x / y
+}";
+    let with_panics = compile(prg).map_err(|e| pretty_print(e, prg))?;
+    let without_panics = GarbleProgram {
+        circuit: with_panics.circuit.clone().remove_panic_tracking(),
+        ..with_panics.clone()
+    };
+    assert!(without_panics.circuit.validate().is_ok());
+    assert!(without_panics.circuit.gates.len() < with_panics.circuit.gates.len());
+    for x in [0, 7, 255] {
+        for y in [0, 1, 3] {
+            let mut eval = without_panics.evaluator();
+            eval.set_u8(x);
+            eval.set_u8(y);
+            let output = u8::try_from(eval.run()?)?;
+            if let Some(expected) = x.checked_div(y) {
+                assert_eq!(output, expected);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn panic_in_repeated_call_after_discarded_branch() -> Result<(), String> {
+    let prg = "
+pub fn main(x: u8, b: bool) -> u8 {
+    let y = if b { inc(x) } else { 0u8 };
+    y + inc(x)
+}
+
+fn inc(x: u8) -> u8 {
+    x + 1u8
+}";
+    let prg = compile(prg).map_err(|e| e.prettify(prg))?;
+    for b in [true, false] {
+        let mut computation = prg.evaluator();
+        computation.set_u8(255);
+        computation.set_bool(b);
+        let res = computation.run();
+        expect_panic(res, PanicReason::Overflow);
+    }
+    Ok(())
+}
+
+#[test]
+fn panic_on_fixed_point_mul_with_overflow() -> Result<(), String> {
+    let prg = "
+pub fn main(x: fx8_4, y: fx8_4) -> fx8_4 {
+    x * y
+}";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    for (x, y) in [(4.0, 2.0), (-4.0, 2.5), (7.9375, 7.9375)] {
+        let mut computation = compiled.evaluator();
+        computation.set_f64(x).map_err(|e| e.to_string())?;
+        computation.set_f64(y).map_err(|e| e.to_string())?;
+        expect_panic(computation.run(), PanicReason::Overflow);
+    }
+    Ok(())
+}
+
 fn expect_panic(eval_result: Result<EvalOutput, EvalError>, expected: PanicReason) {
     assert!(eval_result.is_ok());
     let eval_output = Vec::<bool>::try_from(eval_result.unwrap());
@@ -344,3 +570,258 @@ fn pretty_print<E: Into<Error>>(e: E, prg: &str) -> Error {
     println!("{pretty}");
     e
 }
+
+#[test]
+fn list_panic_sites() -> Result<(), String> {
+    let prg = "
+pub fn main(a: [u8; 4], i: usize, x: u8) -> u8 {
+    let y = a[i];
+    (y / x) ^ (y / 2u8)
+}";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    let out_of_bounds = MetaInfo {
+        start: (2, 12),
+        end: (2, 16),
+    };
+    let div_by_zero = MetaInfo {
+        start: (3, 5),
+        end: (3, 10),
+    };
+    assert_eq!(
+        compiled.panic_sites(),
+        vec![
+            (PanicReason::OutOfBounds, out_of_bounds),
+            (PanicReason::DivByZero, div_by_zero)
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn report_first_of_simultaneous_panics() -> Result<(), String> {
+    for (prg, expected) in [
+        (
+            "
+pub fn main(arr: [u8; 2], i: usize, x: u8) -> (u8, u8) {
+    (arr[i], 10u8 / x)
+}",
+            PanicReason::OutOfBounds,
+        ),
+        (
+            "
+pub fn main(arr: [u8; 2], i: usize, x: u8) -> (u8, u8) {
+    (10u8 / x, arr[i])
+}",
+            PanicReason::DivByZero,
+        ),
+    ] {
+        let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+        assert_eq!(compiled.panic_sites().len(), 2);
+        let (_, first_site) = compiled.panic_sites()[0].clone();
+        let mut eval = compiled.evaluator();
+        eval.set_literal(Literal::Array(vec![1u8.into(), 2u8.into()]))
+            .map_err(|e| e.prettify(prg))?;
+        eval.set_usize(5);
+        eval.set_u8(0);
+        let output = eval.run().map_err(|e| e.prettify(prg))?;
+        match Vec::<bool>::try_from(output) {
+            Err(EvalError::Panic(EvalPanic {
+                reason,
+                panicked_at,
+                ..
+            })) => {
+                assert_eq!(reason, expected);
+                assert_eq!(panicked_at, first_site);
+            }
+            r => panic!("Expected a panic, but found {r:?}"),
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn reject_invalid_panic_reason() -> Result<(), String> {
+    let prg = "
+pub fn main(x: u8) -> u8 {
+    x
+}";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    let mut bits = vec![true];
+    for i in (0..32).rev() {
+        bits.push((99 >> i) & 1 == 1);
+    }
+    bits.extend(vec![false; 5 * 32 + 8]);
+    let result = Literal::from_result_bits(
+        &compiled.program,
+        &compiled.main.ty,
+        &bits,
+        &compiled.const_sizes,
+    );
+    match result {
+        Err(EvalError::InvalidPanicReason(99)) => Ok(()),
+        r => Err(format!("Expected an invalid panic reason, but found {r:?}")),
+    }
+}
+
+#[test]
+fn report_panics_at_source_spans_of_sugar() -> Result<(), String> {
+    let prg = "
+pub fn main(xs: [u8; 3]) -> u8 {
+    let mut acc = 0u8;
+    for x in xs {
+        acc += x;
+    }
+    for i in 0u8..3u8 {
+        acc = acc / i;
+    }
+    acc
+}";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    let overflow = MetaInfo {
+        start: (4, 8),
+        end: (4, 16),
+    };
+    let div_by_zero = MetaInfo {
+        start: (7, 14),
+        end: (7, 21),
+    };
+    assert_eq!(
+        compiled.panic_sites(),
+        vec![
+            (PanicReason::Overflow, overflow),
+            (PanicReason::DivByZero, div_by_zero)
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn panic_on_failed_assertion() -> Result<(), String> {
+    let prg = "
+pub fn main(x: u8, y: u8) -> u8 {
+    assert(x < 100u8);
+    assert_eq(x + 1u8, y);
+    y
+}";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    let assert_lt = MetaInfo {
+        start: (2, 4),
+        end: (2, 21),
+    };
+    let assert_eq = MetaInfo {
+        start: (3, 4),
+        end: (3, 25),
+    };
+    let overflow = MetaInfo {
+        start: (3, 14),
+        end: (3, 21),
+    };
+    assert_eq!(
+        compiled.panic_sites(),
+        vec![
+            (PanicReason::AssertionFailed, assert_lt),
+            (PanicReason::Overflow, overflow),
+            (PanicReason::AssertionFailed, assert_eq),
+        ]
+    );
+    for (x, y, expected) in [
+        (5, 6, None),
+        (5, 7, Some(assert_eq)),
+        (200, 201, Some(assert_lt)),
+    ] {
+        let mut computation = compiled.evaluator();
+        computation.set_u8(x);
+        computation.set_u8(y);
+        let output = computation.run().map_err(|e| e.prettify(prg))?;
+        match (u8::try_from(output), expected) {
+            (Ok(r), None) => assert_eq!(r, y),
+            (Err(EvalError::Panic(panic)), Some(panicked_at)) => {
+                assert_eq!(panic.reason, PanicReason::AssertionFailed);
+                assert_eq!(panic.panicked_at, panicked_at);
+            }
+            (r, _) => panic!("Unexpected result for x = {x}, y = {y}: {r:?}"),
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn report_code_of_failed_assertion() -> Result<(), String> {
+    let prg = "
+pub fn main(x: u8, y: u8) -> u8 {
+    assert(x < 100u8, 1);
+    assert_eq(x, y, 2);
+    assert(y != 50u8);
+    y
+}";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    for (x, y, expected_code) in [
+        (200, 200, Some(1)),
+        (5, 6, Some(2)),
+        (50, 50, Some(0)),
+        (5, 5, None),
+    ] {
+        let mut computation = compiled.evaluator();
+        computation.set_u8(x);
+        computation.set_u8(y);
+        let output = computation.run().map_err(|e| e.prettify(prg))?;
+        match (u8::try_from(output), expected_code) {
+            (Ok(r), None) => assert_eq!(r, y),
+            (Err(EvalError::Panic(panic)), Some(code)) => {
+                assert_eq!(panic.reason, PanicReason::AssertionFailed);
+                assert_eq!(panic.code, code);
+            }
+            (r, _) => panic!("Unexpected result for x = {x}, y = {y}: {r:?}"),
+        }
+    }
+
+    let prg = "
+pub fn main(x: u8, code: usize) -> u8 {
+    assert(x < 100u8, code);
+    x
+}";
+    assert!(compile(prg).is_err());
+    Ok(())
+}
+
+#[test]
+fn panic_on_unsigned_and_signed_sub_at_boundaries() -> Result<(), String> {
+    let prg = "
+pub fn main(x: u8, y: i8) -> (u8, i8) {
+    (x - 1u8, y - 1i8)
+}";
+    let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+    for (x, y, expected_col) in [(0, 0, Some(5)), (1, -128, Some(14)), (1, -127, None)] {
+        let mut computation = compiled.evaluator();
+        computation.set_u8(x);
+        computation.set_i8(y);
+        let output = computation.run().map_err(|e| e.prettify(prg))?;
+        match (output.into_literal(), expected_col) {
+            (Ok(r), None) => assert_eq!(r, Literal::Tuple(vec![0u8.into(), (-128i8).into()])),
+            (Err(EvalError::Panic(panic)), Some(col)) => {
+                assert_eq!(panic.reason, PanicReason::Overflow);
+                assert_eq!(panic.panicked_at.start, (2, col));
+            }
+            (r, _) => panic!("Unexpected result for x = {x}, y = {y}: {r:?}"),
+        }
+    }
+
+    for prg in [
+        "
+pub fn main(_x: u8) -> u8 {
+    0u8 - 1u8
+}",
+        "
+pub fn main(_x: i8) -> i8 {
+    -128i8 - 1i8
+}",
+    ] {
+        let compiled = compile(prg).map_err(|e| e.prettify(prg))?;
+        let mut computation = compiled.evaluator();
+        computation
+            .parse_literal("0")
+            .map_err(|e| e.prettify(prg))?;
+        expect_panic(computation.run(), PanicReason::Overflow);
+    }
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use garble_lang::{
     ast::{
         Op::{self, *},
@@ -5,7 +7,10 @@ use garble_lang::{
     },
     compile,
     eval::EvalError,
-    literal::Literal::{self, NumSigned, NumUnsigned},
+    literal::{
+        Literal::{self, NumSigned, NumUnsigned},
+        VariantLiteral,
+    },
     token::{
         SignedNumType::{self, *},
         UnsignedNumType::{self, *},
@@ -31,6 +36,9 @@ impl Arbitrary for OperatorTestCase {
             Mul,
             Div,
             Mod,
+            SaturatingAdd,
+            SaturatingSub,
+            SaturatingMul,
             BitAnd,
             BitXor,
             BitOr,
@@ -40,6 +48,8 @@ impl Arbitrary for OperatorTestCase {
             NotEq,
             ShiftLeft,
             ShiftRight,
+            RotateRight,
+            RotateLeft,
         ];
         let op = g.choose(&ops).unwrap();
         let num_tys = [
@@ -53,7 +63,8 @@ impl Arbitrary for OperatorTestCase {
             Type::Signed(I64),
         ];
         let (x, ty_x, y, ty_y, result, ty_result, op) = match op {
-            Add | Sub | Mul | Div | Mod | BitAnd | BitXor | BitOr => {
+            Add | Sub | Mul | Div | Mod | SaturatingAdd | SaturatingSub | SaturatingMul
+            | BitAnd | BitXor | BitOr => {
                 let ty = g.choose(&num_tys).unwrap();
                 let x = arbitrary_literal_of_ty(g, ty);
                 let y = arbitrary_literal_of_ty(g, ty);
@@ -76,6 +87,7 @@ impl Arbitrary for OperatorTestCase {
                 let result = match x {
                     NumUnsigned(x, unsigned_ty) => match unsigned_ty {
                         Usize => unreachable!("usize types must not be tested"),
+                        U256 => unreachable!("u256 is not supported"),
                         U8 => (x as u8).checked_shl(y_u8 as u32).map(|z| z.into()),
                         U16 => (x as u16).checked_shl(y_u8 as u32).map(|z| z.into()),
                         U32 => (x as u32).checked_shl(y_u8 as u32).map(|z| z.into()),
@@ -102,6 +114,7 @@ impl Arbitrary for OperatorTestCase {
                 let result = match x {
                     NumUnsigned(x, unsigned_ty) => match unsigned_ty {
                         Usize => unreachable!("usize types must not be tested"),
+                        U256 => unreachable!("u256 is not supported"),
                         U8 => (x as u8).checked_shr(y_u8 as u32).map(|z| z.into()),
                         U16 => (x as u16).checked_shr(y_u8 as u32).map(|z| z.into()),
                         U32 => (x as u32).checked_shr(y_u8 as u32).map(|z| z.into()),
@@ -119,6 +132,60 @@ impl Arbitrary for OperatorTestCase {
                 };
                 (x, ty.clone(), y, ty_u8, result, ty.clone(), op)
             }
+            RotateRight => {
+                let ty = g.choose(&num_tys).unwrap();
+                let ty_u8 = Type::Unsigned(U8);
+                let x = arbitrary_literal_of_ty(g, ty);
+                let y_u8 = u8::arbitrary(g);
+                let y = NumUnsigned(y_u8 as u64, U8);
+                let result = match x {
+                    NumUnsigned(x, unsigned_ty) => match unsigned_ty {
+                        Usize => unreachable!("usize types must not be tested"),
+                        U256 => unreachable!("u256 is not supported"),
+                        U8 => Some((x as u8).rotate_right(y_u8 as u32).into()),
+                        U16 => Some((x as u16).rotate_right(y_u8 as u32).into()),
+                        U32 => Some((x as u32).rotate_right(y_u8 as u32).into()),
+                        U64 => Some(x.rotate_right(y_u8 as u32).into()),
+                        UnsignedNumType::Unspecified => unreachable!(),
+                    },
+                    NumSigned(x, signed_ty) => match signed_ty {
+                        I8 => Some((x as i8).rotate_right(y_u8 as u32).into()),
+                        I16 => Some((x as i16).rotate_right(y_u8 as u32).into()),
+                        I32 => Some((x as i32).rotate_right(y_u8 as u32).into()),
+                        I64 => Some(x.rotate_right(y_u8 as u32).into()),
+                        SignedNumType::Unspecified => unreachable!(),
+                    },
+                    _ => unreachable!("rotate expects a num type"),
+                };
+                (x, ty.clone(), y, ty_u8, result, ty.clone(), op)
+            }
+            RotateLeft => {
+                let ty = g.choose(&num_tys).unwrap();
+                let ty_u8 = Type::Unsigned(U8);
+                let x = arbitrary_literal_of_ty(g, ty);
+                let y_u8 = u8::arbitrary(g);
+                let y = NumUnsigned(y_u8 as u64, U8);
+                let result = match x {
+                    NumUnsigned(x, unsigned_ty) => match unsigned_ty {
+                        Usize => unreachable!("usize types must not be tested"),
+                        U256 => unreachable!("u256 is not supported"),
+                        U8 => Some((x as u8).rotate_left(y_u8 as u32).into()),
+                        U16 => Some((x as u16).rotate_left(y_u8 as u32).into()),
+                        U32 => Some((x as u32).rotate_left(y_u8 as u32).into()),
+                        U64 => Some(x.rotate_left(y_u8 as u32).into()),
+                        UnsignedNumType::Unspecified => unreachable!(),
+                    },
+                    NumSigned(x, signed_ty) => match signed_ty {
+                        I8 => Some((x as i8).rotate_left(y_u8 as u32).into()),
+                        I16 => Some((x as i16).rotate_left(y_u8 as u32).into()),
+                        I32 => Some((x as i32).rotate_left(y_u8 as u32).into()),
+                        I64 => Some(x.rotate_left(y_u8 as u32).into()),
+                        SignedNumType::Unspecified => unreachable!(),
+                    },
+                    _ => unreachable!("rotate expects a num type"),
+                };
+                (x, ty.clone(), y, ty_u8, result, ty.clone(), op)
+            }
             ShortCircuitAnd | ShortCircuitOr => unreachable!("&& and || expect bool types"),
         };
         let prg = format!("pub fn main(x: {ty_x}, y: {ty_y}) -> {ty_result} {{ x {op} y }}");
@@ -130,6 +197,7 @@ fn arbitrary_literal_of_ty(g: &mut quickcheck::Gen, ty: &Type) -> Literal {
     match ty {
         Type::Unsigned(ty) => match ty {
             Usize => unreachable!("usize is not supported"),
+            U256 => unreachable!("u256 is not supported"),
             U8 => NumUnsigned(u8::arbitrary(g) as u64, *ty),
             U16 => NumUnsigned(u16::arbitrary(g) as u64, *ty),
             U32 => NumUnsigned(u32::arbitrary(g) as u64, *ty),
@@ -174,6 +242,9 @@ macro_rules! apply {
             Mul => $x.checked_mul($y).map(|z| Literal::from(z)),
             Div => $x.checked_div($y).map(|z| Literal::from(z)),
             Mod => $x.checked_rem($y).map(|z| Literal::from(z)),
+            SaturatingAdd => Some($x.saturating_add($y)).map(|z| Literal::from(z)),
+            SaturatingSub => Some($x.saturating_sub($y)).map(|z| Literal::from(z)),
+            SaturatingMul => Some($x.saturating_mul($y)).map(|z| Literal::from(z)),
             BitAnd => Some($x & $y).map(|z| Literal::from(z)),
             BitXor => Some($x ^ $y).map(|z| Literal::from(z)),
             BitOr => Some($x | $y).map(|z| Literal::from(z)),
@@ -183,6 +254,8 @@ macro_rules! apply {
             NotEq => Some($x != $y).map(|z| Literal::from(z)),
             ShiftLeft => $x.checked_shl($y as u32).map(|z| Literal::from(z)),
             ShiftRight => $x.checked_shr($y as u32).map(|z| Literal::from(z)),
+            RotateRight => Some($x.rotate_right($y as u32)).map(|z| Literal::from(z)),
+            RotateLeft => Some($x.rotate_left($y as u32)).map(|z| Literal::from(z)),
             ShortCircuitAnd => unreachable!("&& can only be applied to bools"),
             ShortCircuitOr => unreachable!("|| can only be applied to bools"),
         }
@@ -205,3 +278,50 @@ fn quickcheck_operator(test_case: OperatorTestCase) -> Result<(), Error> {
     }
     Ok(())
 }
+
+#[quickcheck]
+fn quickcheck_literal_bits_roundtrip(
+    arr: (u8, u8, u8),
+    n: i16,
+    b: bool,
+    tag: u8,
+    x: u8,
+    y: i32,
+) -> Result<(), Error> {
+    let prg = "
+enum Op {
+    Add(u8, i32),
+    Zero,
+    Not(bool),
+}
+
+pub fn main(x: ([u8; 3], (i16, bool), Op)) -> ([u8; 3], (i16, bool), Op) {
+    x
+}
+";
+    let compiled = compile(prg)?;
+    let ty = compiled.main.params[0].ty.clone();
+    let variant = match tag % 3 {
+        0 => VariantLiteral::Tuple(vec![x.into(), y.into()]),
+        1 => VariantLiteral::Unit,
+        _ => VariantLiteral::Tuple(vec![b.into()]),
+    };
+    let variant_name = ["Add", "Zero", "Not"][(tag % 3) as usize];
+    let literal = Literal::Tuple(vec![
+        Literal::Array(vec![arr.0.into(), arr.1.into(), arr.2.into()]),
+        Literal::Tuple(vec![n.into(), b.into()]),
+        Literal::Enum("Op".to_string(), variant_name.to_string(), variant),
+    ]);
+    let consts = HashMap::new();
+    let bits = literal.as_bits(&compiled.program, &consts);
+    assert_eq!(bits.len(), compiled.circuit.input_gates[0]);
+    let decoded = Literal::from_unwrapped_bits(&compiled.program, &ty, &bits, &consts)?;
+    assert_eq!(decoded, literal);
+
+    // the bits must match the encoding of the typed setters and the decoding of the output:
+    let mut eval = compiled.evaluator();
+    eval.set_bits(&bits)?;
+    let output = eval.run()?;
+    assert_eq!(output.into_literal()?, literal);
+    Ok(())
+}
@@ -1,4 +1,7 @@
-use garble_lang::scan::{scan, ScanError};
+use garble_lang::{
+    scan::{scan, ScanError, ScanErrorEnum},
+    token::{MetaInfo, SignedNumType, TokenEnum, UnsignedNumType},
+};
 
 #[test]
 fn scan_exhaustive_enum_pattern_with_literals() -> Result<(), Vec<ScanError>> {
@@ -25,3 +28,143 @@ fn main(choice: A::u8, x: A::u8, y: A::u8) -> u8 {
     scan(prg)?;
     Ok(())
 }
+
+#[test]
+fn scan_shift_and_rotate_operators() -> Result<(), Vec<ScanError>> {
+    let prg = "x >> y >>> z << w >>= v";
+    let tokens: Vec<TokenEnum> = scan(prg)?.0.into_iter().map(|t| t.0).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            TokenEnum::Identifier("x".to_string()),
+            TokenEnum::DoubleGreaterThan,
+            TokenEnum::Identifier("y".to_string()),
+            TokenEnum::TripleGreaterThan,
+            TokenEnum::Identifier("z".to_string()),
+            TokenEnum::DoubleLessThan,
+            TokenEnum::Identifier("w".to_string()),
+            TokenEnum::ShrAssign,
+            TokenEnum::Identifier("v".to_string()),
+        ]
+    );
+    let formatted: Vec<String> = tokens.iter().map(|t| t.to_string()).collect();
+    let rescanned: Vec<TokenEnum> = scan(&formatted.join(" "))?
+        .0
+        .into_iter()
+        .map(|t| t.0)
+        .collect();
+    assert_eq!(tokens, rescanned);
+    Ok(())
+}
+
+#[test]
+fn scan_string_literals() -> Result<(), Vec<ScanError>> {
+    let prg = r#"x == "A\"B\\\n""#;
+    let tokens: Vec<TokenEnum> = scan(prg)?.0.into_iter().map(|t| t.0).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            TokenEnum::Identifier("x".to_string()),
+            TokenEnum::DoubleEq,
+            TokenEnum::Str("A\"B\\\n".to_string()),
+        ]
+    );
+    assert!(scan("\"AB\nCD\"").is_err());
+    assert!(scan(r#""A\qB""#).is_err());
+    Ok(())
+}
+
+#[test]
+fn scan_char_literals() -> Result<(), Vec<ScanError>> {
+    let prg = r"'A' < '\'' ";
+    let tokens: Vec<TokenEnum> = scan(prg)?.0.into_iter().map(|t| t.0).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            TokenEnum::Char('A'),
+            TokenEnum::LessThan,
+            TokenEnum::Char('\''),
+        ]
+    );
+    assert!(scan("''").is_err());
+    assert!(scan("'AB'").is_err());
+    Ok(())
+}
+
+#[test]
+fn scan_hex_octal_binary_and_separated_literals() -> Result<(), Vec<ScanError>> {
+    let prg = "0xFFu32 0o17 0b1010u8 1_000_000u64 -0x80i8 -1_000";
+    let tokens: Vec<TokenEnum> = scan(prg)?.0.into_iter().map(|t| t.0).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            TokenEnum::UnsignedNum(255, UnsignedNumType::U32),
+            TokenEnum::UnsignedNum(15, UnsignedNumType::Unspecified),
+            TokenEnum::UnsignedNum(10, UnsignedNumType::U8),
+            TokenEnum::UnsignedNum(1_000_000, UnsignedNumType::U64),
+            TokenEnum::SignedNum(-128, SignedNumType::I8),
+            TokenEnum::SignedNum(-1000, SignedNumType::Unspecified),
+        ]
+    );
+
+    let Err(errors) = scan("x + 0x1FFu8") else {
+        panic!("0x1FFu8 does not fit into a u8")
+    };
+    let [ScanError(ScanErrorEnum::NumOutOfRange(ty), meta)] = errors.as_slice() else {
+        panic!("expected a single out of range error, found {errors:?}")
+    };
+    assert_eq!(ty, "u8");
+    assert_eq!(
+        *meta,
+        MetaInfo {
+            start: (0, 4),
+            end: (0, 11)
+        }
+    );
+    assert!(scan("-0x81i8").is_err());
+    assert!(scan("0x1_0000_0000_0000_0000u64").is_err());
+    let tokens: Vec<TokenEnum> = scan("0x1_0000_0000_0000_0000 18446744073709551617u256")?
+        .0
+        .into_iter()
+        .map(|t| t.0)
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            TokenEnum::UnsignedBigNum(
+                vec![1, 0, 0, 0, 0, 0, 0, 0, 0],
+                UnsignedNumType::Unspecified
+            ),
+            TokenEnum::UnsignedBigNum(vec![1, 0, 0, 0, 0, 0, 0, 0, 1], UnsignedNumType::U256),
+        ]
+    );
+    assert!(scan(&format!("0x1{}", "0".repeat(64))).is_err());
+    assert!(scan("0b102").is_err());
+    Ok(())
+}
+
+#[test]
+fn scan_fixed_point_literals() -> Result<(), Vec<ScanError>> {
+    let prg = "1.5fx8_4 -0.25fx16_8 3fx8_0 0.1fx8_4 t.0.1";
+    let tokens: Vec<TokenEnum> = scan(prg)?.0.into_iter().map(|t| t.0).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            TokenEnum::FixedNum(24, 8, 4),
+            TokenEnum::FixedNum(-64, 16, 8),
+            TokenEnum::FixedNum(3, 8, 0),
+            // 0.1 * 16 = 1.6 is rounded to the nearest representable number:
+            TokenEnum::FixedNum(2, 8, 4),
+            TokenEnum::Identifier("t".to_string()),
+            TokenEnum::Dot,
+            TokenEnum::UnsignedNum(0, UnsignedNumType::Unspecified),
+            TokenEnum::Dot,
+            TokenEnum::UnsignedNum(1, UnsignedNumType::Unspecified),
+        ]
+    );
+    assert!(scan("8.0fx8_4").is_err());
+    assert!(scan("-8.0fx8_4").is_ok());
+    assert!(scan("1.5fx8_9").is_err());
+    assert!(scan("1.5fx65_4").is_err());
+    Ok(())
+}